@@ -1,37 +1,188 @@
 extern crate glk;
 
-use std::ffi::CString;
-use std::io::{Error,ErrorKind,Read,Result,Seek,SeekFrom,Write};
-use std::os::raw::{c_char,c_uchar};
-
-use glk::{Glk,DateType,EventType,IdType,TimeValType};
-
-mod array_registry;
-mod c_interface;
+use std::io::{BufRead,BufReader,Write};
+use std::path::PathBuf;
+
+use glk::{Glk,IdType};
+use glk::blorb::{Blorb,Usage};
+use glk::datetime::{Date,TimeVal};
+use glk::session;
+use glk::streams::{Encoding,FRefId,FileRefs,IOStream,StrId,Streams,WindowSink};
+use glk::windows::{Event,Metrics,WinId,Windows};
+
+pub use glk::args::Argument;
+
+// Parses the library options and the program's arguments, then calls
+// main_func with a CheapGlk on stdin and stdout, until it returns or
+// its session ends.
+pub fn init(arguments: Vec<Argument>, main_func: fn(CheapGlk,Vec<String>)) {
+    let library = vec![
+        Argument::NoValue("-u".to_string(), "UTF-8 input and output".to_string()),
+        Argument::NoValue("-l".to_string(), "Latin-1 input and output (default)".to_string()),
+        Argument::NumberValue("-w".to_string(), "Screen width".to_string()),
+        Argument::NumberValue("-h".to_string(), "Screen height".to_string()),
+        ];
+    let args: Vec<String> = std::env::args().collect();
+    let (args,options) = match glk::args::parse(&arguments, &library, &args) {
+        Some(parsed) => parsed,
+        None => {
+            eprint!("{}", glk::args::usage(&args[0], &arguments, &library));
+            std::process::exit(1);
+        },
+    };
+    let mut glk = CheapGlk::new(Box::new(BufReader::new(std::io::stdin())), Box::new(std::io::stdout()));
+    for (option,value) in options {
+        let n = value.and_then(|n| n.parse().ok()).unwrap_or(0);
+        let (width,height) = glk.screen_size();
+        match option.as_str() {
+            "-u" => glk.set_encoding(Encoding::Utf8),
+            "-l" => glk.set_encoding(Encoding::Latin1),
+            "-w" => glk.set_screen_size(n, height),
+            "-h" => glk.set_screen_size(width, n),
+            _ => (),
+        }
+    }
+    let _ = session::run(|| {
+        main_func(glk, args);
+        Ok(())
+    });
+}
 
-pub use c_interface::{glk_main,glkunix_arguments,glkunix_startup_code};
+struct Output {
+    output: Box<Write + Send>,
+    encoding: Encoding,
+}
 
-pub fn set_arguments(args: Vec<Argument>) {
-    c_interface::set_arguments(args);
+impl WindowSink<WinId> for Output {
+    fn put_window(&mut self, _win: &WinId, s: &[u32]) {
+        let mut bytes = Vec::new();
+        for &ch in s {
+            match self.encoding {
+                Encoding::Latin1 => bytes.push(if ch > 0xff { b'?' } else { ch as u8 }),
+                Encoding::Utf8 => {
+                    let ch = std::char::from_u32(ch).unwrap_or('?');
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                },
+            }
+        }
+        let _ = self.output.write_all(&bytes);
+    }
 }
 
-pub fn init(main_func: fn(CheapGlk,Vec<String>)) {
-    array_registry::init();
-    c_interface::init(main_func);
+enum LineBuf {
+    Latin1((u32,Box<[u8]>)),
+    Uni((u32,Box<[u32]>)),
 }
 
-pub enum Argument {
-    ValueFollows(String,String),
-    NoValue(String,String),
-    ValueCanFollow(String,String),
-    NumberValue(String,String),
+// A Glk with a single window, which is a stream of text on the output,
+// with input read a line at a time.
+pub struct CheapGlk {
+    input: Box<BufRead + Send>,
+    output: Output,
+    windows: Windows,
+    streams: Streams<WinId>,
+    frefs: FileRefs,
+    line_request: Option<LineBuf>,
+    char_request: Option<bool>,
+    echo_input: bool,
+    resources: Option<Blorb>,
 }
 
-fn main_func(main_func: fn(CheapGlk,Vec<String>), args: Vec<String>) {
-    main_func(CheapGlk{}, args);
+impl CheapGlk {
+    pub fn new(input: Box<BufRead + Send>, output: Box<Write + Send>) -> Self {
+        CheapGlk{
+            input,
+            output: Output{ output, encoding: Encoding::Latin1 },
+            windows: Windows::new(Metrics::cells(80, 24)),
+            streams: Streams::new(),
+            frefs: FileRefs::new(PathBuf::from(".")),
+            line_request: None,
+            char_request: None,
+            echo_input: false,
+            resources: None,
+        }
+    }
+
+    // The encoding of input and output, and of text files.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.output.encoding = encoding;
+        self.streams.set_encoding(encoding);
+    }
+
+    pub fn screen_size(&self) -> (u32,u32) {
+        let metrics = self.windows.metrics();
+        (metrics.width,metrics.height)
+    }
+
+    pub fn set_screen_size(&mut self, width: u32, height: u32) {
+        self.windows.set_metrics(Metrics::cells(width, height));
+    }
+
+    // Whether input is copied to the output, as when the input isn't
+    // typed on a terminal.
+    pub fn set_echo_input(&mut self, echo_input: bool) {
+        self.echo_input = echo_input;
+    }
+
+    // A line of input without its newline, or None at the end of input.
+    fn read_line(&mut self) -> Option<Vec<u32>> {
+        let _ = self.output.output.flush();
+        let mut line = Vec::new();
+        match self.input.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => (),
+        }
+        if self.echo_input {
+            if line.last() != Some(&b'\n') {
+                line.push(b'\n');
+            }
+            let _ = self.output.output.write_all(&line);
+        }
+        while line.last() == Some(&b'\n') || line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Some(match self.output.encoding {
+            Encoding::Latin1 => line.iter().map(|&b| b as u32).collect(),
+            Encoding::Utf8 => String::from_utf8_lossy(&line).chars().map(|ch| ch as u32).collect(),
+        })
+    }
+
+    fn line_event(&mut self, buf: LineBuf, line: &[u32]) -> Event {
+        let win = self.windows.root();
+        let mut event = Event::new(glk::evtype_LineInput, win, 0, 0);
+        let echoed = match buf {
+            LineBuf::Latin1((addr,mut buf)) => {
+                let len = line.len().min(buf.len());
+                for i in 0 .. len {
+                    buf[i] = if line[i] > 0xff { b'?' } else { line[i] as u8 };
+                }
+                event.val1 = len as u32;
+                event.buf = Some((addr,buf));
+                &line[.. len]
+            },
+            LineBuf::Uni((addr,mut buf)) => {
+                let len = line.len().min(buf.len());
+                buf[.. len].copy_from_slice(&line[.. len]);
+                event.val1 = len as u32;
+                event.buf_uni = Some((addr,buf));
+                &line[.. len]
+            },
+        };
+        // The input is already on the terminal, so it only goes to the echo stream.
+        let winstr = self.window_get_stream(&win);
+        let echo = self.streams.echo(&winstr);
+        if !echo.is_null() {
+            let mut echoed = echoed.to_vec();
+            echoed.push('\n' as u32);
+            self.streams.put(&mut self.output, &echo, &echoed);
+        }
+        event
+    }
 }
 
-pub struct CheapGlk {
+fn to_chars(s: &[u8]) -> Vec<u32> {
+    s.iter().map(|&ch| ch as u32).collect()
 }
 
 impl<'a> Glk<'a> for CheapGlk {
@@ -42,1040 +193,642 @@ impl<'a> Glk<'a> for CheapGlk {
     type Event = Event;
     type TimeVal = TimeVal;
     type Date = Date;
-    type IOStream = IOStream;
+    type IOStream = IOStream<WinId>;
 
     fn exit(&mut self) -> ! {
-        unsafe {
-            c_interface::glk_exit();
-        }
+        let _ = self.output.output.flush();
+        session::end(Ok(()))
     }
 
-    fn set_interrupt_handler(&mut self, handler: extern fn()) {
-        unsafe {
-            c_interface::glk_set_interrupt_handler(handler);
-        }
+    fn set_interrupt_handler(&mut self, _handler: extern fn()) {
     }
 
     fn tick(&mut self) {
-        unsafe {
-            c_interface::glk_tick();
-        }
     }
 
 
     fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
-        unsafe {
-            c_interface::glk_gestalt(sel, val)
-        }
+        self.gestalt_ext(sel, val, &mut [])
     }
 
     fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
-        let len = arr.len() as u32;
-        unsafe {
-            c_interface::glk_gestalt_ext(sel, val, &mut arr[0], len)
+        let printable = val >= 32 && val < 0x110000 && (val < 127 || val > 159);
+        match sel {
+            glk::gestalt_Version => 0x00070500,
+            glk::gestalt_CharInput => if printable || val == glk::keycode_Return { 1 } else { 0 },
+            glk::gestalt_LineInput => if printable { 1 } else { 0 },
+            glk::gestalt_CharOutput => {
+                if arr.len() > 0 {
+                    arr[0] = 1;
+                }
+                if printable && (val <= 0xff || self.output.encoding == Encoding::Utf8) {
+                    glk::gestalt_CharOutput_ExactPrint
+                } else {
+                    glk::gestalt_CharOutput_CannotPrint
+                }
+            },
+            glk::gestalt_Unicode => 1,
+            glk::gestalt_UnicodeNorm => 1,
+            glk::gestalt_DateTime => 1,
+            glk::gestalt_ResourceStream => 1,
+            _ => 0,
         }
     }
 
 
     fn char_to_lower(&mut self, ch: u8) -> u8 {
-        unsafe {
-            c_interface::glk_char_to_lower(ch as std::os::raw::c_uchar) as u8
-        }
+        glk::unicode::latin1_to_lower(ch)
     }
 
     fn char_to_upper(&mut self, ch: u8) -> u8 {
-        unsafe {
-            c_interface::glk_char_to_upper(ch as std::os::raw::c_uchar) as u8
-        }
+        glk::unicode::latin1_to_upper(ch)
     }
 
 
     fn window_get_root(&mut self) -> Self::WinId {
-        let ptr = unsafe {
-            c_interface::glk_window_get_root()
-        };
-        WinId{ ptr }
+        self.windows.root()
     }
 
+    // Only one window may be open.
     fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
-        let ptr = unsafe {
-            c_interface::glk_window_open(split.ptr, method, size, wintype, rock)
+        if !self.windows.root().is_null() {
+            return WinId(0);
+        }
+        match wintype {
+            glk::wintype_TextBuffer | glk::wintype_TextGrid => (),
+            _ => return WinId(0),
+        }
+        let win = match self.windows.open(split, method, size, wintype, rock) {
+            Some((win,_)) => win,
+            None => return WinId(0),
         };
-        WinId{ ptr }
+        let str = self.streams.open_window(win);
+        self.windows.get_mut(&win).unwrap().str = str;
+        win
     }
 
     fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
-        let mut result = Default::default();
-        unsafe {
-            c_interface::glk_window_close(win.ptr, &mut result);
+        let mut result = (0,0);
+        for (id,w) in self.windows.close(win) {
+            let (readcount,writecount,_,_) = self.streams.close(&w.str);
+            if id == *win {
+                result = (readcount,writecount);
+            }
         }
-        win.ptr = std::ptr::null();
-        (result.readcount,result.writecount,
-         array_registry::retrieve_stream_memory(),
-         array_registry::retrieve_stream_memory_uni())
+        self.line_request = None;
+        self.char_request = None;
+        *win = WinId(0);
+        (result.0,result.1,None,None)
     }
 
     fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
-        let mut width = 0;
-        let mut height = 0;
-        unsafe {
-            c_interface::glk_window_get_size(win.ptr, &mut width, &mut height);
-        }
-        (width,height)
+        self.windows.size(win)
     }
 
-    fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
-        unsafe {
-            c_interface::glk_window_set_arrangement(win.ptr, method, size, keywin.ptr);
-        }
+    fn window_set_arrangement(&mut self, _win: &Self::WinId, _method: u32, _size: u32, _keywin: &Self::WinId) {
     }
 
     fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
-        let mut method = 0;
-        let mut size = 0;
-        let mut keywin = std::ptr::null();
-        unsafe {
-            c_interface::glk_window_get_arrangement(win.ptr, &mut method, &mut size, &mut keywin);
-        }
-        (method,size,WinId{ ptr: keywin })
+        self.windows.get_arrangement(win)
     }
 
     fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe {
-            c_interface::glk_window_iterate(win.ptr, &mut rock)
-        };
-        (WinId{ ptr },rock)
+        self.windows.iterate(win)
     }
 
     fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
-        unsafe {
-            c_interface::glk_window_get_rock(win.ptr)
-        }
+        self.windows.get(win).map(|w| w.rock).unwrap_or(0)
     }
 
     fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
-        unsafe {
-            c_interface::glk_window_get_type(win.ptr)
-        }
+        self.windows.get(win).map(|w| w.wintype).unwrap_or(0)
     }
 
-    fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
-        let ptr = unsafe { c_interface::glk_window_get_parent(win.ptr) };
-        WinId{ ptr }
+    fn window_get_parent(&mut self, _win: &Self::WinId) -> Self::WinId {
+        WinId(0)
     }
 
-    fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
-        let ptr = unsafe { c_interface::glk_window_get_sibling(win.ptr) };
-        WinId{ ptr }
+    fn window_get_sibling(&mut self, _win: &Self::WinId) -> Self::WinId {
+        WinId(0)
     }
 
-    fn window_clear(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_window_clear(win.ptr);
-        }
+    fn window_clear(&mut self, _win: &Self::WinId) {
     }
 
-    fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
-        unsafe {
-            c_interface::glk_window_move_cursor(win.ptr, xpos, ypos);
-        }
+    fn window_move_cursor(&mut self, _win: &Self::WinId, _xpos: u32, _ypos: u32) {
     }
 
 
     fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_window_get_stream(win.ptr) };
-        StrId{ ptr }
+        self.windows.get(win).map(|w| w.str).unwrap_or(StrId(0))
     }
 
     fn window_set_echo_stream(&mut self, win: &Self::WinId, str: &Self::StrId) {
-        unsafe {
-            c_interface::glk_window_set_echo_stream(win.ptr, str.ptr);
-        }
+        let winstr = self.window_get_stream(win);
+        self.streams.set_echo(&winstr, str);
     }
 
     fn window_get_echo_stream(&mut self, win: &Self::WinId) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_window_get_echo_stream(win.ptr) };
-        StrId{ ptr }
+        let winstr = self.window_get_stream(win);
+        self.streams.echo(&winstr)
     }
 
     fn set_window(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_set_window(win.ptr);
-        }
+        let str = self.window_get_stream(win);
+        self.streams.set_current(&str);
     }
 
 
     fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_file(fileref.ptr, fmode, rock) };
-        StrId{ ptr }
+        self.streams.open_file(&self.frefs, fileref, fmode, rock, false)
     }
 
     fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
-        let buflen = buf.1.len() as u32;
-        let ptr = unsafe {
-            c_interface::glk_stream_open_memory(array_registry::register_stream_memory(buf), buflen, fmode, rock)
-        };
-        StrId{ ptr }
+        self.streams.open_memory(buf, fmode, rock)
     }
 
     fn stream_close(&mut self, str: &mut Self::StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
-        let mut result = Default::default();
-        unsafe {
-            c_interface::glk_stream_close(str.ptr, &mut result);
+        // Window streams are closed with their windows.
+        if self.streams.window(str).is_some() {
+            return (0,0,None,None);
         }
-        str.ptr = std::ptr::null();
-        (result.readcount,result.writecount,
-         array_registry::retrieve_stream_memory(),
-         array_registry::retrieve_stream_memory_uni())
+        let result = self.streams.close(str);
+        *str = StrId(0);
+        result
     }
 
     fn stream_iterate(&mut self, str: &Self::StrId) -> (Self::StrId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe {
-            c_interface::glk_stream_iterate(str.ptr, &mut rock)
-        };
-        (StrId{ ptr },rock)
+        self.streams.iterate(str)
     }
 
     fn stream_get_rock(&mut self, str: &Self::StrId) -> u32 {
-        unsafe {
-            c_interface::glk_stream_get_rock(str.ptr)
-        }
+        self.streams.rock(str)
     }
 
     fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
-        unsafe {
-            c_interface::glk_stream_set_position(str.ptr, pos, seekmode);
-        }
+        self.streams.set_position(str, pos, seekmode);
     }
 
     fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
-        unsafe {
-            c_interface::glk_stream_get_position(str.ptr)
-        }
+        self.streams.position(str)
     }
 
     fn stream_set_current(&mut self, str: &Self::StrId) {
-        unsafe {
-            c_interface::glk_stream_set_current(str.ptr);
-        }
+        self.streams.set_current(str);
     }
 
     fn stream_get_current(&mut self) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_get_current() };
-        StrId{ ptr }
+        self.streams.current()
     }
 
 
     fn put_char(&mut self, ch: u8) {
-        unsafe {
-            c_interface::glk_put_char(ch as c_uchar);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.output, &str, &[ch as u32]);
     }
 
     fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
-        unsafe {
-            c_interface::glk_put_char_stream(str.ptr, ch as c_uchar);
-        }
+        self.streams.put(&mut self.output, str, &[ch as u32]);
     }
 
     fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
-        let _ = c_interface::glk_put_string;
-        self.put_buffer(s.as_ref());
+        let str = self.streams.current();
+        self.streams.put(&mut self.output, &str, &to_chars(s.as_ref()));
     }
 
     fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &Self::StrId, s: S) {
-        let _ = c_interface::glk_put_string_stream;
-        self.put_buffer_stream(str, s.as_ref());
+        self.streams.put(&mut self.output, str, &to_chars(s.as_ref()));
     }
 
     fn put_buffer(&mut self, buf: &[u8]) {
-        unsafe {
-            c_interface::glk_put_buffer(buf.as_ptr() as *const c_char, buf.len() as u32);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.output, &str, &to_chars(buf));
     }
 
     fn put_buffer_stream(&mut self, str: &Self::StrId, buf: &[u8]) {
-        unsafe {
-            c_interface::glk_put_buffer_stream(str.ptr, buf.as_ptr() as *const c_char, buf.len() as u32);
-        }
+        self.streams.put(&mut self.output, str, &to_chars(buf));
     }
 
     fn set_style(&mut self, styl: u32) {
-        unsafe {
-            c_interface::glk_set_style(styl);
-        }
+        let str = self.streams.current();
+        self.streams.set_style(&mut self.output, &str, styl);
     }
 
     fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
-        unsafe {
-            c_interface::glk_set_style_stream(str.ptr, styl);
-        }
+        self.streams.set_style(&mut self.output, str, styl);
     }
 
 
     fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
-        unsafe {
-            c_interface::glk_get_char_stream(str.ptr)
-        }
+        self.streams.get_char(str, false)
     }
 
     fn get_line_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
-        unsafe {
-            c_interface::glk_get_line_stream(str.ptr, buf.as_mut_ptr() as *mut c_char, buf.len() as u32)
-        }
+        self.streams.get_line(str, buf)
     }
 
     fn get_buffer_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
-        unsafe {
-            c_interface::glk_get_buffer_stream(str.ptr, buf.as_mut_ptr() as *mut c_char, buf.len() as u32)
-        }
+        self.streams.get_buffer(str, buf)
     }
 
 
-    fn stylehint_set(&mut self, wintype: u32, styl: u32, hint: u32, val: i32) {
-        unsafe {
-            c_interface::glk_stylehint_set(wintype, styl, hint, val);
-        }
+    fn stylehint_set(&mut self, _wintype: u32, _styl: u32, _hint: u32, _val: i32) {
     }
 
-    fn stylehint_clear(&mut self, wintype: u32, styl: u32, hint: u32) {
-        unsafe {
-            c_interface::glk_stylehint_clear(wintype, styl, hint);
-        }
+    fn stylehint_clear(&mut self, _wintype: u32, _styl: u32, _hint: u32) {
     }
 
-    fn style_distinguish(&mut self, win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
-        let result = unsafe {
-            c_interface::glk_style_distinguish(win.ptr, styl1, styl2)
-        };
-        result != 0
+    fn style_distinguish(&mut self, _win: &Self::WinId, _styl1: u32, _styl2: u32) -> bool {
+        false
     }
 
-    fn style_measure(&mut self, win: &Self::WinId, styl: u32, hint: u32) -> (bool,u32) {
-        let mut result = 0;
-        let success = unsafe {
-            c_interface::glk_style_measure(win.ptr, styl, hint, &mut result)
-        };
-        (success != 0,result)
+    fn style_measure(&mut self, _win: &Self::WinId, _styl: u32, _hint: u32) -> (bool,u32) {
+        (false,0)
     }
 
 
     fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
-        let ptr = unsafe {
-            c_interface::glk_fileref_create_temp(usage, rock)
-        };
-        FRefId{ ptr }
+        self.frefs.create_temp(usage, rock)
     }
 
     fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
-        let mut vec = Vec::new();
-        vec.extend_from_slice(name.as_ref());
-        let ptr = if let Ok(cname) = CString::new(vec) {
-            unsafe {
-                c_interface::glk_fileref_create_by_name(usage, cname.as_ptr(), rock)
-            }
-        } else {
-            std::ptr::null()
-        };
-        FRefId{ ptr }
+        self.frefs.create_by_name(usage, name, rock)
     }
 
     fn fileref_create_by_prompt(&mut self, usage: u32, fmode: u32, rock: u32) -> Self::FRefId {
-        let ptr = unsafe {
-            c_interface::glk_fileref_create_by_prompt(usage, fmode, rock)
+        let prompt: &[u8] = match (usage & glk::fileusage_TypeMask,fmode) {
+            (glk::fileusage_SavedGame,glk::filemode_Read) => b"\nRestore from file: ",
+            (glk::fileusage_SavedGame,_) => b"\nSave to file: ",
+            (glk::fileusage_Transcript,_) => b"\nTranscript file: ",
+            (glk::fileusage_InputRecord,glk::filemode_Read) => b"\nRead commands from file: ",
+            (glk::fileusage_InputRecord,_) => b"\nRecord commands to file: ",
+            _ => b"\nFile name: ",
         };
-        FRefId{ ptr }
+        let root = self.windows.root();
+        self.output.put_window(&root, &to_chars(prompt));
+        let name: String = match self.read_line() {
+            Some(line) => line.iter().filter_map(|&ch| std::char::from_u32(ch)).collect(),
+            None => return FRefId(0),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return FRefId(0);
+        }
+        self.frefs.create_by_path(PathBuf::from(name), usage, rock)
     }
 
     fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
-        let ptr = unsafe {
-            c_interface::glk_fileref_create_from_fileref(usage, fref.ptr, rock)
-        };
-        FRefId{ ptr }
+        self.frefs.create_from_fileref(usage, fref, rock)
     }
 
     fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
-        unsafe {
-            c_interface::glk_fileref_destroy(fref.ptr);
-        }
-        fref.ptr = std::ptr::null();
+        self.frefs.destroy(fref);
+        *fref = FRefId(0);
     }
 
     fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe {
-            c_interface::glk_fileref_iterate(fref.ptr, &mut rock)
-        };
-        (FRefId{ ptr },rock)
+        self.frefs.iterate(fref)
     }
 
     fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
-        unsafe {
-            c_interface::glk_fileref_get_rock(fref.ptr)
-        }
+        self.frefs.rock(fref)
     }
 
     fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
-        unsafe {
-            c_interface::glk_fileref_delete_file(fref.ptr);
-        }
+        self.frefs.delete_file(fref);
     }
 
     fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
-        let result = unsafe {
-            c_interface::glk_fileref_does_file_exist(fref.ptr)
-        };
-        result != 0
+        self.frefs.does_file_exist(fref)
     }
 
 
+    // Waits for a line of input, which is the only source of events.
+    // The session ends at the end of input, or if there is no request
+    // that could ever be satisfied.
     fn select(&mut self) -> Self::Event {
-        let mut event = c_interface::event_t {
-            evtype: 0, win: std::ptr::null(), val1: 0, val2: 0
-        };
-        unsafe {
-            c_interface::glk_select(&mut event);
+        if self.line_request.is_none() && self.char_request.is_none() {
+            self.exit();
         }
-        Event(event)
+        let line = match self.read_line() {
+            Some(line) => line,
+            None => self.exit(),
+        };
+        if let Some(buf) = self.line_request.take() {
+            return self.line_event(buf, &line);
+        }
+        let uni = self.char_request.take().unwrap_or(false);
+        let ch = match line.first() {
+            None => glk::keycode_Return,
+            Some(&ch) if !uni && ch > 0xff => glk::keycode_Unknown,
+            Some(&ch) => ch,
+        };
+        Event::new(glk::evtype_CharInput, self.windows.root(), ch, 0)
     }
 
     fn select_poll(&mut self) -> Self::Event {
-        let mut event = c_interface::event_t {
-            evtype: 0, win: std::ptr::null(), val1: 0, val2: 0
-        };
-        unsafe {
-            c_interface::glk_select_poll(&mut event);
-        }
-        Event(event)
+        Event::none()
     }
 
 
-    fn request_timer_events(&mut self, millisecs: u32) {
-        unsafe {
-            c_interface::glk_request_timer_events(millisecs);
-        }
+    fn request_timer_events(&mut self, _millisecs: u32) {
     }
 
 
-    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
-        let maxlen = buf.1.len() as u32;
-        let cbuf = array_registry::register_line_event(buf);
-        unsafe {
-            c_interface::glk_request_line_event(win.ptr, cbuf, maxlen, initlen);
+    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), _initlen: u32) {
+        if self.windows.get(win).is_some() {
+            self.line_request = Some(LineBuf::Latin1(buf));
         }
     }
 
     fn request_char_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_char_event(win.ptr);
+        if self.windows.get(win).is_some() {
+            self.char_request = Some(false);
         }
     }
 
-    fn request_mouse_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_mouse_event(win.ptr);
-        }
+    fn request_mouse_event(&mut self, _win: &Self::WinId) {
     }
 
 
-    fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
-        let mut event = c_interface::event_t {
-            evtype: 0, win: std::ptr::null(), val1: 0, val2: 0
-        };
-        unsafe {
-            c_interface::glk_cancel_line_event(win.ptr, &mut event);
+    fn cancel_line_event(&mut self, _win: &Self::WinId) -> Self::Event {
+        match self.line_request.take() {
+            Some(buf) => self.line_event(buf, &[]),
+            None => Event::none(),
         }
-        Event(event)
     }
 
-    fn cancel_char_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_cancel_char_event(win.ptr);
-        }
+    fn cancel_char_event(&mut self, _win: &Self::WinId) {
+        self.char_request = None;
     }
 
-    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_cancel_mouse_event(win.ptr);
-        }
+    fn cancel_mouse_event(&mut self, _win: &Self::WinId) {
     }
 
 
-    fn set_echo_line_event(&mut self, win: &Self::WinId, val: u32) {
-        unsafe {
-            c_interface::glk_set_echo_line_event(win.ptr, val);
-        }
+    fn set_echo_line_event(&mut self, _win: &Self::WinId, _val: u32) {
     }
 
 
-    fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
-        unsafe {
-            c_interface::glk_set_terminators_line_event(win.ptr, keycodes.as_ptr(), keycodes.len() as u32);
-        }
+    fn set_terminators_line_event(&mut self, _win: &Self::WinId, _keycodes: &[u32]) {
     }
 
 
     fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_to_lower_case_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::to_lower_case(buf, numchars)
     }
 
     fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_to_upper_case_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::to_upper_case(buf, numchars)
     }
 
     fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_to_title_case_uni(buf.as_mut_ptr(), buf.len() as u32, numchars, lowerrest)
-        }
+        glk::unicode::to_title_case(buf, numchars, lowerrest != 0)
     }
 
 
     fn put_char_uni(&mut self, ch: u32) {
-        unsafe {
-            c_interface::glk_put_char_uni(ch);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.output, &str, &[ch]);
     }
 
     fn put_string_uni<SU: AsRef<[u32]>>(&mut self, s: SU) {
-        let _ = c_interface::glk_put_string_uni;
-        self.put_buffer_uni(s.as_ref());
+        let str = self.streams.current();
+        self.streams.put(&mut self.output, &str, s.as_ref());
     }
 
     fn put_buffer_uni(&mut self, buf: &[u32]) {
-        unsafe {
-            c_interface::glk_put_buffer_uni(buf.as_ptr(), buf.len() as u32);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.output, &str, buf);
     }
 
     fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
-        unsafe {
-            c_interface::glk_put_char_stream_uni(str.ptr, ch);
-        }
+        self.streams.put(&mut self.output, str, &[ch]);
     }
 
     fn put_string_stream_uni<SU: AsRef<[u32]>>(&mut self, str: &Self::StrId, s: SU) {
-        let _ = c_interface::glk_put_string_stream_uni;
-        self.put_buffer_stream_uni(str, s.as_ref());
+        self.streams.put(&mut self.output, str, s.as_ref());
     }
 
     fn put_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &[u32]) {
-        unsafe {
-            c_interface::glk_put_buffer_stream_uni(str.ptr, buf.as_ptr(), buf.len() as u32);
-        }
+        self.streams.put(&mut self.output, str, buf);
     }
 
 
     fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
-        unsafe {
-            c_interface::glk_get_char_stream_uni(str.ptr)
-        }
+        self.streams.get_char(str, true)
     }
 
     fn get_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
-        unsafe {
-            c_interface::glk_get_buffer_stream_uni(str.ptr, buf.as_mut_ptr(), buf.len() as u32)
-        }
+        self.streams.get_buffer_uni(str, buf)
     }
 
     fn get_line_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
-        unsafe {
-            c_interface::glk_get_line_stream_uni(str.ptr, buf.as_mut_ptr(), buf.len() as u32)
-        }
+        self.streams.get_line_uni(str, buf)
     }
 
 
     fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_file_uni(fileref.ptr, fmode, rock) };
-        StrId{ ptr }
+        self.streams.open_file(&self.frefs, fileref, fmode, rock, true)
     }
 
     fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
-        let buflen = buf.1.len() as u32;
-        let ptr = unsafe {
-            c_interface::glk_stream_open_memory_uni(array_registry::register_stream_memory_uni(buf), buflen, fmode, rock)
-        };
-        StrId{ ptr }
+        self.streams.open_memory_uni(buf, fmode, rock)
     }
 
 
     fn request_char_event_uni(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_char_event_uni(win.ptr);
+        if self.windows.get(win).is_some() {
+            self.char_request = Some(true);
         }
     }
 
-    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
-        let maxlen = buf.1.len() as u32;
-        let cbuf = array_registry::register_line_event_uni(buf);
-        unsafe {
-            c_interface::glk_request_line_event_uni(win.ptr, cbuf, maxlen, initlen);
+    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), _initlen: u32) {
+        if self.windows.get(win).is_some() {
+            self.line_request = Some(LineBuf::Uni(buf));
         }
     }
 
 
     fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_canon_decompose_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::canon_decompose(buf, numchars)
     }
 
     fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_canon_normalize_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::canon_normalize(buf, numchars)
     }
 
 
-    fn image_draw(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32) -> bool {
-        let result = unsafe {
-            c_interface::glk_image_draw(win.ptr, image, val1, val2)
-        };
-        result != 0
+    fn image_draw(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32) -> bool {
+        false
     }
 
-    fn image_draw_scaled(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> bool {
-        let result = unsafe {
-            c_interface::glk_image_draw_scaled(win.ptr, image, val1, val2, width, height)
-        };
-        result != 0
+    fn image_draw_scaled(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32, _width: u32, _height: u32) -> bool {
+        false
     }
 
-    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
-        let mut width = 0;
-        let mut height = 0;
-        let result = unsafe {
-            c_interface::glk_image_get_info(image, &mut width, &mut height)
-        };
-        (result != 0,width,height)
+    fn image_get_info(&mut self, _image: u32) -> (bool,u32,u32) {
+        (false,0,0)
     }
 
 
-    fn window_flow_break(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_window_flow_break(win.ptr);
-        }
+    fn window_flow_break(&mut self, _win: &Self::WinId) {
     }
 
 
-    fn window_erase_rect(&mut self, win: &Self::WinId, left: i32, top: i32, width: u32, height: u32) {
-        unsafe {
-            c_interface::glk_window_erase_rect(win.ptr, left, top, width, height);
-        }
+    fn window_erase_rect(&mut self, _win: &Self::WinId, _left: i32, _top: i32, _width: u32, _height: u32) {
     }
 
-    fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32) {
-        unsafe {
-            c_interface::glk_window_fill_rect(win.ptr, color, left, top, width, height);
-        }
+    fn window_fill_rect(&mut self, _win: &Self::WinId, _color: u32, _left: i32, _top: i32, _width: u32, _height: u32) {
     }
 
-    fn window_set_background_color(&mut self, win: &Self::WinId, color: u32) {
-        unsafe {
-            c_interface::glk_window_set_background_color(win.ptr, color);
-        }
+    fn window_set_background_color(&mut self, _win: &Self::WinId, _color: u32) {
     }
 
 
-    fn schannel_create(&mut self, rock: u32) -> Self::SChanId {
-        let ptr = unsafe { c_interface::glk_schannel_create(rock) };
-        SChanId{ ptr }
+    fn image_draw_scaled_ext(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32, _width: u32, _height: u32, _imagerule: u32, _maxwidth: u32) -> bool {
+        false
     }
 
-    fn schannel_destroy(&mut self, chan: &mut Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_destroy(chan.ptr);
-        }
-        chan.ptr = std::ptr::null();
+
+    fn schannel_create(&mut self, _rock: u32) -> Self::SChanId {
+        SChanId(())
     }
 
-    fn schannel_iterate(&mut self, chan: &Self::SChanId) -> (Self::SChanId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe { c_interface::glk_schannel_iterate(chan.ptr, &mut rock) };
-        (SChanId{ ptr },rock)
+    fn schannel_destroy(&mut self, _chan: &mut Self::SChanId) {
     }
 
-    fn schannel_get_rock(&mut self, chan: &Self::SChanId) -> u32 {
-        unsafe {
-            c_interface::glk_schannel_get_rock(chan.ptr)
-        }
+    fn schannel_iterate(&mut self, _chan: &Self::SChanId) -> (Self::SChanId,u32) {
+        (SChanId(()),0)
+    }
+
+    fn schannel_get_rock(&mut self, _chan: &Self::SChanId) -> u32 {
+        0
     }
 
 
-    fn schannel_play(&mut self, chan: &Self::SChanId, snd: u32) -> bool {
-        let result = unsafe { c_interface::glk_schannel_play(chan.ptr, snd) };
-        result != 0
+    fn schannel_play(&mut self, _chan: &Self::SChanId, _snd: u32) -> bool {
+        false
     }
 
-    fn schannel_play_ext(&mut self, chan: &Self::SChanId, snd: u32, repeat: u32, notify: u32) -> bool {
-        let result = unsafe { c_interface::glk_schannel_play_ext(chan.ptr, snd, repeat, notify) };
-        result != 0
+    fn schannel_play_ext(&mut self, _chan: &Self::SChanId, _snd: u32, _repeat: u32, _notify: u32) -> bool {
+        false
     }
 
-    fn schannel_stop(&mut self, chan: &Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_stop(chan.ptr);
-        }
+    fn schannel_stop(&mut self, _chan: &Self::SChanId) {
     }
 
-    fn schannel_set_volume(&mut self, chan: &Self::SChanId, vol: u32) {
-        unsafe {
-            c_interface::glk_schannel_set_volume(chan.ptr, vol);
-        }
+    fn schannel_set_volume(&mut self, _chan: &Self::SChanId, _vol: u32) {
     }
 
 
-    fn sound_load_hint(&mut self, snd: u32, flag: u32) {
-        unsafe {
-            c_interface::glk_sound_load_hint(snd, flag);
-        }
+    fn sound_load_hint(&mut self, _snd: u32, _flag: u32) {
     }
 
 
-    fn schannel_create_ext(&mut self, rock: u32, volume: u32) -> Self::SChanId {
-        let ptr = unsafe { c_interface::glk_schannel_create_ext(rock, volume) };
-        SChanId{ ptr }
+    fn schannel_create_ext(&mut self, _rock: u32, _volume: u32) -> Self::SChanId {
+        SChanId(())
     }
 
-    fn schannel_play_multi(&mut self, chanarray: &[Self::SChanId], sndarray: &[u32], notify: u32) -> bool {
-        let mut chans = Vec::with_capacity(chanarray.len());
-        for chan in chanarray {
-            chans.push(chan.ptr);
-        }
-        let result = unsafe {
-            c_interface::glk_schannel_play_multi(chans.as_ptr(), chanarray.len() as u32, sndarray.as_ptr(), sndarray.len() as u32, notify)
-        };
-        result != 0
+    fn schannel_play_multi(&mut self, _chanarray: &[Self::SChanId], _sndarray: &[u32], _notify: u32) -> bool {
+        false
     }
 
-    fn schannel_pause(&mut self, chan: &Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_pause(chan.ptr);
-        }
+    fn schannel_pause(&mut self, _chan: &Self::SChanId) {
     }
 
-    fn schannel_unpause(&mut self, chan: &Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_unpause(chan.ptr);
-        }
+    fn schannel_unpause(&mut self, _chan: &Self::SChanId) {
     }
 
-    fn schannel_set_volume_ext(&mut self, chan: &Self::SChanId, vol: u32, duration: u32, notify: u32) {
-        unsafe {
-            c_interface::glk_schannel_set_volume_ext(chan.ptr, vol, duration, notify);
-        }
+    fn schannel_set_volume_ext(&mut self, _chan: &Self::SChanId, _vol: u32, _duration: u32, _notify: u32) {
     }
 
 
-    fn set_hyperlink(&mut self, linkval: u32) {
-        unsafe {
-            c_interface::glk_set_hyperlink(linkval);
-        }
+    fn set_hyperlink(&mut self, _linkval: u32) {
     }
 
-    fn set_hyperlink_stream(&mut self, str: &Self::StrId, linkval: u32) {
-        unsafe {
-            c_interface::glk_set_hyperlink_stream(str.ptr, linkval);
-        }
+    fn set_hyperlink_stream(&mut self, _str: &Self::StrId, _linkval: u32) {
     }
 
-    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_hyperlink_event(win.ptr);
-        }
+    fn request_hyperlink_event(&mut self, _win: &Self::WinId) {
     }
 
-    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_cancel_hyperlink_event(win.ptr);
-        }
+    fn cancel_hyperlink_event(&mut self, _win: &Self::WinId) {
     }
 
 
+    // There is no time zone information, so local time is UTC.
     fn current_time(&mut self) -> Self::TimeVal {
-        let mut time = Default::default();
-        unsafe {
-            c_interface::glk_current_time(&mut time);
-        }
-        TimeVal(time)
+        TimeVal::now()
     }
 
     fn current_simple_time(&mut self, factor: u32) -> i32 {
-        unsafe {
-            c_interface::glk_current_simple_time(factor)
-        }
+        TimeVal::now().to_simple(factor)
     }
 
     fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_time_to_date_utc(&time.0, &mut date);
-        }
-        Date(date)
+        Date::from_time(time, 0)
     }
 
     fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_time_to_date_local(&time.0, &mut date);
-        }
-        Date(date)
+        Date::from_time(time, 0)
     }
 
     fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_simple_time_to_date_utc(time, factor, &mut date);
-        }
-        Date(date)
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
     }
 
     fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_simple_time_to_date_local(time, factor, &mut date);
-        }
-        Date(date)
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
     }
 
     fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
-        let mut time = Default::default();
-        unsafe {
-            c_interface::glk_date_to_time_utc(&date.0, &mut time);
-        }
-        TimeVal(time)
+        date.to_time(0)
     }
 
     fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
-        let mut time = Default::default();
-        unsafe {
-            c_interface::glk_date_to_time_local(&date.0, &mut time);
-        }
-        TimeVal(time)
+        date.to_time(0)
     }
 
     fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
-        unsafe {
-            c_interface::glk_date_to_simple_time_utc(&date.0, factor)
-        }
+        date.to_time(0).to_simple(factor)
     }
 
     fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
-        unsafe {
-            c_interface::glk_date_to_simple_time_local(&date.0, factor)
-        }
+        date.to_time(0).to_simple(factor)
     }
 
 
     fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_resource(filenum, rock) };
-        StrId{ ptr }
+        let resource = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum));
+        self.streams.open_resource(resource, rock, false)
     }
 
     fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_resource_uni(filenum, rock) };
-        StrId{ ptr }
+        let resource = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum));
+        self.streams.open_resource(resource, rock, true)
     }
 
 
     fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
-        IOStream(str.ptr)
+        self.streams.io_stream(str)
     }
-}
 
-#[derive(Clone,Eq,Hash,PartialEq)]
-pub struct WinId {
-    ptr: c_interface::winid_t,
-}
 
-impl IdType for WinId {
-    fn null() -> Self {
-        WinId{ ptr: std::ptr::null() }
-    }
-
-    fn is_null(&self) -> bool {
-        self.ptr.is_null()
+    fn set_resource_map(&mut self, blorb: Blorb) {
+        self.resources = Some(blorb);
     }
 }
 
 #[derive(Clone,Eq,Hash,PartialEq)]
-pub struct StrId {
-    ptr: c_interface::strid_t,
-}
-
-impl IdType for StrId {
-    fn null() -> Self {
-        StrId{ ptr: std::ptr::null() }
-    }
-
-    fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
-}
-
-pub struct IOStream(c_interface::strid_t);
-
-impl Read for IOStream {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        if self.0.is_null() {
-            return Err(Error::new(ErrorKind::NotConnected, "null stream"))
-        }
-        let count = unsafe {
-            c_interface::glk_get_buffer_stream(self.0, buf.as_mut_ptr() as *mut c_char, buf.len() as u32)
-        };
-        Ok(count as usize)
-    }
-}
-
-impl Write for IOStream {
-    fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        if self.0.is_null() {
-            return Err(Error::new(ErrorKind::NotConnected, "null stream"))
-        }
-        unsafe {
-            c_interface::glk_put_buffer_stream(self.0, buf.as_ptr() as *const c_char, buf.len() as u32);
-        }
-        Ok(buf.len())
-    }
-
-    fn flush(&mut self) -> Result<()> {
-        Ok(())
-    }
-}
-
-impl Seek for IOStream {
-    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
-        let (p,seekmode) = match pos {
-            SeekFrom::Start(p) => (p as i32,glk::seekmode_Start),
-            SeekFrom::End(p) => (p as i32,glk::seekmode_End),
-            SeekFrom::Current(p) => (p as i32,glk::seekmode_Current),
-        };
-        unsafe {
-            c_interface::glk_stream_set_position(self.0, p, seekmode);
-            Ok(c_interface::glk_stream_get_position(self.0) as u64)
-        }
-    }
-}
-
-#[derive(Clone,Eq,Hash,PartialEq)]
-pub struct FRefId {
-    ptr: c_interface::frefid_t,
-}
-
-impl IdType for FRefId {
-    fn null() -> Self {
-        FRefId{ ptr: std::ptr::null() }
-    }
-
-    fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
-}
-
-#[derive(Clone,Eq,Hash,PartialEq)]
-pub struct SChanId {
-    ptr: c_interface::schanid_t,
-}
+pub struct SChanId(());
 
 impl IdType for SChanId {
     fn null() -> Self {
-        SChanId{ ptr: std::ptr::null() }
+        SChanId(())
     }
 
     fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
-}
-
-pub struct Event(c_interface::event_t);
-
-impl EventType<WinId> for Event {
-    fn evtype(&self) -> u32 {
-        self.0.evtype
-    }
-
-    fn win(&self) -> WinId {
-        WinId { ptr: self.0.win }
-    }
-
-    fn val1(&self) -> u32 {
-        self.0.val1
-    }
-
-    fn val2(&self) -> u32 {
-        self.0.val2
-    }
-
-    fn buf(&mut self) -> Option<(u32,Box<[u8]>)> {
-        array_registry::retrieve_line_event()
-    }
-
-    fn buf_uni(&mut self) -> Option<(u32,Box<[u32]>)> {
-        array_registry::retrieve_line_event_uni()
-    }
-}
-
-pub struct TimeVal(c_interface::glktimeval_t);
-
-impl TimeValType for TimeVal {
-    fn new(high_sec: i32, low_sec: u32, microsec: i32) -> Self {
-        TimeVal(c_interface::glktimeval_t{ high_sec, low_sec, microsec })
-    }
-
-    fn high_sec(&self) -> i32 {
-        self.0.high_sec
-    }
-
-    fn low_sec(&self) -> u32 {
-        self.0.low_sec
-    }
-
-    fn microsec(&self) -> i32 {
-        self.0.microsec
-    }
-}
-
-pub struct Date(c_interface::glkdate_t);
-
-impl DateType for Date {
-    fn new(year: i32, month: i32, day: i32, weekday: i32, hour: i32, minute: i32, second: i32, microsec: i32) -> Self {
-        Date(c_interface::glkdate_t{ year, month, day, weekday, hour, minute, second, microsec })
-    }
-
-    fn year(&self) -> i32 {
-        self.0.year
-    }
-
-    fn month(&self) -> i32 {
-        self.0.month
-    }
-
-    fn day(&self) -> i32 {
-        self.0.day
-    }
-
-    fn weekday(&self) -> i32 {
-        self.0.weekday
-    }
-
-    fn hour(&self) -> i32 {
-        self.0.hour
-    }
-
-    fn minute(&self) -> i32 {
-        self.0.minute
-    }
-
-    fn second(&self) -> i32 {
-        self.0.second
-    }
-
-    fn microsec(&self) -> i32 {
-        self.0.microsec
+        true
     }
 }
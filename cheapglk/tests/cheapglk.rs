@@ -0,0 +1,124 @@
+extern crate cheapglk;
+extern crate glk;
+
+use std::io::{Cursor,Result,Write};
+use std::sync::{Arc,Mutex};
+use std::thread;
+
+use cheapglk::CheapGlk;
+use glk::{Glk,IdType};
+use glk::streams::Encoding;
+
+#[derive(Clone)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn new(input: &[u8], encoding: Encoding) -> (CheapGlk,Output) {
+    let output = Output(Arc::new(Mutex::new(Vec::new())));
+    let mut glk = CheapGlk::new(Box::new(Cursor::new(input.to_vec())), Box::new(output.clone()));
+    glk.set_encoding(encoding);
+    (glk,output)
+}
+
+#[test]
+fn latin1() {
+    let (mut glk,output) = new(b"look\n\xe9\n", Encoding::Latin1);
+    let win = glk.window_open(&glk::windows::WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+    assert!(!win.is_null());
+    assert!(glk.window_open(&win, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 0).is_null());
+    glk.set_window(&win);
+    glk.put_string_uni(&[0x68, 0xe9, 0x2022, 0x0a][..]);
+
+    let mut echo = glk.stream_open_memory((0,vec![0; 8].into_boxed_slice()), glk::filemode_Write, 0);
+    glk.window_set_echo_stream(&win, &echo);
+    glk.request_line_event(&win, (0,vec![0; 3].into_boxed_slice()), 0);
+    let event = glk.select();
+    assert_eq!(glk::evtype_LineInput, event.evtype);
+    assert_eq!(3, event.val1);
+    assert_eq!(b"loo", &event.buf.unwrap().1[..]);
+    let (_,writecount,buf,_) = glk.stream_close(&mut echo);
+    assert_eq!(4, writecount);
+    assert_eq!(b"loo\n", &buf.unwrap().1[..4]);
+
+    glk.request_char_event(&win);
+    let event = glk.select();
+    assert_eq!(glk::evtype_CharInput, event.evtype);
+    assert_eq!(0xe9, event.val1);
+
+    assert_eq!(b"h\xe9?\n", &output.0.lock().unwrap()[..]);
+}
+
+#[test]
+fn utf8() {
+    let (mut glk,output) = new("\u{2022}\n\n".as_bytes(), Encoding::Utf8);
+    let win = glk.window_open(&glk::windows::WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+    let str = glk.window_get_stream(&win);
+    glk.put_string_stream_uni(&str, &[0x68, 0xe9, 0x2022, 0x0a][..]);
+
+    glk.request_char_event(&win);
+    assert_eq!(glk::keycode_Unknown, glk.select().val1);
+    glk.request_char_event_uni(&win);
+    assert_eq!(glk::keycode_Return, glk.select().val1);
+
+    assert_eq!("h\u{e9}\u{2022}\n".as_bytes(), &output.0.lock().unwrap()[..]);
+}
+
+#[test]
+fn echo_input() {
+    let (mut glk,output) = new(b"look\nx", Encoding::Latin1);
+    glk.set_echo_input(true);
+    let win = glk.window_open(&glk::windows::WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    glk.put_string(">");
+    glk.request_line_event(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+    assert_eq!(4, glk.select().val1);
+    glk.put_string(">");
+    glk.request_char_event(&win);
+    assert_eq!(0x78, glk.select().val1);
+
+    assert_eq!(b">look\n>x\n", &output.0.lock().unwrap()[..]);
+}
+
+// Writes the data to a temporary file, and reads it back.
+fn temp_file(mut glk: CheapGlk, data: Vec<u8>) -> Vec<u8> {
+    let fref = glk.fileref_create_temp(glk::fileusage_Data | glk::fileusage_BinaryMode, 0);
+    assert!(!fref.is_null());
+    let mut str = glk.stream_open_file(&fref, glk::filemode_Write, 0);
+    for _ in 0 .. 100 {
+        glk.put_buffer_stream(&str, &data);
+    }
+    glk.stream_close(&mut str);
+    let mut str = glk.stream_open_file(&fref, glk::filemode_Read, 0);
+    let mut buf = vec![0; 100*data.len() + 1];
+    let len = glk.get_buffer_stream(&str, &mut buf);
+    glk.stream_close(&mut str);
+    glk.fileref_delete_file(&fref);
+    buf.truncate(len as usize);
+    buf
+}
+
+// CheapGlks moved to threads of their own each get their own temporary
+// files.
+#[test]
+fn temp_files() {
+    let threads: Vec<thread::JoinHandle<(Vec<u8>,Vec<u8>)>> = (0 .. 8u8).map(|i| {
+        let (glk,_) = new(b"", Encoding::Latin1);
+        thread::spawn(move || {
+            let data = vec![b'a' + i; 1000];
+            (temp_file(glk, data.clone()),data.repeat(100))
+        })
+    }).collect();
+    for thread in threads {
+        let (read,written) = thread.join().unwrap();
+        assert_eq!(written, read);
+    }
+}
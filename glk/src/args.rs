@@ -0,0 +1,152 @@
+// Command line arguments in the style of glkunix_arguments, shared by
+// the implementations that start the program themselves.
+
+pub enum Argument {
+    ValueFollows(String,String),
+    NoValue(String,String),
+    ValueCanFollow(String,String),
+    NumberValue(String,String),
+}
+
+impl Argument {
+    // An empty name matches an argument that isn't an option.
+    pub fn name(&self) -> &str {
+        match self {
+            &Argument::ValueFollows(ref name,_) => name,
+            &Argument::NoValue(ref name,_) => name,
+            &Argument::ValueCanFollow(ref name,_) => name,
+            &Argument::NumberValue(ref name,_) => name,
+        }
+    }
+
+    pub fn desc(&self) -> &str {
+        match self {
+            &Argument::ValueFollows(_,ref desc) => desc,
+            &Argument::NoValue(_,ref desc) => desc,
+            &Argument::ValueCanFollow(_,ref desc) => desc,
+            &Argument::NumberValue(_,ref desc) => desc,
+        }
+    }
+}
+
+// Splits args into the program's arguments, which start with the program
+// name, and the library's options with their values.  Returns None if
+// args don't match.
+pub fn parse(arguments: &[Argument], library: &[Argument], args: &[String]) -> Option<(Vec<String>,Vec<(String,Option<String>)>)> {
+    let mut program_args = vec![args.get(0).cloned().unwrap_or(String::new())];
+    let mut library_args = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+        i += 1;
+        let name = if arg.starts_with("-") { arg.as_str() } else { "" };
+        if let Some(argument) = library.iter().find(|a| a.name() == name && name != "") {
+            let value = take_value(argument, args, &mut i)?;
+            library_args.push((arg.clone(),value));
+            continue;
+        }
+        let argument = arguments.iter().find(|a| a.name() == name)?;
+        program_args.push(arg.clone());
+        if name != "" {
+            if let Some(value) = take_value(argument, args, &mut i)? {
+                program_args.push(value);
+            }
+        }
+    }
+    Some((program_args,library_args))
+}
+
+fn take_value(argument: &Argument, args: &[String], i: &mut usize) -> Option<Option<String>> {
+    let value = match argument {
+        &Argument::NoValue(_,_) => return Some(None),
+        &Argument::ValueFollows(_,_) => args.get(*i)?.clone(),
+        &Argument::ValueCanFollow(_,_) => {
+            match args.get(*i) {
+                Some(value) if !value.starts_with("-") => value.clone(),
+                _ => return Some(None),
+            }
+        },
+        &Argument::NumberValue(_,_) => {
+            let value = args.get(*i)?;
+            value.parse::<i64>().ok()?;
+            value.clone()
+        },
+    };
+    *i += 1;
+    Some(Some(value))
+}
+
+pub fn usage(program: &str, arguments: &[Argument], library: &[Argument]) -> String {
+    let mut usage = format!("usage: {} [ options ... ]", program);
+    for argument in arguments {
+        if argument.name() == "" {
+            usage.push_str(" ");
+            usage.push_str(argument.desc());
+        }
+    }
+    usage.push_str("\n");
+    for &(title,list) in [("library options:",library),("options:",arguments)].iter() {
+        if list.iter().all(|a| a.name() == "") {
+            continue;
+        }
+        usage.push_str(title);
+        usage.push_str("\n");
+        for argument in list.iter().filter(|a| a.name() != "") {
+            let value = match argument {
+                &Argument::NoValue(_,_) => "",
+                &Argument::ValueFollows(_,_) => " val",
+                &Argument::ValueCanFollow(_,_) => " [val]",
+                &Argument::NumberValue(_,_) => " NUM",
+            };
+            usage.push_str(&format!("  {}{}: {}\n", argument.name(), value, argument.desc()));
+        }
+    }
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse,Argument};
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn arguments() -> Vec<Argument> {
+        vec![
+            Argument::ValueFollows("".to_string(), "FILE".to_string()),
+            Argument::ValueFollows("-o".to_string(), "output".to_string()),
+            Argument::NoValue("-q".to_string(), "quiet".to_string()),
+            Argument::ValueCanFollow("-v".to_string(), "verbosity".to_string()),
+            Argument::NumberValue("-n".to_string(), "count".to_string()),
+        ]
+    }
+
+    #[test]
+    fn program_and_library() {
+        let library = vec![Argument::NumberValue("-width".to_string(), "width".to_string())];
+        let (program,library) = parse(&arguments(), &library, &strings(&["prog", "-q", "-width", "80", "-o", "out", "-v", "-n", "-3", "story"])).unwrap();
+        assert_eq!(strings(&["prog", "-q", "-o", "out", "-v", "-n", "-3", "story"]), program);
+        assert_eq!(vec![("-width".to_string(),Some("80".to_string()))], library);
+        let (program,_) = parse(&arguments(), &[], &strings(&["prog", "-v", "2"])).unwrap();
+        assert_eq!(strings(&["prog", "-v", "2"]), program);
+    }
+
+    #[test]
+    fn unknown_options() {
+        assert!(parse(&arguments(), &[], &strings(&["prog", "-x"])).is_none());
+        assert!(parse(&[], &[], &strings(&["prog", "story"])).is_none());
+        assert!(parse(&arguments(), &[], &strings(&["prog", "-o"])).is_none());
+        assert!(parse(&arguments(), &[], &strings(&["prog", "-n", "many"])).is_none());
+    }
+
+    // glkunix_arguments was limited to 10 arguments.
+    #[test]
+    fn more_than_ten() {
+        let arguments: Vec<Argument> = (0..12).map(|i| Argument::NoValue(format!("-{}", i), String::new())).collect();
+        let args: Vec<String> = (0..13).map(|i| if i == 0 { "prog".to_string() } else { format!("-{}", 12-i) }).collect();
+        let (program,library) = parse(&arguments, &[], &args).unwrap();
+        assert_eq!(args, program);
+        assert!(library.is_empty());
+    }
+}
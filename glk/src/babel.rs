@@ -0,0 +1,171 @@
+// Identifying stories as the Treaty of Babel says, by their IFIDs, and
+// describing them with iFiction records.
+
+use super::blorb::Blorb;
+
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Format {
+    Glulx,
+    ZCode,
+}
+
+impl Format {
+    // The name in the Treaty and in iFiction records.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Format::Glulx => "glulx",
+            Format::ZCode => "zcode",
+        }
+    }
+}
+
+// The format of the story, or of the story in a blorb.
+pub fn format(story: &[u8]) -> Option<Format> {
+    if story.starts_with(b"FORM") {
+        let blorb = Blorb::new(story.to_vec()).ok()?;
+        return match &blorb.exec()?.chunk_type {
+            b"GLUL" => Some(Format::Glulx),
+            b"ZCOD" => Some(Format::ZCode),
+            _ => None,
+        };
+    }
+    if story.starts_with(b"Glul") {
+        Some(Format::Glulx)
+    } else if story.len() >= 64 && story[0] >= 1 && story[0] <= 8 {
+        Some(Format::ZCode)
+    } else {
+        None
+    }
+}
+
+// The story's IFID: from the blorb's metadata, from a UUID://...// in the
+// story, or else made from its header.
+pub fn ifid(story: &[u8]) -> Option<String> {
+    if story.starts_with(b"FORM") {
+        let blorb = Blorb::new(story.to_vec()).ok()?;
+        if let Some(ifid) = blorb.metadata().ifid {
+            return Some(ifid);
+        }
+        let exec = blorb.exec()?;
+        return match &exec.chunk_type {
+            b"GLUL" => glulx_ifid(exec.data),
+            b"ZCOD" => zcode_ifid(exec.data),
+            _ => None,
+        };
+    }
+    match format(story)? {
+        Format::Glulx => glulx_ifid(story),
+        Format::ZCode => zcode_ifid(story),
+    }
+}
+
+fn uuid(story: &[u8]) -> Option<String> {
+    let index = story.windows(7).position(|window| window == b"UUID://")?;
+    let uuid = &story[index+7 ..];
+    if uuid.len() < 38 || &uuid[36 .. 38] != b"//" {
+        return None;
+    }
+    ::std::str::from_utf8(&uuid[.. 36]).ok().map(|uuid| uuid.to_uppercase())
+}
+
+// GLULX-RELEASE-SERIAL-CHECKSUM for stories with Inform's header
+// extension, or else GLULX-CHECKSUM.
+fn glulx_ifid(story: &[u8]) -> Option<String> {
+    if let Some(uuid) = uuid(story) {
+        return Some(uuid);
+    }
+    if story.len() < 36 || &story[.. 4] != b"Glul" {
+        return None;
+    }
+    let checksum = read_u32(&story[32 ..]);
+    match glulx_release(story) {
+        Some((release,serial)) => Some(format!("GLULX-{}-{}-{:08X}", release, serial, checksum)),
+        None => Some(format!("GLULX-{:08X}", checksum)),
+    }
+}
+
+fn glulx_release(story: &[u8]) -> Option<(u16,String)> {
+    if story.len() < 0x3c || &story[0x24 .. 0x28] != b"Info" {
+        return None;
+    }
+    Some(((story[0x34] as u16) << 8 | story[0x35] as u16,serial(&story[0x36 .. 0x3c])))
+}
+
+// ZCODE-RELEASE-SERIAL-CHECKSUM.  Stories from before headers had
+// checksums have serials that aren't dates, or are dates in the 1980s,
+// and leave the checksum out.
+fn zcode_ifid(story: &[u8]) -> Option<String> {
+    if let Some(uuid) = uuid(story) {
+        return Some(uuid);
+    }
+    if story.len() < 64 {
+        return None;
+    }
+    let release = (story[2] as u16) << 8 | story[3] as u16;
+    let serial = serial(&story[0x12 .. 0x18]);
+    let checksum = (story[0x1c] as u16) << 8 | story[0x1d] as u16;
+    if serial == "000000" || serial.starts_with('8') || !serial.bytes().all(|b| b.is_ascii_digit()) {
+        Some(format!("ZCODE-{}-{}", release, serial))
+    } else {
+        Some(format!("ZCODE-{}-{}-{:04X}", release, serial, checksum))
+    }
+}
+
+// Serial numbers are dates, and other characters become dashes.
+fn serial(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| if b.is_ascii_alphanumeric() { b as char } else { '-' }).collect()
+}
+
+// The blorb's iFiction record, or else a record with the story's IFID,
+// format and what the header says of its release.
+pub fn ifiction(story: &[u8]) -> Option<String> {
+    if story.starts_with(b"FORM") {
+        let blorb = Blorb::new(story.to_vec()).ok()?;
+        if let Some(ifiction) = blorb.ifiction() {
+            return Some(ifiction);
+        }
+        let exec = blorb.exec()?;
+        return ifiction_record(exec.data, ifid(story)?);
+    }
+    ifiction_record(story, ifid(story)?)
+}
+
+fn ifiction_record(story: &[u8], ifid: String) -> Option<String> {
+    let format = self::format(story)?;
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<ifindex version=\"1.0\" xmlns=\"http://babel.ifarchive.org/protocol/iFiction/\">\n");
+    xml.push_str("  <story>\n");
+    xml.push_str("    <identification>\n");
+    xml.push_str(&format!("      <ifid>{}</ifid>\n", ifid));
+    xml.push_str(&format!("      <format>{}</format>\n", format.name()));
+    xml.push_str("    </identification>\n");
+    xml.push_str("    <bibliographic>\n");
+    xml.push_str("      <title>An Interactive Fiction</title>\n");
+    xml.push_str("      <author>Anonymous</author>\n");
+    xml.push_str("    </bibliographic>\n");
+    match format {
+        Format::Glulx => {
+            if let Some((release,serial)) = glulx_release(story) {
+                xml.push_str("    <glulx>\n");
+                xml.push_str(&format!("      <serial>{}</serial>\n", serial));
+                xml.push_str(&format!("      <release>{}</release>\n", release));
+                xml.push_str("    </glulx>\n");
+            }
+        },
+        Format::ZCode => {
+            xml.push_str("    <zcode>\n");
+            xml.push_str(&format!("      <serial>{}</serial>\n", serial(&story[0x12 .. 0x18])));
+            xml.push_str(&format!("      <release>{}</release>\n", (story[2] as u16) << 8 | story[3] as u16));
+            xml.push_str(&format!("      <checksum>{:04X}</checksum>\n", (story[0x1c] as u16) << 8 | story[0x1d] as u16));
+            xml.push_str("    </zcode>\n");
+        },
+    }
+    xml.push_str("  </story>\n");
+    xml.push_str("</ifindex>\n");
+    Some(xml)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8 | bytes[3] as u32
+}
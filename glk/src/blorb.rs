@@ -0,0 +1,300 @@
+use std::io;
+use std::io::{Error,ErrorKind,Read};
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Usage {
+    Pict,
+    Snd,
+    Data,
+    Exec,
+}
+
+impl Usage {
+    fn from_id(id: &[u8]) -> Option<Self> {
+        match id {
+            b"Pict" => Some(Usage::Pict),
+            b"Snd " => Some(Usage::Snd),
+            b"Data" => Some(Usage::Data),
+            b"Exec" => Some(Usage::Exec),
+            _ => None,
+        }
+    }
+}
+
+pub struct Resource<'a> {
+    pub usage: Usage,
+    pub number: u32,
+    pub chunk_type: [u8;4],
+    pub data: &'a [u8],
+}
+
+#[derive(Clone,Debug,Default,Eq,PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub headline: Option<String>,
+    pub release: Option<u32>,
+    pub ifid: Option<String>,
+    pub frontispiece: Option<u32>,
+}
+
+struct Chunk {
+    id: [u8;4],
+    offset: usize,
+    start: usize,
+    len: usize,
+}
+
+struct IndexEntry {
+    usage: Usage,
+    number: u32,
+    chunk: usize,
+}
+
+pub struct Blorb {
+    data: Box<[u8]>,
+    chunks: Vec<Chunk>,
+    index: Vec<IndexEntry>,
+}
+
+impl Blorb {
+    pub fn new(data: Vec<u8>) -> io::Result<Self> {
+        if data.len() < 12 || &data[0..4] != b"FORM" || &data[8..12] != b"IFRS" {
+            return Err(invalid_data("not a blorb file"));
+        }
+        let form_end = 8 + read_u32(&data, 4) as usize;
+        if form_end > data.len() {
+            return Err(invalid_data("truncated blorb file"));
+        }
+
+        let mut chunks = Vec::new();
+        let mut offset = 12;
+        while offset + 8 <= form_end {
+            let start = offset + 8;
+            let len = read_u32(&data, offset + 4) as usize;
+            if start + len > form_end {
+                return Err(invalid_data("truncated blorb chunk"));
+            }
+            let mut id = [0; 4];
+            id.copy_from_slice(&data[offset .. offset + 4]);
+            chunks.push(Chunk{ id, offset, start, len });
+            offset = start + len + (len & 1);
+        }
+
+        let mut index = Vec::new();
+        {
+            let ridx = match chunks.first() {
+                Some(chunk) if &chunk.id == b"RIdx" => &data[chunk.start .. chunk.start + chunk.len],
+                _ => return Err(invalid_data("missing RIdx chunk")),
+            };
+            if ridx.len() < 4 || ridx.len() < 4 + 12*read_u32(ridx, 0) as usize {
+                return Err(invalid_data("invalid RIdx chunk"));
+            }
+            for i in 0 .. read_u32(ridx, 0) as usize {
+                let entry = &ridx[4 + 12*i .. 16 + 12*i];
+                let usage = match Usage::from_id(&entry[0..4]) {
+                    Some(usage) => usage,
+                    None => continue,
+                };
+                let number = read_u32(entry, 4);
+                let start = read_u32(entry, 8) as usize;
+                match chunks.iter().position(|chunk| chunk.offset == start) {
+                    Some(chunk) => index.push(IndexEntry{ usage, number, chunk }),
+                    None => return Err(invalid_data("RIdx entry does not start a chunk")),
+                }
+            }
+        }
+
+        Ok(Blorb{ data: data.into_boxed_slice(), chunks, index })
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+        Blorb::new(data)
+    }
+
+    pub fn resource(&self, usage: Usage, number: u32) -> Option<Resource> {
+        for entry in &self.index {
+            if entry.usage == usage && entry.number == number {
+                return Some(self.make_resource(entry));
+            }
+        }
+        None
+    }
+
+    pub fn resources(&self, usage: Usage) -> Vec<Resource> {
+        self.index.iter().filter(|entry| entry.usage == usage).map(|entry| self.make_resource(entry)).collect()
+    }
+
+    // The game file, which is conventionally Exec resource 0.
+    pub fn exec(&self) -> Option<Resource> {
+        self.resource(Usage::Exec, 0).or_else(|| self.resources(Usage::Exec).into_iter().next())
+    }
+
+    // The first chunk of the given type, for chunks that are not
+    // resources, such as IFmd or RelN.
+    pub fn chunk(&self, id: &[u8;4]) -> Option<&[u8]> {
+        for chunk in &self.chunks {
+            if &chunk.id == id {
+                return Some(&self.data[chunk.start .. chunk.start + chunk.len]);
+            }
+        }
+        None
+    }
+
+    // The iFiction XML record.
+    pub fn ifiction(&self) -> Option<String> {
+        self.chunk(b"IFmd").map(|data| String::from_utf8_lossy(data).into_owned())
+    }
+
+    // The Pict resource number of the cover art.
+    pub fn frontispiece(&self) -> Option<u32> {
+        match self.chunk(b"Fspc") {
+            Some(data) if data.len() >= 4 => Some(read_u32(data, 0)),
+            _ => None,
+        }
+    }
+
+    // The release number from RelN, or else from the IFhd of a Z-code
+    // game.
+    pub fn release(&self) -> Option<u32> {
+        match (self.chunk(b"RelN"),self.chunk(b"IFhd")) {
+            (Some(data),_) if data.len() >= 2 => Some((data[0] as u32) << 8 | data[1] as u32),
+            (_,Some(data)) if data.len() >= 2 => Some((data[0] as u32) << 8 | data[1] as u32),
+            _ => None,
+        }
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        let ifiction = self.ifiction().unwrap_or(String::new());
+        Metadata{
+            title: xml_text(&ifiction, "title"),
+            author: xml_text(&ifiction, "author"),
+            headline: xml_text(&ifiction, "headline"),
+            release: self.release(),
+            ifid: xml_text(&ifiction, "ifid"),
+            frontispiece: self.frontispiece(),
+        }
+    }
+
+    // The width and height of a PNG or JPEG Pict resource.
+    pub fn image_size(&self, number: u32) -> Option<(u32,u32)> {
+        let pict = self.resource(Usage::Pict, number)?;
+        match &pict.chunk_type {
+            b"PNG " => png_size(pict.data),
+            b"JPEG" => jpeg_size(pict.data),
+            _ => None,
+        }
+    }
+
+    fn make_resource(&self, entry: &IndexEntry) -> Resource {
+        let chunk = &self.chunks[entry.chunk];
+        Resource{
+            usage: entry.usage,
+            number: entry.number,
+            chunk_type: chunk.id,
+            data: &self.data[chunk.start .. chunk.start + chunk.len],
+        }
+    }
+}
+
+fn png_size(data: &[u8]) -> Option<(u32,u32)> {
+    if data.len() < 24 || &data[0..8] != b"\x89PNG\r\n\x1a\n" || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    Some((read_u32(data, 16),read_u32(data, 20)))
+}
+
+fn jpeg_size(data: &[u8]) -> Option<(u32,u32)> {
+    if data.len() < 4 || data[0] != 0xff || data[1] != 0xd8 {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xff {
+            return None;
+        }
+        let marker = data[i+1];
+        if marker == 0xff {
+            i += 1;
+            continue;
+        }
+        if marker == 0xd8 || (marker >= 0xd0 && marker <= 0xd7) {
+            i += 2;
+            continue;
+        }
+        let len = (data[i+2] as usize) << 8 | data[i+3] as usize;
+        match marker {
+            0xc0 ..= 0xc3 | 0xc5 ..= 0xc7 | 0xc9 ..= 0xcb | 0xcd ..= 0xcf => {
+                if i + 9 > data.len() {
+                    return None;
+                }
+                let height = (data[i+5] as u32) << 8 | data[i+6] as u32;
+                let width = (data[i+7] as u32) << 8 | data[i+8] as u32;
+                return Some((width,height));
+            },
+            _ => i += 2 + len,
+        }
+    }
+    None
+}
+
+// The contents of the first element with the given tag, which is enough
+// for the flat, well-known structure of iFiction records.  The opening
+// tag may have attributes.
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut start = 0;
+    let start = loop {
+        start += xml[start..].find(&open)? + open.len();
+        let end = start + xml[start..].find('>')?;
+        match xml[start..].chars().next() {
+            Some('>') => break end + 1,
+            Some(ch) if ch.is_whitespace() && !xml[.. end].ends_with('/') => break end + 1,
+            _ => start = end,
+        }
+    };
+    let len = xml[start..].find(&close)?;
+    let mut text = String::new();
+    let mut rest = xml[start .. start + len].trim();
+    while let Some(i) = rest.find('&') {
+        text.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let ch = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(::std::char::from_u32),
+            entity if entity.starts_with('#') => entity[1..].parse().ok().and_then(::std::char::from_u32),
+            _ => None,
+        };
+        match ch {
+            Some(ch) => {
+                text.push(ch);
+                rest = &rest[end+1..];
+            },
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    text.push_str(rest);
+    Some(text)
+}
+
+fn read_u32(data: &[u8], addr: usize) -> u32 {
+    (data[addr] as u32) << 24 | (data[addr+1] as u32) << 16 | (data[addr+2] as u32) << 8 | data[addr+3] as u32
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
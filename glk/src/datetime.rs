@@ -0,0 +1,176 @@
+use std::time::{SystemTime,UNIX_EPOCH};
+
+use super::{DateType,TimeValType};
+
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub struct TimeVal {
+    pub high_sec: i32,
+    pub low_sec: u32,
+    pub microsec: i32,
+}
+
+impl TimeVal {
+    pub fn from_secs(secs: i64, microsec: i32) -> Self {
+        TimeVal{ high_sec: (secs >> 32) as i32, low_sec: secs as u32, microsec }
+    }
+
+    pub fn secs(&self) -> i64 {
+        (self.high_sec as i64) << 32 | self.low_sec as i64
+    }
+
+    pub fn now() -> Self {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => TimeVal::from_secs(d.as_secs() as i64, d.subsec_nanos() as i32 / 1000),
+            Err(e) => {
+                let d = e.duration();
+                let micros = d.subsec_nanos() as i32 / 1000;
+                if micros == 0 {
+                    TimeVal::from_secs(-(d.as_secs() as i64), 0)
+                } else {
+                    TimeVal::from_secs(-(d.as_secs() as i64) - 1, 1000000 - micros)
+                }
+            },
+        }
+    }
+
+    // The time divided by factor seconds, rounded down.
+    pub fn to_simple(&self, factor: u32) -> i32 {
+        if factor == 0 {
+            return 0;
+        }
+        floor_div(self.secs(), factor as i64) as i32
+    }
+
+    pub fn from_simple(time: i32, factor: u32) -> Self {
+        TimeVal::from_secs(time as i64 * factor as i64, 0)
+    }
+}
+
+impl TimeValType for TimeVal {
+    fn new(high_sec: i32, low_sec: u32, microsec: i32) -> Self {
+        TimeVal{ high_sec, low_sec, microsec }
+    }
+
+    fn high_sec(&self) -> i32 {
+        self.high_sec
+    }
+
+    fn low_sec(&self) -> u32 {
+        self.low_sec
+    }
+
+    fn microsec(&self) -> i32 {
+        self.microsec
+    }
+}
+
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub struct Date {
+    pub year: i32,
+    pub month: i32,
+    pub day: i32,
+    pub weekday: i32,
+    pub hour: i32,
+    pub minute: i32,
+    pub second: i32,
+    pub microsec: i32,
+}
+
+impl Date {
+    // The date offset seconds east of UTC.
+    pub fn from_time(time: &TimeVal, offset: i64) -> Self {
+        let secs = time.secs() + offset;
+        let days = floor_div(secs, 86400);
+        let rem = secs - days * 86400;
+        let (year,month,day) = civil_from_days(days);
+        Date{
+            year: year as i32,
+            month: month as i32,
+            day: day as i32,
+            weekday: (days + 4).rem_euclid(7) as i32,
+            hour: (rem / 3600) as i32,
+            minute: (rem / 60 % 60) as i32,
+            second: (rem % 60) as i32,
+            microsec: time.microsec,
+        }
+    }
+
+    // Out of range fields are normalized, so that, for example, the
+    // 32nd of January is the 1st of February.  The weekday is ignored.
+    pub fn to_time(&self, offset: i64) -> TimeVal {
+        let months = self.year as i64 * 12 + self.month as i64 - 1;
+        let year = floor_div(months, 12);
+        let month = months - year * 12 + 1;
+        let days = days_from_civil(year, month, 1) + self.day as i64 - 1;
+        let micros = self.microsec as i64;
+        let secs = days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64 + floor_div(micros, 1000000) - offset;
+        TimeVal::from_secs(secs, (micros - floor_div(micros, 1000000) * 1000000) as i32)
+    }
+}
+
+impl DateType for Date {
+    fn new(year: i32, month: i32, day: i32, weekday: i32, hour: i32, minute: i32, second: i32, microsec: i32) -> Self {
+        Date{ year, month, day, weekday, hour, minute, second, microsec }
+    }
+
+    fn year(&self) -> i32 {
+        self.year
+    }
+
+    fn month(&self) -> i32 {
+        self.month
+    }
+
+    fn day(&self) -> i32 {
+        self.day
+    }
+
+    fn weekday(&self) -> i32 {
+        self.weekday
+    }
+
+    fn hour(&self) -> i32 {
+        self.hour
+    }
+
+    fn minute(&self) -> i32 {
+        self.minute
+    }
+
+    fn second(&self) -> i32 {
+        self.second
+    }
+
+    fn microsec(&self) -> i32 {
+        self.microsec
+    }
+}
+
+fn floor_div(a: i64, b: i64) -> i64 {
+    let q = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) { q - 1 } else { q }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = floor_div(y, 400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64,i64,i64) {
+    let z = days + 719468;
+    let era = floor_div(z, 146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year,month,day)
+}
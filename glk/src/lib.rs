@@ -2,6 +2,19 @@
 
 use std::io::{Read,Seek,Write};
 
+pub mod args;
+pub mod babel;
+pub mod blorb;
+pub mod datetime;
+pub mod record;
+pub mod session;
+pub mod streams;
+pub mod transcript;
+pub mod typed;
+pub mod unicode;
+mod unicode_tables;
+pub mod windows;
+
 pub trait Glk<'a> {
     type WinId: 'a+IdType;
     type StrId: 'a+IdType;
@@ -130,6 +143,8 @@ pub trait Glk<'a> {
     fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32);
     fn window_set_background_color(&mut self, win: &Self::WinId, color: u32);
 
+    fn image_draw_scaled_ext(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32, imagerule: u32, maxwidth: u32) -> bool;
+
     fn schannel_create(&mut self, rock: u32) -> Self::SChanId;
     fn schannel_destroy(&mut self, chan: &mut Self::SChanId);
     fn schannel_iterate(&mut self, chan: &Self::SChanId) -> (Self::SChanId,u32);
@@ -168,6 +183,35 @@ pub trait Glk<'a> {
     fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId;
 
     fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream;
+
+    // Resources for stream_open_resource, image_get_info, schannel_play,
+    // etc.  Implementations that load resources by other means can
+    // ignore this.
+    fn set_resource_map(&mut self, _blorb: blorb::Blorb) {
+    }
+
+    // For implementations that can show it in a window title.
+    fn set_story_title(&mut self, _title: &str) {
+    }
+
+    // The address of the VM instruction making the calls that follow,
+    // for implementations that record them.
+    fn set_call_address(&mut self, _addr: u32) {
+    }
+
+    // Gargoyle extensions.  Implementations that support them should
+    // return 1 for gestalt_GarglkText.
+    fn garglk_set_zcolors(&mut self, _fg: u32, _bg: u32) {
+    }
+
+    fn garglk_set_zcolors_stream(&mut self, _str: &Self::StrId, _fg: u32, _bg: u32) {
+    }
+
+    fn garglk_set_reversevideo(&mut self, _reverse: u32) {
+    }
+
+    fn garglk_set_reversevideo_stream(&mut self, _str: &Self::StrId, _reverse: u32) {
+    }
 }
 
 pub trait IdType: Clone + Eq + std::hash::Hash {
@@ -230,6 +274,8 @@ pub const gestalt_DateTime: u32 = 20;
 pub const gestalt_Sound2: u32 = 21;
 pub const gestalt_ResourceStream: u32 = 22;
 pub const gestalt_GraphicsCharInput: u32 = 23;
+pub const gestalt_DrawImageScale: u32 = 24;
+pub const gestalt_GarglkText: u32 = 0x1100;
 
 pub const evtype_None: u32 = 0;
 pub const evtype_Timer: u32 = 1;
@@ -282,6 +328,11 @@ pub const style_User1: u32 = 9;
 pub const style_User2: u32 = 10;
 pub const style_NUMSTYLES: u32 = 11;
 
+pub const zcolor_Transparent: u32 = 0xfffffffc;
+pub const zcolor_Cursor: u32 = 0xfffffffd;
+pub const zcolor_Current: u32 = 0xfffffffe;
+pub const zcolor_Default: u32 = 0xffffffff;
+
 pub const wintype_AllTypes: u32 = 0;
 pub const wintype_Pair: u32 = 1;
 pub const wintype_Blank: u32 = 2;
@@ -337,3 +388,18 @@ pub const stylehint_just_LeftFlush: u32 = 0;
 pub const stylehint_just_LeftRight: u32 = 1;
 pub const stylehint_just_Centered: u32 = 2;
 pub const stylehint_just_RightFlush: u32 = 3;
+
+pub const imagealign_InlineUp: u32 = 0x01;
+pub const imagealign_InlineDown: u32 = 0x02;
+pub const imagealign_InlineCenter: u32 = 0x03;
+pub const imagealign_MarginLeft: u32 = 0x04;
+pub const imagealign_MarginRight: u32 = 0x05;
+
+pub const imagerule_WidthOrig: u32 = 0x01;
+pub const imagerule_WidthFixed: u32 = 0x02;
+pub const imagerule_WidthRatio: u32 = 0x03;
+pub const imagerule_WidthMask: u32 = 0x03;
+pub const imagerule_HeightOrig: u32 = 0x04;
+pub const imagerule_HeightFixed: u32 = 0x08;
+pub const imagerule_AspectRatio: u32 = 0x0c;
+pub const imagerule_HeightMask: u32 = 0x0c;
@@ -0,0 +1,2109 @@
+// Glk wrappers for reproducing bugs.  RecordingGlk logs each call made
+// through it, with its arguments and results, one call per line after a
+// line with the VM's random seed.  ReplayGlk answers a fresh VM's calls
+// from such a log, with no display and no files, and panics with a report
+// of the first call that differs from the log.
+//
+// Each line is the address of the calling instruction in hex, the name of
+// the call, the arguments, ->, and the results.  Ids are numbered from 1
+// in the order they are first seen, with 0 for null.  Values are decimal
+// numbers, "byte strings", u"character strings", or - for nothing.
+
+use std::fmt;
+use std::io;
+use std::io::{BufRead,BufWriter,Cursor,Error,ErrorKind,Read,Seek,SeekFrom,Write};
+use std::marker::PhantomData;
+
+use super::{Glk,DateType,EventType,IdType,TimeValType};
+use super::session;
+use super::datetime::{Date,TimeVal};
+
+#[derive(Clone,Debug,PartialEq)]
+enum Value {
+    Num(i64),
+    Bytes(Vec<u8>),
+    Chars(Vec<u32>),
+    Nothing,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Bytes(ref bytes) => {
+                f.write_str("\"")?;
+                for &b in bytes {
+                    match b {
+                        b'"' | b'\\' => write!(f, "\\{}", b as char)?,
+                        b'\n' => f.write_str("\\n")?,
+                        0x20 ..= 0x7e => write!(f, "{}", b as char)?,
+                        _ => write!(f, "\\x{:02x}", b)?,
+                    }
+                }
+                f.write_str("\"")
+            },
+            Value::Chars(ref chars) => {
+                f.write_str("u\"")?;
+                for &ch in chars {
+                    match ch {
+                        0x22 | 0x5c => write!(f, "\\{}", ch as u8 as char)?,
+                        0x0a => f.write_str("\\n")?,
+                        0x20 ..= 0x7e => write!(f, "{}", ch as u8 as char)?,
+                        _ => write!(f, "\\u{{{:x}}}", ch)?,
+                    }
+                }
+                f.write_str("\"")
+            },
+            Value::Nothing => f.write_str("-"),
+        }
+    }
+}
+
+fn num<N: Into<i64>>(n: N) -> Value {
+    Value::Num(n.into())
+}
+
+fn bytes(buf: &[u8]) -> Value {
+    Value::Bytes(buf.to_vec())
+}
+
+fn chars(buf: &[u32]) -> Value {
+    Value::Chars(buf.to_vec())
+}
+
+fn push_buf(values: &mut Vec<Value>, buf: &Option<(u32,Box<[u8]>)>) {
+    match *buf {
+        Some((key,ref buf)) => {
+            values.push(num(key));
+            values.push(bytes(buf));
+        },
+        None => {
+            values.push(Value::Nothing);
+            values.push(Value::Nothing);
+        },
+    }
+}
+
+fn push_buf_uni(values: &mut Vec<Value>, buf: &Option<(u32,Box<[u32]>)>) {
+    match *buf {
+        Some((key,ref buf)) => {
+            values.push(num(key));
+            values.push(chars(buf));
+        },
+        None => {
+            values.push(Value::Nothing);
+            values.push(Value::Nothing);
+        },
+    }
+}
+
+fn stream_result(result: &(u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>)) -> Vec<Value> {
+    let mut values = vec![num(result.0),num(result.1)];
+    push_buf(&mut values, &result.2);
+    push_buf_uni(&mut values, &result.3);
+    values
+}
+
+fn time_values<T: TimeValType>(values: &mut Vec<Value>, time: &T) {
+    values.push(num(time.high_sec()));
+    values.push(num(time.low_sec()));
+    values.push(num(time.microsec()));
+}
+
+fn date_values<D: DateType>(values: &mut Vec<Value>, date: &D) {
+    for &n in &[date.year(),date.month(),date.day(),date.weekday(),date.hour(),date.minute(),date.second(),date.microsec()] {
+        values.push(num(n));
+    }
+}
+
+fn format_call(name: &str, args: &[Value]) -> String {
+    let mut s = name.to_string();
+    for arg in args {
+        s.push_str(&format!(" {}", arg));
+    }
+    s
+}
+
+struct Call {
+    pc: u32,
+    name: String,
+    args: Vec<Value>,
+    results: Vec<Value>,
+}
+
+fn parse_call(line: &str) -> Option<Call> {
+    let mut parts = line.splitn(3, ' ');
+    let pc = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let name = parts.next()?.to_string();
+    let mut values = parse_values(parts.next().unwrap_or(""))?;
+    let arrow = values.iter().position(|value| value.is_none())?;
+    let results = values.split_off(arrow + 1).into_iter().collect::<Option<Vec<Value>>>()?;
+    values.pop();
+    let args = values.into_iter().collect::<Option<Vec<Value>>>()?;
+    Some(Call{ pc, name, args, results })
+}
+
+// The values in a line, with None for the -> between the arguments and
+// the results.
+fn parse_values(s: &str) -> Option<Vec<Option<Value>>> {
+    let s = s.as_bytes();
+    let mut values = Vec::new();
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] == b' ' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if s[i] == b'"' || (s[i] == b'u' && s.get(i+1) == Some(&b'"')) {
+            i += if s[i] == b'u' { 2 } else { 1 };
+            while i < s.len() && s[i] != b'"' {
+                i += if s[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+            if i > s.len() {
+                return None;
+            }
+        } else {
+            while i < s.len() && s[i] != b' ' {
+                i += 1;
+            }
+        }
+        values.push(parse_value(&s[start .. i])?);
+    }
+    Some(values)
+}
+
+fn parse_value(token: &[u8]) -> Option<Option<Value>> {
+    match token {
+        b"->" => Some(None),
+        b"-" => Some(Some(Value::Nothing)),
+        _ if token[0] == b'"' => {
+            let chars = unescape(&token[1 .. token.len()-1])?;
+            if chars.iter().any(|&ch| ch > 0xff) {
+                return None;
+            }
+            Some(Some(Value::Bytes(chars.into_iter().map(|ch| ch as u8).collect())))
+        },
+        _ if token[0] == b'u' => Some(Some(Value::Chars(unescape(&token[2 .. token.len()-1])?))),
+        _ => ::std::str::from_utf8(token).ok()?.parse().ok().map(|n| Some(Value::Num(n))),
+    }
+}
+
+fn unescape(s: &[u8]) -> Option<Vec<u32>> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] != b'\\' {
+            result.push(s[i] as u32);
+            i += 1;
+            continue;
+        }
+        match *s.get(i+1)? {
+            b'n' => {
+                result.push(10);
+                i += 2;
+            },
+            b'x' => {
+                result.push(hex(s.get(i+2 .. i+4)?)?);
+                i += 4;
+            },
+            b'u' => {
+                let end = i + s[i ..].iter().position(|&b| b == b'}')?;
+                result.push(hex(s.get(i+3 .. end)?)?);
+                i = end + 1;
+            },
+            b => {
+                result.push(b as u32);
+                i += 2;
+            },
+        }
+    }
+    Some(result)
+}
+
+fn hex(s: &[u8]) -> Option<u32> {
+    u32::from_str_radix(::std::str::from_utf8(s).ok()?, 16).ok()
+}
+
+// An event that owns its line input buffer, for wrappers that look at
+// the input.
+pub struct Event<W> {
+    pub evtype: u32,
+    pub win: W,
+    pub val1: u32,
+    pub val2: u32,
+    pub buf: Option<(u32,Box<[u8]>)>,
+    pub buf_uni: Option<(u32,Box<[u32]>)>,
+}
+
+impl<W> Event<W> {
+    pub fn new<E: EventType<W>>(mut event: E) -> Self {
+        Event{
+            evtype: event.evtype(),
+            win: event.win(),
+            val1: event.val1(),
+            val2: event.val2(),
+            buf: event.buf(),
+            buf_uni: event.buf_uni(),
+        }
+    }
+}
+
+impl<W: Clone> EventType<W> for Event<W> {
+    fn evtype(&self) -> u32 {
+        self.evtype
+    }
+
+    fn win(&self) -> W {
+        self.win.clone()
+    }
+
+    fn val1(&self) -> u32 {
+        self.val1
+    }
+
+    fn val2(&self) -> u32 {
+        self.val2
+    }
+
+    fn buf(&mut self) -> Option<(u32,Box<[u8]>)> {
+        self.buf.take()
+    }
+
+    fn buf_uni(&mut self) -> Option<(u32,Box<[u32]>)> {
+        self.buf_uni.take()
+    }
+}
+
+// Numbers ids in the order they are first seen.  The numbers of closed
+// objects are reused.
+struct Ids<T> {
+    list: Vec<T>,
+}
+
+impl<T: IdType> Ids<T> {
+    fn new() -> Self {
+        Ids{ list: Vec::new() }
+    }
+
+    fn value(&mut self, id: &T) -> Value {
+        if id.is_null() {
+            return num(0);
+        }
+        if let Some(index) = self.list.iter().position(|item| item == id) {
+            return num(index as u32 + 1);
+        }
+        match self.list.iter().position(|item| item.is_null()) {
+            Some(index) => {
+                self.list[index] = id.clone();
+                num(index as u32 + 1)
+            },
+            None => {
+                self.list.push(id.clone());
+                num(self.list.len() as u32)
+            },
+        }
+    }
+
+    fn remove(&mut self, id: &T) -> Value {
+        let value = self.value(id);
+        if let Value::Num(n) = value {
+            if n > 0 {
+                self.list[n as usize - 1] = T::null();
+            }
+        }
+        value
+    }
+}
+
+// Wraps any Glk implementation, logging each call the VM makes except
+// tick and set_interrupt_handler.  The log is flushed before each select
+// and on exit.  The VM should be run with the same random seed, see
+// glulx::Options::set_random_seed.
+pub struct RecordingGlk<'a,G: Glk<'a>,W: Write> {
+    glk: G,
+    out: BufWriter<W>,
+    error: Option<Error>,
+    address: u32,
+    wins: Ids<G::WinId>,
+    strs: Ids<G::StrId>,
+    frefs: Ids<G::FRefId>,
+    schans: Ids<G::SChanId>,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a,G: Glk<'a>,W: Write> RecordingGlk<'a,G,W> {
+    pub fn new(glk: G, out: W, seed: u32) -> Self {
+        let mut out = BufWriter::new(out);
+        let error = writeln!(out, "seed {}", seed).err();
+        RecordingGlk{
+            glk,
+            out,
+            error,
+            address: 0,
+            wins: Ids::new(),
+            strs: Ids::new(),
+            frefs: Ids::new(),
+            schans: Ids::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn glk(&mut self) -> &mut G {
+        &mut self.glk
+    }
+
+    // Returns the first error writing the log, if any.
+    pub fn finish(self) -> (G,io::Result<W>) {
+        let RecordingGlk{ glk, out, error, .. } = self;
+        match error {
+            Some(err) => (glk,Err(err)),
+            None => (glk,out.into_inner().map_err(Error::from)),
+        }
+    }
+
+    fn log(&mut self, name: &str, args: Vec<Value>, results: Vec<Value>) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut line = format!("{:x} {} ->", self.address, format_call(name, &args));
+        for result in &results {
+            line.push_str(&format!(" {}", result));
+        }
+        line.push('\n');
+        self.error = self.out.write_all(line.as_bytes()).err();
+    }
+
+    fn flush(&mut self) {
+        if self.error.is_none() {
+            self.error = self.out.flush().err();
+        }
+    }
+
+    fn event(&mut self, name: &str, args: Vec<Value>, event: G::Event) -> Event<G::WinId> {
+        let event = Event::new(event);
+        let mut results = vec![num(event.evtype),self.wins.value(&event.win),num(event.val1),num(event.val2)];
+        push_buf(&mut results, &event.buf);
+        push_buf_uni(&mut results, &event.buf_uni);
+        self.log(name, args, results);
+        event
+    }
+}
+
+impl<'a,G: Glk<'a>,W: Write> Glk<'a> for RecordingGlk<'a,G,W> {
+    type WinId = G::WinId;
+    type StrId = G::StrId;
+    type FRefId = G::FRefId;
+    type SChanId = G::SChanId;
+    type Event = Event<G::WinId>;
+    type TimeVal = G::TimeVal;
+    type Date = G::Date;
+    type IOStream = G::IOStream;
+
+    fn exit(&mut self) -> ! {
+        self.log("exit", vec![], vec![]);
+        self.flush();
+        self.glk.exit()
+    }
+
+    fn set_interrupt_handler(&mut self, handler: extern fn()) {
+        self.glk.set_interrupt_handler(handler);
+    }
+
+    fn tick(&mut self) {
+        self.glk.tick();
+    }
+
+
+    fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
+        let result = self.glk.gestalt(sel, val);
+        self.log("gestalt", vec![num(sel),num(val)], vec![num(result)]);
+        result
+    }
+
+    fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
+        let args = vec![num(sel),num(val),chars(arr)];
+        let result = self.glk.gestalt_ext(sel, val, arr);
+        self.log("gestalt_ext", args, vec![num(result),chars(arr)]);
+        result
+    }
+
+
+    fn char_to_lower(&mut self, ch: u8) -> u8 {
+        let result = self.glk.char_to_lower(ch);
+        self.log("char_to_lower", vec![num(ch)], vec![num(result)]);
+        result
+    }
+
+    fn char_to_upper(&mut self, ch: u8) -> u8 {
+        let result = self.glk.char_to_upper(ch);
+        self.log("char_to_upper", vec![num(ch)], vec![num(result)]);
+        result
+    }
+
+
+    fn window_get_root(&mut self) -> Self::WinId {
+        let win = self.glk.window_get_root();
+        let result = self.wins.value(&win);
+        self.log("window_get_root", vec![], vec![result]);
+        win
+    }
+
+    fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
+        let args = vec![self.wins.value(split),num(method),num(size),num(wintype),num(rock)];
+        let win = self.glk.window_open(split, method, size, wintype, rock);
+        let result = self.wins.value(&win);
+        self.log("window_open", args, vec![result]);
+        win
+    }
+
+    fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        let args = vec![self.wins.remove(win)];
+        let result = self.glk.window_close(win);
+        self.log("window_close", args, stream_result(&result));
+        result
+    }
+
+    fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
+        let args = vec![self.wins.value(win)];
+        let (width,height) = self.glk.window_get_size(win);
+        self.log("window_get_size", args, vec![num(width),num(height)]);
+        (width,height)
+    }
+
+    fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
+        let args = vec![self.wins.value(win),num(method),num(size),self.wins.value(keywin)];
+        self.glk.window_set_arrangement(win, method, size, keywin);
+        self.log("window_set_arrangement", args, vec![]);
+    }
+
+    fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        let (method,size,keywin) = self.glk.window_get_arrangement(win);
+        let results = vec![num(method),num(size),self.wins.value(&keywin)];
+        self.log("window_get_arrangement", args, results);
+        (method,size,keywin)
+    }
+
+    fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
+        let args = vec![self.wins.value(win)];
+        let (next,rock) = self.glk.window_iterate(win);
+        let results = vec![self.wins.value(&next),num(rock)];
+        self.log("window_iterate", args, results);
+        (next,rock)
+    }
+
+    fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
+        let args = vec![self.wins.value(win)];
+        let result = self.glk.window_get_rock(win);
+        self.log("window_get_rock", args, vec![num(result)]);
+        result
+    }
+
+    fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
+        let args = vec![self.wins.value(win)];
+        let result = self.glk.window_get_type(win);
+        self.log("window_get_type", args, vec![num(result)]);
+        result
+    }
+
+    fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
+        let args = vec![self.wins.value(win)];
+        let parent = self.glk.window_get_parent(win);
+        let result = self.wins.value(&parent);
+        self.log("window_get_parent", args, vec![result]);
+        parent
+    }
+
+    fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
+        let args = vec![self.wins.value(win)];
+        let sibling = self.glk.window_get_sibling(win);
+        let result = self.wins.value(&sibling);
+        self.log("window_get_sibling", args, vec![result]);
+        sibling
+    }
+
+    fn window_clear(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.window_clear(win);
+        self.log("window_clear", args, vec![]);
+    }
+
+    fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
+        let args = vec![self.wins.value(win),num(xpos),num(ypos)];
+        self.glk.window_move_cursor(win, xpos, ypos);
+        self.log("window_move_cursor", args, vec![]);
+    }
+
+
+    fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        let args = vec![self.wins.value(win)];
+        let str = self.glk.window_get_stream(win);
+        let result = self.strs.value(&str);
+        self.log("window_get_stream", args, vec![result]);
+        str
+    }
+
+    fn window_set_echo_stream(&mut self, win: &Self::WinId, str: &Self::StrId) {
+        let args = vec![self.wins.value(win),self.strs.value(str)];
+        self.glk.window_set_echo_stream(win, str);
+        self.log("window_set_echo_stream", args, vec![]);
+    }
+
+    fn window_get_echo_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        let args = vec![self.wins.value(win)];
+        let str = self.glk.window_get_echo_stream(win);
+        let result = self.strs.value(&str);
+        self.log("window_get_echo_stream", args, vec![result]);
+        str
+    }
+
+    fn set_window(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.set_window(win);
+        self.log("set_window", args, vec![]);
+    }
+
+
+    fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        let args = vec![self.frefs.value(fileref),num(fmode),num(rock)];
+        let str = self.glk.stream_open_file(fileref, fmode, rock);
+        let result = self.strs.value(&str);
+        self.log("stream_open_file", args, vec![result]);
+        str
+    }
+
+    fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
+        let args = vec![num(buf.0),bytes(&buf.1),num(fmode),num(rock)];
+        let str = self.glk.stream_open_memory(buf, fmode, rock);
+        let result = self.strs.value(&str);
+        self.log("stream_open_memory", args, vec![result]);
+        str
+    }
+
+    fn stream_close(&mut self, str: &mut Self::StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        let args = vec![self.strs.remove(str)];
+        let result = self.glk.stream_close(str);
+        self.log("stream_close", args, stream_result(&result));
+        result
+    }
+
+    fn stream_iterate(&mut self, str: &Self::StrId) -> (Self::StrId,u32) {
+        let args = vec![self.strs.value(str)];
+        let (next,rock) = self.glk.stream_iterate(str);
+        let results = vec![self.strs.value(&next),num(rock)];
+        self.log("stream_iterate", args, results);
+        (next,rock)
+    }
+
+    fn stream_get_rock(&mut self, str: &Self::StrId) -> u32 {
+        let args = vec![self.strs.value(str)];
+        let result = self.glk.stream_get_rock(str);
+        self.log("stream_get_rock", args, vec![num(result)]);
+        result
+    }
+
+    fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
+        let args = vec![self.strs.value(str),num(pos),num(seekmode)];
+        self.glk.stream_set_position(str, pos, seekmode);
+        self.log("stream_set_position", args, vec![]);
+    }
+
+    fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
+        let args = vec![self.strs.value(str)];
+        let result = self.glk.stream_get_position(str);
+        self.log("stream_get_position", args, vec![num(result)]);
+        result
+    }
+
+    fn stream_set_current(&mut self, str: &Self::StrId) {
+        let args = vec![self.strs.value(str)];
+        self.glk.stream_set_current(str);
+        self.log("stream_set_current", args, vec![]);
+    }
+
+    fn stream_get_current(&mut self) -> Self::StrId {
+        let str = self.glk.stream_get_current();
+        let result = self.strs.value(&str);
+        self.log("stream_get_current", vec![], vec![result]);
+        str
+    }
+
+
+    fn put_char(&mut self, ch: u8) {
+        self.glk.put_char(ch);
+        self.log("put_char", vec![num(ch)], vec![]);
+    }
+
+    fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
+        let args = vec![self.strs.value(str),num(ch)];
+        self.glk.put_char_stream(str, ch);
+        self.log("put_char_stream", args, vec![]);
+    }
+
+    fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
+        let args = vec![bytes(s.as_ref())];
+        self.glk.put_string(s);
+        self.log("put_string", args, vec![]);
+    }
+
+    fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &Self::StrId, s: S) {
+        let args = vec![self.strs.value(str),bytes(s.as_ref())];
+        self.glk.put_string_stream(str, s);
+        self.log("put_string_stream", args, vec![]);
+    }
+
+    fn put_buffer(&mut self, buf: &[u8]) {
+        self.glk.put_buffer(buf);
+        self.log("put_buffer", vec![bytes(buf)], vec![]);
+    }
+
+    fn put_buffer_stream(&mut self, str: &Self::StrId, buf: &[u8]) {
+        let args = vec![self.strs.value(str),bytes(buf)];
+        self.glk.put_buffer_stream(str, buf);
+        self.log("put_buffer_stream", args, vec![]);
+    }
+
+    fn set_style(&mut self, styl: u32) {
+        self.glk.set_style(styl);
+        self.log("set_style", vec![num(styl)], vec![]);
+    }
+
+    fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
+        let args = vec![self.strs.value(str),num(styl)];
+        self.glk.set_style_stream(str, styl);
+        self.log("set_style_stream", args, vec![]);
+    }
+
+
+    fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
+        let args = vec![self.strs.value(str)];
+        let result = self.glk.get_char_stream(str);
+        self.log("get_char_stream", args, vec![num(result)]);
+        result
+    }
+
+    fn get_line_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        let args = vec![self.strs.value(str),num(buf.len() as u32)];
+        let result = self.glk.get_line_stream(str, buf);
+        self.log("get_line_stream", args, vec![num(result),bytes(buf)]);
+        result
+    }
+
+    fn get_buffer_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        let args = vec![self.strs.value(str),num(buf.len() as u32)];
+        let result = self.glk.get_buffer_stream(str, buf);
+        self.log("get_buffer_stream", args, vec![num(result),bytes(buf)]);
+        result
+    }
+
+
+    fn stylehint_set(&mut self, wintype: u32, styl: u32, hint: u32, val: i32) {
+        self.glk.stylehint_set(wintype, styl, hint, val);
+        self.log("stylehint_set", vec![num(wintype),num(styl),num(hint),num(val)], vec![]);
+    }
+
+    fn stylehint_clear(&mut self, wintype: u32, styl: u32, hint: u32) {
+        self.glk.stylehint_clear(wintype, styl, hint);
+        self.log("stylehint_clear", vec![num(wintype),num(styl),num(hint)], vec![]);
+    }
+
+    fn style_distinguish(&mut self, win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
+        let args = vec![self.wins.value(win),num(styl1),num(styl2)];
+        let result = self.glk.style_distinguish(win, styl1, styl2);
+        self.log("style_distinguish", args, vec![num(result as u32)]);
+        result
+    }
+
+    fn style_measure(&mut self, win: &Self::WinId, styl: u32, hint: u32) -> (bool,u32) {
+        let args = vec![self.wins.value(win),num(styl),num(hint)];
+        let (ok,val) = self.glk.style_measure(win, styl, hint);
+        self.log("style_measure", args, vec![num(ok as u32),num(val)]);
+        (ok,val)
+    }
+
+
+    fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
+        let fref = self.glk.fileref_create_temp(usage, rock);
+        let result = self.frefs.value(&fref);
+        self.log("fileref_create_temp", vec![num(usage),num(rock)], vec![result]);
+        fref
+    }
+
+    fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
+        let args = vec![num(usage),bytes(name.as_ref()),num(rock)];
+        let fref = self.glk.fileref_create_by_name(usage, name, rock);
+        let result = self.frefs.value(&fref);
+        self.log("fileref_create_by_name", args, vec![result]);
+        fref
+    }
+
+    fn fileref_create_by_prompt(&mut self, usage: u32, fmode: u32, rock: u32) -> Self::FRefId {
+        self.flush();
+        let fref = self.glk.fileref_create_by_prompt(usage, fmode, rock);
+        let result = self.frefs.value(&fref);
+        self.log("fileref_create_by_prompt", vec![num(usage),num(fmode),num(rock)], vec![result]);
+        fref
+    }
+
+    fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
+        let args = vec![num(usage),self.frefs.value(fref),num(rock)];
+        let newfref = self.glk.fileref_create_from_fileref(usage, fref, rock);
+        let result = self.frefs.value(&newfref);
+        self.log("fileref_create_from_fileref", args, vec![result]);
+        newfref
+    }
+
+    fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
+        let args = vec![self.frefs.remove(fref)];
+        self.glk.fileref_destroy(fref);
+        self.log("fileref_destroy", args, vec![]);
+    }
+
+    fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
+        let args = vec![self.frefs.value(fref)];
+        let (next,rock) = self.glk.fileref_iterate(fref);
+        let results = vec![self.frefs.value(&next),num(rock)];
+        self.log("fileref_iterate", args, results);
+        (next,rock)
+    }
+
+    fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
+        let args = vec![self.frefs.value(fref)];
+        let result = self.glk.fileref_get_rock(fref);
+        self.log("fileref_get_rock", args, vec![num(result)]);
+        result
+    }
+
+    fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
+        let args = vec![self.frefs.value(fref)];
+        self.glk.fileref_delete_file(fref);
+        self.log("fileref_delete_file", args, vec![]);
+    }
+
+    fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
+        let args = vec![self.frefs.value(fref)];
+        let result = self.glk.fileref_does_file_exist(fref);
+        self.log("fileref_does_file_exist", args, vec![num(result as u32)]);
+        result
+    }
+
+
+    fn select(&mut self) -> Self::Event {
+        self.flush();
+        let event = self.glk.select();
+        self.event("select", vec![], event)
+    }
+
+    fn select_poll(&mut self) -> Self::Event {
+        let event = self.glk.select_poll();
+        self.event("select_poll", vec![], event)
+    }
+
+
+    fn request_timer_events(&mut self, millisecs: u32) {
+        self.glk.request_timer_events(millisecs);
+        self.log("request_timer_events", vec![num(millisecs)], vec![]);
+    }
+
+
+    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
+        let args = vec![self.wins.value(win),num(buf.0),bytes(&buf.1),num(initlen)];
+        self.glk.request_line_event(win, buf, initlen);
+        self.log("request_line_event", args, vec![]);
+    }
+
+    fn request_char_event(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.request_char_event(win);
+        self.log("request_char_event", args, vec![]);
+    }
+
+    fn request_mouse_event(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.request_mouse_event(win);
+        self.log("request_mouse_event", args, vec![]);
+    }
+
+
+    fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
+        let args = vec![self.wins.value(win)];
+        let event = self.glk.cancel_line_event(win);
+        self.event("cancel_line_event", args, event)
+    }
+
+    fn cancel_char_event(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.cancel_char_event(win);
+        self.log("cancel_char_event", args, vec![]);
+    }
+
+    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.cancel_mouse_event(win);
+        self.log("cancel_mouse_event", args, vec![]);
+    }
+
+
+    fn set_echo_line_event(&mut self, win: &Self::WinId, val: u32) {
+        let args = vec![self.wins.value(win),num(val)];
+        self.glk.set_echo_line_event(win, val);
+        self.log("set_echo_line_event", args, vec![]);
+    }
+
+
+    fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
+        let args = vec![self.wins.value(win),chars(keycodes)];
+        self.glk.set_terminators_line_event(win, keycodes);
+        self.log("set_terminators_line_event", args, vec![]);
+    }
+
+
+    fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let args = vec![chars(buf),num(numchars)];
+        let result = self.glk.buffer_to_lower_case_uni(buf, numchars);
+        self.log("buffer_to_lower_case_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+    fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let args = vec![chars(buf),num(numchars)];
+        let result = self.glk.buffer_to_upper_case_uni(buf, numchars);
+        self.log("buffer_to_upper_case_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+    fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
+        let args = vec![chars(buf),num(numchars),num(lowerrest)];
+        let result = self.glk.buffer_to_title_case_uni(buf, numchars, lowerrest);
+        self.log("buffer_to_title_case_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+
+    fn put_char_uni(&mut self, ch: u32) {
+        self.glk.put_char_uni(ch);
+        self.log("put_char_uni", vec![num(ch)], vec![]);
+    }
+
+    fn put_string_uni<SU: AsRef<[u32]>>(&mut self, s: SU) {
+        let args = vec![chars(s.as_ref())];
+        self.glk.put_string_uni(s);
+        self.log("put_string_uni", args, vec![]);
+    }
+
+    fn put_buffer_uni(&mut self, buf: &[u32]) {
+        self.glk.put_buffer_uni(buf);
+        self.log("put_buffer_uni", vec![chars(buf)], vec![]);
+    }
+
+    fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
+        let args = vec![self.strs.value(str),num(ch)];
+        self.glk.put_char_stream_uni(str, ch);
+        self.log("put_char_stream_uni", args, vec![]);
+    }
+
+    fn put_string_stream_uni<SU: AsRef<[u32]>>(&mut self, str: &Self::StrId, s: SU) {
+        let args = vec![self.strs.value(str),chars(s.as_ref())];
+        self.glk.put_string_stream_uni(str, s);
+        self.log("put_string_stream_uni", args, vec![]);
+    }
+
+    fn put_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &[u32]) {
+        let args = vec![self.strs.value(str),chars(buf)];
+        self.glk.put_buffer_stream_uni(str, buf);
+        self.log("put_buffer_stream_uni", args, vec![]);
+    }
+
+
+    fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
+        let args = vec![self.strs.value(str)];
+        let result = self.glk.get_char_stream_uni(str);
+        self.log("get_char_stream_uni", args, vec![num(result)]);
+        result
+    }
+
+    fn get_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        let args = vec![self.strs.value(str),num(buf.len() as u32)];
+        let result = self.glk.get_buffer_stream_uni(str, buf);
+        self.log("get_buffer_stream_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+    fn get_line_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        let args = vec![self.strs.value(str),num(buf.len() as u32)];
+        let result = self.glk.get_line_stream_uni(str, buf);
+        self.log("get_line_stream_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+
+    fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        let args = vec![self.frefs.value(fileref),num(fmode),num(rock)];
+        let str = self.glk.stream_open_file_uni(fileref, fmode, rock);
+        let result = self.strs.value(&str);
+        self.log("stream_open_file_uni", args, vec![result]);
+        str
+    }
+
+    fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
+        let args = vec![num(buf.0),chars(&buf.1),num(fmode),num(rock)];
+        let str = self.glk.stream_open_memory_uni(buf, fmode, rock);
+        let result = self.strs.value(&str);
+        self.log("stream_open_memory_uni", args, vec![result]);
+        str
+    }
+
+
+    fn request_char_event_uni(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.request_char_event_uni(win);
+        self.log("request_char_event_uni", args, vec![]);
+    }
+
+    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
+        let args = vec![self.wins.value(win),num(buf.0),chars(&buf.1),num(initlen)];
+        self.glk.request_line_event_uni(win, buf, initlen);
+        self.log("request_line_event_uni", args, vec![]);
+    }
+
+
+    fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let args = vec![chars(buf),num(numchars)];
+        let result = self.glk.buffer_canon_decompose_uni(buf, numchars);
+        self.log("buffer_canon_decompose_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+    fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let args = vec![chars(buf),num(numchars)];
+        let result = self.glk.buffer_canon_normalize_uni(buf, numchars);
+        self.log("buffer_canon_normalize_uni", args, vec![num(result),chars(buf)]);
+        result
+    }
+
+
+    fn image_draw(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32) -> bool {
+        let args = vec![self.wins.value(win),num(image),num(val1),num(val2)];
+        let result = self.glk.image_draw(win, image, val1, val2);
+        self.log("image_draw", args, vec![num(result as u32)]);
+        result
+    }
+
+    fn image_draw_scaled(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> bool {
+        let args = vec![self.wins.value(win),num(image),num(val1),num(val2),num(width),num(height)];
+        let result = self.glk.image_draw_scaled(win, image, val1, val2, width, height);
+        self.log("image_draw_scaled", args, vec![num(result as u32)]);
+        result
+    }
+
+    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
+        let (ok,width,height) = self.glk.image_get_info(image);
+        self.log("image_get_info", vec![num(image)], vec![num(ok as u32),num(width),num(height)]);
+        (ok,width,height)
+    }
+
+
+    fn window_flow_break(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.window_flow_break(win);
+        self.log("window_flow_break", args, vec![]);
+    }
+
+
+    fn window_erase_rect(&mut self, win: &Self::WinId, left: i32, top: i32, width: u32, height: u32) {
+        let args = vec![self.wins.value(win),num(left),num(top),num(width),num(height)];
+        self.glk.window_erase_rect(win, left, top, width, height);
+        self.log("window_erase_rect", args, vec![]);
+    }
+
+    fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32) {
+        let args = vec![self.wins.value(win),num(color),num(left),num(top),num(width),num(height)];
+        self.glk.window_fill_rect(win, color, left, top, width, height);
+        self.log("window_fill_rect", args, vec![]);
+    }
+
+    fn window_set_background_color(&mut self, win: &Self::WinId, color: u32) {
+        let args = vec![self.wins.value(win),num(color)];
+        self.glk.window_set_background_color(win, color);
+        self.log("window_set_background_color", args, vec![]);
+    }
+
+
+    fn image_draw_scaled_ext(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32, imagerule: u32, maxwidth: u32) -> bool {
+        let args = vec![self.wins.value(win),num(image),num(val1),num(val2),num(width),num(height),num(imagerule),num(maxwidth)];
+        let result = self.glk.image_draw_scaled_ext(win, image, val1, val2, width, height, imagerule, maxwidth);
+        self.log("image_draw_scaled_ext", args, vec![num(result as u32)]);
+        result
+    }
+
+
+    fn schannel_create(&mut self, rock: u32) -> Self::SChanId {
+        let chan = self.glk.schannel_create(rock);
+        let result = self.schans.value(&chan);
+        self.log("schannel_create", vec![num(rock)], vec![result]);
+        chan
+    }
+
+    fn schannel_destroy(&mut self, chan: &mut Self::SChanId) {
+        let args = vec![self.schans.remove(chan)];
+        self.glk.schannel_destroy(chan);
+        self.log("schannel_destroy", args, vec![]);
+    }
+
+    fn schannel_iterate(&mut self, chan: &Self::SChanId) -> (Self::SChanId,u32) {
+        let args = vec![self.schans.value(chan)];
+        let (next,rock) = self.glk.schannel_iterate(chan);
+        let results = vec![self.schans.value(&next),num(rock)];
+        self.log("schannel_iterate", args, results);
+        (next,rock)
+    }
+
+    fn schannel_get_rock(&mut self, chan: &Self::SChanId) -> u32 {
+        let args = vec![self.schans.value(chan)];
+        let result = self.glk.schannel_get_rock(chan);
+        self.log("schannel_get_rock", args, vec![num(result)]);
+        result
+    }
+
+
+    fn schannel_play(&mut self, chan: &Self::SChanId, snd: u32) -> bool {
+        let args = vec![self.schans.value(chan),num(snd)];
+        let result = self.glk.schannel_play(chan, snd);
+        self.log("schannel_play", args, vec![num(result as u32)]);
+        result
+    }
+
+    fn schannel_play_ext(&mut self, chan: &Self::SChanId, snd: u32, repeat: u32, notify: u32) -> bool {
+        let args = vec![self.schans.value(chan),num(snd),num(repeat),num(notify)];
+        let result = self.glk.schannel_play_ext(chan, snd, repeat, notify);
+        self.log("schannel_play_ext", args, vec![num(result as u32)]);
+        result
+    }
+
+    fn schannel_stop(&mut self, chan: &Self::SChanId) {
+        let args = vec![self.schans.value(chan)];
+        self.glk.schannel_stop(chan);
+        self.log("schannel_stop", args, vec![]);
+    }
+
+    fn schannel_set_volume(&mut self, chan: &Self::SChanId, vol: u32) {
+        let args = vec![self.schans.value(chan),num(vol)];
+        self.glk.schannel_set_volume(chan, vol);
+        self.log("schannel_set_volume", args, vec![]);
+    }
+
+
+    fn sound_load_hint(&mut self, snd: u32, flag: u32) {
+        self.glk.sound_load_hint(snd, flag);
+        self.log("sound_load_hint", vec![num(snd),num(flag)], vec![]);
+    }
+
+
+    fn schannel_create_ext(&mut self, rock: u32, volume: u32) -> Self::SChanId {
+        let chan = self.glk.schannel_create_ext(rock, volume);
+        let result = self.schans.value(&chan);
+        self.log("schannel_create_ext", vec![num(rock),num(volume)], vec![result]);
+        chan
+    }
+
+    fn schannel_play_multi(&mut self, chanarray: &[Self::SChanId], sndarray: &[u32], notify: u32) -> bool {
+        let mut chans = Vec::new();
+        for chan in chanarray {
+            if let Value::Num(n) = self.schans.value(chan) {
+                chans.push(n as u32);
+            }
+        }
+        let args = vec![Value::Chars(chans),chars(sndarray),num(notify)];
+        let result = self.glk.schannel_play_multi(chanarray, sndarray, notify);
+        self.log("schannel_play_multi", args, vec![num(result as u32)]);
+        result
+    }
+
+    fn schannel_pause(&mut self, chan: &Self::SChanId) {
+        let args = vec![self.schans.value(chan)];
+        self.glk.schannel_pause(chan);
+        self.log("schannel_pause", args, vec![]);
+    }
+
+    fn schannel_unpause(&mut self, chan: &Self::SChanId) {
+        let args = vec![self.schans.value(chan)];
+        self.glk.schannel_unpause(chan);
+        self.log("schannel_unpause", args, vec![]);
+    }
+
+    fn schannel_set_volume_ext(&mut self, chan: &Self::SChanId, vol: u32, duration: u32, notify: u32) {
+        let args = vec![self.schans.value(chan),num(vol),num(duration),num(notify)];
+        self.glk.schannel_set_volume_ext(chan, vol, duration, notify);
+        self.log("schannel_set_volume_ext", args, vec![]);
+    }
+
+
+    fn set_hyperlink(&mut self, linkval: u32) {
+        self.glk.set_hyperlink(linkval);
+        self.log("set_hyperlink", vec![num(linkval)], vec![]);
+    }
+
+    fn set_hyperlink_stream(&mut self, str: &Self::StrId, linkval: u32) {
+        let args = vec![self.strs.value(str),num(linkval)];
+        self.glk.set_hyperlink_stream(str, linkval);
+        self.log("set_hyperlink_stream", args, vec![]);
+    }
+
+    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.request_hyperlink_event(win);
+        self.log("request_hyperlink_event", args, vec![]);
+    }
+
+    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
+        let args = vec![self.wins.value(win)];
+        self.glk.cancel_hyperlink_event(win);
+        self.log("cancel_hyperlink_event", args, vec![]);
+    }
+
+
+    fn current_time(&mut self) -> Self::TimeVal {
+        let time = self.glk.current_time();
+        let mut results = vec![];
+        time_values(&mut results, &time);
+        self.log("current_time", vec![], results);
+        time
+    }
+
+    fn current_simple_time(&mut self, factor: u32) -> i32 {
+        let result = self.glk.current_simple_time(factor);
+        self.log("current_simple_time", vec![num(factor)], vec![num(result)]);
+        result
+    }
+
+    fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
+        let mut args = vec![];
+        time_values(&mut args, time);
+        let date = self.glk.time_to_date_utc(time);
+        let mut results = vec![];
+        date_values(&mut results, &date);
+        self.log("time_to_date_utc", args, results);
+        date
+    }
+
+    fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
+        let mut args = vec![];
+        time_values(&mut args, time);
+        let date = self.glk.time_to_date_local(time);
+        let mut results = vec![];
+        date_values(&mut results, &date);
+        self.log("time_to_date_local", args, results);
+        date
+    }
+
+    fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
+        let date = self.glk.simple_time_to_date_utc(time, factor);
+        let mut results = vec![];
+        date_values(&mut results, &date);
+        self.log("simple_time_to_date_utc", vec![num(time),num(factor)], results);
+        date
+    }
+
+    fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
+        let date = self.glk.simple_time_to_date_local(time, factor);
+        let mut results = vec![];
+        date_values(&mut results, &date);
+        self.log("simple_time_to_date_local", vec![num(time),num(factor)], results);
+        date
+    }
+
+    fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        let time = self.glk.date_to_time_utc(date);
+        let mut results = vec![];
+        time_values(&mut results, &time);
+        self.log("date_to_time_utc", args, results);
+        time
+    }
+
+    fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        let time = self.glk.date_to_time_local(date);
+        let mut results = vec![];
+        time_values(&mut results, &time);
+        self.log("date_to_time_local", args, results);
+        time
+    }
+
+    fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        args.push(num(factor));
+        let result = self.glk.date_to_simple_time_utc(date, factor);
+        self.log("date_to_simple_time_utc", args, vec![num(result)]);
+        result
+    }
+
+    fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        args.push(num(factor));
+        let result = self.glk.date_to_simple_time_local(date, factor);
+        self.log("date_to_simple_time_local", args, vec![num(result)]);
+        result
+    }
+
+
+    fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        let str = self.glk.stream_open_resource(filenum, rock);
+        let result = self.strs.value(&str);
+        self.log("stream_open_resource", vec![num(filenum),num(rock)], vec![result]);
+        str
+    }
+
+    fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        let str = self.glk.stream_open_resource_uni(filenum, rock);
+        let result = self.strs.value(&str);
+        self.log("stream_open_resource_uni", vec![num(filenum),num(rock)], vec![result]);
+        str
+    }
+
+
+    // Logs what the VM could read from the stream, for restore, and
+    // leaves the position where it was.
+    fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
+        let args = vec![self.strs.value(str)];
+        let mut io_stream = self.glk.io_stream(str);
+        let mut data = Vec::new();
+        if let Ok(pos) = io_stream.seek(SeekFrom::Current(0)) {
+            if io_stream.read_to_end(&mut data).is_err() {
+                data.clear();
+            }
+            let _ = io_stream.seek(SeekFrom::Start(pos));
+        }
+        self.log("io_stream", args, vec![Value::Bytes(data)]);
+        io_stream
+    }
+
+    fn set_resource_map(&mut self, blorb: super::blorb::Blorb) {
+        self.glk.set_resource_map(blorb);
+    }
+
+    fn set_story_title(&mut self, title: &str) {
+        self.glk.set_story_title(title);
+    }
+
+    fn set_call_address(&mut self, addr: u32) {
+        self.address = addr;
+        self.glk.set_call_address(addr);
+    }
+
+
+    fn garglk_set_zcolors(&mut self, fg: u32, bg: u32) {
+        self.glk.garglk_set_zcolors(fg, bg);
+        self.log("garglk_set_zcolors", vec![num(fg),num(bg)], vec![]);
+    }
+
+    fn garglk_set_zcolors_stream(&mut self, str: &Self::StrId, fg: u32, bg: u32) {
+        let args = vec![self.strs.value(str),num(fg),num(bg)];
+        self.glk.garglk_set_zcolors_stream(str, fg, bg);
+        self.log("garglk_set_zcolors_stream", args, vec![]);
+    }
+
+    fn garglk_set_reversevideo(&mut self, reverse: u32) {
+        self.glk.garglk_set_reversevideo(reverse);
+        self.log("garglk_set_reversevideo", vec![num(reverse)], vec![]);
+    }
+
+    fn garglk_set_reversevideo_stream(&mut self, str: &Self::StrId, reverse: u32) {
+        let args = vec![self.strs.value(str),num(reverse)];
+        self.glk.garglk_set_reversevideo_stream(str, reverse);
+        self.log("garglk_set_reversevideo_stream", args, vec![]);
+    }
+}
+
+// The recorded numbers of windows, streams, filerefs and sound channels.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct ReplayId(u32);
+
+impl IdType for ReplayId {
+    fn null() -> Self {
+        ReplayId(0)
+    }
+
+    fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+fn id(id: &ReplayId) -> Value {
+    num(id.0)
+}
+
+// The results of a replayed call.
+struct Results {
+    name: String,
+    values: Vec<Value>,
+}
+
+impl Results {
+    fn bad(&self, index: usize) -> ! {
+        panic!("bad result {} of recorded {}", index, self.name)
+    }
+
+    fn num(&self, index: usize) -> i64 {
+        match self.values.get(index) {
+            Some(&Value::Num(n)) => n,
+            _ => self.bad(index),
+        }
+    }
+
+    fn u32(&self, index: usize) -> u32 {
+        self.num(index) as u32
+    }
+
+    fn i32(&self, index: usize) -> i32 {
+        self.num(index) as i32
+    }
+
+    fn bool(&self, index: usize) -> bool {
+        self.num(index) != 0
+    }
+
+    fn id(&self, index: usize) -> ReplayId {
+        ReplayId(self.u32(index))
+    }
+
+    fn bytes(&self, index: usize, buf: &mut [u8]) {
+        match self.values.get(index) {
+            Some(&Value::Bytes(ref bytes)) if bytes.len() == buf.len() => buf.copy_from_slice(bytes),
+            _ => self.bad(index),
+        }
+    }
+
+    fn chars(&self, index: usize, buf: &mut [u32]) {
+        match self.values.get(index) {
+            Some(&Value::Chars(ref chars)) if chars.len() == buf.len() => buf.copy_from_slice(chars),
+            _ => self.bad(index),
+        }
+    }
+
+    fn buf(&self, index: usize) -> Option<(u32,Box<[u8]>)> {
+        match (self.values.get(index),self.values.get(index+1)) {
+            (Some(&Value::Num(key)),Some(&Value::Bytes(ref buf))) => Some((key as u32,buf.clone().into_boxed_slice())),
+            (Some(&Value::Nothing),Some(&Value::Nothing)) => None,
+            _ => self.bad(index),
+        }
+    }
+
+    fn buf_uni(&self, index: usize) -> Option<(u32,Box<[u32]>)> {
+        match (self.values.get(index),self.values.get(index+1)) {
+            (Some(&Value::Num(key)),Some(&Value::Chars(ref buf))) => Some((key as u32,buf.clone().into_boxed_slice())),
+            (Some(&Value::Nothing),Some(&Value::Nothing)) => None,
+            _ => self.bad(index),
+        }
+    }
+
+    fn event(&self) -> Event<ReplayId> {
+        Event{
+            evtype: self.u32(0),
+            win: self.id(1),
+            val1: self.u32(2),
+            val2: self.u32(3),
+            buf: self.buf(4),
+            buf_uni: self.buf_uni(6),
+        }
+    }
+
+    fn stream_result(&self) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        (self.u32(0),self.u32(1),self.buf(2),self.buf_uni(4))
+    }
+
+    fn time(&self) -> TimeVal {
+        TimeVal::new(self.i32(0), self.u32(1), self.i32(2))
+    }
+
+    fn date(&self) -> Date {
+        Date::new(self.i32(0), self.i32(1), self.i32(2), self.i32(3), self.i32(4), self.i32(5), self.i32(6), self.i32(7))
+    }
+}
+
+// Answers each call from a log written by RecordingGlk.  The VM should
+// be run with the recorded random seed.
+pub struct ReplayGlk {
+    calls: Vec<Call>,
+    next: usize,
+    seed: u32,
+    address: u32,
+}
+
+impl ReplayGlk {
+    pub fn new<R: BufRead>(r: R) -> io::Result<Self> {
+        let mut calls = Vec::new();
+        let mut seed = None;
+        for (n,line) in r.lines().enumerate() {
+            let line = line?;
+            let call = if seed.is_none() {
+                seed = if line.starts_with("seed ") { line[5..].parse().ok() } else { None };
+                seed.map(|_| None)
+            } else {
+                parse_call(&line).map(Some)
+            };
+            match call {
+                Some(Some(call)) => calls.push(call),
+                Some(None) => (),
+                None => return Err(Error::new(ErrorKind::InvalidData, format!("line {}: bad recording", n + 1))),
+            }
+        }
+        match seed {
+            Some(seed) => Ok(ReplayGlk{ calls, next: 0, seed, address: 0 }),
+            None => Err(Error::new(ErrorKind::InvalidData, "empty recording")),
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    // The number of recorded calls not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.calls.len() - self.next
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Results {
+        // A select that never returned, at the end of the backend's input,
+        // isn't in the log, so the recorded session ended there.
+        if name == "select" && self.remaining() == 0 {
+            session::end(Ok(()));
+        }
+        let matches = match self.calls.get(self.next) {
+            Some(call) => call.name == name && call.args == args,
+            None => false,
+        };
+        if !matches {
+            let recorded = match self.calls.get(self.next) {
+                Some(call) => format!("{} at pc ${:x}", format_call(&call.name, &call.args), call.pc),
+                None => "no more calls".to_string(),
+            };
+            panic!("replay diverged at call {}, pc ${:x}: the VM called {}, but the recording has {}", self.next + 1, self.address, format_call(name, &args), recorded);
+        }
+        let results = ::std::mem::replace(&mut self.calls[self.next].results, vec![]);
+        self.next += 1;
+        Results{ name: name.to_string(), values: results }
+    }
+}
+
+impl<'a> Glk<'a> for ReplayGlk {
+    type WinId = ReplayId;
+    type StrId = ReplayId;
+    type FRefId = ReplayId;
+    type SChanId = ReplayId;
+    type Event = Event<ReplayId>;
+    type TimeVal = TimeVal;
+    type Date = Date;
+    type IOStream = Cursor<Vec<u8>>;
+
+    // Ends the session, with an error if the recording has calls left.
+    fn exit(&mut self) -> ! {
+        self.call("exit", vec![]);
+        match self.calls.get(self.next) {
+            Some(call) => session::end(Err(Error::new(ErrorKind::InvalidData, format!("replay ended at call {}, but the recording has {} more, starting with {} at pc ${:x}", self.next, self.remaining(), format_call(&call.name, &call.args), call.pc)))),
+            None => session::end(Ok(())),
+        }
+    }
+
+    fn set_interrupt_handler(&mut self, _handler: extern fn()) {
+    }
+
+    fn tick(&mut self) {
+    }
+
+
+    fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
+        self.call("gestalt", vec![num(sel),num(val)]).u32(0)
+    }
+
+    fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
+        let results = self.call("gestalt_ext", vec![num(sel),num(val),chars(arr)]);
+        results.chars(1, arr);
+        results.u32(0)
+    }
+
+
+    fn char_to_lower(&mut self, ch: u8) -> u8 {
+        self.call("char_to_lower", vec![num(ch)]).u32(0) as u8
+    }
+
+    fn char_to_upper(&mut self, ch: u8) -> u8 {
+        self.call("char_to_upper", vec![num(ch)]).u32(0) as u8
+    }
+
+
+    fn window_get_root(&mut self) -> Self::WinId {
+        self.call("window_get_root", vec![]).id(0)
+    }
+
+    fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
+        self.call("window_open", vec![id(split),num(method),num(size),num(wintype),num(rock)]).id(0)
+    }
+
+    fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        self.call("window_close", vec![id(win)]).stream_result()
+    }
+
+    fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
+        let results = self.call("window_get_size", vec![id(win)]);
+        (results.u32(0),results.u32(1))
+    }
+
+    fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
+        self.call("window_set_arrangement", vec![id(win),num(method),num(size),id(keywin)]);
+    }
+
+    fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
+        let results = self.call("window_get_arrangement", vec![id(win)]);
+        (results.u32(0),results.u32(1),results.id(2))
+    }
+
+    fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
+        let results = self.call("window_iterate", vec![id(win)]);
+        (results.id(0),results.u32(1))
+    }
+
+    fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
+        self.call("window_get_rock", vec![id(win)]).u32(0)
+    }
+
+    fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
+        self.call("window_get_type", vec![id(win)]).u32(0)
+    }
+
+    fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
+        self.call("window_get_parent", vec![id(win)]).id(0)
+    }
+
+    fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
+        self.call("window_get_sibling", vec![id(win)]).id(0)
+    }
+
+    fn window_clear(&mut self, win: &Self::WinId) {
+        self.call("window_clear", vec![id(win)]);
+    }
+
+    fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
+        self.call("window_move_cursor", vec![id(win),num(xpos),num(ypos)]);
+    }
+
+
+    fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        self.call("window_get_stream", vec![id(win)]).id(0)
+    }
+
+    fn window_set_echo_stream(&mut self, win: &Self::WinId, str: &Self::StrId) {
+        self.call("window_set_echo_stream", vec![id(win),id(str)]);
+    }
+
+    fn window_get_echo_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        self.call("window_get_echo_stream", vec![id(win)]).id(0)
+    }
+
+    fn set_window(&mut self, win: &Self::WinId) {
+        self.call("set_window", vec![id(win)]);
+    }
+
+
+    fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.call("stream_open_file", vec![id(fileref),num(fmode),num(rock)]).id(0)
+    }
+
+    fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
+        self.call("stream_open_memory", vec![num(buf.0),bytes(&buf.1),num(fmode),num(rock)]).id(0)
+    }
+
+    fn stream_close(&mut self, str: &mut Self::StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        self.call("stream_close", vec![id(str)]).stream_result()
+    }
+
+    fn stream_iterate(&mut self, str: &Self::StrId) -> (Self::StrId,u32) {
+        let results = self.call("stream_iterate", vec![id(str)]);
+        (results.id(0),results.u32(1))
+    }
+
+    fn stream_get_rock(&mut self, str: &Self::StrId) -> u32 {
+        self.call("stream_get_rock", vec![id(str)]).u32(0)
+    }
+
+    fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
+        self.call("stream_set_position", vec![id(str),num(pos),num(seekmode)]);
+    }
+
+    fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
+        self.call("stream_get_position", vec![id(str)]).u32(0)
+    }
+
+    fn stream_set_current(&mut self, str: &Self::StrId) {
+        self.call("stream_set_current", vec![id(str)]);
+    }
+
+    fn stream_get_current(&mut self) -> Self::StrId {
+        self.call("stream_get_current", vec![]).id(0)
+    }
+
+
+    fn put_char(&mut self, ch: u8) {
+        self.call("put_char", vec![num(ch)]);
+    }
+
+    fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
+        self.call("put_char_stream", vec![id(str),num(ch)]);
+    }
+
+    fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
+        self.call("put_string", vec![bytes(s.as_ref())]);
+    }
+
+    fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &Self::StrId, s: S) {
+        self.call("put_string_stream", vec![id(str),bytes(s.as_ref())]);
+    }
+
+    fn put_buffer(&mut self, buf: &[u8]) {
+        self.call("put_buffer", vec![bytes(buf)]);
+    }
+
+    fn put_buffer_stream(&mut self, str: &Self::StrId, buf: &[u8]) {
+        self.call("put_buffer_stream", vec![id(str),bytes(buf)]);
+    }
+
+    fn set_style(&mut self, styl: u32) {
+        self.call("set_style", vec![num(styl)]);
+    }
+
+    fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
+        self.call("set_style_stream", vec![id(str),num(styl)]);
+    }
+
+
+    fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
+        self.call("get_char_stream", vec![id(str)]).i32(0)
+    }
+
+    fn get_line_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        let results = self.call("get_line_stream", vec![id(str),num(buf.len() as u32)]);
+        results.bytes(1, buf);
+        results.u32(0)
+    }
+
+    fn get_buffer_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        let results = self.call("get_buffer_stream", vec![id(str),num(buf.len() as u32)]);
+        results.bytes(1, buf);
+        results.u32(0)
+    }
+
+
+    fn stylehint_set(&mut self, wintype: u32, styl: u32, hint: u32, val: i32) {
+        self.call("stylehint_set", vec![num(wintype),num(styl),num(hint),num(val)]);
+    }
+
+    fn stylehint_clear(&mut self, wintype: u32, styl: u32, hint: u32) {
+        self.call("stylehint_clear", vec![num(wintype),num(styl),num(hint)]);
+    }
+
+    fn style_distinguish(&mut self, win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
+        self.call("style_distinguish", vec![id(win),num(styl1),num(styl2)]).bool(0)
+    }
+
+    fn style_measure(&mut self, win: &Self::WinId, styl: u32, hint: u32) -> (bool,u32) {
+        let results = self.call("style_measure", vec![id(win),num(styl),num(hint)]);
+        (results.bool(0),results.u32(1))
+    }
+
+
+    fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
+        self.call("fileref_create_temp", vec![num(usage),num(rock)]).id(0)
+    }
+
+    fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
+        self.call("fileref_create_by_name", vec![num(usage),bytes(name.as_ref()),num(rock)]).id(0)
+    }
+
+    fn fileref_create_by_prompt(&mut self, usage: u32, fmode: u32, rock: u32) -> Self::FRefId {
+        self.call("fileref_create_by_prompt", vec![num(usage),num(fmode),num(rock)]).id(0)
+    }
+
+    fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
+        self.call("fileref_create_from_fileref", vec![num(usage),id(fref),num(rock)]).id(0)
+    }
+
+    fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
+        self.call("fileref_destroy", vec![id(fref)]);
+    }
+
+    fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
+        let results = self.call("fileref_iterate", vec![id(fref)]);
+        (results.id(0),results.u32(1))
+    }
+
+    fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
+        self.call("fileref_get_rock", vec![id(fref)]).u32(0)
+    }
+
+    fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
+        self.call("fileref_delete_file", vec![id(fref)]);
+    }
+
+    fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
+        self.call("fileref_does_file_exist", vec![id(fref)]).bool(0)
+    }
+
+
+    fn select(&mut self) -> Self::Event {
+        self.call("select", vec![]).event()
+    }
+
+    fn select_poll(&mut self) -> Self::Event {
+        self.call("select_poll", vec![]).event()
+    }
+
+
+    fn request_timer_events(&mut self, millisecs: u32) {
+        self.call("request_timer_events", vec![num(millisecs)]);
+    }
+
+
+    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
+        self.call("request_line_event", vec![id(win),num(buf.0),bytes(&buf.1),num(initlen)]);
+    }
+
+    fn request_char_event(&mut self, win: &Self::WinId) {
+        self.call("request_char_event", vec![id(win)]);
+    }
+
+    fn request_mouse_event(&mut self, win: &Self::WinId) {
+        self.call("request_mouse_event", vec![id(win)]);
+    }
+
+
+    fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
+        self.call("cancel_line_event", vec![id(win)]).event()
+    }
+
+    fn cancel_char_event(&mut self, win: &Self::WinId) {
+        self.call("cancel_char_event", vec![id(win)]);
+    }
+
+    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
+        self.call("cancel_mouse_event", vec![id(win)]);
+    }
+
+
+    fn set_echo_line_event(&mut self, win: &Self::WinId, val: u32) {
+        self.call("set_echo_line_event", vec![id(win),num(val)]);
+    }
+
+
+    fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
+        self.call("set_terminators_line_event", vec![id(win),chars(keycodes)]);
+    }
+
+
+    fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let results = self.call("buffer_to_lower_case_uni", vec![chars(buf),num(numchars)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+    fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let results = self.call("buffer_to_upper_case_uni", vec![chars(buf),num(numchars)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+    fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
+        let results = self.call("buffer_to_title_case_uni", vec![chars(buf),num(numchars),num(lowerrest)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+
+    fn put_char_uni(&mut self, ch: u32) {
+        self.call("put_char_uni", vec![num(ch)]);
+    }
+
+    fn put_string_uni<SU: AsRef<[u32]>>(&mut self, s: SU) {
+        self.call("put_string_uni", vec![chars(s.as_ref())]);
+    }
+
+    fn put_buffer_uni(&mut self, buf: &[u32]) {
+        self.call("put_buffer_uni", vec![chars(buf)]);
+    }
+
+    fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
+        self.call("put_char_stream_uni", vec![id(str),num(ch)]);
+    }
+
+    fn put_string_stream_uni<SU: AsRef<[u32]>>(&mut self, str: &Self::StrId, s: SU) {
+        self.call("put_string_stream_uni", vec![id(str),chars(s.as_ref())]);
+    }
+
+    fn put_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &[u32]) {
+        self.call("put_buffer_stream_uni", vec![id(str),chars(buf)]);
+    }
+
+
+    fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
+        self.call("get_char_stream_uni", vec![id(str)]).i32(0)
+    }
+
+    fn get_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        let results = self.call("get_buffer_stream_uni", vec![id(str),num(buf.len() as u32)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+    fn get_line_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        let results = self.call("get_line_stream_uni", vec![id(str),num(buf.len() as u32)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+
+    fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.call("stream_open_file_uni", vec![id(fileref),num(fmode),num(rock)]).id(0)
+    }
+
+    fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
+        self.call("stream_open_memory_uni", vec![num(buf.0),chars(&buf.1),num(fmode),num(rock)]).id(0)
+    }
+
+
+    fn request_char_event_uni(&mut self, win: &Self::WinId) {
+        self.call("request_char_event_uni", vec![id(win)]);
+    }
+
+    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
+        self.call("request_line_event_uni", vec![id(win),num(buf.0),chars(&buf.1),num(initlen)]);
+    }
+
+
+    fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let results = self.call("buffer_canon_decompose_uni", vec![chars(buf),num(numchars)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+    fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        let results = self.call("buffer_canon_normalize_uni", vec![chars(buf),num(numchars)]);
+        results.chars(1, buf);
+        results.u32(0)
+    }
+
+
+    fn image_draw(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32) -> bool {
+        self.call("image_draw", vec![id(win),num(image),num(val1),num(val2)]).bool(0)
+    }
+
+    fn image_draw_scaled(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> bool {
+        self.call("image_draw_scaled", vec![id(win),num(image),num(val1),num(val2),num(width),num(height)]).bool(0)
+    }
+
+    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
+        let results = self.call("image_get_info", vec![num(image)]);
+        (results.bool(0),results.u32(1),results.u32(2))
+    }
+
+
+    fn window_flow_break(&mut self, win: &Self::WinId) {
+        self.call("window_flow_break", vec![id(win)]);
+    }
+
+
+    fn window_erase_rect(&mut self, win: &Self::WinId, left: i32, top: i32, width: u32, height: u32) {
+        self.call("window_erase_rect", vec![id(win),num(left),num(top),num(width),num(height)]);
+    }
+
+    fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32) {
+        self.call("window_fill_rect", vec![id(win),num(color),num(left),num(top),num(width),num(height)]);
+    }
+
+    fn window_set_background_color(&mut self, win: &Self::WinId, color: u32) {
+        self.call("window_set_background_color", vec![id(win),num(color)]);
+    }
+
+
+    fn image_draw_scaled_ext(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32, imagerule: u32, maxwidth: u32) -> bool {
+        self.call("image_draw_scaled_ext", vec![id(win),num(image),num(val1),num(val2),num(width),num(height),num(imagerule),num(maxwidth)]).bool(0)
+    }
+
+
+    fn schannel_create(&mut self, rock: u32) -> Self::SChanId {
+        self.call("schannel_create", vec![num(rock)]).id(0)
+    }
+
+    fn schannel_destroy(&mut self, chan: &mut Self::SChanId) {
+        self.call("schannel_destroy", vec![id(chan)]);
+    }
+
+    fn schannel_iterate(&mut self, chan: &Self::SChanId) -> (Self::SChanId,u32) {
+        let results = self.call("schannel_iterate", vec![id(chan)]);
+        (results.id(0),results.u32(1))
+    }
+
+    fn schannel_get_rock(&mut self, chan: &Self::SChanId) -> u32 {
+        self.call("schannel_get_rock", vec![id(chan)]).u32(0)
+    }
+
+
+    fn schannel_play(&mut self, chan: &Self::SChanId, snd: u32) -> bool {
+        self.call("schannel_play", vec![id(chan),num(snd)]).bool(0)
+    }
+
+    fn schannel_play_ext(&mut self, chan: &Self::SChanId, snd: u32, repeat: u32, notify: u32) -> bool {
+        self.call("schannel_play_ext", vec![id(chan),num(snd),num(repeat),num(notify)]).bool(0)
+    }
+
+    fn schannel_stop(&mut self, chan: &Self::SChanId) {
+        self.call("schannel_stop", vec![id(chan)]);
+    }
+
+    fn schannel_set_volume(&mut self, chan: &Self::SChanId, vol: u32) {
+        self.call("schannel_set_volume", vec![id(chan),num(vol)]);
+    }
+
+
+    fn sound_load_hint(&mut self, snd: u32, flag: u32) {
+        self.call("sound_load_hint", vec![num(snd),num(flag)]);
+    }
+
+
+    fn schannel_create_ext(&mut self, rock: u32, volume: u32) -> Self::SChanId {
+        self.call("schannel_create_ext", vec![num(rock),num(volume)]).id(0)
+    }
+
+    fn schannel_play_multi(&mut self, chanarray: &[Self::SChanId], sndarray: &[u32], notify: u32) -> bool {
+        let chans = Value::Chars(chanarray.iter().map(|chan| chan.0).collect());
+        self.call("schannel_play_multi", vec![chans,chars(sndarray),num(notify)]).bool(0)
+    }
+
+    fn schannel_pause(&mut self, chan: &Self::SChanId) {
+        self.call("schannel_pause", vec![id(chan)]);
+    }
+
+    fn schannel_unpause(&mut self, chan: &Self::SChanId) {
+        self.call("schannel_unpause", vec![id(chan)]);
+    }
+
+    fn schannel_set_volume_ext(&mut self, chan: &Self::SChanId, vol: u32, duration: u32, notify: u32) {
+        self.call("schannel_set_volume_ext", vec![id(chan),num(vol),num(duration),num(notify)]);
+    }
+
+
+    fn set_hyperlink(&mut self, linkval: u32) {
+        self.call("set_hyperlink", vec![num(linkval)]);
+    }
+
+    fn set_hyperlink_stream(&mut self, str: &Self::StrId, linkval: u32) {
+        self.call("set_hyperlink_stream", vec![id(str),num(linkval)]);
+    }
+
+    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
+        self.call("request_hyperlink_event", vec![id(win)]);
+    }
+
+    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
+        self.call("cancel_hyperlink_event", vec![id(win)]);
+    }
+
+
+    fn current_time(&mut self) -> Self::TimeVal {
+        self.call("current_time", vec![]).time()
+    }
+
+    fn current_simple_time(&mut self, factor: u32) -> i32 {
+        self.call("current_simple_time", vec![num(factor)]).i32(0)
+    }
+
+    fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
+        let mut args = vec![];
+        time_values(&mut args, time);
+        self.call("time_to_date_utc", args).date()
+    }
+
+    fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
+        let mut args = vec![];
+        time_values(&mut args, time);
+        self.call("time_to_date_local", args).date()
+    }
+
+    fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
+        self.call("simple_time_to_date_utc", vec![num(time),num(factor)]).date()
+    }
+
+    fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
+        self.call("simple_time_to_date_local", vec![num(time),num(factor)]).date()
+    }
+
+    fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        self.call("date_to_time_utc", args).time()
+    }
+
+    fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        self.call("date_to_time_local", args).time()
+    }
+
+    fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        args.push(num(factor));
+        self.call("date_to_simple_time_utc", args).i32(0)
+    }
+
+    fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        let mut args = vec![];
+        date_values(&mut args, date);
+        args.push(num(factor));
+        self.call("date_to_simple_time_local", args).i32(0)
+    }
+
+
+    fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        self.call("stream_open_resource", vec![num(filenum),num(rock)]).id(0)
+    }
+
+    fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        self.call("stream_open_resource_uni", vec![num(filenum),num(rock)]).id(0)
+    }
+
+
+    // Writes, as for save, are discarded.
+    fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
+        let results = self.call("io_stream", vec![id(str)]);
+        match results.values.get(0) {
+            Some(&Value::Bytes(ref data)) => Cursor::new(data.clone()),
+            _ => results.bad(0),
+        }
+    }
+
+    fn set_call_address(&mut self, addr: u32) {
+        self.address = addr;
+    }
+
+
+    fn garglk_set_zcolors(&mut self, fg: u32, bg: u32) {
+        self.call("garglk_set_zcolors", vec![num(fg),num(bg)]);
+    }
+
+    fn garglk_set_zcolors_stream(&mut self, str: &Self::StrId, fg: u32, bg: u32) {
+        self.call("garglk_set_zcolors_stream", vec![id(str),num(fg),num(bg)]);
+    }
+
+    fn garglk_set_reversevideo(&mut self, reverse: u32) {
+        self.call("garglk_set_reversevideo", vec![num(reverse)]);
+    }
+
+    fn garglk_set_reversevideo_stream(&mut self, str: &Self::StrId, reverse: u32) {
+        self.call("garglk_set_reversevideo_stream", vec![id(str),num(reverse)]);
+    }
+}
@@ -0,0 +1,24 @@
+use std::io;
+use std::panic::{self,AssertUnwindSafe};
+
+// A session is a game running on a Glk implementation.  Implementations
+// end it at glk_exit or the end of their input by unwinding to run, so
+// that other sessions in the process carry on.
+
+// Runs main until it returns or its session ends.
+pub fn run<F: FnOnce() -> io::Result<()>>(main: F) -> io::Result<()> {
+    match panic::catch_unwind(AssertUnwindSafe(main)) {
+        Ok(result) => result,
+        Err(payload) => match payload.downcast::<End>() {
+            Ok(end) => end.0,
+            Err(payload) => panic::resume_unwind(payload),
+        },
+    }
+}
+
+struct End(io::Result<()>);
+
+// Ends the session, with the result for run to return.
+pub fn end(result: io::Result<()>) -> ! {
+    panic::resume_unwind(Box::new(End(result)))
+}
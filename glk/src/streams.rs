@@ -0,0 +1,695 @@
+use std::fs::{File,OpenOptions};
+use std::io;
+use std::io::{Error,ErrorKind,Read,Seek,SeekFrom,Write};
+use std::path::PathBuf;
+use std::sync::{Arc,Mutex};
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use super::IdType;
+use super::blorb::Resource;
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct StrId(pub usize);
+
+impl IdType for StrId {
+    fn null() -> Self {
+        StrId(0)
+    }
+
+    fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct FRefId(pub usize);
+
+impl IdType for FRefId {
+    fn null() -> Self {
+        FRefId(0)
+    }
+
+    fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+// The encoding of text mode files opened with the non-Unicode calls.
+// Text mode files opened with the Unicode calls are always UTF-8.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Encoding {
+    Latin1,
+    Utf8,
+}
+
+// Receives what is written to window streams.
+pub trait WindowSink<W> {
+    fn put_window(&mut self, win: &W, s: &[u32]);
+
+    fn set_window_style(&mut self, _win: &W, _styl: u32) {
+    }
+
+    fn set_window_hyperlink(&mut self, _win: &W, _linkval: u32) {
+    }
+}
+
+enum Kind<W> {
+    Window{ win: W, echo: StrId },
+    Memory(Option<(u32,Box<[u8]>)>),
+    MemoryUni(Option<(u32,Box<[u32]>)>),
+    File{ file: File, text: bool, unicode: bool, encoding: Encoding },
+}
+
+struct Stream<W> {
+    rock: u32,
+    fmode: u32,
+    readcount: u32,
+    writecount: u32,
+    kind: Kind<W>,
+    // Position and end for memory streams.
+    pos: usize,
+    end: usize,
+}
+
+pub struct Streams<W> {
+    list: Vec<Option<Arc<Mutex<Stream<W>>>>>,
+    current: StrId,
+    encoding: Encoding,
+}
+
+impl<W: Clone> Streams<W> {
+    pub fn new() -> Self {
+        Streams{ list: vec![None], current: StrId(0), encoding: Encoding::Latin1 }
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn open_window(&mut self, win: W) -> StrId {
+        self.add(super::filemode_Write, 0, Kind::Window{ win, echo: StrId(0) }, 0)
+    }
+
+    pub fn open_file(&mut self, frefs: &FileRefs, fref: &FRefId, fmode: u32, rock: u32, unicode: bool) -> StrId {
+        let (path,usage) = match frefs.get(fref) {
+            Some(f) => (f.path.clone(),f.usage),
+            None => return StrId(0),
+        };
+        let mut options = OpenOptions::new();
+        match fmode {
+            super::filemode_Write => options.write(true).create(true).truncate(true),
+            super::filemode_Read => options.read(true),
+            super::filemode_ReadWrite => options.read(true).write(true).create(true),
+            super::filemode_WriteAppend => options.read(true).write(true).create(true),
+            _ => return StrId(0),
+        };
+        let mut file = match options.open(&path) {
+            Ok(file) => file,
+            Err(_) => return StrId(0),
+        };
+        if fmode == super::filemode_WriteAppend && file.seek(SeekFrom::End(0)).is_err() {
+            return StrId(0);
+        }
+        let text = usage & super::fileusage_TextMode != 0;
+        let encoding = if unicode { Encoding::Utf8 } else { self.encoding };
+        self.add(fmode, rock, Kind::File{ file, text, unicode, encoding }, 0)
+    }
+
+    pub fn open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> StrId {
+        let end = if fmode == super::filemode_Write { 0 } else { buf.1.len() };
+        self.add(fmode, rock, Kind::Memory(Some(buf)), end)
+    }
+
+    pub fn open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> StrId {
+        let end = if fmode == super::filemode_Write { 0 } else { buf.1.len() };
+        self.add(fmode, rock, Kind::MemoryUni(Some(buf)), end)
+    }
+
+    // A read-only stream of a Data resource.  TEXT resources are
+    // decoded as Latin-1, or as UTF-8 for Unicode streams, and BINA
+    // resources are read as bytes, or as big-endian words for Unicode
+    // streams.
+    pub fn open_resource(&mut self, resource: Option<Resource>, rock: u32, unicode: bool) -> StrId {
+        let resource = match resource {
+            Some(resource) => resource,
+            None => return StrId(0),
+        };
+        let fmode = super::filemode_Read;
+        match (&resource.chunk_type,unicode) {
+            (b"TEXT",false) | (b"BINA",false) => {
+                let data = resource.data.to_vec().into_boxed_slice();
+                self.open_memory((0,data), fmode, rock)
+            },
+            (b"TEXT",true) => {
+                let data: Vec<u32> = String::from_utf8_lossy(resource.data).chars().map(|ch| ch as u32).collect();
+                self.open_memory_uni((0,data.into_boxed_slice()), fmode, rock)
+            },
+            (b"BINA",true) => {
+                let data: Vec<u32> = resource.data.chunks(4).filter(|word| word.len() == 4).map(|word| (word[0] as u32) << 24 | (word[1] as u32) << 16 | (word[2] as u32) << 8 | word[3] as u32).collect();
+                self.open_memory_uni((0,data.into_boxed_slice()), fmode, rock)
+            },
+            _ => StrId(0),
+        }
+    }
+
+    pub fn close(&mut self, str: &StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        let stream = match self.list.get_mut(str.0).and_then(|s| s.take()) {
+            Some(stream) => stream,
+            None => return (0,0,None,None),
+        };
+        if self.current == *str {
+            self.current = StrId(0);
+        }
+        for s in self.list.iter() {
+            if let &Some(ref s) = s {
+                if let Kind::Window{ ref mut echo, .. } = s.lock().unwrap().kind {
+                    if *echo == *str {
+                        *echo = StrId(0);
+                    }
+                }
+            }
+        }
+        let mut stream = stream.lock().unwrap();
+        let (buf,buf_uni) = match stream.kind {
+            Kind::Memory(ref mut buf) => (buf.take(),None),
+            Kind::MemoryUni(ref mut buf) => (None,buf.take()),
+            Kind::File{ ref mut file, .. } => {
+                let _ = file.flush();
+                (None,None)
+            },
+            Kind::Window{ .. } => (None,None),
+        };
+        (stream.readcount,stream.writecount,buf,buf_uni)
+    }
+
+    pub fn iterate(&self, str: &StrId) -> (StrId,u32) {
+        for i in str.0 + 1 .. self.list.len() {
+            if let Some(ref s) = self.list[i] {
+                return (StrId(i),s.lock().unwrap().rock);
+            }
+        }
+        (StrId(0),0)
+    }
+
+    pub fn rock(&self, str: &StrId) -> u32 {
+        self.with(str, 0, |s| s.rock)
+    }
+
+    // The window of a window stream.
+    pub fn window(&self, str: &StrId) -> Option<W> {
+        self.with(str, None, |s| match s.kind {
+            Kind::Window{ ref win, .. } => Some(win.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn set_echo(&mut self, winstr: &StrId, str: &StrId) {
+        self.with(winstr, (), |s| if let Kind::Window{ ref mut echo, .. } = s.kind {
+            *echo = *str;
+        });
+    }
+
+    pub fn echo(&self, winstr: &StrId) -> StrId {
+        self.with(winstr, StrId(0), |s| match s.kind {
+            Kind::Window{ echo, .. } => echo,
+            _ => StrId(0),
+        })
+    }
+
+    pub fn set_position(&mut self, str: &StrId, pos: i32, seekmode: u32) {
+        self.with(str, (), |s| s.set_position(pos, seekmode));
+    }
+
+    pub fn position(&self, str: &StrId) -> u32 {
+        self.with(str, 0, |s| s.position())
+    }
+
+    pub fn set_current(&mut self, str: &StrId) {
+        self.current = *str;
+    }
+
+    pub fn current(&self) -> StrId {
+        self.current
+    }
+
+    pub fn put<S: WindowSink<W>>(&mut self, sink: &mut S, str: &StrId, s: &[u32]) {
+        let echo = self.with(str, StrId(0), |stream| stream.put(sink, s));
+        if !echo.is_null() && echo != *str {
+            self.put(sink, &echo, s);
+        }
+    }
+
+    // Latin-1 output.
+    pub fn put_bytes<S: WindowSink<W>>(&mut self, sink: &mut S, str: &StrId, s: &[u8]) {
+        let chars: Vec<u32> = s.iter().map(|&ch| ch as u32).collect();
+        self.put(sink, str, &chars);
+    }
+
+    pub fn set_style<S: WindowSink<W>>(&mut self, sink: &mut S, str: &StrId, styl: u32) {
+        let echo = self.with(str, StrId(0), |stream| match stream.kind {
+            Kind::Window{ ref win, echo } => {
+                sink.set_window_style(win, styl);
+                echo
+            },
+            _ => StrId(0),
+        });
+        if !echo.is_null() && echo != *str {
+            self.set_style(sink, &echo, styl);
+        }
+    }
+
+    pub fn set_hyperlink<S: WindowSink<W>>(&mut self, sink: &mut S, str: &StrId, linkval: u32) {
+        let echo = self.with(str, StrId(0), |stream| match stream.kind {
+            Kind::Window{ ref win, echo } => {
+                sink.set_window_hyperlink(win, linkval);
+                echo
+            },
+            _ => StrId(0),
+        });
+        if !echo.is_null() && echo != *str {
+            self.set_hyperlink(sink, &echo, linkval);
+        }
+    }
+
+    // Non-Unicode reads give '?' for characters beyond Latin-1.
+    pub fn get_char(&mut self, str: &StrId, unicode: bool) -> i32 {
+        self.with(str, -1, |s| match s.get() {
+            Some(ch) if !unicode && ch > 0xff => '?' as i32,
+            Some(ch) => ch as i32,
+            None => -1,
+        })
+    }
+
+    pub fn get_buffer(&mut self, str: &StrId, buf: &mut [u8]) -> u32 {
+        self.with(str, 0, |s| {
+            let mut count = 0;
+            while count < buf.len() {
+                match s.get() {
+                    Some(ch) => buf[count] = if ch > 0xff { b'?' } else { ch as u8 },
+                    None => break,
+                }
+                count += 1;
+            }
+            count as u32
+        })
+    }
+
+    pub fn get_buffer_uni(&mut self, str: &StrId, buf: &mut [u32]) -> u32 {
+        self.with(str, 0, |s| {
+            let mut count = 0;
+            while count < buf.len() {
+                match s.get() {
+                    Some(ch) => buf[count] = ch,
+                    None => break,
+                }
+                count += 1;
+            }
+            count as u32
+        })
+    }
+
+    // Reads up to a newline, leaving room for a terminating null.
+    pub fn get_line(&mut self, str: &StrId, buf: &mut [u8]) -> u32 {
+        self.with(str, 0, |s| {
+            let mut count = 0;
+            while count + 1 < buf.len() {
+                match s.get() {
+                    Some(ch) => buf[count] = if ch > 0xff { b'?' } else { ch as u8 },
+                    None => break,
+                }
+                count += 1;
+                if buf[count-1] == b'\n' {
+                    break;
+                }
+            }
+            if count < buf.len() {
+                buf[count] = 0;
+            }
+            count as u32
+        })
+    }
+
+    pub fn get_line_uni(&mut self, str: &StrId, buf: &mut [u32]) -> u32 {
+        self.with(str, 0, |s| {
+            let mut count = 0;
+            while count + 1 < buf.len() {
+                match s.get() {
+                    Some(ch) => buf[count] = ch,
+                    None => break,
+                }
+                count += 1;
+                if buf[count-1] == '\n' as u32 {
+                    break;
+                }
+            }
+            if count < buf.len() {
+                buf[count] = 0;
+            }
+            count as u32
+        })
+    }
+
+    // Byte-oriented access to a stream, for save files.
+    pub fn io_stream(&self, str: &StrId) -> IOStream<W> {
+        IOStream(self.list.get(str.0).and_then(|s| s.clone()))
+    }
+
+    fn add(&mut self, fmode: u32, rock: u32, kind: Kind<W>, end: usize) -> StrId {
+        let stream = Some(Arc::new(Mutex::new(Stream{ rock, fmode, readcount: 0, writecount: 0, kind, pos: 0, end })));
+        for i in 1 .. self.list.len() {
+            if self.list[i].is_none() {
+                self.list[i] = stream;
+                return StrId(i);
+            }
+        }
+        self.list.push(stream);
+        StrId(self.list.len() - 1)
+    }
+
+    fn with<T,F: FnOnce(&mut Stream<W>) -> T>(&self, str: &StrId, default: T, f: F) -> T {
+        match self.list.get(str.0) {
+            Some(&Some(ref s)) => f(&mut s.lock().unwrap()),
+            _ => default,
+        }
+    }
+}
+
+impl<W> Stream<W> {
+    // Returns the echo stream of a window stream.
+    fn put<S: WindowSink<W>>(&mut self, sink: &mut S, s: &[u32]) -> StrId {
+        if self.fmode == super::filemode_Read {
+            return StrId(0);
+        }
+        self.writecount += s.len() as u32;
+        match self.kind {
+            Kind::Window{ ref win, echo } => {
+                sink.put_window(win, s);
+                return echo;
+            },
+            Kind::Memory(Some((_,ref mut buf))) => {
+                for &ch in s {
+                    if self.pos >= buf.len() {
+                        break;
+                    }
+                    buf[self.pos] = if ch > 0xff { b'?' } else { ch as u8 };
+                    self.pos += 1;
+                }
+            },
+            Kind::MemoryUni(Some((_,ref mut buf))) => {
+                for &ch in s {
+                    if self.pos >= buf.len() {
+                        break;
+                    }
+                    buf[self.pos] = ch;
+                    self.pos += 1;
+                }
+            },
+            Kind::File{ ref mut file, text, unicode, encoding } => {
+                let mut bytes = Vec::with_capacity(s.len());
+                for &ch in s {
+                    encode(&mut bytes, ch, text, unicode, encoding);
+                }
+                let _ = file.write_all(&bytes);
+            },
+            _ => (),
+        }
+        self.end = ::std::cmp::max(self.end, self.pos);
+        StrId(0)
+    }
+
+    fn get(&mut self) -> Option<u32> {
+        if self.fmode == super::filemode_Write || self.fmode == super::filemode_WriteAppend {
+            return None;
+        }
+        let ch = match self.kind {
+            Kind::Memory(Some((_,ref buf))) if self.pos < self.end && self.pos < buf.len() => {
+                self.pos += 1;
+                Some(buf[self.pos-1] as u32)
+            },
+            Kind::MemoryUni(Some((_,ref buf))) if self.pos < self.end && self.pos < buf.len() => {
+                self.pos += 1;
+                Some(buf[self.pos-1])
+            },
+            Kind::File{ ref mut file, text, unicode, encoding } => decode(file, text, unicode, encoding),
+            _ => None,
+        };
+        if ch.is_some() {
+            self.readcount += 1;
+        }
+        ch
+    }
+
+    fn set_position(&mut self, pos: i32, seekmode: u32) {
+        match self.kind {
+            Kind::File{ ref mut file, text, unicode, .. } => {
+                // Binary Unicode files are positioned in words.
+                let scale = if unicode && !text { 4 } else { 1 };
+                let pos = pos as i64 * scale;
+                let _ = match seekmode {
+                    super::seekmode_Start => file.seek(SeekFrom::Start(::std::cmp::max(pos, 0) as u64)),
+                    super::seekmode_Current => file.seek(SeekFrom::Current(pos)),
+                    super::seekmode_End => file.seek(SeekFrom::End(pos)),
+                    _ => return,
+                };
+            },
+            Kind::Window{ .. } => (),
+            _ => {
+                let pos = match seekmode {
+                    super::seekmode_Start => pos as isize,
+                    super::seekmode_Current => self.pos as isize + pos as isize,
+                    super::seekmode_End => self.end as isize + pos as isize,
+                    _ => return,
+                };
+                self.pos = ::std::cmp::min(::std::cmp::max(pos, 0) as usize, self.end_limit());
+            },
+        }
+    }
+
+    fn position(&mut self) -> u32 {
+        match self.kind {
+            Kind::File{ ref mut file, text, unicode, .. } => {
+                let pos = file.seek(SeekFrom::Current(0)).unwrap_or(0);
+                (if unicode && !text { pos / 4 } else { pos }) as u32
+            },
+            Kind::Window{ .. } => 0,
+            _ => self.pos as u32,
+        }
+    }
+
+    fn end_limit(&self) -> usize {
+        match self.kind {
+            Kind::Memory(Some((_,ref buf))) => buf.len(),
+            Kind::MemoryUni(Some((_,ref buf))) => buf.len(),
+            _ => 0,
+        }
+    }
+}
+
+fn encode(bytes: &mut Vec<u8>, ch: u32, text: bool, unicode: bool, encoding: Encoding) {
+    if !text && unicode {
+        bytes.extend_from_slice(&[(ch >> 24) as u8, (ch >> 16) as u8, (ch >> 8) as u8, ch as u8]);
+    } else if !text || encoding == Encoding::Latin1 {
+        bytes.push(if ch > 0xff { b'?' } else { ch as u8 });
+    } else {
+        let ch = ::std::char::from_u32(ch).unwrap_or('?');
+        let mut buf = [0; 4];
+        bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+    }
+}
+
+fn decode(file: &mut File, text: bool, unicode: bool, encoding: Encoding) -> Option<u32> {
+    let mut byte = [0; 1];
+    if file.read(&mut byte).ok()? == 0 {
+        return None;
+    }
+    if !text && unicode {
+        let mut rest = [0; 3];
+        file.read_exact(&mut rest).ok()?;
+        return Some((byte[0] as u32) << 24 | (rest[0] as u32) << 16 | (rest[1] as u32) << 8 | rest[2] as u32);
+    }
+    if !text || encoding == Encoding::Latin1 || byte[0] < 0x80 {
+        return Some(byte[0] as u32);
+    }
+    let len = match byte[0] {
+        0xc0 ..= 0xdf => 2,
+        0xe0 ..= 0xef => 3,
+        0xf0 ..= 0xf7 => 4,
+        _ => return Some('?' as u32),
+    };
+    let mut buf = [byte[0],0,0,0];
+    file.read_exact(&mut buf[1..len]).ok()?;
+    match ::std::str::from_utf8(&buf[..len]) {
+        Ok(s) => s.chars().next().map(|ch| ch as u32),
+        Err(_) => Some('?' as u32),
+    }
+}
+
+pub struct IOStream<W>(Option<Arc<Mutex<Stream<W>>>>);
+
+fn not_connected() -> Error {
+    Error::new(ErrorKind::NotConnected, "stream is closed")
+}
+
+impl<W> Read for IOStream<W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = self.0.as_ref().ok_or_else(not_connected)?;
+        let mut stream = stream.lock().unwrap();
+        let mut count = 0;
+        while count < buf.len() {
+            match stream.get() {
+                Some(ch) => buf[count] = ch as u8,
+                None => break,
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+struct NoWindow;
+
+impl<W> WindowSink<W> for NoWindow {
+    fn put_window(&mut self, _win: &W, _s: &[u32]) {
+    }
+}
+
+impl<W> Write for IOStream<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let stream = self.0.as_ref().ok_or_else(not_connected)?;
+        let chars: Vec<u32> = buf.iter().map(|&b| b as u32).collect();
+        stream.lock().unwrap().put(&mut NoWindow, &chars);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> Seek for IOStream<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let stream = self.0.as_ref().ok_or_else(not_connected)?;
+        let mut stream = stream.lock().unwrap();
+        match pos {
+            SeekFrom::Start(pos) => stream.set_position(pos as i32, super::seekmode_Start),
+            SeekFrom::Current(pos) => stream.set_position(pos as i32, super::seekmode_Current),
+            SeekFrom::End(pos) => stream.set_position(pos as i32, super::seekmode_End),
+        }
+        Ok(stream.position() as u64)
+    }
+}
+
+// The name of a file created by name, stripped of characters that are
+// unsafe in file names and given a suffix for its usage.
+pub fn file_name<S: AsRef<[u8]>>(usage: u32, name: S) -> String {
+    let mut base = String::new();
+    for &b in name.as_ref() {
+        match b {
+            b'.' if !base.is_empty() => break,
+            b'/' | b'\\' | b'<' | b'>' | b':' | b'|' | b'?' | b'*' | b'"' | b'\'' | b'.' => (),
+            0 ..= 0x1f | 0x7f ..= 0xff => (),
+            _ => base.push(b as char),
+        }
+    }
+    if base.is_empty() {
+        base.push_str("null");
+    }
+    let suffix = match usage & super::fileusage_TypeMask {
+        super::fileusage_SavedGame => ".glksave",
+        super::fileusage_Transcript | super::fileusage_InputRecord => ".txt",
+        _ => ".glkdata",
+    };
+    base + suffix
+}
+
+pub struct FileRef {
+    pub rock: u32,
+    pub path: PathBuf,
+    pub usage: u32,
+}
+
+pub struct FileRefs {
+    list: Vec<Option<FileRef>>,
+    dir: PathBuf,
+}
+
+static TEMP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+impl FileRefs {
+    // Files created by name are put in dir.
+    pub fn new(dir: PathBuf) -> Self {
+        FileRefs{ list: vec![None], dir }
+    }
+
+    pub fn get(&self, fref: &FRefId) -> Option<&FileRef> {
+        self.list.get(fref.0).and_then(|f| f.as_ref())
+    }
+
+    // Temporary files are created here, so that no other FileRefs in
+    // this process or another can have the same one.
+    pub fn create_temp(&mut self, usage: u32, rock: u32) -> FRefId {
+        loop {
+            let count = TEMP_COUNT.fetch_add(1, Ordering::SeqCst);
+            let path = ::std::env::temp_dir().join(format!("glktmp-{}-{}", ::std::process::id(), count));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return self.create_by_path(path, usage, rock),
+                Err(ref err) if err.kind() == ErrorKind::AlreadyExists => (),
+                Err(_) => return FRefId(0),
+            }
+        }
+    }
+
+    pub fn create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> FRefId {
+        let path = self.dir.join(file_name(usage, name));
+        self.create_by_path(path, usage, rock)
+    }
+
+    // For prompted names, which are used as given.
+    pub fn create_by_path(&mut self, path: PathBuf, usage: u32, rock: u32) -> FRefId {
+        let fref = Some(FileRef{ rock, path, usage });
+        for i in 1 .. self.list.len() {
+            if self.list[i].is_none() {
+                self.list[i] = fref;
+                return FRefId(i);
+            }
+        }
+        self.list.push(fref);
+        FRefId(self.list.len() - 1)
+    }
+
+    pub fn create_from_fileref(&mut self, usage: u32, fref: &FRefId, rock: u32) -> FRefId {
+        match self.get(fref).map(|f| f.path.clone()) {
+            Some(path) => self.create_by_path(path, usage, rock),
+            None => FRefId(0),
+        }
+    }
+
+    pub fn destroy(&mut self, fref: &FRefId) {
+        if let Some(f) = self.list.get_mut(fref.0) {
+            *f = None;
+        }
+    }
+
+    pub fn iterate(&self, fref: &FRefId) -> (FRefId,u32) {
+        for i in fref.0 + 1 .. self.list.len() {
+            if let Some(ref f) = self.list[i] {
+                return (FRefId(i),f.rock);
+            }
+        }
+        (FRefId(0),0)
+    }
+
+    pub fn rock(&self, fref: &FRefId) -> u32 {
+        self.get(fref).map(|f| f.rock).unwrap_or(0)
+    }
+
+    pub fn delete_file(&self, fref: &FRefId) {
+        if let Some(f) = self.get(fref) {
+            let _ = ::std::fs::remove_file(&f.path);
+        }
+    }
+
+    pub fn does_file_exist(&self, fref: &FRefId) -> bool {
+        self.get(fref).map(|f| f.path.is_file()).unwrap_or(false)
+    }
+}
@@ -0,0 +1,897 @@
+// A Glk wrapper that keeps a transcript of the main window, the root
+// window the game opens first, rendered as HTML or Markdown.  Text styles
+// become CSS classes or emphasis, and the player's line input can be
+// included in the Input style.
+
+use std::io;
+use std::io::{Error,Write};
+use std::marker::PhantomData;
+use std::mem;
+
+use super::{Glk,IdType};
+use super::record::Event;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Format {
+    Html,
+    Markdown,
+}
+
+pub struct TranscriptGlk<'a,G: Glk<'a>,W: Write> {
+    glk: G,
+    out: W,
+    format: Format,
+    include_input: bool,
+    error: Option<Error>,
+    title: String,
+    started: bool,
+    finished: bool,
+    main: Option<G::WinId>,
+    main_str: Option<G::StrId>,
+    current: bool,
+    style: u32,
+    echo: bool,
+    runs: Vec<(u32,String)>,
+    line_empty: bool,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a,G: Glk<'a>,W: Write> TranscriptGlk<'a,G,W> {
+    pub fn new(glk: G, out: W, format: Format) -> Self {
+        TranscriptGlk{
+            glk,
+            out,
+            format,
+            include_input: false,
+            error: None,
+            title: "Transcript".to_string(),
+            started: false,
+            finished: false,
+            main: None,
+            main_str: None,
+            current: false,
+            style: super::style_Normal,
+            echo: true,
+            runs: Vec::new(),
+            line_empty: true,
+            phantom: PhantomData,
+        }
+    }
+
+    // Whether to write the player's line input to the transcript, as the
+    // library echoes it.
+    pub fn set_include_input(&mut self, include_input: bool) {
+        self.include_input = include_input;
+    }
+
+    pub fn glk(&mut self) -> &mut G {
+        &mut self.glk
+    }
+
+    // Returns the first error writing the transcript, if any.
+    pub fn finish(mut self) -> (G,io::Result<W>) {
+        self.end();
+        let TranscriptGlk{ glk, out, error, .. } = self;
+        match error {
+            Some(err) => (glk,Err(err)),
+            None => (glk,Ok(out)),
+        }
+    }
+
+    fn is_main_str(&self, str: &G::StrId) -> bool {
+        self.main_str.as_ref() == Some(str)
+    }
+
+    fn push_bytes(&mut self, buf: &[u8]) {
+        let text: String = buf.iter().map(|&b| b as char).collect();
+        let style = self.style;
+        self.push(style, &text);
+    }
+
+    fn push_chars(&mut self, buf: &[u32]) {
+        let text: String = buf.iter().map(|&ch| ::std::char::from_u32(ch).unwrap_or('\u{fffd}')).collect();
+        let style = self.style;
+        self.push(style, &text);
+    }
+
+    fn push(&mut self, style: u32, text: &str) {
+        if let Some(&mut (last_style,ref mut last_text)) = self.runs.last_mut() {
+            if last_style == style {
+                last_text.push_str(text);
+                return;
+            }
+        }
+        self.runs.push((style,text.to_string()));
+    }
+
+    fn input(&mut self, event: &Event<G::WinId>) {
+        if !self.include_input || !self.echo || event.evtype != super::evtype_LineInput || self.main.as_ref() != Some(&event.win) {
+            return;
+        }
+        let len = event.val1 as usize;
+        let mut text = match (&event.buf,&event.buf_uni) {
+            (&Some((_,ref buf)),_) => buf[..len.min(buf.len())].iter().map(|&b| b as char).collect(),
+            (_,&Some((_,ref buf))) => buf[..len.min(buf.len())].iter().map(|&ch| ::std::char::from_u32(ch).unwrap_or('\u{fffd}')).collect(),
+            _ => String::new(),
+        };
+        text.push('\n');
+        self.push(super::style_Input, &text);
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.error.is_none() {
+            self.error = self.out.write_all(text.as_bytes()).err();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.finished {
+            return;
+        }
+        if !self.started {
+            self.started = true;
+            if self.format == Format::Html {
+                let header = html_header(&self.title);
+                self.write(&header);
+            }
+        }
+        let mut text = String::new();
+        for (style,run) in mem::replace(&mut self.runs, Vec::new()) {
+            match self.format {
+                Format::Html => html_run(&mut text, style, &run),
+                Format::Markdown => markdown_run(&mut text, &mut self.line_empty, style, &run),
+            }
+        }
+        self.write(&text);
+        if self.error.is_none() {
+            self.error = self.out.flush().err();
+        }
+    }
+
+    fn end(&mut self) {
+        self.flush();
+        if !self.finished && self.format == Format::Html {
+            self.write("</div>\n</body>\n</html>\n");
+            if self.error.is_none() {
+                self.error = self.out.flush().err();
+            }
+        }
+        self.finished = true;
+    }
+}
+
+fn style_class(style: u32) -> &'static str {
+    match style {
+        super::style_Emphasized => "emphasized",
+        super::style_Preformatted => "preformatted",
+        super::style_Header => "header",
+        super::style_Subheader => "subheader",
+        super::style_Alert => "alert",
+        super::style_Note => "note",
+        super::style_BlockQuote => "blockquote",
+        super::style_Input => "input",
+        super::style_User1 => "user1",
+        super::style_User2 => "user2",
+        _ => "normal",
+    }
+}
+
+fn html_escape(text: &mut String, s: &str) {
+    for ch in s.chars() {
+        match ch {
+            '&' => text.push_str("&amp;"),
+            '<' => text.push_str("&lt;"),
+            '>' => text.push_str("&gt;"),
+            '"' => text.push_str("&quot;"),
+            _ => text.push(ch),
+        }
+    }
+}
+
+fn html_header(title: &str) -> String {
+    let mut header = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    html_escape(&mut header, title);
+    header.push_str("</title>\n<style>\n\
+        .transcript { white-space: pre-wrap; font-family: serif; }\n\
+        .emphasized, .note { font-style: italic; }\n\
+        .preformatted { font-family: monospace; }\n\
+        .header { font-weight: bold; font-size: larger; }\n\
+        .subheader, .alert, .input { font-weight: bold; }\n\
+        .alert { color: #a00; }\n\
+        .blockquote { display: inline-block; margin-left: 2em; }\n\
+        </style>\n</head>\n<body>\n<div class=\"transcript\">\n");
+    header
+}
+
+fn html_run(text: &mut String, style: u32, run: &str) {
+    if style == super::style_Normal {
+        html_escape(text, run);
+    } else {
+        text.push_str(&format!("<span class=\"{}\">", style_class(style)));
+        html_escape(text, run);
+        text.push_str("</span>");
+    }
+}
+
+fn markdown_marker(style: u32) -> &'static str {
+    match style {
+        super::style_Emphasized | super::style_Note => "*",
+        super::style_Header | super::style_Subheader | super::style_Alert | super::style_Input => "**",
+        _ => "",
+    }
+}
+
+// Markers go around the text of each line, since emphasis can't span
+// lines, and lines with text end in a hard line break.
+fn markdown_run(text: &mut String, line_empty: &mut bool, style: u32, run: &str) {
+    let marker = markdown_marker(style);
+    for (i,line) in run.split('\n').enumerate() {
+        if i > 0 {
+            text.push_str(if *line_empty { "\n" } else { "  \n" });
+            *line_empty = true;
+        }
+        let start = line.len() - line.trim_start().len();
+        let end = line.trim_end().len();
+        if start >= end {
+            text.push_str(line);
+            continue;
+        }
+        *line_empty = false;
+        text.push_str(&line[..start]);
+        text.push_str(marker);
+        for ch in line[start..end].chars() {
+            match ch {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' => {
+                    text.push('\\');
+                    text.push(ch);
+                },
+                _ => text.push(ch),
+            }
+        }
+        text.push_str(marker);
+        text.push_str(&line[end..]);
+    }
+}
+
+impl<'a,G: Glk<'a>,W: Write> Glk<'a> for TranscriptGlk<'a,G,W> {
+    type WinId = G::WinId;
+    type StrId = G::StrId;
+    type FRefId = G::FRefId;
+    type SChanId = G::SChanId;
+    type Event = Event<G::WinId>;
+    type TimeVal = G::TimeVal;
+    type Date = G::Date;
+    type IOStream = G::IOStream;
+
+    fn exit(&mut self) -> ! {
+        self.end();
+        self.glk.exit()
+    }
+
+    fn set_interrupt_handler(&mut self, handler: extern fn()) {
+        self.glk.set_interrupt_handler(handler);
+    }
+
+    fn tick(&mut self) {
+        self.glk.tick();
+    }
+
+
+    fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
+        self.glk.gestalt(sel, val)
+    }
+
+    fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
+        self.glk.gestalt_ext(sel, val, arr)
+    }
+
+
+    fn char_to_lower(&mut self, ch: u8) -> u8 {
+        self.glk.char_to_lower(ch)
+    }
+
+    fn char_to_upper(&mut self, ch: u8) -> u8 {
+        self.glk.char_to_upper(ch)
+    }
+
+
+    fn window_get_root(&mut self) -> Self::WinId {
+        self.glk.window_get_root()
+    }
+
+    fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
+        let win = self.glk.window_open(split, method, size, wintype, rock);
+        if self.main.is_none() && split.is_null() && !win.is_null() {
+            self.main_str = Some(self.glk.window_get_stream(&win));
+            self.main = Some(win.clone());
+        }
+        win
+    }
+
+    fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        if self.main.as_ref() == Some(win) {
+            self.main = None;
+            self.main_str = None;
+            self.current = false;
+        }
+        self.glk.window_close(win)
+    }
+
+    fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
+        self.glk.window_get_size(win)
+    }
+
+    fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
+        self.glk.window_set_arrangement(win, method, size, keywin);
+    }
+
+    fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
+        self.glk.window_get_arrangement(win)
+    }
+
+    fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
+        self.glk.window_iterate(win)
+    }
+
+    fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
+        self.glk.window_get_rock(win)
+    }
+
+    fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
+        self.glk.window_get_type(win)
+    }
+
+    fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
+        self.glk.window_get_parent(win)
+    }
+
+    fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
+        self.glk.window_get_sibling(win)
+    }
+
+    fn window_clear(&mut self, win: &Self::WinId) {
+        self.glk.window_clear(win);
+    }
+
+    fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
+        self.glk.window_move_cursor(win, xpos, ypos);
+    }
+
+
+    fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        self.glk.window_get_stream(win)
+    }
+
+    fn window_set_echo_stream(&mut self, win: &Self::WinId, str: &Self::StrId) {
+        self.glk.window_set_echo_stream(win, str);
+    }
+
+    fn window_get_echo_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        self.glk.window_get_echo_stream(win)
+    }
+
+    fn set_window(&mut self, win: &Self::WinId) {
+        self.current = self.main.as_ref() == Some(win);
+        self.glk.set_window(win);
+    }
+
+
+    fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.glk.stream_open_file(fileref, fmode, rock)
+    }
+
+    fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
+        self.glk.stream_open_memory(buf, fmode, rock)
+    }
+
+    fn stream_close(&mut self, str: &mut Self::StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        self.glk.stream_close(str)
+    }
+
+    fn stream_iterate(&mut self, str: &Self::StrId) -> (Self::StrId,u32) {
+        self.glk.stream_iterate(str)
+    }
+
+    fn stream_get_rock(&mut self, str: &Self::StrId) -> u32 {
+        self.glk.stream_get_rock(str)
+    }
+
+    fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
+        self.glk.stream_set_position(str, pos, seekmode);
+    }
+
+    fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
+        self.glk.stream_get_position(str)
+    }
+
+    fn stream_set_current(&mut self, str: &Self::StrId) {
+        self.current = self.is_main_str(str);
+        self.glk.stream_set_current(str);
+    }
+
+    fn stream_get_current(&mut self) -> Self::StrId {
+        self.glk.stream_get_current()
+    }
+
+
+    fn put_char(&mut self, ch: u8) {
+        if self.current {
+            self.push_bytes(&[ch]);
+        }
+        self.glk.put_char(ch);
+    }
+
+    fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
+        if self.is_main_str(str) {
+            self.push_bytes(&[ch]);
+        }
+        self.glk.put_char_stream(str, ch);
+    }
+
+    fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
+        if self.current {
+            self.push_bytes(s.as_ref());
+        }
+        self.glk.put_string(s);
+    }
+
+    fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &Self::StrId, s: S) {
+        if self.is_main_str(str) {
+            self.push_bytes(s.as_ref());
+        }
+        self.glk.put_string_stream(str, s);
+    }
+
+    fn put_buffer(&mut self, buf: &[u8]) {
+        if self.current {
+            self.push_bytes(buf);
+        }
+        self.glk.put_buffer(buf);
+    }
+
+    fn put_buffer_stream(&mut self, str: &Self::StrId, buf: &[u8]) {
+        if self.is_main_str(str) {
+            self.push_bytes(buf);
+        }
+        self.glk.put_buffer_stream(str, buf);
+    }
+
+    fn set_style(&mut self, styl: u32) {
+        if self.current {
+            self.style = styl;
+        }
+        self.glk.set_style(styl);
+    }
+
+    fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
+        if self.is_main_str(str) {
+            self.style = styl;
+        }
+        self.glk.set_style_stream(str, styl);
+    }
+
+
+    fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
+        self.glk.get_char_stream(str)
+    }
+
+    fn get_line_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        self.glk.get_line_stream(str, buf)
+    }
+
+    fn get_buffer_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        self.glk.get_buffer_stream(str, buf)
+    }
+
+
+    fn stylehint_set(&mut self, wintype: u32, styl: u32, hint: u32, val: i32) {
+        self.glk.stylehint_set(wintype, styl, hint, val);
+    }
+
+    fn stylehint_clear(&mut self, wintype: u32, styl: u32, hint: u32) {
+        self.glk.stylehint_clear(wintype, styl, hint);
+    }
+
+    fn style_distinguish(&mut self, win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
+        self.glk.style_distinguish(win, styl1, styl2)
+    }
+
+    fn style_measure(&mut self, win: &Self::WinId, styl: u32, hint: u32) -> (bool,u32) {
+        self.glk.style_measure(win, styl, hint)
+    }
+
+
+    fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
+        self.glk.fileref_create_temp(usage, rock)
+    }
+
+    fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
+        self.glk.fileref_create_by_name(usage, name, rock)
+    }
+
+    fn fileref_create_by_prompt(&mut self, usage: u32, fmode: u32, rock: u32) -> Self::FRefId {
+        self.glk.fileref_create_by_prompt(usage, fmode, rock)
+    }
+
+    fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
+        self.glk.fileref_create_from_fileref(usage, fref, rock)
+    }
+
+    fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
+        self.glk.fileref_destroy(fref);
+    }
+
+    fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
+        self.glk.fileref_iterate(fref)
+    }
+
+    fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
+        self.glk.fileref_get_rock(fref)
+    }
+
+    fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
+        self.glk.fileref_delete_file(fref);
+    }
+
+    fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
+        self.glk.fileref_does_file_exist(fref)
+    }
+
+
+    fn select(&mut self) -> Self::Event {
+        self.flush();
+        let event = Event::new(self.glk.select());
+        self.input(&event);
+        event
+    }
+
+    fn select_poll(&mut self) -> Self::Event {
+        let event = Event::new(self.glk.select_poll());
+        self.input(&event);
+        event
+    }
+
+
+    fn request_timer_events(&mut self, millisecs: u32) {
+        self.glk.request_timer_events(millisecs);
+    }
+
+
+    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
+        self.glk.request_line_event(win, buf, initlen);
+    }
+
+    fn request_char_event(&mut self, win: &Self::WinId) {
+        self.glk.request_char_event(win);
+    }
+
+    fn request_mouse_event(&mut self, win: &Self::WinId) {
+        self.glk.request_mouse_event(win);
+    }
+
+
+    fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
+        Event::new(self.glk.cancel_line_event(win))
+    }
+
+    fn cancel_char_event(&mut self, win: &Self::WinId) {
+        self.glk.cancel_char_event(win);
+    }
+
+    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
+        self.glk.cancel_mouse_event(win);
+    }
+
+
+    fn set_echo_line_event(&mut self, win: &Self::WinId, val: u32) {
+        if self.main.as_ref() == Some(win) {
+            self.echo = val != 0;
+        }
+        self.glk.set_echo_line_event(win, val);
+    }
+
+
+    fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
+        self.glk.set_terminators_line_event(win, keycodes);
+    }
+
+
+    fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        self.glk.buffer_to_lower_case_uni(buf, numchars)
+    }
+
+    fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        self.glk.buffer_to_upper_case_uni(buf, numchars)
+    }
+
+    fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
+        self.glk.buffer_to_title_case_uni(buf, numchars, lowerrest)
+    }
+
+
+    fn put_char_uni(&mut self, ch: u32) {
+        if self.current {
+            self.push_chars(&[ch]);
+        }
+        self.glk.put_char_uni(ch);
+    }
+
+    fn put_string_uni<SU: AsRef<[u32]>>(&mut self, s: SU) {
+        if self.current {
+            self.push_chars(s.as_ref());
+        }
+        self.glk.put_string_uni(s);
+    }
+
+    fn put_buffer_uni(&mut self, buf: &[u32]) {
+        if self.current {
+            self.push_chars(buf);
+        }
+        self.glk.put_buffer_uni(buf);
+    }
+
+    fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
+        if self.is_main_str(str) {
+            self.push_chars(&[ch]);
+        }
+        self.glk.put_char_stream_uni(str, ch);
+    }
+
+    fn put_string_stream_uni<SU: AsRef<[u32]>>(&mut self, str: &Self::StrId, s: SU) {
+        if self.is_main_str(str) {
+            self.push_chars(s.as_ref());
+        }
+        self.glk.put_string_stream_uni(str, s);
+    }
+
+    fn put_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &[u32]) {
+        if self.is_main_str(str) {
+            self.push_chars(buf);
+        }
+        self.glk.put_buffer_stream_uni(str, buf);
+    }
+
+
+    fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
+        self.glk.get_char_stream_uni(str)
+    }
+
+    fn get_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        self.glk.get_buffer_stream_uni(str, buf)
+    }
+
+    fn get_line_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        self.glk.get_line_stream_uni(str, buf)
+    }
+
+
+    fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.glk.stream_open_file_uni(fileref, fmode, rock)
+    }
+
+    fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
+        self.glk.stream_open_memory_uni(buf, fmode, rock)
+    }
+
+
+    fn request_char_event_uni(&mut self, win: &Self::WinId) {
+        self.glk.request_char_event_uni(win);
+    }
+
+    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
+        self.glk.request_line_event_uni(win, buf, initlen);
+    }
+
+
+    fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        self.glk.buffer_canon_decompose_uni(buf, numchars)
+    }
+
+    fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        self.glk.buffer_canon_normalize_uni(buf, numchars)
+    }
+
+
+    fn image_draw(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32) -> bool {
+        self.glk.image_draw(win, image, val1, val2)
+    }
+
+    fn image_draw_scaled(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> bool {
+        self.glk.image_draw_scaled(win, image, val1, val2, width, height)
+    }
+
+    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
+        self.glk.image_get_info(image)
+    }
+
+
+    fn window_flow_break(&mut self, win: &Self::WinId) {
+        self.glk.window_flow_break(win);
+    }
+
+
+    fn window_erase_rect(&mut self, win: &Self::WinId, left: i32, top: i32, width: u32, height: u32) {
+        self.glk.window_erase_rect(win, left, top, width, height);
+    }
+
+    fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32) {
+        self.glk.window_fill_rect(win, color, left, top, width, height);
+    }
+
+    fn window_set_background_color(&mut self, win: &Self::WinId, color: u32) {
+        self.glk.window_set_background_color(win, color);
+    }
+
+
+    fn image_draw_scaled_ext(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32, imagerule: u32, maxwidth: u32) -> bool {
+        self.glk.image_draw_scaled_ext(win, image, val1, val2, width, height, imagerule, maxwidth)
+    }
+
+
+    fn schannel_create(&mut self, rock: u32) -> Self::SChanId {
+        self.glk.schannel_create(rock)
+    }
+
+    fn schannel_destroy(&mut self, chan: &mut Self::SChanId) {
+        self.glk.schannel_destroy(chan);
+    }
+
+    fn schannel_iterate(&mut self, chan: &Self::SChanId) -> (Self::SChanId,u32) {
+        self.glk.schannel_iterate(chan)
+    }
+
+    fn schannel_get_rock(&mut self, chan: &Self::SChanId) -> u32 {
+        self.glk.schannel_get_rock(chan)
+    }
+
+
+    fn schannel_play(&mut self, chan: &Self::SChanId, snd: u32) -> bool {
+        self.glk.schannel_play(chan, snd)
+    }
+
+    fn schannel_play_ext(&mut self, chan: &Self::SChanId, snd: u32, repeat: u32, notify: u32) -> bool {
+        self.glk.schannel_play_ext(chan, snd, repeat, notify)
+    }
+
+    fn schannel_stop(&mut self, chan: &Self::SChanId) {
+        self.glk.schannel_stop(chan);
+    }
+
+    fn schannel_set_volume(&mut self, chan: &Self::SChanId, vol: u32) {
+        self.glk.schannel_set_volume(chan, vol);
+    }
+
+
+    fn sound_load_hint(&mut self, snd: u32, flag: u32) {
+        self.glk.sound_load_hint(snd, flag);
+    }
+
+
+    fn schannel_create_ext(&mut self, rock: u32, volume: u32) -> Self::SChanId {
+        self.glk.schannel_create_ext(rock, volume)
+    }
+
+    fn schannel_play_multi(&mut self, chanarray: &[Self::SChanId], sndarray: &[u32], notify: u32) -> bool {
+        self.glk.schannel_play_multi(chanarray, sndarray, notify)
+    }
+
+    fn schannel_pause(&mut self, chan: &Self::SChanId) {
+        self.glk.schannel_pause(chan);
+    }
+
+    fn schannel_unpause(&mut self, chan: &Self::SChanId) {
+        self.glk.schannel_unpause(chan);
+    }
+
+    fn schannel_set_volume_ext(&mut self, chan: &Self::SChanId, vol: u32, duration: u32, notify: u32) {
+        self.glk.schannel_set_volume_ext(chan, vol, duration, notify);
+    }
+
+
+    fn set_hyperlink(&mut self, linkval: u32) {
+        self.glk.set_hyperlink(linkval);
+    }
+
+    fn set_hyperlink_stream(&mut self, str: &Self::StrId, linkval: u32) {
+        self.glk.set_hyperlink_stream(str, linkval);
+    }
+
+    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
+        self.glk.request_hyperlink_event(win);
+    }
+
+    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
+        self.glk.cancel_hyperlink_event(win);
+    }
+
+
+    fn current_time(&mut self) -> Self::TimeVal {
+        self.glk.current_time()
+    }
+
+    fn current_simple_time(&mut self, factor: u32) -> i32 {
+        self.glk.current_simple_time(factor)
+    }
+
+    fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
+        self.glk.time_to_date_utc(time)
+    }
+
+    fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
+        self.glk.time_to_date_local(time)
+    }
+
+    fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
+        self.glk.simple_time_to_date_utc(time, factor)
+    }
+
+    fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
+        self.glk.simple_time_to_date_local(time, factor)
+    }
+
+    fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
+        self.glk.date_to_time_utc(date)
+    }
+
+    fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
+        self.glk.date_to_time_local(date)
+    }
+
+    fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        self.glk.date_to_simple_time_utc(date, factor)
+    }
+
+    fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        self.glk.date_to_simple_time_local(date, factor)
+    }
+
+
+    fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        self.glk.stream_open_resource(filenum, rock)
+    }
+
+    fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        self.glk.stream_open_resource_uni(filenum, rock)
+    }
+
+
+    fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
+        self.glk.io_stream(str)
+    }
+
+
+    fn set_resource_map(&mut self, blorb: super::blorb::Blorb) {
+        self.glk.set_resource_map(blorb);
+    }
+
+
+    fn set_story_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        self.glk.set_story_title(title);
+    }
+
+
+    fn set_call_address(&mut self, addr: u32) {
+        self.glk.set_call_address(addr);
+    }
+
+
+    fn garglk_set_zcolors(&mut self, fg: u32, bg: u32) {
+        self.glk.garglk_set_zcolors(fg, bg);
+    }
+
+
+    fn garglk_set_zcolors_stream(&mut self, str: &Self::StrId, fg: u32, bg: u32) {
+        self.glk.garglk_set_zcolors_stream(str, fg, bg);
+    }
+
+
+    fn garglk_set_reversevideo(&mut self, reverse: u32) {
+        self.glk.garglk_set_reversevideo(reverse);
+    }
+
+
+    fn garglk_set_reversevideo_stream(&mut self, str: &Self::StrId, reverse: u32) {
+        self.glk.garglk_set_reversevideo_stream(str, reverse);
+    }
+}
@@ -0,0 +1,716 @@
+use std::io;
+use std::io::{Error,ErrorKind,SeekFrom};
+use std::marker::PhantomData;
+
+use super::{Glk,EventType,IdType};
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum WinType {
+    Pair,
+    Blank,
+    TextBuffer,
+    TextGrid,
+    Graphics,
+}
+
+impl WinType {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            WinType::Pair => super::wintype_Pair,
+            WinType::Blank => super::wintype_Blank,
+            WinType::TextBuffer => super::wintype_TextBuffer,
+            WinType::TextGrid => super::wintype_TextGrid,
+            WinType::Graphics => super::wintype_Graphics,
+        }
+    }
+
+    pub fn from_u32(wintype: u32) -> Option<Self> {
+        match wintype {
+            super::wintype_Pair => Some(WinType::Pair),
+            super::wintype_Blank => Some(WinType::Blank),
+            super::wintype_TextBuffer => Some(WinType::TextBuffer),
+            super::wintype_TextGrid => Some(WinType::TextGrid),
+            super::wintype_Graphics => Some(WinType::Graphics),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Above,
+    Below,
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Size {
+    Fixed(u32),
+    Proportional(u32),
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct WinMethod {
+    pub direction: Direction,
+    pub size: Size,
+    pub border: bool,
+}
+
+impl WinMethod {
+    pub fn new(direction: Direction, size: Size) -> Self {
+        WinMethod{ direction, size, border: true }
+    }
+
+    // The method and size arguments of window_open and
+    // window_set_arrangement.
+    pub fn to_u32(self) -> (u32,u32) {
+        let direction = match self.direction {
+            Direction::Left => super::winmethod_Left,
+            Direction::Right => super::winmethod_Right,
+            Direction::Above => super::winmethod_Above,
+            Direction::Below => super::winmethod_Below,
+        };
+        let (division,size) = match self.size {
+            Size::Fixed(size) => (super::winmethod_Fixed,size),
+            Size::Proportional(size) => (super::winmethod_Proportional,size),
+        };
+        let border = if self.border { super::winmethod_Border } else { super::winmethod_NoBorder };
+        (direction | division | border,size)
+    }
+
+    pub fn from_u32(method: u32, size: u32) -> Option<Self> {
+        let direction = match method & super::winmethod_DirMask {
+            super::winmethod_Left => Direction::Left,
+            super::winmethod_Right => Direction::Right,
+            super::winmethod_Above => Direction::Above,
+            super::winmethod_Below => Direction::Below,
+            _ => return None,
+        };
+        let size = match method & super::winmethod_DivisionMask {
+            super::winmethod_Fixed => Size::Fixed(size),
+            super::winmethod_Proportional => Size::Proportional(size),
+            _ => return None,
+        };
+        let border = method & super::winmethod_BorderMask == super::winmethod_Border;
+        Some(WinMethod{ direction, size, border })
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Style {
+    Normal,
+    Emphasized,
+    Preformatted,
+    Header,
+    Subheader,
+    Alert,
+    Note,
+    BlockQuote,
+    Input,
+    User1,
+    User2,
+}
+
+impl Style {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            Style::Normal => super::style_Normal,
+            Style::Emphasized => super::style_Emphasized,
+            Style::Preformatted => super::style_Preformatted,
+            Style::Header => super::style_Header,
+            Style::Subheader => super::style_Subheader,
+            Style::Alert => super::style_Alert,
+            Style::Note => super::style_Note,
+            Style::BlockQuote => super::style_BlockQuote,
+            Style::Input => super::style_Input,
+            Style::User1 => super::style_User1,
+            Style::User2 => super::style_User2,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum StyleHint {
+    Indentation,
+    ParaIndentation,
+    Justification,
+    Size,
+    Weight,
+    Oblique,
+    Proportional,
+    TextColor,
+    BackColor,
+    ReverseColor,
+}
+
+impl StyleHint {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            StyleHint::Indentation => super::stylehint_Indentation,
+            StyleHint::ParaIndentation => super::stylehint_ParaIndentation,
+            StyleHint::Justification => super::stylehint_Justification,
+            StyleHint::Size => super::stylehint_Size,
+            StyleHint::Weight => super::stylehint_Weight,
+            StyleHint::Oblique => super::stylehint_Oblique,
+            StyleHint::Proportional => super::stylehint_Proportional,
+            StyleHint::TextColor => super::stylehint_TextColor,
+            StyleHint::BackColor => super::stylehint_BackColor,
+            StyleHint::ReverseColor => super::stylehint_ReverseColor,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum FileUsage {
+    Data,
+    SavedGame,
+    Transcript,
+    InputRecord,
+}
+
+impl FileUsage {
+    pub fn to_u32(self, text_mode: bool) -> u32 {
+        let usage = match self {
+            FileUsage::Data => super::fileusage_Data,
+            FileUsage::SavedGame => super::fileusage_SavedGame,
+            FileUsage::Transcript => super::fileusage_Transcript,
+            FileUsage::InputRecord => super::fileusage_InputRecord,
+        };
+        usage | if text_mode { super::fileusage_TextMode } else { super::fileusage_BinaryMode }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum FileMode {
+    Write,
+    Read,
+    ReadWrite,
+    WriteAppend,
+}
+
+impl FileMode {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            FileMode::Write => super::filemode_Write,
+            FileMode::Read => super::filemode_Read,
+            FileMode::ReadWrite => super::filemode_ReadWrite,
+            FileMode::WriteAppend => super::filemode_WriteAppend,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Key {
+    Char(char),
+    Unknown,
+    Left,
+    Right,
+    Up,
+    Down,
+    Return,
+    Delete,
+    Escape,
+    Tab,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Func(u32),
+}
+
+impl Key {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            Key::Char(ch) => ch as u32,
+            Key::Unknown => super::keycode_Unknown,
+            Key::Left => super::keycode_Left,
+            Key::Right => super::keycode_Right,
+            Key::Up => super::keycode_Up,
+            Key::Down => super::keycode_Down,
+            Key::Return => super::keycode_Return,
+            Key::Delete => super::keycode_Delete,
+            Key::Escape => super::keycode_Escape,
+            Key::Tab => super::keycode_Tab,
+            Key::PageUp => super::keycode_PageUp,
+            Key::PageDown => super::keycode_PageDown,
+            Key::Home => super::keycode_Home,
+            Key::End => super::keycode_End,
+            Key::Func(n) if n >= 1 && n <= 12 => super::keycode_Func1 + 1 - n,
+            Key::Func(_) => super::keycode_Unknown,
+        }
+    }
+
+    pub fn from_u32(keycode: u32) -> Self {
+        match keycode {
+            super::keycode_Left => Key::Left,
+            super::keycode_Right => Key::Right,
+            super::keycode_Up => Key::Up,
+            super::keycode_Down => Key::Down,
+            super::keycode_Return => Key::Return,
+            super::keycode_Delete => Key::Delete,
+            super::keycode_Escape => Key::Escape,
+            super::keycode_Tab => Key::Tab,
+            super::keycode_PageUp => Key::PageUp,
+            super::keycode_PageDown => Key::PageDown,
+            super::keycode_Home => Key::Home,
+            super::keycode_End => Key::End,
+            _ if keycode <= super::keycode_Func1 && keycode >= super::keycode_Func12 => Key::Func(super::keycode_Func1 + 1 - keycode),
+            _ => match ::std::char::from_u32(keycode) {
+                Some(ch) => Key::Char(ch),
+                None => Key::Unknown,
+            },
+        }
+    }
+}
+
+#[derive(Debug,Default)]
+pub struct StreamResult {
+    pub readcount: u32,
+    pub writecount: u32,
+    pub buf: Option<Box<[u8]>>,
+    pub buf_uni: Option<Box<[u32]>>,
+}
+
+impl StreamResult {
+    fn new(result: (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>)) -> Self {
+        let (readcount,writecount,buf,buf_uni) = result;
+        StreamResult{
+            readcount,
+            writecount,
+            buf: buf.map(|(_,buf)| buf),
+            buf_uni: buf_uni.map(|(_,buf)| buf),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Event<W> {
+    None,
+    Timer,
+    CharInput{ win: W, key: Key },
+    LineInput{ win: W, buf: Box<[u8]>, len: usize, terminator: Option<Key> },
+    LineInputUni{ win: W, buf: Box<[u32]>, len: usize, terminator: Option<Key> },
+    MouseInput{ win: W, x: u32, y: u32 },
+    Arrange{ win: W },
+    Redraw{ win: W },
+    SoundNotify{ snd: u32, notify: u32 },
+    Hyperlink{ win: W, linkval: u32 },
+    VolumeNotify{ notify: u32 },
+}
+
+impl<W: IdType> Event<W> {
+    fn new<E: EventType<W>>(mut event: E) -> Self {
+        let win = event.win();
+        let terminator = if event.val2() == 0 { None } else { Some(Key::from_u32(event.val2())) };
+        match event.evtype() {
+            super::evtype_Timer => Event::Timer,
+            super::evtype_CharInput => Event::CharInput{ win, key: Key::from_u32(event.val1()) },
+            super::evtype_LineInput => {
+                let len = event.val1() as usize;
+                if let Some((_,buf)) = event.buf() {
+                    Event::LineInput{ win, buf, len, terminator }
+                } else if let Some((_,buf)) = event.buf_uni() {
+                    Event::LineInputUni{ win, buf, len, terminator }
+                } else {
+                    Event::None
+                }
+            },
+            super::evtype_MouseInput => Event::MouseInput{ win, x: event.val1(), y: event.val2() },
+            super::evtype_Arrange => Event::Arrange{ win },
+            super::evtype_Redraw => Event::Redraw{ win },
+            super::evtype_SoundNotify => Event::SoundNotify{ snd: event.val1(), notify: event.val2() },
+            super::evtype_Hyperlink => Event::Hyperlink{ win, linkval: event.val1() },
+            super::evtype_VolumeNotify => Event::VolumeNotify{ notify: event.val2() },
+            _ => Event::None,
+        }
+    }
+}
+
+fn some<T: IdType>(id: T) -> Option<T> {
+    if id.is_null() { None } else { Some(id) }
+}
+
+fn or_null<T: IdType + Clone>(id: Option<&T>) -> T {
+    id.cloned().unwrap_or(T::null())
+}
+
+fn position(pos: i64) -> Option<i32> {
+    if pos >= i32::min_value() as i64 && pos <= i32::max_value() as i64 { Some(pos as i32) } else { None }
+}
+
+// Wraps any Glk implementation with typed arguments, and with Option
+// or Result instead of null ids and magic numbers.
+pub struct TypedGlk<'a,G: Glk<'a>> {
+    glk: G,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a,G: Glk<'a>> TypedGlk<'a,G> {
+    pub fn new(glk: G) -> Self {
+        TypedGlk{ glk, phantom: PhantomData }
+    }
+
+    // For calls not covered here.
+    pub fn glk(&mut self) -> &mut G {
+        &mut self.glk
+    }
+
+    pub fn into_inner(self) -> G {
+        self.glk
+    }
+
+    pub fn exit(&mut self) -> ! {
+        self.glk.exit()
+    }
+
+    pub fn tick(&mut self) {
+        self.glk.tick();
+    }
+
+    pub fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
+        self.glk.gestalt(sel, val)
+    }
+
+
+    pub fn window_get_root(&mut self) -> Option<G::WinId> {
+        some(self.glk.window_get_root())
+    }
+
+    // The root window is opened with no split.
+    pub fn window_open(&mut self, split: Option<(&G::WinId,WinMethod)>, wintype: WinType, rock: u32) -> Option<G::WinId> {
+        let win = match split {
+            Some((split,method)) => {
+                let (method,size) = method.to_u32();
+                self.glk.window_open(split, method, size, wintype.to_u32(), rock)
+            },
+            None => self.glk.window_open(&G::WinId::null(), 0, 0, wintype.to_u32(), rock),
+        };
+        some(win)
+    }
+
+    pub fn window_close(&mut self, mut win: G::WinId) -> StreamResult {
+        StreamResult::new(self.glk.window_close(&mut win))
+    }
+
+    pub fn window_get_size(&mut self, win: &G::WinId) -> (u32,u32) {
+        self.glk.window_get_size(win)
+    }
+
+    pub fn window_set_arrangement(&mut self, win: &G::WinId, method: WinMethod, keywin: Option<&G::WinId>) {
+        let (method,size) = method.to_u32();
+        self.glk.window_set_arrangement(win, method, size, &or_null(keywin));
+    }
+
+    pub fn window_get_arrangement(&mut self, win: &G::WinId) -> (Option<WinMethod>,Option<G::WinId>) {
+        let (method,size,keywin) = self.glk.window_get_arrangement(win);
+        (WinMethod::from_u32(method, size),some(keywin))
+    }
+
+    pub fn window_iterate(&mut self, win: Option<&G::WinId>) -> Option<(G::WinId,u32)> {
+        let (win,rock) = self.glk.window_iterate(&or_null(win));
+        some(win).map(|win| (win,rock))
+    }
+
+    pub fn window_get_rock(&mut self, win: &G::WinId) -> u32 {
+        self.glk.window_get_rock(win)
+    }
+
+    pub fn window_get_type(&mut self, win: &G::WinId) -> Option<WinType> {
+        WinType::from_u32(self.glk.window_get_type(win))
+    }
+
+    pub fn window_get_parent(&mut self, win: &G::WinId) -> Option<G::WinId> {
+        some(self.glk.window_get_parent(win))
+    }
+
+    pub fn window_get_sibling(&mut self, win: &G::WinId) -> Option<G::WinId> {
+        some(self.glk.window_get_sibling(win))
+    }
+
+    pub fn window_clear(&mut self, win: &G::WinId) {
+        self.glk.window_clear(win);
+    }
+
+    pub fn window_move_cursor(&mut self, win: &G::WinId, xpos: u32, ypos: u32) {
+        self.glk.window_move_cursor(win, xpos, ypos);
+    }
+
+    pub fn window_get_stream(&mut self, win: &G::WinId) -> G::StrId {
+        self.glk.window_get_stream(win)
+    }
+
+    pub fn window_set_echo_stream(&mut self, win: &G::WinId, str: Option<&G::StrId>) {
+        self.glk.window_set_echo_stream(win, &or_null(str));
+    }
+
+    pub fn window_get_echo_stream(&mut self, win: &G::WinId) -> Option<G::StrId> {
+        some(self.glk.window_get_echo_stream(win))
+    }
+
+    pub fn set_window(&mut self, win: Option<&G::WinId>) {
+        self.glk.set_window(&or_null(win));
+    }
+
+
+    pub fn stream_open_file(&mut self, fileref: &G::FRefId, fmode: FileMode, rock: u32) -> io::Result<G::StrId> {
+        match some(self.glk.stream_open_file(fileref, fmode.to_u32(), rock)) {
+            Some(str) => Ok(str),
+            None if fmode == FileMode::Read && !self.glk.fileref_does_file_exist(fileref) => Err(Error::new(ErrorKind::NotFound, "file not found")),
+            None => Err(Error::new(ErrorKind::Other, "could not open file")),
+        }
+    }
+
+    pub fn stream_open_memory(&mut self, buf: Box<[u8]>, fmode: FileMode, rock: u32) -> Option<G::StrId> {
+        some(self.glk.stream_open_memory((0,buf), fmode.to_u32(), rock))
+    }
+
+    pub fn stream_open_memory_uni(&mut self, buf: Box<[u32]>, fmode: FileMode, rock: u32) -> Option<G::StrId> {
+        some(self.glk.stream_open_memory_uni((0,buf), fmode.to_u32(), rock))
+    }
+
+    pub fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Option<G::StrId> {
+        some(self.glk.stream_open_resource(filenum, rock))
+    }
+
+    pub fn stream_close(&mut self, mut str: G::StrId) -> StreamResult {
+        StreamResult::new(self.glk.stream_close(&mut str))
+    }
+
+    pub fn stream_iterate(&mut self, str: Option<&G::StrId>) -> Option<(G::StrId,u32)> {
+        let (str,rock) = self.glk.stream_iterate(&or_null(str));
+        some(str).map(|str| (str,rock))
+    }
+
+    pub fn stream_get_rock(&mut self, str: &G::StrId) -> u32 {
+        self.glk.stream_get_rock(str)
+    }
+
+    // Glk positions are 32 bits, so larger ones are rejected.
+    pub fn stream_set_position(&mut self, str: &G::StrId, pos: SeekFrom) -> io::Result<()> {
+        let (pos,seekmode) = match pos {
+            SeekFrom::Start(pos) => (if pos <= i32::max_value() as u64 { Some(pos as i32) } else { None },super::seekmode_Start),
+            SeekFrom::Current(pos) => (position(pos),super::seekmode_Current),
+            SeekFrom::End(pos) => (position(pos),super::seekmode_End),
+        };
+        match pos {
+            Some(pos) => {
+                self.glk.stream_set_position(str, pos, seekmode);
+                Ok(())
+            },
+            None => Err(Error::new(ErrorKind::InvalidInput, "position out of range")),
+        }
+    }
+
+    pub fn stream_get_position(&mut self, str: &G::StrId) -> u32 {
+        self.glk.stream_get_position(str)
+    }
+
+    pub fn stream_set_current(&mut self, str: Option<&G::StrId>) {
+        self.glk.stream_set_current(&or_null(str));
+    }
+
+    pub fn stream_get_current(&mut self) -> Option<G::StrId> {
+        some(self.glk.stream_get_current())
+    }
+
+
+    pub fn put_char(&mut self, ch: u8) {
+        self.glk.put_char(ch);
+    }
+
+    pub fn put_char_stream(&mut self, str: &G::StrId, ch: u8) {
+        self.glk.put_char_stream(str, ch);
+    }
+
+    // Latin-1 output.
+    pub fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
+        self.glk.put_string(s);
+    }
+
+    pub fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &G::StrId, s: S) {
+        self.glk.put_string_stream(str, s);
+    }
+
+    // Unicode output.
+    pub fn put_str(&mut self, s: &str) {
+        let buf: Vec<u32> = s.chars().map(|ch| ch as u32).collect();
+        self.glk.put_buffer_uni(&buf);
+    }
+
+    pub fn put_str_stream(&mut self, str: &G::StrId, s: &str) {
+        let buf: Vec<u32> = s.chars().map(|ch| ch as u32).collect();
+        self.glk.put_buffer_stream_uni(str, &buf);
+    }
+
+    pub fn set_style(&mut self, styl: Style) {
+        self.glk.set_style(styl.to_u32());
+    }
+
+    pub fn set_style_stream(&mut self, str: &G::StrId, styl: Style) {
+        self.glk.set_style_stream(str, styl.to_u32());
+    }
+
+    pub fn set_hyperlink(&mut self, linkval: Option<u32>) {
+        self.glk.set_hyperlink(linkval.unwrap_or(0));
+    }
+
+
+    pub fn get_char_stream(&mut self, str: &G::StrId) -> Option<u8> {
+        match self.glk.get_char_stream(str) {
+            -1 => None,
+            ch => Some(ch as u8),
+        }
+    }
+
+    pub fn get_char_stream_uni(&mut self, str: &G::StrId) -> Option<u32> {
+        match self.glk.get_char_stream_uni(str) {
+            -1 => None,
+            ch => Some(ch as u32),
+        }
+    }
+
+    pub fn get_line_stream(&mut self, str: &G::StrId, buf: &mut [u8]) -> usize {
+        self.glk.get_line_stream(str, buf) as usize
+    }
+
+    pub fn get_buffer_stream(&mut self, str: &G::StrId, buf: &mut [u8]) -> usize {
+        self.glk.get_buffer_stream(str, buf) as usize
+    }
+
+
+    // A wintype of None means all window types.
+    pub fn stylehint_set(&mut self, wintype: Option<WinType>, styl: Style, hint: StyleHint, val: i32) {
+        let wintype = wintype.map(WinType::to_u32).unwrap_or(super::wintype_AllTypes);
+        self.glk.stylehint_set(wintype, styl.to_u32(), hint.to_u32(), val);
+    }
+
+    pub fn stylehint_clear(&mut self, wintype: Option<WinType>, styl: Style, hint: StyleHint) {
+        let wintype = wintype.map(WinType::to_u32).unwrap_or(super::wintype_AllTypes);
+        self.glk.stylehint_clear(wintype, styl.to_u32(), hint.to_u32());
+    }
+
+    pub fn style_distinguish(&mut self, win: &G::WinId, styl1: Style, styl2: Style) -> bool {
+        self.glk.style_distinguish(win, styl1.to_u32(), styl2.to_u32())
+    }
+
+    pub fn style_measure(&mut self, win: &G::WinId, styl: Style, hint: StyleHint) -> Option<u32> {
+        match self.glk.style_measure(win, styl.to_u32(), hint.to_u32()) {
+            (true,val) => Some(val),
+            (false,_) => None,
+        }
+    }
+
+
+    pub fn fileref_create_temp(&mut self, usage: FileUsage, text_mode: bool, rock: u32) -> Option<G::FRefId> {
+        some(self.glk.fileref_create_temp(usage.to_u32(text_mode), rock))
+    }
+
+    pub fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: FileUsage, text_mode: bool, name: S, rock: u32) -> Option<G::FRefId> {
+        some(self.glk.fileref_create_by_name(usage.to_u32(text_mode), name, rock))
+    }
+
+    pub fn fileref_create_by_prompt(&mut self, usage: FileUsage, text_mode: bool, fmode: FileMode, rock: u32) -> Option<G::FRefId> {
+        some(self.glk.fileref_create_by_prompt(usage.to_u32(text_mode), fmode.to_u32(), rock))
+    }
+
+    pub fn fileref_create_from_fileref(&mut self, usage: FileUsage, text_mode: bool, fref: &G::FRefId, rock: u32) -> Option<G::FRefId> {
+        some(self.glk.fileref_create_from_fileref(usage.to_u32(text_mode), fref, rock))
+    }
+
+    pub fn fileref_destroy(&mut self, mut fref: G::FRefId) {
+        self.glk.fileref_destroy(&mut fref);
+    }
+
+    pub fn fileref_iterate(&mut self, fref: Option<&G::FRefId>) -> Option<(G::FRefId,u32)> {
+        let (fref,rock) = self.glk.fileref_iterate(&or_null(fref));
+        some(fref).map(|fref| (fref,rock))
+    }
+
+    pub fn fileref_get_rock(&mut self, fref: &G::FRefId) -> u32 {
+        self.glk.fileref_get_rock(fref)
+    }
+
+    pub fn fileref_delete_file(&mut self, fref: &G::FRefId) {
+        self.glk.fileref_delete_file(fref);
+    }
+
+    pub fn fileref_does_file_exist(&mut self, fref: &G::FRefId) -> bool {
+        self.glk.fileref_does_file_exist(fref)
+    }
+
+
+    pub fn select(&mut self) -> Event<G::WinId> {
+        Event::new(self.glk.select())
+    }
+
+    pub fn select_poll(&mut self) -> Event<G::WinId> {
+        Event::new(self.glk.select_poll())
+    }
+
+    // None turns timer events off.
+    pub fn request_timer_events(&mut self, millisecs: Option<u32>) {
+        self.glk.request_timer_events(millisecs.unwrap_or(0));
+    }
+
+    pub fn request_line_event(&mut self, win: &G::WinId, buf: Box<[u8]>, initlen: usize) {
+        self.glk.request_line_event(win, (0,buf), initlen as u32);
+    }
+
+    pub fn request_line_event_uni(&mut self, win: &G::WinId, buf: Box<[u32]>, initlen: usize) {
+        self.glk.request_line_event_uni(win, (0,buf), initlen as u32);
+    }
+
+    pub fn request_char_event(&mut self, win: &G::WinId) {
+        self.glk.request_char_event(win);
+    }
+
+    pub fn request_char_event_uni(&mut self, win: &G::WinId) {
+        self.glk.request_char_event_uni(win);
+    }
+
+    pub fn request_mouse_event(&mut self, win: &G::WinId) {
+        self.glk.request_mouse_event(win);
+    }
+
+    pub fn request_hyperlink_event(&mut self, win: &G::WinId) {
+        self.glk.request_hyperlink_event(win);
+    }
+
+    pub fn cancel_line_event(&mut self, win: &G::WinId) -> Event<G::WinId> {
+        Event::new(self.glk.cancel_line_event(win))
+    }
+
+    pub fn cancel_char_event(&mut self, win: &G::WinId) {
+        self.glk.cancel_char_event(win);
+    }
+
+    pub fn cancel_mouse_event(&mut self, win: &G::WinId) {
+        self.glk.cancel_mouse_event(win);
+    }
+
+    pub fn cancel_hyperlink_event(&mut self, win: &G::WinId) {
+        self.glk.cancel_hyperlink_event(win);
+    }
+
+    pub fn set_echo_line_event(&mut self, win: &G::WinId, echo: bool) {
+        self.glk.set_echo_line_event(win, if echo { 1 } else { 0 });
+    }
+
+    pub fn set_terminators_line_event(&mut self, win: &G::WinId, keys: &[Key]) {
+        let keycodes: Vec<u32> = keys.iter().map(|key| key.to_u32()).collect();
+        self.glk.set_terminators_line_event(win, &keycodes);
+    }
+
+
+    pub fn image_draw(&mut self, win: &G::WinId, image: u32, val1: i32, val2: i32) -> bool {
+        self.glk.image_draw(win, image, val1, val2)
+    }
+
+    pub fn image_get_info(&mut self, image: u32) -> Option<(u32,u32)> {
+        match self.glk.image_get_info(image) {
+            (true,width,height) => Some((width,height)),
+            (false,_,_) => None,
+        }
+    }
+}
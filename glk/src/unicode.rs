@@ -0,0 +1,195 @@
+// Case mapping and normalization of buffers for the Glk Unicode calls.
+// Each function converts the first numchars characters of buf, and
+// returns the length of the result, which is truncated to fit in buf.
+
+use super::unicode_tables::{COMBINING_CLASSES,COMPOSITIONS,DECOMPOSITIONS,TITLE_CASES};
+
+pub fn to_lower_case(buf: &mut [u32], numchars: u32) -> u32 {
+    let mut result = Vec::new();
+    for &ch in chars(buf, numchars) {
+        match ::std::char::from_u32(ch) {
+            Some(c) => result.extend(c.to_lowercase().map(|c| c as u32)),
+            None => result.push(ch),
+        }
+    }
+    store(buf, &result)
+}
+
+pub fn to_upper_case(buf: &mut [u32], numchars: u32) -> u32 {
+    let mut result = Vec::new();
+    for &ch in chars(buf, numchars) {
+        upper_case(ch, &mut result);
+    }
+    store(buf, &result)
+}
+
+// Only the first character is title cased.  The rest are lower cased or
+// left alone.
+pub fn to_title_case(buf: &mut [u32], numchars: u32, lowerrest: bool) -> u32 {
+    let mut result = Vec::new();
+    for (i,&ch) in chars(buf, numchars).iter().enumerate() {
+        if i == 0 {
+            match TITLE_CASES.binary_search_by_key(&ch, |&(c,_)| c) {
+                Ok(index) => result.extend_from_slice(TITLE_CASES[index].1),
+                Err(_) => upper_case(ch, &mut result),
+            }
+        } else if lowerrest {
+            match ::std::char::from_u32(ch) {
+                Some(c) => result.extend(c.to_lowercase().map(|c| c as u32)),
+                None => result.push(ch),
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    store(buf, &result)
+}
+
+// For glk_char_to_lower and glk_char_to_upper, where characters that do
+// not map to a single Latin-1 character are unchanged.
+pub fn latin1_to_lower(ch: u8) -> u8 {
+    latin1_case(ch, to_lower_case)
+}
+
+pub fn latin1_to_upper(ch: u8) -> u8 {
+    latin1_case(ch, to_upper_case)
+}
+
+// Canonical decomposition (NFD).
+pub fn canon_decompose(buf: &mut [u32], numchars: u32) -> u32 {
+    let result = decompose(chars(buf, numchars));
+    store(buf, &result)
+}
+
+// Canonical decomposition followed by canonical composition (NFC).
+pub fn canon_normalize(buf: &mut [u32], numchars: u32) -> u32 {
+    let result = compose(decompose(chars(buf, numchars)));
+    store(buf, &result)
+}
+
+fn chars(buf: &[u32], numchars: u32) -> &[u32] {
+    &buf[.. ::std::cmp::min(numchars as usize, buf.len())]
+}
+
+fn store(buf: &mut [u32], result: &[u32]) -> u32 {
+    let len = ::std::cmp::min(buf.len(), result.len());
+    buf[.. len].copy_from_slice(&result[.. len]);
+    result.len() as u32
+}
+
+fn upper_case(ch: u32, result: &mut Vec<u32>) {
+    match ::std::char::from_u32(ch) {
+        Some(c) => result.extend(c.to_uppercase().map(|c| c as u32)),
+        None => result.push(ch),
+    }
+}
+
+fn latin1_case(ch: u8, f: fn(&mut [u32], u32) -> u32) -> u8 {
+    let mut buf = [ch as u32];
+    match f(&mut buf, 1) {
+        1 if buf[0] < 0x100 => buf[0] as u8,
+        _ => ch,
+    }
+}
+
+const S_BASE: u32 = 0xac00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11a7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+fn combining_class(ch: u32) -> u8 {
+    match COMBINING_CLASSES.binary_search_by(|&(first,last,_)| {
+        if last < ch {
+            ::std::cmp::Ordering::Less
+        } else if first > ch {
+            ::std::cmp::Ordering::Greater
+        } else {
+            ::std::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(index) => COMBINING_CLASSES[index].2,
+        Err(_) => 0,
+    }
+}
+
+fn decompose_char(ch: u32, result: &mut Vec<u32>) {
+    if ch >= S_BASE && ch < S_BASE + S_COUNT {
+        let s = ch - S_BASE;
+        result.push(L_BASE + s / N_COUNT);
+        result.push(V_BASE + s % N_COUNT / T_COUNT);
+        if s % T_COUNT != 0 {
+            result.push(T_BASE + s % T_COUNT);
+        }
+        return;
+    }
+    match DECOMPOSITIONS.binary_search_by_key(&ch, |&(c,_)| c) {
+        Ok(index) => {
+            for &c in DECOMPOSITIONS[index].1 {
+                decompose_char(c, result);
+            }
+        },
+        Err(_) => result.push(ch),
+    }
+}
+
+// Fully decomposes, then puts each run of combining characters in order
+// of combining class, keeping the order of characters of the same class.
+fn decompose(chars: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(chars.len());
+    for &ch in chars {
+        decompose_char(ch, &mut result);
+    }
+    for i in 1 .. result.len() {
+        let class = combining_class(result[i]);
+        if class == 0 {
+            continue;
+        }
+        let mut j = i;
+        while j > 0 && combining_class(result[j-1]) > class {
+            result.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+    result
+}
+
+fn compose_pair(first: u32, second: u32) -> Option<u32> {
+    if first >= L_BASE && first < L_BASE + L_COUNT && second >= V_BASE && second < V_BASE + V_COUNT {
+        return Some(S_BASE + ((first - L_BASE) * V_COUNT + second - V_BASE) * T_COUNT);
+    }
+    if first >= S_BASE && first < S_BASE + S_COUNT && (first - S_BASE) % T_COUNT == 0 && second > T_BASE && second < T_BASE + T_COUNT {
+        return Some(first + second - T_BASE);
+    }
+    COMPOSITIONS.binary_search_by_key(&(first,second), |&(a,b,_)| (a,b)).ok().map(|index| COMPOSITIONS[index].2)
+}
+
+// Each character is combined with the last starter unless a character
+// between them is a starter or has a combining class at least as high.
+fn compose(chars: Vec<u32>) -> Vec<u32> {
+    let mut result: Vec<u32> = Vec::with_capacity(chars.len());
+    let mut starter = None;
+    let mut last_class = 0;
+    for ch in chars {
+        let class = combining_class(ch);
+        if let Some(index) = starter {
+            let adjacent = index + 1 == result.len();
+            if adjacent || (last_class != 0 && last_class < class) {
+                if let Some(composed) = compose_pair(result[index], ch) {
+                    result[index] = composed;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(result.len());
+        }
+        last_class = class;
+        result.push(ch);
+    }
+    result
+}
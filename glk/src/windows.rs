@@ -0,0 +1,422 @@
+use super::{EventType,IdType};
+use super::streams::StrId;
+
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct WinId(pub usize);
+
+impl IdType for WinId {
+    fn null() -> Self {
+        WinId(0)
+    }
+
+    fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+pub struct Event {
+    pub evtype: u32,
+    pub win: WinId,
+    pub val1: u32,
+    pub val2: u32,
+    pub buf: Option<(u32,Box<[u8]>)>,
+    pub buf_uni: Option<(u32,Box<[u32]>)>,
+}
+
+impl Event {
+    pub fn new(evtype: u32, win: WinId, val1: u32, val2: u32) -> Self {
+        Event{ evtype, win, val1, val2, buf: None, buf_uni: None }
+    }
+
+    pub fn none() -> Self {
+        Event::new(super::evtype_None, WinId(0), 0, 0)
+    }
+}
+
+impl EventType<WinId> for Event {
+    fn evtype(&self) -> u32 {
+        self.evtype
+    }
+
+    fn win(&self) -> WinId {
+        self.win
+    }
+
+    fn val1(&self) -> u32 {
+        self.val1
+    }
+
+    fn val2(&self) -> u32 {
+        self.val2
+    }
+
+    fn buf(&mut self) -> Option<(u32,Box<[u8]>)> {
+        self.buf.take()
+    }
+
+    fn buf_uni(&mut self) -> Option<(u32,Box<[u32]>)> {
+        self.buf_uni.take()
+    }
+}
+
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub struct Rect {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Sizes for laying out windows, in pixels for a graphical frontend or
+// in character cells for a terminal.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct Metrics {
+    pub width: u32,
+    pub height: u32,
+    // Space between the two children of a pair window with a border.
+    pub spacing: u32,
+    pub grid_char_width: u32,
+    pub grid_char_height: u32,
+    pub grid_margin_x: u32,
+    pub grid_margin_y: u32,
+    pub buffer_char_width: u32,
+    pub buffer_char_height: u32,
+    pub buffer_margin_x: u32,
+    pub buffer_margin_y: u32,
+    pub graphics_margin_x: u32,
+    pub graphics_margin_y: u32,
+}
+
+impl Metrics {
+    // A screen of character cells.
+    pub fn cells(width: u32, height: u32) -> Self {
+        Metrics{
+            width, height,
+            spacing: 1,
+            grid_char_width: 1, grid_char_height: 1, grid_margin_x: 0, grid_margin_y: 0,
+            buffer_char_width: 1, buffer_char_height: 1, buffer_margin_x: 0, buffer_margin_y: 0,
+            graphics_margin_x: 0, graphics_margin_y: 0,
+        }
+    }
+
+    fn cell_size(&self, wintype: u32) -> (u32,u32) {
+        match wintype {
+            super::wintype_TextGrid => (self.grid_char_width,self.grid_char_height),
+            super::wintype_TextBuffer => (self.buffer_char_width,self.buffer_char_height),
+            super::wintype_Graphics => (1,1),
+            _ => (0,0),
+        }
+    }
+
+    fn margins(&self, wintype: u32) -> (u32,u32) {
+        match wintype {
+            super::wintype_TextGrid => (self.grid_margin_x,self.grid_margin_y),
+            super::wintype_TextBuffer => (self.buffer_margin_x,self.buffer_margin_y),
+            super::wintype_Graphics => (self.graphics_margin_x,self.graphics_margin_y),
+            _ => (0,0),
+        }
+    }
+}
+
+pub struct Window {
+    pub wintype: u32,
+    pub rock: u32,
+    pub parent: WinId,
+    pub rect: Rect,
+    pub str: StrId,
+
+    // For pair windows.
+    pub children: (WinId,WinId),
+    pub method: u32,
+    pub size: u32,
+    pub key: WinId,
+}
+
+// The window tree.  The split of each pair window is determined by its
+// key window, which need not be one of its children.
+pub struct Windows {
+    list: Vec<Option<Window>>,
+    root: WinId,
+    metrics: Metrics,
+}
+
+impl Windows {
+    pub fn new(metrics: Metrics) -> Self {
+        Windows{ list: vec![None], root: WinId(0), metrics }
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+        self.layout();
+    }
+
+    pub fn root(&self) -> WinId {
+        self.root
+    }
+
+    pub fn get(&self, win: &WinId) -> Option<&Window> {
+        self.list.get(win.0).and_then(|w| w.as_ref())
+    }
+
+    pub fn get_mut(&mut self, win: &WinId) -> Option<&mut Window> {
+        self.list.get_mut(win.0).and_then(|w| w.as_mut())
+    }
+
+    // The stream of the new window must be set afterwards.  Returns
+    // the new window and, if the split created one, the new pair window.
+    pub fn open(&mut self, split: &WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Option<(WinId,Option<WinId>)> {
+        if split.is_null() {
+            if !self.root.is_null() {
+                return None;
+            }
+            let win = self.add(Window::new(wintype, rock, WinId(0)));
+            self.root = win;
+            self.layout();
+            return Some((win,None));
+        }
+        let parent = match self.get(split) {
+            Some(w) => w.parent,
+            None => return None,
+        };
+        let win = self.add(Window::new(wintype, rock, WinId(0)));
+        let mut pair = Window::new(super::wintype_Pair, 0, parent);
+        pair.method = method;
+        pair.size = size;
+        pair.key = win;
+        pair.children = if is_backward(method) { (win,*split) } else { (*split,win) };
+        let pair = self.add(pair);
+        self.replace_child(parent, *split, pair);
+        self.list[win.0].as_mut().unwrap().parent = pair;
+        self.list[split.0].as_mut().unwrap().parent = pair;
+        self.layout();
+        Some((win,Some(pair)))
+    }
+
+    // Returns the closed windows: the window, its descendants, and the
+    // pair window that contained it.
+    pub fn close(&mut self, win: &WinId) -> Vec<(WinId,Window)> {
+        let mut closed = Vec::new();
+        let parent = match self.get(win) {
+            Some(w) => w.parent,
+            None => return closed,
+        };
+        if parent.is_null() {
+            self.root = WinId(0);
+        } else {
+            let (child1,child2) = self.list[parent.0].as_ref().unwrap().children;
+            let sibling = if child1 == *win { child2 } else { child1 };
+            let grandparent = self.list[parent.0].as_ref().unwrap().parent;
+            self.replace_child(grandparent, parent, sibling);
+            self.list[sibling.0].as_mut().unwrap().parent = grandparent;
+            closed.push((parent,self.list[parent.0].take().unwrap()));
+        }
+        self.remove_tree(win, &mut closed);
+        for w in self.list.iter_mut() {
+            if let &mut Some(ref mut w) = w {
+                if closed.iter().any(|&(id,_)| id == w.key) {
+                    w.key = WinId(0);
+                }
+            }
+        }
+        self.layout();
+        closed
+    }
+
+    pub fn set_arrangement(&mut self, win: &WinId, method: u32, size: u32, keywin: &WinId) {
+        if !keywin.is_null() && !self.is_descendant(keywin, win) {
+            return;
+        }
+        if let Some(w) = self.get_mut(win) {
+            if w.wintype != super::wintype_Pair {
+                return;
+            }
+            // The split cannot change between horizontal and vertical.
+            if is_vertical(method) != is_vertical(w.method) {
+                return;
+            }
+            if is_backward(method) != is_backward(w.method) {
+                w.children = (w.children.1,w.children.0);
+            }
+            w.method = method;
+            w.size = size;
+            if !keywin.is_null() {
+                w.key = *keywin;
+            }
+        }
+        self.layout();
+    }
+
+    pub fn get_arrangement(&self, win: &WinId) -> (u32,u32,WinId) {
+        match self.get(win) {
+            Some(w) if w.wintype == super::wintype_Pair => (w.method,w.size,w.key),
+            _ => (0,0,WinId(0)),
+        }
+    }
+
+    pub fn iterate(&self, win: &WinId) -> (WinId,u32) {
+        for i in win.0 + 1 .. self.list.len() {
+            if let Some(ref w) = self.list[i] {
+                return (WinId(i),w.rock);
+            }
+        }
+        (WinId(0),0)
+    }
+
+    pub fn sibling(&self, win: &WinId) -> WinId {
+        match self.get(win).and_then(|w| self.get(&w.parent)) {
+            Some(parent) if parent.children.0 == *win => parent.children.1,
+            Some(parent) => parent.children.0,
+            None => WinId(0),
+        }
+    }
+
+    // The size in characters for text windows and in pixels for
+    // graphics windows.
+    pub fn size(&self, win: &WinId) -> (u32,u32) {
+        match self.get(win) {
+            Some(w) => {
+                let (cw,ch) = self.metrics.cell_size(w.wintype);
+                let (mx,my) = self.metrics.margins(w.wintype);
+                if cw == 0 || ch == 0 {
+                    (0,0)
+                } else {
+                    (w.rect.width.saturating_sub(mx) / cw,w.rect.height.saturating_sub(my) / ch)
+                }
+            },
+            None => (0,0),
+        }
+    }
+
+    fn add(&mut self, w: Window) -> WinId {
+        for i in 1 .. self.list.len() {
+            if self.list[i].is_none() {
+                self.list[i] = Some(w);
+                return WinId(i);
+            }
+        }
+        self.list.push(Some(w));
+        WinId(self.list.len() - 1)
+    }
+
+    fn replace_child(&mut self, parent: WinId, old: WinId, new: WinId) {
+        if parent.is_null() {
+            self.root = new;
+        } else {
+            let w = self.list[parent.0].as_mut().unwrap();
+            if w.children.0 == old {
+                w.children.0 = new;
+            } else {
+                w.children.1 = new;
+            }
+        }
+    }
+
+    fn remove_tree(&mut self, win: &WinId, closed: &mut Vec<(WinId,Window)>) {
+        if let Some(w) = self.list[win.0].take() {
+            if w.wintype == super::wintype_Pair {
+                self.remove_tree(&w.children.0, closed);
+                self.remove_tree(&w.children.1, closed);
+            }
+            closed.push((*win,w));
+        }
+    }
+
+    fn is_descendant(&self, win: &WinId, ancestor: &WinId) -> bool {
+        let mut w = *win;
+        while !w.is_null() {
+            if w == *ancestor {
+                return true;
+            }
+            w = match self.get(&w) {
+                Some(w) => w.parent,
+                None => return false,
+            };
+        }
+        false
+    }
+
+    fn layout(&mut self) {
+        let root = self.root;
+        let rect = Rect{ left: 0, top: 0, width: self.metrics.width, height: self.metrics.height };
+        self.layout_window(root, rect);
+    }
+
+    fn layout_window(&mut self, win: WinId, rect: Rect) {
+        let (wintype,method,size,key,children) = match self.list.get_mut(win.0) {
+            Some(&mut Some(ref mut w)) => {
+                w.rect = rect;
+                (w.wintype,w.method,w.size,w.key,w.children)
+            },
+            _ => return,
+        };
+        if wintype != super::wintype_Pair {
+            return;
+        }
+        let vertical = is_vertical(method);
+        let total = if vertical { rect.width } else { rect.height };
+        let spacing = if method & super::winmethod_BorderMask == super::winmethod_NoBorder { 0 } else { self.metrics.spacing };
+        let available = total.saturating_sub(spacing);
+        let keysize = match method & super::winmethod_DivisionMask {
+            super::winmethod_Fixed => {
+                let keytype = self.get(&key).map(|w| w.wintype).unwrap_or(super::wintype_Blank);
+                let (cw,ch) = self.metrics.cell_size(keytype);
+                let (mx,my) = self.metrics.margins(keytype);
+                if cw == 0 {
+                    0
+                } else if vertical {
+                    size.saturating_mul(cw).saturating_add(mx)
+                } else {
+                    size.saturating_mul(ch).saturating_add(my)
+                }
+            },
+            _ => ::std::cmp::min(available as u64 * size as u64 / 100, available as u64) as u32,
+        };
+        let keysize = ::std::cmp::min(keysize, available);
+        // The first child gets the key window's share for left and above.
+        let first = if is_backward(method) { keysize } else { available - keysize };
+        // The second child starts after the border, within the pair's box.
+        let offset = ::std::cmp::min(first + spacing, total);
+        let (rect1,rect2) = if vertical {
+            (Rect{ width: first, .. rect },
+             Rect{ left: rect.left + offset, width: available - first, .. rect })
+        } else {
+            (Rect{ height: first, .. rect },
+             Rect{ top: rect.top + offset, height: available - first, .. rect })
+        };
+        self.layout_window(children.0, rect1);
+        self.layout_window(children.1, rect2);
+    }
+}
+
+impl Window {
+    fn new(wintype: u32, rock: u32, parent: WinId) -> Self {
+        Window{
+            wintype, rock, parent,
+            rect: Rect::default(),
+            str: StrId(0),
+            children: (WinId(0),WinId(0)),
+            method: 0,
+            size: 0,
+            key: WinId(0),
+        }
+    }
+}
+
+fn is_vertical(method: u32) -> bool {
+    match method & super::winmethod_DirMask {
+        super::winmethod_Left | super::winmethod_Right => true,
+        _ => false,
+    }
+}
+
+// Whether the key window comes first.
+fn is_backward(method: u32) -> bool {
+    match method & super::winmethod_DirMask {
+        super::winmethod_Left | super::winmethod_Above => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,105 @@
+extern crate glk;
+
+use glk::babel::{self,Format};
+
+fn push_u32(bytes: &mut Vec<u8>, val: u32) {
+    bytes.extend_from_slice(&[(val >> 24) as u8, (val >> 16) as u8, (val >> 8) as u8, val as u8]);
+}
+
+fn glulx(info: bool, extra: &[u8]) -> Vec<u8> {
+    let mut story = vec![0; 256];
+    story[0 .. 4].copy_from_slice(b"Glul");
+    story[32 .. 36].copy_from_slice(&[0x12, 0x34, 0xab, 0xcd]);
+    if info {
+        story[0x24 .. 0x28].copy_from_slice(b"Info");
+        story[0x34 .. 0x36].copy_from_slice(&[0, 9]);
+        story[0x36 .. 0x3c].copy_from_slice(b"161114");
+    }
+    story.extend_from_slice(extra);
+    story
+}
+
+fn zcode(serial: &[u8;6]) -> Vec<u8> {
+    let mut story = vec![0; 256];
+    story[0] = 5;
+    story[2 .. 4].copy_from_slice(&[0, 88]);
+    story[0x12 .. 0x18].copy_from_slice(serial);
+    story[0x1c .. 0x1e].copy_from_slice(&[0x16, 0xab]);
+    story
+}
+
+fn blorb(exec_type: &[u8;4], exec: &[u8], ifmd: Option<&str>) -> Vec<u8> {
+    let mut chunks = vec![];
+    chunks.extend_from_slice(exec_type);
+    push_u32(&mut chunks, exec.len() as u32);
+    chunks.extend_from_slice(exec);
+    if let Some(ifmd) = ifmd {
+        chunks.extend_from_slice(b"IFmd");
+        push_u32(&mut chunks, ifmd.len() as u32);
+        chunks.extend_from_slice(ifmd.as_bytes());
+    }
+    let mut form = vec![];
+    form.extend_from_slice(b"FORM");
+    push_u32(&mut form, (4 + 8 + 16 + chunks.len()) as u32);
+    form.extend_from_slice(b"IFRSRIdx");
+    form.extend_from_slice(&[0, 0, 0, 16, 0, 0, 0, 1]);
+    form.extend_from_slice(b"Exec");
+    form.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 36]);
+    form.extend_from_slice(&chunks);
+    form
+}
+
+#[test]
+fn glulx_ifid() {
+    assert_eq!(Some("GLULX-9-161114-1234ABCD".to_string()), babel::ifid(&glulx(true, b"")));
+    assert_eq!(Some("GLULX-1234ABCD".to_string()), babel::ifid(&glulx(false, b"")));
+    let uuid = b"UUID://1974a053-7df1-4e4a-9d3c-8e7e46b4cfe1//";
+    assert_eq!(Some("1974A053-7DF1-4E4A-9D3C-8E7E46B4CFE1".to_string()), babel::ifid(&glulx(true, uuid)));
+    assert_eq!(Some(Format::Glulx), babel::format(&glulx(true, b"")));
+}
+
+#[test]
+fn zcode_ifid() {
+    assert_eq!(Some("ZCODE-88-840726".to_string()), babel::ifid(&zcode(b"840726")));
+    assert_eq!(Some("ZCODE-88-000000".to_string()), babel::ifid(&zcode(b"000000")));
+    assert_eq!(Some("ZCODE-88-9x-1-1".to_string()), babel::ifid(&zcode(b"9x 1/1")));
+    assert_eq!(Some("ZCODE-88-950402-16AB".to_string()), babel::ifid(&zcode(b"950402")));
+    assert_eq!(Some(Format::ZCode), babel::format(&zcode(b"950402")));
+    assert_eq!(None, babel::ifid(b"not a story"));
+}
+
+#[test]
+fn blorb_ifid() {
+    let ifmd = "<ifindex><story><identification><ifid>ABCD-1234</ifid></identification></story></ifindex>";
+    assert_eq!(Some("ABCD-1234".to_string()), babel::ifid(&blorb(b"GLUL", &glulx(true, b""), Some(ifmd))));
+    assert_eq!(Some("GLULX-9-161114-1234ABCD".to_string()), babel::ifid(&blorb(b"GLUL", &glulx(true, b""), None)));
+    assert_eq!(Some("ZCODE-88-950402-16AB".to_string()), babel::ifid(&blorb(b"ZCOD", &zcode(b"950402"), None)));
+    assert_eq!(Some(Format::ZCode), babel::format(&blorb(b"ZCOD", &zcode(b"950402"), None)));
+    assert_eq!(Some(ifmd.to_string()), babel::ifiction(&blorb(b"GLUL", &glulx(true, b""), Some(ifmd))));
+}
+
+#[test]
+fn ifiction() {
+    assert_eq!(Some(r#"<?xml version="1.0" encoding="UTF-8"?>
+<ifindex version="1.0" xmlns="http://babel.ifarchive.org/protocol/iFiction/">
+  <story>
+    <identification>
+      <ifid>ZCODE-88-950402-16AB</ifid>
+      <format>zcode</format>
+    </identification>
+    <bibliographic>
+      <title>An Interactive Fiction</title>
+      <author>Anonymous</author>
+    </bibliographic>
+    <zcode>
+      <serial>950402</serial>
+      <release>88</release>
+      <checksum>16AB</checksum>
+    </zcode>
+  </story>
+</ifindex>
+"#.to_string()), babel::ifiction(&zcode(b"950402")));
+    let ifiction = babel::ifiction(&glulx(true, b"")).unwrap();
+    assert!(ifiction.contains("<ifid>GLULX-9-161114-1234ABCD</ifid>\n      <format>glulx</format>"));
+    assert!(ifiction.contains("<glulx>\n      <serial>161114</serial>\n      <release>9</release>\n    </glulx>"));
+}
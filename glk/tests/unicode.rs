@@ -0,0 +1,57 @@
+extern crate glk;
+
+use glk::unicode;
+
+fn chars(s: &str) -> Vec<u32> {
+    s.chars().map(|ch| ch as u32).collect()
+}
+
+fn convert(s: &str, len: usize, f: &Fn(&mut [u32], u32) -> u32) -> (u32,String) {
+    let mut buf = chars(s);
+    let numchars = buf.len() as u32;
+    buf.resize(len, 0);
+    let count = f(&mut buf, numchars);
+    (count,buf.iter().take(count as usize).map(|&ch| std::char::from_u32(ch).unwrap()).collect())
+}
+
+#[test]
+fn case() {
+    assert_eq!((9,"STRASSE Ǆ".to_string()), convert("Straße ǆ", 9, &unicode::to_upper_case));
+    assert_eq!((8,"straße ǆ".to_string()), convert("STRAßE Ǆ", 8, &unicode::to_lower_case));
+    assert_eq!((2,"ǅǆ".to_string()), convert("ǆǆ", 4, &|buf,n| unicode::to_title_case(buf, n, false)));
+    assert_eq!((2,"ǅǆ".to_string()), convert("ǆǄ", 2, &|buf,n| unicode::to_title_case(buf, n, true)));
+    assert_eq!((4,"Ssss".to_string()), convert("ßSS", 4, &|buf,n| unicode::to_title_case(buf, n, true)));
+    assert_eq!((4,"FfiX".to_string()), convert("ﬃX", 4, &|buf,n| unicode::to_title_case(buf, n, false)));
+    assert_eq!(0xe9, unicode::latin1_to_lower(0xc9));
+    assert_eq!(0xdf, unicode::latin1_to_upper(0xdf));
+    assert_eq!(0xff, unicode::latin1_to_upper(0xff));
+}
+
+#[test]
+fn overflow() {
+    let mut buf = chars("ßß");
+    assert_eq!(4, unicode::to_upper_case(&mut buf, 2));
+    assert_eq!(chars("SS"), buf);
+
+    let mut buf = chars("ßßx");
+    assert_eq!(2, unicode::to_upper_case(&mut buf, 1));
+    assert_eq!(chars("SSx"), buf);
+}
+
+#[test]
+fn normalization() {
+    let nfd = |buf: &mut [u32], n| unicode::canon_decompose(buf, n);
+    let nfc = |buf: &mut [u32], n| unicode::canon_normalize(buf, n);
+    assert_eq!((3,"e\u{301}x".to_string()), convert("éx", 3, &nfd));
+    assert_eq!((5,"a\u{323}\u{302}\u{1100}\u{1161}".to_string()), convert("ậ가", 5, &nfd));
+    assert_eq!((2,"q\u{307}".to_string()), convert("q\u{307}", 2, &nfc));
+    assert_eq!((2,"ậ각".to_string()), convert("a\u{302}\u{323}\u{1100}\u{1161}\u{11a8}", 6, &nfc));
+    assert_eq!((1,"Å".to_string()), convert("\u{212b}", 1, &nfc));
+    assert_eq!((2,"\u{e0}\u{5ae}".to_string()), convert("a\u{5ae}\u{300}", 3, &nfc));
+    assert_eq!((1,"\u{1e69}".to_string()), convert("s\u{307}\u{323}", 3, &nfc));
+    assert_eq!((2,"\u{e9}\u{301}".to_string()), convert("e\u{301}\u{301}", 3, &nfc));
+
+    let mut buf = chars("éé");
+    assert_eq!(4, unicode::canon_decompose(&mut buf, 2));
+    assert_eq!(chars("e\u{301}"), buf);
+}
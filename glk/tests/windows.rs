@@ -0,0 +1,27 @@
+extern crate glk;
+
+use glk::windows::{Metrics,Rect,WinId,Windows};
+
+fn rect(windows: &Windows, win: &WinId) -> Rect {
+    windows.get(win).unwrap().rect
+}
+
+// Sizes from the game are clamped to the pair's box.
+#[test]
+fn oversized() {
+    let mut windows = Windows::new(Metrics{ grid_char_width: 8, grid_char_height: 16, grid_margin_x: 4, .. Metrics::cells(800, 600) });
+    let (mainwin,_) = windows.open(&WinId(0), 0, 0, glk::wintype_TextBuffer, 1).unwrap();
+    let (sidewin,pair) = windows.open(&mainwin, glk::winmethod_Right | glk::winmethod_Fixed, 0x20000000, glk::wintype_TextGrid, 2).unwrap();
+    assert_eq!(Rect{ left: 0, top: 0, width: 0, height: 600 }, rect(&windows, &mainwin));
+    assert_eq!(Rect{ left: 1, top: 0, width: 799, height: 600 }, rect(&windows, &sidewin));
+    assert_eq!((99,37), windows.size(&sidewin));
+
+    let pair = pair.unwrap();
+    windows.set_arrangement(&pair, glk::winmethod_Left | glk::winmethod_Proportional, 429496730, &sidewin);
+    assert_eq!(Rect{ left: 0, top: 0, width: 799, height: 600 }, rect(&windows, &sidewin));
+    assert_eq!(Rect{ left: 800, top: 0, width: 0, height: 600 }, rect(&windows, &mainwin));
+
+    windows.set_metrics(Metrics::cells(0, 0));
+    assert_eq!(Rect{ left: 0, top: 0, width: 0, height: 0 }, rect(&windows, &mainwin));
+    assert_eq!(Rect{ left: 0, top: 0, width: 0, height: 0 }, rect(&windows, &sidewin));
+}
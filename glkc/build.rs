@@ -0,0 +1,72 @@
+extern crate gcc;
+
+use std::env;
+use std::path::PathBuf;
+
+// The C sources of each library, which are compiled with its main
+// renamed to glkc_main, and its glkunix_arguments array made a pointer to
+// glkc_arguments, so that the program can give any number of arguments.
+const CHEAPGLK: &'static [&'static str] = &[
+    "cgfref.c", "cggestal.c", "cgmisc.c", "cgstream.c", "cgstyle.c",
+    "cgwindow.c", "cgschan.c", "cgdate.c", "cgunicod.c", "main.c",
+    "gi_dispa.c", "gi_blorb.c", "gi_debug.c", "cgblorb.c",
+];
+
+const GLKTERM: &'static [&'static str] = &[
+    "main.c", "gtevent.c", "gtfref.c", "gtgestal.c", "gtinput.c",
+    "gtmessag.c", "gtmessin.c", "gtmisc.c", "gtstream.c", "gtstyle.c",
+    "gtw_blnk.c", "gtw_buf.c", "gtw_grid.c", "gtw_pair.c", "gtwindow.c",
+    "gtschan.c", "gtblorb.c", "cgunicod.c", "cgdate.c", "gi_dispa.c",
+    "gi_blorb.c",
+];
+
+const REMGLK: &'static [&'static str] = &[
+    "main.c", "rgdata.c", "rgevent.c", "rgfref.c", "rggestal.c",
+    "rgmisc.c", "rgstream.c", "rgstyle.c", "rgwin_blank.c", "rgwin_buf.c",
+    "rgwin_graph.c", "rgwin_grid.c", "rgwin_pair.c", "rgwindow.c",
+    "rgschan.c", "rgblorb.c", "cgunicod.c", "cgdate.c", "gi_dispa.c",
+    "gi_blorb.c", "gi_debug.c",
+];
+
+// garglk is a shared library, but its main is compiled into each
+// interpreter.
+const GARGLK: &'static [&'static str] = &["main.c"];
+
+fn main() {
+    let features = ["cheapglk", "glkterm", "remglk", "garglk"];
+    let selected: Vec<&str> = features.iter().cloned().filter(|feature| env::var_os(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_some()).collect();
+    let library = match selected.len() {
+        // Nothing to link, which is only good for checking the crate.
+        0 => return,
+        1 => selected[0],
+        _ => panic!("only one of the features {} may be selected", selected.join(", ")),
+    };
+    let (files,default_dir) = match library {
+        "cheapglk" => (CHEAPGLK,"cheapglk"),
+        "glkterm" => (GLKTERM,"glkterm"),
+        "remglk" => (REMGLK,"remglk"),
+        _ => (GARGLK,"garglk"),
+    };
+    // The sources are the submodule, unless GLKC_SOURCE_DIR says otherwise.
+    println!("cargo:rerun-if-env-changed=GLKC_SOURCE_DIR");
+    let dir = env::var_os("GLKC_SOURCE_DIR").map(PathBuf::from).unwrap_or(PathBuf::from(default_dir));
+    let mut config = gcc::Config::new();
+    config.define("main", Some("glkc_main"))
+        .define("glkunix_arguments", Some("(*glkc_arguments)"))
+        .include(&dir);
+    for file in files {
+        config.file(dir.join(file));
+    }
+    config.compile("libglkc.a");
+    match library {
+        "glkterm" => println!("cargo:rustc-link-lib=ncurses"),
+        "garglk" => {
+            println!("cargo:rerun-if-env-changed=GLKC_LIB_DIR");
+            if let Some(lib_dir) = env::var_os("GLKC_LIB_DIR") {
+                println!("cargo:rustc-link-search={}", PathBuf::from(lib_dir).display());
+            }
+            println!("cargo:rustc-link-lib=garglk");
+        },
+        _ => (),
+    }
+}
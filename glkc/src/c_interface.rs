@@ -1,13 +1,18 @@
 use std;
+use std::cell::{Cell,RefCell};
 use std::ffi::{CStr,CString};
 use std::os::raw::{c_char,c_int,c_uchar};
+use std::sync::atomic::{AtomicPtr,Ordering};
 
-static mut MAIN_FUNC: *const u8 = 0 as *const u8;
+// The library calls glkunix_startup_code and glk_main on the thread that
+// calls glkc_main.
+thread_local! {
+    static MAIN_FUNC: Cell<Option<fn(super::CGlk,Vec<String>)>> = Cell::new(None);
+    static STARTUP_ARGS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
 
-pub fn init(main_func: fn(super::GlkTerm,Vec<String>)) {
-    unsafe {
-        MAIN_FUNC = std::mem::transmute(main_func);
-    }
+pub fn init(main_func: fn(super::CGlk,Vec<String>)) {
+    MAIN_FUNC.with(|func| func.set(Some(main_func)));
 
     let mut args = Vec::new();
     for arg in std::env::args() {
@@ -22,18 +27,22 @@ pub fn init(main_func: fn(super::GlkTerm,Vec<String>)) {
     }
     argv.push(std::ptr::null());
     unsafe {
-        glkterm_main(argc, argv.as_ptr());
+        glkc_main(argc, argv.as_ptr());
     }
 }
 
 #[no_mangle]
 pub extern fn glk_main() {
-    let main_func: fn(super::GlkTerm,Vec<String>) = unsafe { std::mem::transmute(MAIN_FUNC) };
-    super::main_func(main_func, startup_args());
+    if let Some(main_func) = MAIN_FUNC.with(|func| func.take()) {
+        let args = STARTUP_ARGS.with(|args| args.replace(Vec::new()));
+        super::main_func(main_func, args);
+    }
 }
 
 extern {
-    fn glkterm_main(argc: c_int, argv: *const *const c_char);
+    // The library's main, renamed by the build script.
+    fn glkc_main(argc: c_int, argv: *const *const c_char);
+    pub fn isatty(fd: c_int) -> c_int;
 }
 
 #[derive(Clone,Copy)]
@@ -55,37 +64,23 @@ const glkunix_arg_ValueCanFollow: c_int = 3;
 #[allow(non_upper_case_globals)]
 const glkunix_arg_NumberValue: c_int = 4;
 
-const GLKUNIX_ARG_COUNT: usize = 10;
-
+// The list the library reads as glkunix_arguments, ending with
+// glkunix_arg_End.  See build.rs.  The library can only read a global,
+// so this points to the list of the thread that last set one, which is
+// the thread that goes on to call glkc_main.
 #[allow(non_upper_case_globals)]
 #[no_mangle]
-pub static mut glkunix_arguments: [glkunix_argumentlist_t; GLKUNIX_ARG_COUNT] = [
-    glkunix_argumentlist_t {
-        name: 0 as *const c_char,
-        argtype: glkunix_arg_End,
-        desc: 0 as *const c_char,
-    }; GLKUNIX_ARG_COUNT ];
+pub static glkc_arguments: AtomicPtr<glkunix_argumentlist_t> = AtomicPtr::new(std::ptr::null_mut());
 
-pub fn set_arguments(mut args: Vec<super::Argument>) {
-    unsafe {
-        for i in 0 .. GLKUNIX_ARG_COUNT {
-            let mut arg = &mut glkunix_arguments[i];
-            if arg.argtype != glkunix_arg_End {
-                if !arg.name.is_null() {
-                    CString::from_raw(arg.name as *mut c_char);
-                }
-                if !arg.desc.is_null() {
-                    CString::from_raw(arg.desc as *mut c_char);
-                }
-            }
-            arg.name = std::ptr::null();
-            arg.argtype = glkunix_arg_End;
-            arg.desc = std::ptr::null();
-        }
-    }
+thread_local! {
+    // The list, and the strings it points to.
+    static ARGUMENTS: RefCell<Option<(Vec<glkunix_argumentlist_t>,Vec<CString>)>> = RefCell::new(None);
+}
 
-    args.truncate(GLKUNIX_ARG_COUNT-1);
-    for (arg,i) in args.drain(0 ..).zip(0 .. GLKUNIX_ARG_COUNT-1) {
+pub fn set_arguments(args: Vec<super::Argument>) {
+    let mut list = Vec::with_capacity(args.len() + 1);
+    let mut strings = Vec::with_capacity(2*args.len());
+    for arg in args {
         let (name,argtype,desc) = match arg {
             super::Argument::ValueFollows(name,desc) =>
                 (name,glkunix_arg_ValueFollows,desc),
@@ -96,11 +91,31 @@ pub fn set_arguments(mut args: Vec<super::Argument>) {
             super::Argument::NumberValue(name,desc) =>
                 (name,glkunix_arg_NumberValue,desc),
         };
-        unsafe {
-            glkunix_arguments[i].name = CString::new(name).unwrap().into_raw();
-            glkunix_arguments[i].argtype = argtype;
-            glkunix_arguments[i].desc = CString::new(desc).unwrap().into_raw();
-        }
+        let (name,desc) = (CString::new(name).unwrap(),CString::new(desc).unwrap());
+        list.push(glkunix_argumentlist_t {
+            name: name.as_ptr(),
+            argtype,
+            desc: desc.as_ptr(),
+        });
+        strings.push(name);
+        strings.push(desc);
+    }
+    list.push(glkunix_argumentlist_t {
+        name: std::ptr::null(),
+        argtype: glkunix_arg_End,
+        desc: std::ptr::null(),
+    });
+    ARGUMENTS.with(|arguments| {
+        let mut arguments = arguments.borrow_mut();
+        glkc_arguments.store(list.as_mut_ptr(), Ordering::SeqCst);
+        *arguments = Some((list,strings));
+    });
+}
+
+// The library needs a list even if the program has no arguments.
+pub fn init_arguments() {
+    if ARGUMENTS.with(|arguments| arguments.borrow().is_none()) {
+        set_arguments(Vec::new());
     }
 }
 
@@ -110,8 +125,6 @@ pub struct glkunix_startup_t {
     argv: *const *const c_char,
 }
 
-static mut STARTUP_ARGS: *const u8 = 0 as *const u8;
-
 #[no_mangle]
 pub extern fn glkunix_startup_code(data: *const glkunix_startup_t) -> c_int {
     let mut args: Vec<String> = Vec::new();
@@ -119,22 +132,11 @@ pub extern fn glkunix_startup_code(data: *const glkunix_startup_t) -> c_int {
         for i in 0 .. (*data).argc as isize {
             args.push(CStr::from_ptr(*(*data).argv.offset(i)).to_string_lossy().into_owned());
         }
-        STARTUP_ARGS = Box::into_raw(Box::new(args)) as *const u8;
     }
+    STARTUP_ARGS.with(|startup_args| *startup_args.borrow_mut() = args);
     1
 }
 
-fn startup_args() -> Vec<String> {
-    if unsafe { STARTUP_ARGS } == 0 as *const u8 {
-        return Vec::new();
-    }
-    unsafe {
-        let args: Vec<String> = *Box::from_raw(STARTUP_ARGS as *mut Vec<String>);
-        STARTUP_ARGS = 0 as *const u8;
-        args
-    }
-}
-
 #[allow(non_camel_case_types)]
 pub enum glk_window_struct {}
 #[allow(non_camel_case_types)]
@@ -348,3 +350,16 @@ extern {
     pub fn glk_stream_open_resource(filenum: u32, rock: u32) -> strid_t;
     pub fn glk_stream_open_resource_uni(filenum: u32, rock: u32) -> strid_t;
 }
+
+#[cfg(any(feature = "garglk", feature = "remglk"))]
+extern {
+    pub fn glk_image_draw_scaled_ext(win: winid_t, image: u32, val1: i32, val2: i32, width: u32, height: u32, imagerule: u32, maxwidth: u32) -> u32;
+}
+
+#[cfg(feature = "garglk")]
+extern {
+    pub fn garglk_set_story_title(title: *const c_char);
+    pub fn garglk_set_zcolors(fg: u32, bg: u32);
+    pub fn garglk_set_zcolors_stream(str: strid_t, fg: u32, bg: u32);
+    pub fn garglk_set_reversevideo_stream(str: strid_t, reverse: u32);
+}
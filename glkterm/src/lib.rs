@@ -1,37 +1,397 @@
 extern crate glk;
+extern crate libc;
+
+use std::path::PathBuf;
+use std::time::{Duration,Instant};
+
+use glk::{Glk,IdType};
+use glk::blorb::{Blorb,Usage};
+use glk::datetime::{Date,TimeVal};
+use glk::session;
+use glk::streams::{FRefId,FileRefs,IOStream,StrId,Streams};
+use glk::windows::{Event,Metrics,WinId,Windows};
+
+mod screen;
+mod terminal;
+
+use screen::{Attr,Contents,Frame,Hints,LineBuf,LineRequest,Look,Window};
+
+pub use glk::args::Argument;
+pub use terminal::Terminal;
+
+// How often the terminal size is checked while waiting for input.
+const RESIZE_POLL_MILLIS: u64 = 250;
+
+// Parses the program's arguments, then calls main_func with a GlkTerm
+// on the terminal, until it returns or its session ends.
+pub fn init(arguments: Vec<Argument>, main_func: fn(GlkTerm,Vec<String>)) {
+    let args: Vec<String> = std::env::args().collect();
+    let args = match glk::args::parse(&arguments, &[], &args) {
+        Some((args,_)) => args,
+        None => {
+            eprint!("{}", glk::args::usage(&args[0], &arguments, &[]));
+            std::process::exit(1);
+        },
+    };
+    let _ = session::run(|| {
+        main_func(GlkTerm::new(Terminal::stdio()), args);
+        Ok(())
+    });
+}
 
-use std::ffi::CString;
-use std::io::{Error,ErrorKind,Read,Result,Seek,SeekFrom,Write};
-use std::os::raw::{c_char,c_uchar};
+// A Glk with the full window tree, drawn on a terminal.
+pub struct GlkTerm {
+    terminal: Terminal,
+    windows: Windows,
+    contents: Contents,
+    streams: Streams<WinId>,
+    frefs: FileRefs,
+    // Style hints for buffer and grid windows.
+    buffer_hints: Hints,
+    grid_hints: Hints,
+    history: Vec<Vec<u32>>,
+    timer: Option<(Duration,Instant)>,
+    frame: Option<Frame>,
+    resources: Option<Blorb>,
+}
 
-use glk::{Glk,DateType,EventType,IdType,TimeValType};
+impl GlkTerm {
+    pub fn new(mut terminal: Terminal) -> Self {
+        let (width,height) = terminal.size();
+        GlkTerm{
+            terminal,
+            windows: Windows::new(Metrics::cells(width, height)),
+            contents: Contents(Vec::new()),
+            streams: Streams::new(),
+            frefs: FileRefs::new(PathBuf::from(".")),
+            buffer_hints: Default::default(),
+            grid_hints: Default::default(),
+            history: Vec::new(),
+            timer: None,
+            frame: None,
+            resources: None,
+        }
+    }
+
+    // The text on the screen, a line per row.
+    pub fn screen(&mut self) -> Vec<String> {
+        self.draw().0.text()
+    }
+
+    fn draw(&self) -> (Frame,Option<(u32,u32)>,Option<WinId>) {
+        let metrics = self.windows.metrics();
+        let mut frame = Frame::new(metrics.width, metrics.height);
+        let mut cursor = None;
+        let mut more = None;
+        let mut win = WinId(0);
+        loop {
+            win = self.windows.iterate(&win).0;
+            let w = match self.windows.get(&win) {
+                Some(w) => w,
+                None => break,
+            };
+            if w.wintype == glk::wintype_Pair {
+                let border = w.method & glk::winmethod_BorderMask == glk::winmethod_Border;
+                if let (true,Some(first)) = (border,self.windows.get(&w.children.0)) {
+                    draw_border(&mut frame, &w.rect, &first.rect, w.method);
+                }
+                continue;
+            }
+            if let Some(content) = self.contents.get(&win) {
+                if more.is_none() && content.needs_more(&w.rect) {
+                    more = Some(win);
+                }
+                frame.set_hints(&content.hints);
+                if let Some(pos) = content.draw(&w.rect, &mut frame) {
+                    if cursor.is_none() {
+                        cursor = Some(pos);
+                    }
+                }
+            }
+        }
+        (frame,cursor,more)
+    }
 
-mod array_registry;
-mod c_interface;
+    // Updates the terminal, returning a window with more text than fits.
+    fn refresh(&mut self) -> Option<WinId> {
+        let (frame,cursor,more) = self.draw();
+        let mut out = frame.update(self.frame.as_ref());
+        match cursor {
+            Some((x,y)) if more.is_none() => out.push_str(&format!("\x1b[{};{}H\x1b[?25h", y + 1, x + 1)),
+            _ => out.push_str("\x1b[?25l"),
+        }
+        self.terminal.write(&out);
+        self.terminal.flush();
+        self.frame = Some(frame);
+        more
+    }
 
-pub use c_interface::{glk_main,glkunix_arguments,glkunix_startup_code};
+    fn relayout(&mut self) {
+        for i in 0 .. self.contents.0.len() {
+            let win = WinId(i);
+            let (width,height) = self.windows.size(&win);
+            if let Some(w) = self.contents.get_mut(&win) {
+                w.resize_grid(width, height);
+            }
+        }
+    }
 
-pub fn set_arguments(args: Vec<Argument>) {
-    c_interface::set_arguments(args);
-}
+    // Whether the terminal has changed size.
+    fn check_size(&mut self) -> bool {
+        let (width,height) = self.terminal.size();
+        let metrics = self.windows.metrics();
+        if (width,height) == (metrics.width,metrics.height) {
+            return false;
+        }
+        self.windows.set_metrics(Metrics::cells(width, height));
+        self.relayout();
+        self.frame = None;
+        true
+    }
 
-pub fn init(main_func: fn(GlkTerm,Vec<String>)) {
-    array_registry::init();
-    c_interface::init(main_func);
-}
+    // The window that gets keys: the first one waiting for input.
+    fn focus(&self) -> Option<WinId> {
+        self.contents.0.iter().position(|w| match w {
+            &Some(ref w) => w.line_request.is_some() || w.char_request.is_some(),
+            &None => false,
+        }).map(WinId)
+    }
 
-pub enum Argument {
-    ValueFollows(String,String),
-    NoValue(String,String),
-    ValueCanFollow(String,String),
-    NumberValue(String,String),
+    // Waits for a key, a timer, or a change in the terminal's size.
+    fn wait(&mut self) -> Event {
+        loop {
+            if self.check_size() && !self.windows.root().is_null() {
+                return Event::new(glk::evtype_Arrange, WinId(0), 0, 0);
+            }
+            let more = self.refresh();
+            if more.is_none() {
+                for w in self.contents.0.iter_mut() {
+                    if let &mut Some(ref mut w) = w {
+                        w.mark_seen();
+                    }
+                }
+            }
+            let now = Instant::now();
+            let mut timeout = self.timer.map(|(_,next)| if next > now { next - now } else { Duration::from_millis(0) });
+            if self.terminal.is_tty() {
+                let poll = Duration::from_millis(RESIZE_POLL_MILLIS);
+                timeout = Some(timeout.map(|t| t.min(poll)).unwrap_or(poll));
+            }
+            let key = match self.terminal.read_key(timeout) {
+                Ok(key) => key,
+                Err(_) => self.exit(),
+            };
+            if let Some((interval,next)) = self.timer {
+                if Instant::now() >= next {
+                    self.timer = Some((interval,Instant::now() + interval));
+                    return Event::new(glk::evtype_Timer, WinId(0), 0, 0);
+                }
+            }
+            let key = match key {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Some(win) = more {
+                let rect = self.windows.get(&win).unwrap().rect;
+                self.contents.get_mut(&win).unwrap().page_more(&rect);
+                continue;
+            }
+            if let Some(event) = self.key(key) {
+                return event;
+            }
+        }
+    }
+
+    fn key(&mut self, key: u32) -> Option<Event> {
+        let win = self.focus()?;
+        if let Some(uni) = self.contents.get_mut(&win).unwrap().char_request.take() {
+            let key = if !uni && key > 0xff && key < 0x110000 { glk::keycode_Unknown } else { key };
+            return Some(Event::new(glk::evtype_CharInput, win, key, 0));
+        }
+        let terminator = self.edit_line(&win, key)?;
+        Some(self.finish_line(&win, terminator))
+    }
+
+    // Edits the line input, returning the terminator if the key ends it.
+    fn edit_line(&mut self, win: &WinId, key: u32) -> Option<u32> {
+        let history = &self.history;
+        let w = self.contents.get_mut(win)?;
+        let room = w.input_room();
+        let terminators = w.terminators.clone();
+        let request = w.line_request.as_mut()?;
+        match key {
+            glk::keycode_Return => return Some(0),
+            _ if terminators.contains(&key) => return Some(key),
+            glk::keycode_Left => request.pos = request.pos.saturating_sub(1),
+            glk::keycode_Right => request.pos = (request.pos + 1).min(request.input.len()),
+            glk::keycode_Home => request.pos = 0,
+            glk::keycode_End => request.pos = request.input.len(),
+            glk::keycode_Delete => {
+                if request.pos > 0 {
+                    request.pos -= 1;
+                    request.input.remove(request.pos);
+                }
+            },
+            glk::keycode_Up | glk::keycode_Down => {
+                let index = match (key,request.history) {
+                    (glk::keycode_Up,None) if !history.is_empty() => Some(history.len() - 1),
+                    (glk::keycode_Up,Some(i)) if i > 0 => Some(i - 1),
+                    (glk::keycode_Down,Some(i)) if i + 1 < history.len() => Some(i + 1),
+                    (glk::keycode_Down,Some(_)) => None,
+                    _ => return None,
+                };
+                let max = request.buf.len().min(room.unwrap_or(request.buf.len()));
+                request.input = index.map(|i| history[i].clone()).unwrap_or(Vec::new());
+                request.input.truncate(max);
+                request.pos = request.input.len();
+                request.history = index;
+            },
+            _ if key >= 32 && key < 0x110000 => {
+                let max = request.buf.len().min(room.unwrap_or(request.buf.len()));
+                if request.input.len() < max {
+                    request.input.insert(request.pos, key);
+                    request.pos += 1;
+                }
+            },
+            _ => (),
+        }
+        None
+    }
+
+    fn finish_line(&mut self, win: &WinId, terminator: u32) -> Event {
+        let (request,echo,grid) = match self.contents.get_mut(win) {
+            Some(w) => match w.line_request.take() {
+                Some(request) => (request,w.echo_line,w.is_grid()),
+                None => return Event::none(),
+            },
+            None => return Event::none(),
+        };
+        let mut event = Event::new(glk::evtype_LineInput, *win, request.input.len() as u32, terminator);
+        match request.buf {
+            LineBuf::Latin1((addr,mut buf)) => {
+                for (i,&ch) in request.input.iter().enumerate() {
+                    buf[i] = if ch > 0xff { b'?' } else { ch as u8 };
+                }
+                event.buf = Some((addr,buf));
+            },
+            LineBuf::Uni((addr,mut buf)) => {
+                buf[.. request.input.len()].copy_from_slice(&request.input);
+                event.buf_uni = Some((addr,buf));
+            },
+        }
+        if !request.input.is_empty() && self.history.last() != Some(&request.input) {
+            self.history.push(request.input.clone());
+        }
+        if echo {
+            let str = self.windows.get(win).map(|w| w.str).unwrap_or(StrId(0));
+            let style = self.contents.get(win).map(|w| w.attr.style).unwrap_or(glk::style_Normal);
+            let mut echoed = request.input;
+            if !grid {
+                echoed.push('\n' as u32);
+            }
+            self.streams.set_style(&mut self.contents, &str, glk::style_Input);
+            self.streams.put(&mut self.contents, &str, &echoed);
+            self.streams.set_style(&mut self.contents, &str, style);
+        }
+        event
+    }
+
+    fn request_line(&mut self, win: &WinId, buf: LineBuf, initlen: u32) {
+        if let Some(w) = self.contents.get_mut(win) {
+            let mut input: Vec<u32> = match buf {
+                LineBuf::Latin1((_,ref buf)) => buf.iter().take(initlen as usize).map(|&b| b as u32).collect(),
+                LineBuf::Uni((_,ref buf)) => buf.iter().take(initlen as usize).cloned().collect(),
+            };
+            input.truncate(w.input_room().unwrap_or(input.len()));
+            let pos = input.len();
+            w.line_request = Some(LineRequest{ buf, input, pos, history: None });
+        }
+    }
+
+    // Reads a line in the first buffer window, for file name prompts.
+    fn prompt_line(&mut self, prompt: &str) -> Option<String> {
+        let win = WinId(self.contents.0.iter().position(|w| match w {
+            &Some(ref w) => !w.is_grid(),
+            &None => false,
+        })?);
+        if self.windows.get(&win).map(|w| w.wintype) != Some(glk::wintype_TextBuffer) {
+            return None;
+        }
+        let saved = {
+            let w = self.contents.get_mut(&win).unwrap();
+            let saved = (w.line_request.take(),w.char_request.take());
+            w.put(&prompt.chars().map(|ch| ch as u32).collect::<Vec<u32>>());
+            saved
+        };
+        self.request_line(&win, LineBuf::Uni((0,vec![0; 256].into_boxed_slice())), 0);
+        let result = loop {
+            if self.refresh().is_some() {
+                let rect = self.windows.get(&win).unwrap().rect;
+                self.contents.get_mut(&win).unwrap().page_more(&rect);
+                continue;
+            }
+            let key = match self.terminal.read_key(None) {
+                Ok(Some(key)) => key,
+                Ok(None) => continue,
+                Err(_) => self.exit(),
+            };
+            if key == glk::keycode_Escape {
+                break None;
+            }
+            if self.edit_line(&win, key).is_some() {
+                let w = self.contents.get_mut(&win).unwrap();
+                let input = w.line_request.take().unwrap().input;
+                let mut echoed = input.clone();
+                echoed.push('\n' as u32);
+                w.put(&echoed);
+                break Some(input.iter().filter_map(|&ch| std::char::from_u32(ch)).collect());
+            }
+        };
+        let w = self.contents.get_mut(&win).unwrap();
+        if result.is_none() {
+            w.line_request = None;
+            w.put(&['\n' as u32]);
+        }
+        w.line_request = saved.0;
+        w.char_request = saved.1;
+        result
+    }
+
+    fn stream_window(&mut self, str: &StrId) -> Option<&mut Window> {
+        let win = self.streams.window(str)?;
+        self.contents.get_mut(&win)
+    }
+
+    fn look(&self, win: &WinId, styl: u32) -> Option<Look> {
+        let w = self.contents.get(win)?;
+        Some(Look::new(&w.hints, &Attr{ style: styl, .. Attr::new() }))
+    }
 }
 
-fn main_func(main_func: fn(GlkTerm,Vec<String>), args: Vec<String>) {
-    main_func(GlkTerm{}, args);
+fn draw_border(frame: &mut Frame, rect: &glk::windows::Rect, first: &glk::windows::Rect, method: u32) {
+    let attr = Attr::new();
+    frame.set_hints(&Default::default());
+    match method & glk::winmethod_DirMask {
+        glk::winmethod_Left | glk::winmethod_Right => {
+            let x = first.left + first.width;
+            if x < rect.left + rect.width {
+                for y in rect.top .. rect.top + rect.height {
+                    frame.put_cells(x, y, &[screen::Cell{ ch: '\u{2502}', attr }]);
+                }
+            }
+        },
+        _ => {
+            let y = first.top + first.height;
+            if y < rect.top + rect.height {
+                let line = vec![screen::Cell{ ch: '\u{2500}', attr }; rect.width as usize];
+                frame.put_cells(rect.left, y, &line);
+            }
+        },
+    }
 }
 
-pub struct GlkTerm {
+fn to_chars(s: &[u8]) -> Vec<u32> {
+    s.iter().map(|&ch| ch as u32).collect()
 }
 
 impl<'a> Glk<'a> for GlkTerm {
@@ -42,1040 +402,731 @@ impl<'a> Glk<'a> for GlkTerm {
     type Event = Event;
     type TimeVal = TimeVal;
     type Date = Date;
-    type IOStream = IOStream;
+    type IOStream = IOStream<WinId>;
 
     fn exit(&mut self) -> ! {
-        unsafe {
-            c_interface::glk_exit();
+        self.refresh();
+        if self.terminal.is_tty() {
+            self.terminal.write("\x1b[?25l");
+            let (_,height) = self.terminal.size();
+            self.terminal.write(&format!("\x1b[{};1H\x1b[0m\x1b[7mHit any key to exit.\x1b[0m", height));
+            self.terminal.flush();
+            let _ = self.terminal.read_key(None);
         }
+        self.terminal.restore();
+        session::end(Ok(()))
     }
 
-    fn set_interrupt_handler(&mut self, handler: extern fn()) {
-        unsafe {
-            c_interface::glk_set_interrupt_handler(handler);
-        }
+    fn set_interrupt_handler(&mut self, _handler: extern fn()) {
     }
 
     fn tick(&mut self) {
-        unsafe {
-            c_interface::glk_tick();
-        }
     }
 
 
     fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
-        unsafe {
-            c_interface::glk_gestalt(sel, val)
-        }
+        self.gestalt_ext(sel, val, &mut [])
     }
 
     fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
-        let len = arr.len() as u32;
-        unsafe {
-            c_interface::glk_gestalt_ext(sel, val, &mut arr[0], len)
+        let printable = val >= 32 && val < 0x110000 && (val < 127 || val > 159);
+        let function = val >= glk::keycode_Func12 && val <= glk::keycode_Func1;
+        let special = val >= glk::keycode_Func12 && val <= glk::keycode_Left;
+        match sel {
+            glk::gestalt_Version => 0x00070500,
+            glk::gestalt_CharInput => if printable || special { 1 } else { 0 },
+            glk::gestalt_LineInput => if printable { 1 } else { 0 },
+            glk::gestalt_CharOutput => {
+                if arr.len() > 0 {
+                    arr[0] = 1;
+                }
+                if printable {
+                    glk::gestalt_CharOutput_ExactPrint
+                } else {
+                    glk::gestalt_CharOutput_CannotPrint
+                }
+            },
+            glk::gestalt_Timer => 1,
+            glk::gestalt_Unicode => 1,
+            glk::gestalt_UnicodeNorm => 1,
+            glk::gestalt_LineInputEcho => 1,
+            glk::gestalt_LineTerminators => 1,
+            glk::gestalt_LineTerminatorKey => if val == glk::keycode_Escape || function { 1 } else { 0 },
+            glk::gestalt_DateTime => 1,
+            glk::gestalt_ResourceStream => 1,
+            glk::gestalt_GarglkText => 1,
+            _ => 0,
         }
     }
 
 
     fn char_to_lower(&mut self, ch: u8) -> u8 {
-        unsafe {
-            c_interface::glk_char_to_lower(ch as std::os::raw::c_uchar) as u8
-        }
+        glk::unicode::latin1_to_lower(ch)
     }
 
     fn char_to_upper(&mut self, ch: u8) -> u8 {
-        unsafe {
-            c_interface::glk_char_to_upper(ch as std::os::raw::c_uchar) as u8
-        }
+        glk::unicode::latin1_to_upper(ch)
     }
 
 
     fn window_get_root(&mut self) -> Self::WinId {
-        let ptr = unsafe {
-            c_interface::glk_window_get_root()
-        };
-        WinId{ ptr }
+        self.windows.root()
     }
 
     fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
-        let ptr = unsafe {
-            c_interface::glk_window_open(split.ptr, method, size, wintype, rock)
+        let hints = match wintype {
+            glk::wintype_Blank | glk::wintype_TextBuffer => self.buffer_hints,
+            glk::wintype_TextGrid => self.grid_hints,
+            _ => return WinId(0),
+        };
+        let (win,pair) = match self.windows.open(split, method, size, wintype, rock) {
+            Some(opened) => opened,
+            None => return WinId(0),
         };
-        WinId{ ptr }
+        let str = self.streams.open_window(win);
+        self.windows.get_mut(&win).unwrap().str = str;
+        self.contents.insert(&win, Window::new(wintype, hints));
+        if let Some(pair) = pair {
+            let str = self.streams.open_window(pair);
+            self.windows.get_mut(&pair).unwrap().str = str;
+        }
+        self.relayout();
+        win
     }
 
     fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
-        let mut result = Default::default();
-        unsafe {
-            c_interface::glk_window_close(win.ptr, &mut result);
+        let mut result = (0,0);
+        for (id,w) in self.windows.close(win) {
+            let (readcount,writecount,_,_) = self.streams.close(&w.str);
+            self.contents.remove(&id);
+            if id == *win {
+                result = (readcount,writecount);
+            }
         }
-        win.ptr = std::ptr::null();
-        (result.readcount,result.writecount,
-         array_registry::retrieve_stream_memory(),
-         array_registry::retrieve_stream_memory_uni())
+        self.relayout();
+        *win = WinId(0);
+        (result.0,result.1,None,None)
     }
 
     fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
-        let mut width = 0;
-        let mut height = 0;
-        unsafe {
-            c_interface::glk_window_get_size(win.ptr, &mut width, &mut height);
-        }
-        (width,height)
+        self.windows.size(win)
     }
 
     fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
-        unsafe {
-            c_interface::glk_window_set_arrangement(win.ptr, method, size, keywin.ptr);
-        }
+        self.windows.set_arrangement(win, method, size, keywin);
+        self.relayout();
     }
 
     fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
-        let mut method = 0;
-        let mut size = 0;
-        let mut keywin = std::ptr::null();
-        unsafe {
-            c_interface::glk_window_get_arrangement(win.ptr, &mut method, &mut size, &mut keywin);
-        }
-        (method,size,WinId{ ptr: keywin })
+        self.windows.get_arrangement(win)
     }
 
     fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe {
-            c_interface::glk_window_iterate(win.ptr, &mut rock)
-        };
-        (WinId{ ptr },rock)
+        self.windows.iterate(win)
     }
 
     fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
-        unsafe {
-            c_interface::glk_window_get_rock(win.ptr)
-        }
+        self.windows.get(win).map(|w| w.rock).unwrap_or(0)
     }
 
     fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
-        unsafe {
-            c_interface::glk_window_get_type(win.ptr)
-        }
+        self.windows.get(win).map(|w| w.wintype).unwrap_or(0)
     }
 
     fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
-        let ptr = unsafe { c_interface::glk_window_get_parent(win.ptr) };
-        WinId{ ptr }
+        self.windows.get(win).map(|w| w.parent).unwrap_or(WinId(0))
     }
 
     fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
-        let ptr = unsafe { c_interface::glk_window_get_sibling(win.ptr) };
-        WinId{ ptr }
+        self.windows.sibling(win)
     }
 
     fn window_clear(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_window_clear(win.ptr);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.clear();
         }
     }
 
     fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
-        unsafe {
-            c_interface::glk_window_move_cursor(win.ptr, xpos, ypos);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.move_cursor(xpos, ypos);
         }
     }
 
 
     fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_window_get_stream(win.ptr) };
-        StrId{ ptr }
+        self.windows.get(win).map(|w| w.str).unwrap_or(StrId(0))
     }
 
     fn window_set_echo_stream(&mut self, win: &Self::WinId, str: &Self::StrId) {
-        unsafe {
-            c_interface::glk_window_set_echo_stream(win.ptr, str.ptr);
-        }
+        let winstr = self.window_get_stream(win);
+        self.streams.set_echo(&winstr, str);
     }
 
     fn window_get_echo_stream(&mut self, win: &Self::WinId) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_window_get_echo_stream(win.ptr) };
-        StrId{ ptr }
+        let winstr = self.window_get_stream(win);
+        self.streams.echo(&winstr)
     }
 
     fn set_window(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_set_window(win.ptr);
-        }
+        let str = self.window_get_stream(win);
+        self.streams.set_current(&str);
     }
 
 
     fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_file(fileref.ptr, fmode, rock) };
-        StrId{ ptr }
+        self.streams.open_file(&self.frefs, fileref, fmode, rock, false)
     }
 
     fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
-        let buflen = buf.1.len() as u32;
-        let ptr = unsafe {
-            c_interface::glk_stream_open_memory(array_registry::register_stream_memory(buf), buflen, fmode, rock)
-        };
-        StrId{ ptr }
+        self.streams.open_memory(buf, fmode, rock)
     }
 
     fn stream_close(&mut self, str: &mut Self::StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
-        let mut result = Default::default();
-        unsafe {
-            c_interface::glk_stream_close(str.ptr, &mut result);
+        // Window streams are closed with their windows.
+        if self.streams.window(str).is_some() {
+            return (0,0,None,None);
         }
-        str.ptr = std::ptr::null();
-        (result.readcount,result.writecount,
-         array_registry::retrieve_stream_memory(),
-         array_registry::retrieve_stream_memory_uni())
+        let result = self.streams.close(str);
+        *str = StrId(0);
+        result
     }
 
     fn stream_iterate(&mut self, str: &Self::StrId) -> (Self::StrId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe {
-            c_interface::glk_stream_iterate(str.ptr, &mut rock)
-        };
-        (StrId{ ptr },rock)
+        self.streams.iterate(str)
     }
 
     fn stream_get_rock(&mut self, str: &Self::StrId) -> u32 {
-        unsafe {
-            c_interface::glk_stream_get_rock(str.ptr)
-        }
+        self.streams.rock(str)
     }
 
     fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
-        unsafe {
-            c_interface::glk_stream_set_position(str.ptr, pos, seekmode);
-        }
+        self.streams.set_position(str, pos, seekmode);
     }
 
     fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
-        unsafe {
-            c_interface::glk_stream_get_position(str.ptr)
-        }
+        self.streams.position(str)
     }
 
     fn stream_set_current(&mut self, str: &Self::StrId) {
-        unsafe {
-            c_interface::glk_stream_set_current(str.ptr);
-        }
+        self.streams.set_current(str);
     }
 
     fn stream_get_current(&mut self) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_get_current() };
-        StrId{ ptr }
+        self.streams.current()
     }
 
 
     fn put_char(&mut self, ch: u8) {
-        unsafe {
-            c_interface::glk_put_char(ch as c_uchar);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &[ch as u32]);
     }
 
     fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
-        unsafe {
-            c_interface::glk_put_char_stream(str.ptr, ch as c_uchar);
-        }
+        self.streams.put(&mut self.contents, str, &[ch as u32]);
     }
 
     fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
-        let _ = c_interface::glk_put_string;
-        self.put_buffer(s.as_ref());
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &to_chars(s.as_ref()));
     }
 
     fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &Self::StrId, s: S) {
-        let _ = c_interface::glk_put_string_stream;
-        self.put_buffer_stream(str, s.as_ref());
+        self.streams.put(&mut self.contents, str, &to_chars(s.as_ref()));
     }
 
     fn put_buffer(&mut self, buf: &[u8]) {
-        unsafe {
-            c_interface::glk_put_buffer(buf.as_ptr() as *const c_char, buf.len() as u32);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &to_chars(buf));
     }
 
     fn put_buffer_stream(&mut self, str: &Self::StrId, buf: &[u8]) {
-        unsafe {
-            c_interface::glk_put_buffer_stream(str.ptr, buf.as_ptr() as *const c_char, buf.len() as u32);
-        }
+        self.streams.put(&mut self.contents, str, &to_chars(buf));
     }
 
     fn set_style(&mut self, styl: u32) {
-        unsafe {
-            c_interface::glk_set_style(styl);
-        }
+        let str = self.streams.current();
+        self.streams.set_style(&mut self.contents, &str, styl);
     }
 
     fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
-        unsafe {
-            c_interface::glk_set_style_stream(str.ptr, styl);
-        }
+        self.streams.set_style(&mut self.contents, str, styl);
     }
 
 
     fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
-        unsafe {
-            c_interface::glk_get_char_stream(str.ptr)
-        }
+        self.streams.get_char(str, false)
     }
 
     fn get_line_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
-        unsafe {
-            c_interface::glk_get_line_stream(str.ptr, buf.as_mut_ptr() as *mut c_char, buf.len() as u32)
-        }
+        self.streams.get_line(str, buf)
     }
 
     fn get_buffer_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
-        unsafe {
-            c_interface::glk_get_buffer_stream(str.ptr, buf.as_mut_ptr() as *mut c_char, buf.len() as u32)
-        }
+        self.streams.get_buffer(str, buf)
     }
 
 
     fn stylehint_set(&mut self, wintype: u32, styl: u32, hint: u32, val: i32) {
-        unsafe {
-            c_interface::glk_stylehint_set(wintype, styl, hint, val);
+        if styl >= glk::style_NUMSTYLES || hint >= glk::stylehint_NUMHINTS {
+            return;
+        }
+        if wintype == glk::wintype_AllTypes || wintype == glk::wintype_TextBuffer {
+            self.buffer_hints[styl as usize][hint as usize] = Some(val);
+        }
+        if wintype == glk::wintype_AllTypes || wintype == glk::wintype_TextGrid {
+            self.grid_hints[styl as usize][hint as usize] = Some(val);
         }
     }
 
     fn stylehint_clear(&mut self, wintype: u32, styl: u32, hint: u32) {
-        unsafe {
-            c_interface::glk_stylehint_clear(wintype, styl, hint);
+        if styl >= glk::style_NUMSTYLES || hint >= glk::stylehint_NUMHINTS {
+            return;
+        }
+        if wintype == glk::wintype_AllTypes || wintype == glk::wintype_TextBuffer {
+            self.buffer_hints[styl as usize][hint as usize] = None;
+        }
+        if wintype == glk::wintype_AllTypes || wintype == glk::wintype_TextGrid {
+            self.grid_hints[styl as usize][hint as usize] = None;
         }
     }
 
     fn style_distinguish(&mut self, win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
-        let result = unsafe {
-            c_interface::glk_style_distinguish(win.ptr, styl1, styl2)
-        };
-        result != 0
+        match (self.look(win, styl1),self.look(win, styl2)) {
+            (Some(look1),Some(look2)) => look1 != look2,
+            _ => false,
+        }
     }
 
     fn style_measure(&mut self, win: &Self::WinId, styl: u32, hint: u32) -> (bool,u32) {
-        let mut result = 0;
-        let success = unsafe {
-            c_interface::glk_style_measure(win.ptr, styl, hint, &mut result)
+        let look = match self.look(win, styl) {
+            Some(look) => look,
+            None => return (false,0),
         };
-        (success != 0,result)
+        match hint {
+            glk::stylehint_Weight => (true,if look.bold { 1 } else { 0 }),
+            glk::stylehint_Oblique => (true,if look.italic { 1 } else { 0 }),
+            glk::stylehint_ReverseColor => (true,if look.reverse { 1 } else { 0 }),
+            glk::stylehint_TextColor => look.fg.map(|color| (true,color)).unwrap_or((false,0)),
+            glk::stylehint_BackColor => look.bg.map(|color| (true,color)).unwrap_or((false,0)),
+            _ => (false,0),
+        }
     }
 
 
     fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
-        let ptr = unsafe {
-            c_interface::glk_fileref_create_temp(usage, rock)
-        };
-        FRefId{ ptr }
+        self.frefs.create_temp(usage, rock)
     }
 
     fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
-        let mut vec = Vec::new();
-        vec.extend_from_slice(name.as_ref());
-        let ptr = if let Ok(cname) = CString::new(vec) {
-            unsafe {
-                c_interface::glk_fileref_create_by_name(usage, cname.as_ptr(), rock)
-            }
-        } else {
-            std::ptr::null()
-        };
-        FRefId{ ptr }
+        self.frefs.create_by_name(usage, name, rock)
     }
 
     fn fileref_create_by_prompt(&mut self, usage: u32, fmode: u32, rock: u32) -> Self::FRefId {
-        let ptr = unsafe {
-            c_interface::glk_fileref_create_by_prompt(usage, fmode, rock)
+        let prompt = match (usage & glk::fileusage_TypeMask,fmode) {
+            (glk::fileusage_SavedGame,glk::filemode_Read) => "Restore from file: ",
+            (glk::fileusage_SavedGame,_) => "Save to file: ",
+            (glk::fileusage_Transcript,_) => "Transcript file: ",
+            (glk::fileusage_InputRecord,glk::filemode_Read) => "Read commands from file: ",
+            (glk::fileusage_InputRecord,_) => "Record commands to file: ",
+            _ => "File name: ",
+        };
+        let name = match self.prompt_line(prompt) {
+            Some(name) => name,
+            None => return FRefId(0),
         };
-        FRefId{ ptr }
+        let name = name.trim();
+        if name.is_empty() {
+            return FRefId(0);
+        }
+        self.frefs.create_by_path(PathBuf::from(name), usage, rock)
     }
 
     fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
-        let ptr = unsafe {
-            c_interface::glk_fileref_create_from_fileref(usage, fref.ptr, rock)
-        };
-        FRefId{ ptr }
+        self.frefs.create_from_fileref(usage, fref, rock)
     }
 
     fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
-        unsafe {
-            c_interface::glk_fileref_destroy(fref.ptr);
-        }
-        fref.ptr = std::ptr::null();
+        self.frefs.destroy(fref);
+        *fref = FRefId(0);
     }
 
     fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe {
-            c_interface::glk_fileref_iterate(fref.ptr, &mut rock)
-        };
-        (FRefId{ ptr },rock)
+        self.frefs.iterate(fref)
     }
 
     fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
-        unsafe {
-            c_interface::glk_fileref_get_rock(fref.ptr)
-        }
+        self.frefs.rock(fref)
     }
 
     fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
-        unsafe {
-            c_interface::glk_fileref_delete_file(fref.ptr);
-        }
+        self.frefs.delete_file(fref);
     }
 
     fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
-        let result = unsafe {
-            c_interface::glk_fileref_does_file_exist(fref.ptr)
-        };
-        result != 0
+        self.frefs.does_file_exist(fref)
     }
 
 
+    // Waits for a key for a window with an input request, a timer, or a
+    // change in the terminal's size.
     fn select(&mut self) -> Self::Event {
-        let mut event = c_interface::event_t {
-            evtype: 0, win: std::ptr::null(), val1: 0, val2: 0
-        };
-        unsafe {
-            c_interface::glk_select(&mut event);
+        if self.focus().is_none() && self.timer.is_none() {
+            self.exit();
         }
-        Event(event)
+        self.wait()
     }
 
     fn select_poll(&mut self) -> Self::Event {
-        let mut event = c_interface::event_t {
-            evtype: 0, win: std::ptr::null(), val1: 0, val2: 0
-        };
-        unsafe {
-            c_interface::glk_select_poll(&mut event);
+        if self.check_size() && !self.windows.root().is_null() {
+            return Event::new(glk::evtype_Arrange, WinId(0), 0, 0);
         }
-        Event(event)
+        if let Some((interval,next)) = self.timer {
+            if Instant::now() >= next {
+                self.timer = Some((interval,Instant::now() + interval));
+                return Event::new(glk::evtype_Timer, WinId(0), 0, 0);
+            }
+        }
+        Event::none()
     }
 
 
     fn request_timer_events(&mut self, millisecs: u32) {
-        unsafe {
-            c_interface::glk_request_timer_events(millisecs);
-        }
+        self.timer = if millisecs == 0 {
+            None
+        } else {
+            let interval = Duration::from_millis(millisecs as u64);
+            Some((interval,Instant::now() + interval))
+        };
     }
 
 
     fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
-        let maxlen = buf.1.len() as u32;
-        let cbuf = array_registry::register_line_event(buf);
-        unsafe {
-            c_interface::glk_request_line_event(win.ptr, cbuf, maxlen, initlen);
-        }
+        self.request_line(win, LineBuf::Latin1(buf), initlen);
     }
 
     fn request_char_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_char_event(win.ptr);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.char_request = Some(false);
         }
     }
 
-    fn request_mouse_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_mouse_event(win.ptr);
-        }
+    fn request_mouse_event(&mut self, _win: &Self::WinId) {
     }
 
 
     fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
-        let mut event = c_interface::event_t {
-            evtype: 0, win: std::ptr::null(), val1: 0, val2: 0
-        };
-        unsafe {
-            c_interface::glk_cancel_line_event(win.ptr, &mut event);
-        }
-        Event(event)
+        self.finish_line(win, 0)
     }
 
     fn cancel_char_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_cancel_char_event(win.ptr);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.char_request = None;
         }
     }
 
-    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_cancel_mouse_event(win.ptr);
-        }
+    fn cancel_mouse_event(&mut self, _win: &Self::WinId) {
     }
 
 
     fn set_echo_line_event(&mut self, win: &Self::WinId, val: u32) {
-        unsafe {
-            c_interface::glk_set_echo_line_event(win.ptr, val);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.echo_line = val != 0;
         }
     }
 
 
     fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
-        unsafe {
-            c_interface::glk_set_terminators_line_event(win.ptr, keycodes.as_ptr(), keycodes.len() as u32);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.terminators = keycodes.to_vec();
         }
     }
 
 
     fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_to_lower_case_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::to_lower_case(buf, numchars)
     }
 
     fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_to_upper_case_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::to_upper_case(buf, numchars)
     }
 
     fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_to_title_case_uni(buf.as_mut_ptr(), buf.len() as u32, numchars, lowerrest)
-        }
+        glk::unicode::to_title_case(buf, numchars, lowerrest != 0)
     }
 
 
     fn put_char_uni(&mut self, ch: u32) {
-        unsafe {
-            c_interface::glk_put_char_uni(ch);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &[ch]);
     }
 
     fn put_string_uni<SU: AsRef<[u32]>>(&mut self, s: SU) {
-        let _ = c_interface::glk_put_string_uni;
-        self.put_buffer_uni(s.as_ref());
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, s.as_ref());
     }
 
     fn put_buffer_uni(&mut self, buf: &[u32]) {
-        unsafe {
-            c_interface::glk_put_buffer_uni(buf.as_ptr(), buf.len() as u32);
-        }
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, buf);
     }
 
     fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
-        unsafe {
-            c_interface::glk_put_char_stream_uni(str.ptr, ch);
-        }
+        self.streams.put(&mut self.contents, str, &[ch]);
     }
 
     fn put_string_stream_uni<SU: AsRef<[u32]>>(&mut self, str: &Self::StrId, s: SU) {
-        let _ = c_interface::glk_put_string_stream_uni;
-        self.put_buffer_stream_uni(str, s.as_ref());
+        self.streams.put(&mut self.contents, str, s.as_ref());
     }
 
     fn put_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &[u32]) {
-        unsafe {
-            c_interface::glk_put_buffer_stream_uni(str.ptr, buf.as_ptr(), buf.len() as u32);
-        }
+        self.streams.put(&mut self.contents, str, buf);
     }
 
 
     fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
-        unsafe {
-            c_interface::glk_get_char_stream_uni(str.ptr)
-        }
+        self.streams.get_char(str, true)
     }
 
     fn get_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
-        unsafe {
-            c_interface::glk_get_buffer_stream_uni(str.ptr, buf.as_mut_ptr(), buf.len() as u32)
-        }
+        self.streams.get_buffer_uni(str, buf)
     }
 
     fn get_line_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
-        unsafe {
-            c_interface::glk_get_line_stream_uni(str.ptr, buf.as_mut_ptr(), buf.len() as u32)
-        }
+        self.streams.get_line_uni(str, buf)
     }
 
 
     fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_file_uni(fileref.ptr, fmode, rock) };
-        StrId{ ptr }
+        self.streams.open_file(&self.frefs, fileref, fmode, rock, true)
     }
 
     fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
-        let buflen = buf.1.len() as u32;
-        let ptr = unsafe {
-            c_interface::glk_stream_open_memory_uni(array_registry::register_stream_memory_uni(buf), buflen, fmode, rock)
-        };
-        StrId{ ptr }
+        self.streams.open_memory_uni(buf, fmode, rock)
     }
 
 
     fn request_char_event_uni(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_char_event_uni(win.ptr);
+        if let Some(w) = self.contents.get_mut(win) {
+            w.char_request = Some(true);
         }
     }
 
     fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
-        let maxlen = buf.1.len() as u32;
-        let cbuf = array_registry::register_line_event_uni(buf);
-        unsafe {
-            c_interface::glk_request_line_event_uni(win.ptr, cbuf, maxlen, initlen);
-        }
+        self.request_line(win, LineBuf::Uni(buf), initlen);
     }
 
 
     fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_canon_decompose_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::canon_decompose(buf, numchars)
     }
 
     fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        unsafe {
-            c_interface::glk_buffer_canon_normalize_uni(buf.as_mut_ptr(), buf.len() as u32, numchars)
-        }
+        glk::unicode::canon_normalize(buf, numchars)
     }
 
 
-    fn image_draw(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32) -> bool {
-        let result = unsafe {
-            c_interface::glk_image_draw(win.ptr, image, val1, val2)
-        };
-        result != 0
+    fn image_draw(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32) -> bool {
+        false
     }
 
-    fn image_draw_scaled(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> bool {
-        let result = unsafe {
-            c_interface::glk_image_draw_scaled(win.ptr, image, val1, val2, width, height)
-        };
-        result != 0
+    fn image_draw_scaled(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32, _width: u32, _height: u32) -> bool {
+        false
     }
 
-    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
-        let mut width = 0;
-        let mut height = 0;
-        let result = unsafe {
-            c_interface::glk_image_get_info(image, &mut width, &mut height)
-        };
-        (result != 0,width,height)
+    fn image_get_info(&mut self, _image: u32) -> (bool,u32,u32) {
+        (false,0,0)
     }
 
 
-    fn window_flow_break(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_window_flow_break(win.ptr);
-        }
+    fn window_flow_break(&mut self, _win: &Self::WinId) {
     }
 
 
-    fn window_erase_rect(&mut self, win: &Self::WinId, left: i32, top: i32, width: u32, height: u32) {
-        unsafe {
-            c_interface::glk_window_erase_rect(win.ptr, left, top, width, height);
-        }
+    fn window_erase_rect(&mut self, _win: &Self::WinId, _left: i32, _top: i32, _width: u32, _height: u32) {
     }
 
-    fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32) {
-        unsafe {
-            c_interface::glk_window_fill_rect(win.ptr, color, left, top, width, height);
-        }
+    fn window_fill_rect(&mut self, _win: &Self::WinId, _color: u32, _left: i32, _top: i32, _width: u32, _height: u32) {
     }
 
-    fn window_set_background_color(&mut self, win: &Self::WinId, color: u32) {
-        unsafe {
-            c_interface::glk_window_set_background_color(win.ptr, color);
-        }
+    fn window_set_background_color(&mut self, _win: &Self::WinId, _color: u32) {
     }
 
 
-    fn schannel_create(&mut self, rock: u32) -> Self::SChanId {
-        let ptr = unsafe { c_interface::glk_schannel_create(rock) };
-        SChanId{ ptr }
+    fn image_draw_scaled_ext(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32, _width: u32, _height: u32, _imagerule: u32, _maxwidth: u32) -> bool {
+        false
     }
 
-    fn schannel_destroy(&mut self, chan: &mut Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_destroy(chan.ptr);
-        }
-        chan.ptr = std::ptr::null();
+
+    fn schannel_create(&mut self, _rock: u32) -> Self::SChanId {
+        SChanId(())
     }
 
-    fn schannel_iterate(&mut self, chan: &Self::SChanId) -> (Self::SChanId,u32) {
-        let mut rock = 0;
-        let ptr = unsafe { c_interface::glk_schannel_iterate(chan.ptr, &mut rock) };
-        (SChanId{ ptr },rock)
+    fn schannel_destroy(&mut self, _chan: &mut Self::SChanId) {
     }
 
-    fn schannel_get_rock(&mut self, chan: &Self::SChanId) -> u32 {
-        unsafe {
-            c_interface::glk_schannel_get_rock(chan.ptr)
-        }
+    fn schannel_iterate(&mut self, _chan: &Self::SChanId) -> (Self::SChanId,u32) {
+        (SChanId(()),0)
+    }
+
+    fn schannel_get_rock(&mut self, _chan: &Self::SChanId) -> u32 {
+        0
     }
 
 
-    fn schannel_play(&mut self, chan: &Self::SChanId, snd: u32) -> bool {
-        let result = unsafe { c_interface::glk_schannel_play(chan.ptr, snd) };
-        result != 0
+    fn schannel_play(&mut self, _chan: &Self::SChanId, _snd: u32) -> bool {
+        false
     }
 
-    fn schannel_play_ext(&mut self, chan: &Self::SChanId, snd: u32, repeat: u32, notify: u32) -> bool {
-        let result = unsafe { c_interface::glk_schannel_play_ext(chan.ptr, snd, repeat, notify) };
-        result != 0
+    fn schannel_play_ext(&mut self, _chan: &Self::SChanId, _snd: u32, _repeat: u32, _notify: u32) -> bool {
+        false
     }
 
-    fn schannel_stop(&mut self, chan: &Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_stop(chan.ptr);
-        }
+    fn schannel_stop(&mut self, _chan: &Self::SChanId) {
     }
 
-    fn schannel_set_volume(&mut self, chan: &Self::SChanId, vol: u32) {
-        unsafe {
-            c_interface::glk_schannel_set_volume(chan.ptr, vol);
-        }
+    fn schannel_set_volume(&mut self, _chan: &Self::SChanId, _vol: u32) {
     }
 
 
-    fn sound_load_hint(&mut self, snd: u32, flag: u32) {
-        unsafe {
-            c_interface::glk_sound_load_hint(snd, flag);
-        }
+    fn sound_load_hint(&mut self, _snd: u32, _flag: u32) {
     }
 
 
-    fn schannel_create_ext(&mut self, rock: u32, volume: u32) -> Self::SChanId {
-        let ptr = unsafe { c_interface::glk_schannel_create_ext(rock, volume) };
-        SChanId{ ptr }
+    fn schannel_create_ext(&mut self, _rock: u32, _volume: u32) -> Self::SChanId {
+        SChanId(())
     }
 
-    fn schannel_play_multi(&mut self, chanarray: &[Self::SChanId], sndarray: &[u32], notify: u32) -> bool {
-        let mut chans = Vec::with_capacity(chanarray.len());
-        for chan in chanarray {
-            chans.push(chan.ptr);
-        }
-        let result = unsafe {
-            c_interface::glk_schannel_play_multi(chans.as_ptr(), chanarray.len() as u32, sndarray.as_ptr(), sndarray.len() as u32, notify)
-        };
-        result != 0
+    fn schannel_play_multi(&mut self, _chanarray: &[Self::SChanId], _sndarray: &[u32], _notify: u32) -> bool {
+        false
     }
 
-    fn schannel_pause(&mut self, chan: &Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_pause(chan.ptr);
-        }
+    fn schannel_pause(&mut self, _chan: &Self::SChanId) {
     }
 
-    fn schannel_unpause(&mut self, chan: &Self::SChanId) {
-        unsafe {
-            c_interface::glk_schannel_unpause(chan.ptr);
-        }
+    fn schannel_unpause(&mut self, _chan: &Self::SChanId) {
     }
 
-    fn schannel_set_volume_ext(&mut self, chan: &Self::SChanId, vol: u32, duration: u32, notify: u32) {
-        unsafe {
-            c_interface::glk_schannel_set_volume_ext(chan.ptr, vol, duration, notify);
-        }
+    fn schannel_set_volume_ext(&mut self, _chan: &Self::SChanId, _vol: u32, _duration: u32, _notify: u32) {
     }
 
 
-    fn set_hyperlink(&mut self, linkval: u32) {
-        unsafe {
-            c_interface::glk_set_hyperlink(linkval);
-        }
+    fn set_hyperlink(&mut self, _linkval: u32) {
     }
 
-    fn set_hyperlink_stream(&mut self, str: &Self::StrId, linkval: u32) {
-        unsafe {
-            c_interface::glk_set_hyperlink_stream(str.ptr, linkval);
-        }
+    fn set_hyperlink_stream(&mut self, _str: &Self::StrId, _linkval: u32) {
     }
 
-    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_request_hyperlink_event(win.ptr);
-        }
+    fn request_hyperlink_event(&mut self, _win: &Self::WinId) {
     }
 
-    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
-        unsafe {
-            c_interface::glk_cancel_hyperlink_event(win.ptr);
-        }
+    fn cancel_hyperlink_event(&mut self, _win: &Self::WinId) {
     }
 
 
+    // There is no time zone information, so local time is UTC.
     fn current_time(&mut self) -> Self::TimeVal {
-        let mut time = Default::default();
-        unsafe {
-            c_interface::glk_current_time(&mut time);
-        }
-        TimeVal(time)
+        TimeVal::now()
     }
 
     fn current_simple_time(&mut self, factor: u32) -> i32 {
-        unsafe {
-            c_interface::glk_current_simple_time(factor)
-        }
+        TimeVal::now().to_simple(factor)
     }
 
     fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_time_to_date_utc(&time.0, &mut date);
-        }
-        Date(date)
+        Date::from_time(time, 0)
     }
 
     fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_time_to_date_local(&time.0, &mut date);
-        }
-        Date(date)
+        Date::from_time(time, 0)
     }
 
     fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_simple_time_to_date_utc(time, factor, &mut date);
-        }
-        Date(date)
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
     }
 
     fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
-        let mut date = Default::default();
-        unsafe {
-            c_interface::glk_simple_time_to_date_local(time, factor, &mut date);
-        }
-        Date(date)
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
     }
 
     fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
-        let mut time = Default::default();
-        unsafe {
-            c_interface::glk_date_to_time_utc(&date.0, &mut time);
-        }
-        TimeVal(time)
+        date.to_time(0)
     }
 
     fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
-        let mut time = Default::default();
-        unsafe {
-            c_interface::glk_date_to_time_local(&date.0, &mut time);
-        }
-        TimeVal(time)
+        date.to_time(0)
     }
 
     fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
-        unsafe {
-            c_interface::glk_date_to_simple_time_utc(&date.0, factor)
-        }
+        date.to_time(0).to_simple(factor)
     }
 
     fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
-        unsafe {
-            c_interface::glk_date_to_simple_time_local(&date.0, factor)
-        }
+        date.to_time(0).to_simple(factor)
     }
 
 
     fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_resource(filenum, rock) };
-        StrId{ ptr }
+        let resource = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum));
+        self.streams.open_resource(resource, rock, false)
     }
 
     fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
-        let ptr = unsafe { c_interface::glk_stream_open_resource_uni(filenum, rock) };
-        StrId{ ptr }
+        let resource = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum));
+        self.streams.open_resource(resource, rock, true)
     }
 
 
     fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
-        IOStream(str.ptr)
+        self.streams.io_stream(str)
     }
-}
 
-#[derive(Clone,Eq,Hash,PartialEq)]
-pub struct WinId {
-    ptr: c_interface::winid_t,
-}
 
-impl IdType for WinId {
-    fn null() -> Self {
-        WinId{ ptr: std::ptr::null() }
+    fn set_resource_map(&mut self, blorb: Blorb) {
+        self.resources = Some(blorb);
     }
 
-    fn is_null(&self) -> bool {
-        self.ptr.is_null()
+    fn set_story_title(&mut self, title: &str) {
+        self.terminal.set_title(title);
     }
-}
 
-#[derive(Clone,Eq,Hash,PartialEq)]
-pub struct StrId {
-    ptr: c_interface::strid_t,
-}
-
-impl IdType for StrId {
-    fn null() -> Self {
-        StrId{ ptr: std::ptr::null() }
+    fn garglk_set_zcolors(&mut self, fg: u32, bg: u32) {
+        let str = self.streams.current();
+        self.garglk_set_zcolors_stream(&str, fg, bg);
     }
 
-    fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
-}
-
-pub struct IOStream(c_interface::strid_t);
-
-impl Read for IOStream {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        if self.0.is_null() {
-            return Err(Error::new(ErrorKind::NotConnected, "null stream"))
+    fn garglk_set_zcolors_stream(&mut self, str: &Self::StrId, fg: u32, bg: u32) {
+        if let Some(w) = self.stream_window(str) {
+            if fg != glk::zcolor_Current {
+                w.attr.fg = fg;
+            }
+            if bg != glk::zcolor_Current {
+                w.attr.bg = bg;
+            }
         }
-        let count = unsafe {
-            c_interface::glk_get_buffer_stream(self.0, buf.as_mut_ptr() as *mut c_char, buf.len() as u32)
-        };
-        Ok(count as usize)
     }
-}
 
-impl Write for IOStream {
-    fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        if self.0.is_null() {
-            return Err(Error::new(ErrorKind::NotConnected, "null stream"))
-        }
-        unsafe {
-            c_interface::glk_put_buffer_stream(self.0, buf.as_ptr() as *const c_char, buf.len() as u32);
-        }
-        Ok(buf.len())
+    fn garglk_set_reversevideo(&mut self, reverse: u32) {
+        let str = self.streams.current();
+        self.garglk_set_reversevideo_stream(&str, reverse);
     }
 
-    fn flush(&mut self) -> Result<()> {
-        Ok(())
-    }
-}
- 
-impl Seek for IOStream {
-    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
-        let (p,seekmode) = match pos {
-            SeekFrom::Start(p) => (p as i32,glk::seekmode_Start),
-            SeekFrom::End(p) => (p as i32,glk::seekmode_End),
-            SeekFrom::Current(p) => (p as i32,glk::seekmode_Current),
-        };
-        unsafe {
-            c_interface::glk_stream_set_position(self.0, p, seekmode);
-            Ok(c_interface::glk_stream_get_position(self.0) as u64)
+    fn garglk_set_reversevideo_stream(&mut self, str: &Self::StrId, reverse: u32) {
+        if let Some(w) = self.stream_window(str) {
+            w.attr.reverse = reverse != 0;
         }
     }
 }
 
 #[derive(Clone,Eq,Hash,PartialEq)]
-pub struct FRefId {
-    ptr: c_interface::frefid_t,
-}
-
-impl IdType for FRefId {
-    fn null() -> Self {
-        FRefId{ ptr: std::ptr::null() }
-    }
-
-    fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
-}
-
-#[derive(Clone,Eq,Hash,PartialEq)]
-pub struct SChanId {
-    ptr: c_interface::schanid_t,
-}
+pub struct SChanId(());
 
 impl IdType for SChanId {
     fn null() -> Self {
-        SChanId{ ptr: std::ptr::null() }
+        SChanId(())
     }
 
     fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
-}
-
-pub struct Event(c_interface::event_t);
-
-impl EventType<WinId> for Event {
-    fn evtype(&self) -> u32 {
-        self.0.evtype
-    }
-
-    fn win(&self) -> WinId {
-        WinId { ptr: self.0.win }
-    }
-
-    fn val1(&self) -> u32 {
-        self.0.val1
-    }
-
-    fn val2(&self) -> u32 {
-        self.0.val2
-    }
-
-    fn buf(&mut self) -> Option<(u32,Box<[u8]>)> {
-        array_registry::retrieve_line_event()
-    }
-
-    fn buf_uni(&mut self) -> Option<(u32,Box<[u32]>)> {
-        array_registry::retrieve_line_event_uni()
-    }
-}
-
-pub struct TimeVal(c_interface::glktimeval_t);
-
-impl TimeValType for TimeVal {
-    fn new(high_sec: i32, low_sec: u32, microsec: i32) -> Self {
-        TimeVal(c_interface::glktimeval_t{ high_sec, low_sec, microsec })
-    }
-
-    fn high_sec(&self) -> i32 {
-        self.0.high_sec
-    }
-
-    fn low_sec(&self) -> u32 {
-        self.0.low_sec
-    }
-
-    fn microsec(&self) -> i32 {
-        self.0.microsec
-    }
-}
-
-pub struct Date(c_interface::glkdate_t);
-
-impl DateType for Date {
-     fn new(year: i32, month: i32, day: i32, weekday: i32, hour: i32, minute: i32, second: i32, microsec: i32) -> Self {
-        Date(c_interface::glkdate_t{ year, month, day, weekday, hour, minute, second, microsec })
-    }
-
-   fn year(&self) -> i32 {
-        self.0.year
-    }
-
-    fn month(&self) -> i32 {
-        self.0.month
-    }
-
-    fn day(&self) -> i32 {
-        self.0.day
-    }
-
-    fn weekday(&self) -> i32 {
-        self.0.weekday
-    }
-
-    fn hour(&self) -> i32 {
-        self.0.hour
-    }
-
-    fn minute(&self) -> i32 {
-        self.0.minute
-    }
-
-    fn second(&self) -> i32 {
-        self.0.second
-    }
-
-    fn microsec(&self) -> i32 {
-        self.0.microsec
+        true
     }
 }
@@ -0,0 +1,506 @@
+use glk;
+use glk::streams::WindowSink;
+use glk::windows::{Rect,WinId};
+
+// The most paragraphs kept in a buffer window.
+const SCROLLBACK: usize = 1000;
+
+// Colors are 0xRRGGBB or zcolor_Default.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct Attr {
+    pub style: u32,
+    pub fg: u32,
+    pub bg: u32,
+    pub reverse: bool,
+}
+
+impl Attr {
+    pub fn new() -> Self {
+        Attr{ style: glk::style_Normal, fg: glk::zcolor_Default, bg: glk::zcolor_Default, reverse: false }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    pub attr: Attr,
+}
+
+impl Cell {
+    pub fn blank(attr: Attr) -> Self {
+        Cell{ ch: ' ', attr }
+    }
+}
+
+// Style hints, indexed by style and hint.
+pub type Hints = [[Option<i32>; glk::stylehint_NUMHINTS as usize]; glk::style_NUMSTYLES as usize];
+
+// How text is shown on the terminal.
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub struct Look {
+    pub bold: bool,
+    pub italic: bool,
+    pub reverse: bool,
+    pub fg: Option<u32>,
+    pub bg: Option<u32>,
+}
+
+impl Look {
+    pub fn new(hints: &Hints, attr: &Attr) -> Self {
+        let (bold,italic) = match attr.style {
+            glk::style_Emphasized | glk::style_Note => (false,true),
+            glk::style_Header | glk::style_Subheader | glk::style_Alert | glk::style_Input => (true,false),
+            _ => (false,false),
+        };
+        let hint = |h: u32| hints.get(attr.style as usize).and_then(|hs| hs[h as usize]);
+        let color = |zcolor: u32, h: u32| {
+            if zcolor != glk::zcolor_Default && zcolor <= 0xffffff {
+                Some(zcolor)
+            } else {
+                hint(h).map(|c| c as u32 & 0xffffff)
+            }
+        };
+        Look{
+            bold: hint(glk::stylehint_Weight).map(|w| w > 0).unwrap_or(bold),
+            italic: hint(glk::stylehint_Oblique).map(|o| o != 0).unwrap_or(italic),
+            reverse: attr.reverse != hint(glk::stylehint_ReverseColor).map(|r| r != 0).unwrap_or(false),
+            fg: color(attr.fg, glk::stylehint_TextColor),
+            bg: color(attr.bg, glk::stylehint_BackColor),
+        }
+    }
+
+    // The SGR escape sequence.
+    pub fn sgr(&self) -> String {
+        let mut sgr = "\x1b[0".to_string();
+        if self.bold {
+            sgr.push_str(";1");
+        }
+        if self.italic {
+            sgr.push_str(";3");
+        }
+        if self.reverse {
+            sgr.push_str(";7");
+        }
+        if let Some(fg) = self.fg {
+            sgr.push_str(&format!(";38;2;{};{};{}", fg >> 16, fg >> 8 & 0xff, fg & 0xff));
+        }
+        if let Some(bg) = self.bg {
+            sgr.push_str(&format!(";48;2;{};{};{}", bg >> 16, bg >> 8 & 0xff, bg & 0xff));
+        }
+        sgr.push_str("m");
+        sgr
+    }
+}
+
+pub enum LineBuf {
+    Latin1((u32,Box<[u8]>)),
+    Uni((u32,Box<[u32]>)),
+}
+
+impl LineBuf {
+    pub fn len(&self) -> usize {
+        match self {
+            &LineBuf::Latin1((_,ref buf)) => buf.len(),
+            &LineBuf::Uni((_,ref buf)) => buf.len(),
+        }
+    }
+}
+
+pub struct LineRequest {
+    pub buf: LineBuf,
+    pub input: Vec<u32>,
+    pub pos: usize,
+    // The history entry being edited, if any.
+    pub history: Option<usize>,
+}
+
+enum Text {
+    // The last paragraph is the one being written.  Rows after mark
+    // haven't been seen, and more is the number of them that have been
+    // paged past.
+    Buffer{ paras: Vec<Vec<Cell>>, mark: usize, more: usize },
+    Grid{ width: usize, height: usize, cells: Vec<Vec<Cell>>, x: usize, y: usize },
+    Blank,
+}
+
+pub struct Window {
+    pub attr: Attr,
+    pub hints: Hints,
+    pub line_request: Option<LineRequest>,
+    // Whether the request is for Unicode.
+    pub char_request: Option<bool>,
+    pub echo_line: bool,
+    pub terminators: Vec<u32>,
+    text: Text,
+}
+
+impl Window {
+    pub fn new(wintype: u32, hints: Hints) -> Self {
+        Window{
+            attr: Attr::new(),
+            hints,
+            line_request: None,
+            char_request: None,
+            echo_line: true,
+            terminators: Vec::new(),
+            text: match wintype {
+                glk::wintype_TextBuffer => Text::Buffer{ paras: vec![Vec::new()], mark: 0, more: 0 },
+                glk::wintype_TextGrid => Text::Grid{ width: 0, height: 0, cells: Vec::new(), x: 0, y: 0 },
+                _ => Text::Blank,
+            },
+        }
+    }
+
+    pub fn put(&mut self, s: &[u32]) {
+        let attr = self.attr;
+        match self.text {
+            Text::Buffer{ ref mut paras, ref mut mark, .. } => {
+                for &ch in s {
+                    if ch == '\n' as u32 {
+                        paras.push(Vec::new());
+                    } else {
+                        paras.last_mut().unwrap().push(Cell{ ch: to_char(ch), attr });
+                    }
+                }
+                if paras.len() > SCROLLBACK {
+                    let excess = paras.len() - SCROLLBACK;
+                    paras.drain(.. excess);
+                    *mark = mark.saturating_sub(excess);
+                }
+            },
+            Text::Grid{ width, height, ref mut cells, ref mut x, ref mut y } => {
+                for &ch in s {
+                    if ch == '\n' as u32 {
+                        *x = 0;
+                        *y += 1;
+                        continue;
+                    }
+                    if *x >= width {
+                        *x = 0;
+                        *y += 1;
+                    }
+                    if *y >= height {
+                        break;
+                    }
+                    cells[*y][*x] = Cell{ ch: to_char(ch), attr };
+                    *x += 1;
+                }
+            },
+            Text::Blank => (),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        let attr = self.attr;
+        match self.text {
+            Text::Buffer{ ref mut paras, ref mut mark, ref mut more } => {
+                *paras = vec![Vec::new()];
+                *mark = 0;
+                *more = 0;
+            },
+            Text::Grid{ ref mut cells, ref mut x, ref mut y, .. } => {
+                for line in cells.iter_mut() {
+                    for cell in line.iter_mut() {
+                        *cell = Cell::blank(attr);
+                    }
+                }
+                *x = 0;
+                *y = 0;
+            },
+            Text::Blank => (),
+        }
+    }
+
+    pub fn move_cursor(&mut self, xpos: u32, ypos: u32) {
+        if let Text::Grid{ ref mut x, ref mut y, .. } = self.text {
+            *x = xpos as usize;
+            *y = ypos as usize;
+        }
+    }
+
+    pub fn resize_grid(&mut self, new_width: u32, new_height: u32) {
+        if let Text::Grid{ ref mut width, ref mut height, ref mut cells, .. } = self.text {
+            *width = new_width as usize;
+            *height = new_height as usize;
+            cells.resize(*height, Vec::new());
+            for line in cells.iter_mut() {
+                line.resize(*width, Cell::blank(Attr::new()));
+            }
+        }
+    }
+
+    // Everything in the window has been seen.
+    pub fn mark_seen(&mut self) {
+        if let Text::Buffer{ ref paras, ref mut mark, ref mut more } = self.text {
+            *mark = paras.len() - 1;
+            *more = 0;
+        }
+    }
+
+    // Whether the unseen text doesn't fit in the rect, in which case
+    // page_more shows the next page.
+    pub fn needs_more(&self, rect: &Rect) -> bool {
+        match self.text {
+            Text::Buffer{ ref paras, mark, more } => {
+                let height = rect.height as usize;
+                height > 1 && self.rows(&paras[mark ..], rect.width as usize).0.len() > more + height
+            },
+            _ => false,
+        }
+    }
+
+    pub fn page_more(&mut self, rect: &Rect) {
+        if let Text::Buffer{ ref mut more, .. } = self.text {
+            *more += rect.height as usize - 1;
+        }
+    }
+
+    // The paragraphs, with the line input appended to the last one, as
+    // rows of (paragraph,start,end), and the position of the input cursor.
+    fn rows(&self, paras: &[Vec<Cell>], width: usize) -> (Vec<(usize,usize,usize)>,Option<(usize,usize)>) {
+        let mut rows = Vec::new();
+        let mut cursor = None;
+        for (i,para) in paras.iter().enumerate() {
+            let last = i == paras.len() - 1;
+            let para = if last { self.with_input(para) } else { para.clone() };
+            let first_row = rows.len();
+            wrap(&para, width, i, &mut rows);
+            if last {
+                if let Some(ref request) = self.line_request {
+                    let pos = para.len() - request.input.len() + request.pos;
+                    let row = (first_row .. rows.len()).rev().find(|&r| rows[r].1 <= pos).unwrap_or(first_row);
+                    cursor = Some((row,pos - rows[row].1));
+                }
+            }
+        }
+        (rows,cursor)
+    }
+
+    fn with_input(&self, para: &Vec<Cell>) -> Vec<Cell> {
+        let mut para = para.clone();
+        if let Some(ref request) = self.line_request {
+            let attr = Attr{ style: glk::style_Input, .. self.attr };
+            para.extend(request.input.iter().map(|&ch| Cell{ ch: to_char(ch), attr }));
+        }
+        para
+    }
+
+    // Draws the window, returning the cursor position if there is
+    // line input.
+    pub fn draw(&self, rect: &Rect, frame: &mut Frame) -> Option<(u32,u32)> {
+        let (width,height) = (rect.width as usize,rect.height as usize);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        match self.text {
+            Text::Buffer{ ref paras, mark, more } => {
+                let unseen = self.rows(&paras[mark ..], width).0.len();
+                if height > 1 && unseen > more + height {
+                    // Show the next page of unseen text.
+                    let unseen_paras: Vec<Vec<Cell>> = paras[mark ..].iter().enumerate().map(|(i,p)| if mark + i == paras.len() - 1 { self.with_input(p) } else { p.clone() }).collect();
+                    let mut rows = Vec::new();
+                    for (i,para) in unseen_paras.iter().enumerate() {
+                        wrap(para, width, i, &mut rows);
+                    }
+                    for (y,&(p,start,end)) in rows[more .. more + height - 1].iter().enumerate() {
+                        frame.put_cells(rect.left, rect.top + y as u32, &unseen_paras[p][start .. end]);
+                    }
+                    let attr = Attr{ style: glk::style_Alert, .. Attr::new() };
+                    let prompt: Vec<Cell> = "[MORE]".chars().map(|ch| Cell{ ch, attr }).collect();
+                    frame.put_cells(rect.left, rect.top + height as u32 - 1, &prompt[.. prompt.len().min(width)]);
+                    return None;
+                }
+                // Find enough paragraphs from the end to fill the window.
+                let mut first = paras.len() - 1;
+                while first > 0 && self.rows(&paras[first ..], width).0.len() < height {
+                    first -= 1;
+                }
+                let (rows,cursor) = self.rows(&paras[first ..], width);
+                let skip = rows.len().saturating_sub(height);
+                let input = self.with_input(&paras[paras.len() - 1]);
+                for (y,&(p,start,end)) in rows[skip ..].iter().enumerate() {
+                    let para = if first + p == paras.len() - 1 { &input } else { &paras[first + p] };
+                    frame.put_cells(rect.left, rect.top + y as u32, &para[start .. end]);
+                }
+                cursor.and_then(|(row,col)| {
+                    if row < skip {
+                        None
+                    } else {
+                        Some((rect.left + col.min(width - 1) as u32,rect.top + (row - skip) as u32))
+                    }
+                })
+            },
+            Text::Grid{ ref cells, x, y, .. } => {
+                for (row,line) in cells.iter().enumerate().take(height) {
+                    frame.put_cells(rect.left, rect.top + row as u32, &line[.. line.len().min(width)]);
+                }
+                let request = self.line_request.as_ref()?;
+                let attr = Attr{ style: glk::style_Input, .. self.attr };
+                let input: Vec<Cell> = request.input.iter().map(|&ch| Cell{ ch: to_char(ch), attr }).collect();
+                if y >= height || x >= width {
+                    return None;
+                }
+                frame.put_cells(rect.left + x as u32, rect.top + y as u32, &input[.. input.len().min(width - x)]);
+                Some((rect.left + (x + request.pos).min(width - 1) as u32,rect.top + y as u32))
+            },
+            Text::Blank => None,
+        }
+    }
+
+    // The longest line input in a grid window, which may not go past the
+    // right edge.
+    pub fn input_room(&self) -> Option<usize> {
+        match self.text {
+            Text::Grid{ width, x, .. } => Some(width.saturating_sub(x)),
+            _ => None,
+        }
+    }
+
+    pub fn is_grid(&self) -> bool {
+        match self.text {
+            Text::Grid{ .. } => true,
+            _ => false,
+        }
+    }
+}
+
+// Word wraps a paragraph into rows of at most width cells.
+fn wrap(para: &[Cell], width: usize, p: usize, rows: &mut Vec<(usize,usize,usize)>) {
+    let mut start = 0;
+    loop {
+        if para.len() - start <= width {
+            rows.push((p,start,para.len()));
+            return;
+        }
+        let end = match (start + 1 .. start + width + 1).rev().find(|&i| para[i].ch == ' ') {
+            Some(space) => space,
+            None => start + width,
+        };
+        rows.push((p,start,end));
+        // The space at a break isn't shown.
+        start = if para[end].ch == ' ' { end + 1 } else { end };
+    }
+}
+
+fn to_char(ch: u32) -> char {
+    match std::char::from_u32(ch) {
+        Some(ch) if !ch.is_control() => ch,
+        _ => '?',
+    }
+}
+
+// The content of all windows, indexed by window id.
+pub struct Contents(pub Vec<Option<Window>>);
+
+impl Contents {
+    pub fn get(&self, win: &WinId) -> Option<&Window> {
+        self.0.get(win.0).and_then(|w| w.as_ref())
+    }
+
+    pub fn get_mut(&mut self, win: &WinId) -> Option<&mut Window> {
+        self.0.get_mut(win.0).and_then(|w| w.as_mut())
+    }
+
+    pub fn insert(&mut self, win: &WinId, window: Window) {
+        while self.0.len() <= win.0 {
+            self.0.push(None);
+        }
+        self.0[win.0] = Some(window);
+    }
+
+    pub fn remove(&mut self, win: &WinId) -> Option<Window> {
+        self.0.get_mut(win.0).and_then(|w| w.take())
+    }
+}
+
+impl WindowSink<WinId> for Contents {
+    fn put_window(&mut self, win: &WinId, s: &[u32]) {
+        if let Some(w) = self.get_mut(win) {
+            w.put(s);
+        }
+    }
+
+    fn set_window_style(&mut self, win: &WinId, styl: u32) {
+        if let Some(w) = self.get_mut(win) {
+            w.attr.style = if styl < glk::style_NUMSTYLES { styl } else { glk::style_Normal };
+        }
+    }
+}
+
+// What is on the screen.
+#[derive(Clone,Eq,PartialEq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    cells: Vec<(Cell,Look)>,
+    hints: Hints,
+}
+
+impl Frame {
+    pub fn new(width: u32, height: u32) -> Self {
+        let blank = Cell::blank(Attr::new());
+        Frame{
+            width, height,
+            cells: vec![(blank,Look::default()); (width * height) as usize],
+            hints: Default::default(),
+        }
+    }
+
+    // The style hints for the cells that follow.
+    pub fn set_hints(&mut self, hints: &Hints) {
+        self.hints = *hints;
+    }
+
+    pub fn put_cells(&mut self, x: u32, y: u32, cells: &[Cell]) {
+        if y >= self.height {
+            return;
+        }
+        for (i,cell) in cells.iter().enumerate() {
+            let x = x + i as u32;
+            if x >= self.width {
+                return;
+            }
+            self.cells[(y * self.width + x) as usize] = (*cell,Look::new(&self.hints, &cell.attr));
+        }
+    }
+
+    pub fn text(&self) -> Vec<String> {
+        self.cells.chunks(self.width.max(1) as usize).map(|row| {
+            let line: String = row.iter().map(|&(cell,_)| cell.ch).collect();
+            line.trim_right().to_string()
+        }).collect()
+    }
+
+    // The escape sequences that change the screen from old to this.
+    pub fn update(&self, old: Option<&Frame>) -> String {
+        let mut out = String::new();
+        let mut look = None;
+        let mut pos = None;
+        if old.is_none() {
+            out.push_str("\x1b[0m\x1b[2J");
+        }
+        for y in 0 .. self.height {
+            for x in 0 .. self.width {
+                let i = (y * self.width + x) as usize;
+                let (cell,cell_look) = self.cells[i];
+                if let Some(old) = old {
+                    if old.cells[i] == self.cells[i] {
+                        continue;
+                    }
+                } else if cell.ch == ' ' && cell_look == Look::default() {
+                    continue;
+                }
+                if pos != Some((x,y)) {
+                    out.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+                }
+                if look != Some(cell_look) {
+                    out.push_str(&cell_look.sgr());
+                    look = Some(cell_look);
+                }
+                out.push(cell.ch);
+                pos = Some((x + 1,y));
+            }
+        }
+        out
+    }
+}
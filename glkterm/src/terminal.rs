@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+use std::io::{Error,ErrorKind,Read,Result,Write};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr,Ordering};
+use std::time::Duration;
+
+use glk;
+use libc;
+
+// Escape sequences for special keys, after the ESC.
+const KEYS: [(&'static [u8],u32); 36] = [
+    (b"[A", glk::keycode_Up), (b"[B", glk::keycode_Down),
+    (b"[C", glk::keycode_Right), (b"[D", glk::keycode_Left),
+    (b"OA", glk::keycode_Up), (b"OB", glk::keycode_Down),
+    (b"OC", glk::keycode_Right), (b"OD", glk::keycode_Left),
+    (b"[H", glk::keycode_Home), (b"[F", glk::keycode_End),
+    (b"OH", glk::keycode_Home), (b"OF", glk::keycode_End),
+    (b"[1~", glk::keycode_Home), (b"[4~", glk::keycode_End),
+    (b"[7~", glk::keycode_Home), (b"[8~", glk::keycode_End),
+    (b"[3~", glk::keycode_Delete),
+    (b"[5~", glk::keycode_PageUp), (b"[6~", glk::keycode_PageDown),
+    (b"OP", glk::keycode_Func1), (b"OQ", glk::keycode_Func2),
+    (b"OR", glk::keycode_Func3), (b"OS", glk::keycode_Func4),
+    (b"[11~", glk::keycode_Func1), (b"[12~", glk::keycode_Func2),
+    (b"[13~", glk::keycode_Func3), (b"[14~", glk::keycode_Func4),
+    (b"[15~", glk::keycode_Func5), (b"[17~", glk::keycode_Func6),
+    (b"[18~", glk::keycode_Func7), (b"[19~", glk::keycode_Func8),
+    (b"[20~", glk::keycode_Func9), (b"[21~", glk::keycode_Func10),
+    (b"[23~", glk::keycode_Func11), (b"[24~", glk::keycode_Func12),
+    (b"[Z", glk::keycode_Tab),
+];
+
+// Signals that would otherwise leave the tty in raw mode.
+const SIGNALS: [libc::c_int; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+// The tty's settings from before raw mode, for the signal handler.  The
+// tty belongs to the whole process, so this does too.
+static SAVED: AtomicPtr<libc::termios> = AtomicPtr::new(ptr::null_mut());
+
+// Restores the tty, then dies of the signal as if it weren't handled.
+// Only async-signal-safe calls are made here.
+extern "C" fn on_signal(sig: libc::c_int) {
+    const RESET: &'static [u8] = b"\x1b[0m\x1b[?25h\x1b[?1049l";
+    unsafe {
+        let termios = SAVED.load(Ordering::SeqCst);
+        if !termios.is_null() {
+            libc::write(1, RESET.as_ptr() as *const libc::c_void, RESET.len());
+            libc::tcsetattr(0, libc::TCSAFLUSH, termios);
+        }
+        libc::signal(sig, libc::SIG_DFL);
+        libc::raise(sig);
+    }
+}
+
+// A terminal that understands ANSI escape sequences.  When attached to
+// a tty, it is put in raw mode and restored when dropped.
+pub struct Terminal {
+    input: Box<Read + Send>,
+    output: Box<Write + Send>,
+    tty: Option<libc::termios>,
+    size: (u32,u32),
+    pending: VecDeque<u8>,
+}
+
+impl Terminal {
+    pub fn new(input: Box<Read + Send>, output: Box<Write + Send>, width: u32, height: u32) -> Self {
+        Terminal{ input, output, tty: None, size: (width,height), pending: VecDeque::new() }
+    }
+
+    // The terminal on stdin and stdout, or a 80x24 screen if they
+    // aren't a tty.
+    pub fn stdio() -> Self {
+        let mut terminal = Terminal::new(Box::new(std::io::stdin()), Box::new(std::io::stdout()), 80, 24);
+        unsafe {
+            if libc::isatty(0) == 0 || libc::isatty(1) == 0 {
+                return terminal;
+            }
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(0, &mut termios) != 0 {
+                return terminal;
+            }
+            let mut raw = termios;
+            raw.c_iflag &= !(libc::ICRNL | libc::IXON | libc::ISTRIP | libc::INLCR | libc::IGNCR);
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::IEXTEN);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(0, libc::TCSAFLUSH, &raw) != 0 {
+                return terminal;
+            }
+            terminal.tty = Some(termios);
+            let old = SAVED.swap(Box::into_raw(Box::new(termios)), Ordering::SeqCst);
+            if !old.is_null() {
+                drop(Box::from_raw(old));
+            }
+            for &sig in &SIGNALS {
+                libc::signal(sig, on_signal as *const () as libc::sighandler_t);
+            }
+        }
+        terminal.size = terminal.query_size().unwrap_or((80,24));
+        let _ = terminal.output.write_all(b"\x1b[?1049h\x1b[2J");
+        terminal
+    }
+
+    pub fn is_tty(&self) -> bool {
+        self.tty.is_some()
+    }
+
+    pub fn restore(&mut self) {
+        if let Some(termios) = self.tty.take() {
+            let _ = self.output.write_all(b"\x1b[0m\x1b[?25h\x1b[?1049l");
+            let _ = self.output.flush();
+            unsafe {
+                for &sig in &SIGNALS {
+                    libc::signal(sig, libc::SIG_DFL);
+                }
+                let old = SAVED.swap(ptr::null_mut(), Ordering::SeqCst);
+                if !old.is_null() {
+                    drop(Box::from_raw(old));
+                }
+                libc::tcsetattr(0, libc::TCSAFLUSH, &termios);
+            }
+        }
+    }
+
+    fn query_size(&self) -> Option<(u32,u32)> {
+        unsafe {
+            let mut winsize: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(1, libc::TIOCGWINSZ, &mut winsize) != 0 || winsize.ws_col == 0 || winsize.ws_row == 0 {
+                return None;
+            }
+            Some((winsize.ws_col as u32,winsize.ws_row as u32))
+        }
+    }
+
+    // The size in character cells, which a tty may change at any time.
+    pub fn size(&mut self) -> (u32,u32) {
+        if self.tty.is_some() {
+            if let Some(size) = self.query_size() {
+                self.size = size;
+            }
+        }
+        self.size
+    }
+
+    pub fn write(&mut self, s: &str) {
+        let _ = self.output.write_all(s.as_bytes());
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.output.flush();
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        if self.tty.is_some() {
+            let title: String = title.chars().filter(|ch| !ch.is_control()).collect();
+            self.write(&format!("\x1b]2;{}\x07", title));
+            self.flush();
+        }
+    }
+
+    // Waits for more input.  Returns false if the timeout expires first,
+    // which only happens on a tty.
+    fn fill(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        if self.tty.is_some() {
+            let millis = timeout.map(|t| t.as_secs() as i32 * 1000 + t.subsec_nanos() as i32 / 1000000).unwrap_or(-1);
+            let mut fds = libc::pollfd{ fd: 0, events: libc::POLLIN, revents: 0 };
+            match unsafe { libc::poll(&mut fds, 1, millis) } {
+                n if n < 0 => {
+                    let err = Error::last_os_error();
+                    return if err.kind() == ErrorKind::Interrupted { Ok(false) } else { Err(err) };
+                },
+                0 => return Ok(false),
+                _ => (),
+            }
+            let mut buf = [0; 64];
+            let n = unsafe { libc::read(0, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n <= 0 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "end of input"));
+            }
+            self.pending.extend(&buf[.. n as usize]);
+        } else {
+            let mut buf = [0; 1];
+            if self.input.read(&mut buf)? == 0 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "end of input"));
+            }
+            self.pending.push_back(buf[0]);
+        }
+        Ok(true)
+    }
+
+    // The next key, as a character or a keycode, or None if the timeout
+    // expires first.
+    pub fn read_key(&mut self, timeout: Option<Duration>) -> Result<Option<u32>> {
+        if self.pending.is_empty() && !self.fill(timeout)? {
+            return Ok(None);
+        }
+        let b = self.pending.pop_front().unwrap();
+        let key = match b {
+            b'\r' | b'\n' => glk::keycode_Return,
+            0x7f | 0x08 => glk::keycode_Delete,
+            b'\t' => glk::keycode_Tab,
+            0x1b => self.read_escape(),
+            0 ..= 0x7f => b as u32,
+            _ => self.read_utf8(b),
+        };
+        Ok(Some(key))
+    }
+
+    fn read_escape(&mut self) -> u32 {
+        let mut seq = Vec::new();
+        loop {
+            if self.pending.is_empty() {
+                let wait = Some(Duration::from_millis(25));
+                match self.fill(wait) {
+                    Ok(true) => (),
+                    _ => break,
+                }
+            }
+            let b = self.pending.pop_front().unwrap();
+            seq.push(b);
+            if let Some(&(_,key)) = KEYS.iter().find(|&&(s,_)| s == &seq[..]) {
+                return key;
+            }
+            if !KEYS.iter().any(|&(s,_)| s.starts_with(&seq)) {
+                break;
+            }
+        }
+        // Not a known sequence: the escape key, followed by whatever else.
+        for &b in seq.iter().rev() {
+            self.pending.push_front(b);
+        }
+        glk::keycode_Escape
+    }
+
+    fn read_utf8(&mut self, first: u8) -> u32 {
+        let len = match first {
+            0xc0 ..= 0xdf => 2,
+            0xe0 ..= 0xef => 3,
+            0xf0 ..= 0xf7 => 4,
+            _ => return glk::keycode_Unknown,
+        };
+        let mut bytes = vec![first];
+        while bytes.len() < len {
+            if self.pending.is_empty() && !self.fill(None).unwrap_or(false) {
+                break;
+            }
+            bytes.push(self.pending.pop_front().unwrap());
+        }
+        match std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()) {
+            Some(ch) => ch as u32,
+            None => glk::keycode_Unknown,
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
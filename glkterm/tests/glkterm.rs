@@ -0,0 +1,111 @@
+extern crate glk;
+extern crate glkterm;
+
+use std::io::{Cursor,Result,Write};
+use std::thread;
+
+use glk::{Glk,IdType};
+use glk::windows::WinId;
+use glkterm::{GlkTerm,Terminal};
+
+struct Output;
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn new(input: &[u8], width: u32, height: u32) -> GlkTerm {
+    GlkTerm::new(Terminal::new(Box::new(Cursor::new(input.to_vec())), Box::new(Output), width, height))
+}
+
+#[test]
+fn status_line() {
+    let mut glk = new(b"look\r", 20, 5);
+    let mainwin = glk.window_open(&WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+    let statuswin = glk.window_open(&mainwin, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 2);
+    assert!(!statuswin.is_null());
+    assert_eq!((20,1), glk.window_get_size(&statuswin));
+    assert_eq!((20,3), glk.window_get_size(&mainwin));
+
+    glk.window_move_cursor(&statuswin, 12, 0);
+    let str = glk.window_get_stream(&statuswin);
+    glk.put_string_stream(&str, "Moves: 1");
+    glk.set_window(&mainwin);
+    glk.put_string("A long sentence that wraps.\n>");
+
+    glk.request_line_event(&mainwin, (0,vec![0; 10].into_boxed_slice()), 0);
+    let event = glk.select();
+    assert_eq!(glk::evtype_LineInput, event.evtype);
+    assert!(event.win == mainwin);
+    assert_eq!(4, event.val1);
+    assert_eq!(b"look", &event.buf.unwrap().1[..4]);
+    assert_eq!(vec!["            Moves: 1", &"\u{2500}".repeat(20), "wraps.", ">look", ""], glk.screen());
+}
+
+#[test]
+fn split_with_border() {
+    let mut glk = new(b"", 21, 3);
+    let left = glk.window_open(&WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+    let right = glk.window_open(&left, glk::winmethod_Right | glk::winmethod_Proportional | glk::winmethod_Border, 50, glk::wintype_TextGrid, 2);
+    assert_eq!((10,3), glk.window_get_size(&left));
+    assert_eq!((10,3), glk.window_get_size(&right));
+    let pair = glk.window_get_parent(&right);
+    assert!(glk.window_get_sibling(&right) == left);
+    assert_eq!(glk::winmethod_Right | glk::winmethod_Proportional | glk::winmethod_Border, glk.window_get_arrangement(&pair).0);
+
+    let str = glk.window_get_stream(&right);
+    glk.put_string_stream(&str, "grid");
+    let str = glk.window_get_stream(&left);
+    glk.put_string_stream(&str, "buffer");
+    assert_eq!(vec!["buffer    \u{2502}grid", "          \u{2502}", "          \u{2502}"], glk.screen());
+
+    let mut right = right;
+    glk.window_close(&mut right);
+    assert!(glk.window_get_root() == left);
+    assert_eq!((21,3), glk.window_get_size(&left));
+}
+
+#[test]
+fn editing_and_terminators() {
+    let mut glk = new(b"ab\x1b[Dc\x7f\x7fx\x1b[24~q", 20, 5);
+    let win = glk.window_open(&WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+    assert_eq!(1, glk.gestalt(glk::gestalt_LineTerminatorKey, glk::keycode_Func12));
+    assert_eq!(1, glk.gestalt(glk::gestalt_GarglkText, 0));
+    glk.set_terminators_line_event(&win, &[glk::keycode_Func12]);
+    glk.request_line_event_uni(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+    let event = glk.select();
+    assert_eq!(glk::evtype_LineInput, event.evtype);
+    assert_eq!(glk::keycode_Func12, event.val2);
+    assert_eq!(2, event.val1);
+    assert_eq!(&[0x78, 0x62], &event.buf_uni.unwrap().1[..2]);
+
+    glk.request_char_event(&win);
+    let event = glk.select();
+    assert_eq!(glk::evtype_CharInput, event.evtype);
+    assert_eq!(0x71, event.val1);
+    assert_eq!("xb", glk.screen()[0]);
+}
+
+// Terminals moved to threads of their own each read their own input.
+#[test]
+fn threads() {
+    let threads: Vec<thread::JoinHandle<Vec<String>>> = ["look\r", "wait\r"].iter().map(|input| {
+        let mut glk = new(input.as_bytes(), 20, 3);
+        thread::spawn(move || {
+            let win = glk.window_open(&WinId(0), 0, 0, glk::wintype_TextBuffer, 1);
+            glk.set_window(&win);
+            glk.put_string(">");
+            glk.request_line_event(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+            glk.select();
+            glk.screen()
+        })
+    }).collect();
+    let screens: Vec<Vec<String>> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+    assert_eq!(vec![vec![">look", "", ""], vec![">wait", "", ""]], screens);
+}
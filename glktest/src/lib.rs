@@ -1,12 +1,88 @@
 extern crate glk;
+extern crate regex;
 
+use std::collections::{BTreeMap,HashMap};
+use std::fmt;
+use std::fs::File;
 use std::io::{Error,ErrorKind,Read,Result,Seek,SeekFrom,Write};
 
-use glk::{Glk,DateType,EventType,IdType,TimeValType};
+use regex::Regex;
+
+use glk::{Glk,EventType,IdType};
+use glk::blorb::{Blorb,Usage};
+use glk::session;
+use glk::windows::{Metrics,Windows};
+
+pub use glk::datetime::{Date,TimeVal};
 
 pub enum TestOutput<'a> {
     Match(&'a str),
-    Check(&'a Fn(&str) -> bool),
+    Check(&'a (Fn(&str) -> bool + Sync)),
+    // The output as runs of text in each style.
+    Styled(&'a [(u32,&'a str)]),
+    // A regular expression that must match the whole output.
+    Regex(&'a str),
+    // The path of a file holding the expected output.  With GLKTEST_BLESS
+    // set in the environment, the output is written to the file instead.
+    Snapshot(&'a str),
+}
+
+pub enum TestInput<'a> {
+    // A line for line input, or a single character for char input.
+    Text(&'a str),
+    // A line ended by one of the window's line terminators.
+    Terminated(&'a str,u32),
+    // A keycode, such as glk::keycode_Left, for char input.
+    Key(u32),
+    // Text typed into the pending line input without ending it.  The
+    // next step follows without another select, so a later Timer and
+    // cancel_line_event get the partial line.
+    Typed(&'a str),
+    Timer,
+    // A click at a position in the first window requesting mouse input.
+    Mouse(u32,u32),
+    // A click on a link in the first window requesting hyperlink input.
+    Hyperlink(u32),
+}
+
+impl<'a> fmt::Display for TestInput<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TestInput::Text(text) => write!(f, "{:?}", text),
+            TestInput::Terminated(text,keycode) => write!(f, "{:?} ended by key {:x}", text, keycode),
+            TestInput::Key(keycode) => write!(f, "key {:x}", keycode),
+            TestInput::Typed(text) => write!(f, "typed {:?}", text),
+            TestInput::Timer => write!(f, "timer"),
+            TestInput::Mouse(x,y) => write!(f, "mouse at {},{}", x, y),
+            TestInput::Hyperlink(linkval) => write!(f, "hyperlink {}", linkval),
+        }
+    }
+}
+
+// The first step whose output did not meet its expectation.  Steps are
+// numbered from 1, and input is the input that step went on to give.
+pub struct TestFailure {
+    pub step: usize,
+    pub input: String,
+    pub message: String,
+}
+
+impl fmt::Display for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {} (before input {}): {}", self.step, self.input, self.message)
+    }
+}
+
+impl fmt::Debug for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum GarglkText {
+    ZColors(usize,u32,u32),
+    ReverseVideo(usize,u32),
 }
 
 struct TestStream {
@@ -15,47 +91,618 @@ struct TestStream {
     writecount: u32,
     out: Option<(u32,Box<[u8]>)>,
     out_uni: Option<(u32,Box<[u32]>)>,
-    file: Option<(usize,Vec<u8>)>,
+    file: Option<FileStream>,
     pos: usize,
     end: usize,
 }
 
+// The contents of a file stream, which go back to the file store when the
+// stream is closed.  Unicode streams are UTF-8 in text mode and four bytes
+// a character in binary mode.
+struct FileStream {
+    name: String,
+    data: Vec<u8>,
+    text: bool,
+    unicode: bool,
+}
+
+impl FileStream {
+    fn put(&mut self, pos: &mut usize, ch: u32) {
+        let mut bytes = vec![];
+        if self.unicode && !self.text {
+            bytes.extend_from_slice(&[(ch >> 24) as u8, (ch >> 16) as u8, (ch >> 8) as u8, ch as u8]);
+        } else if self.unicode {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(std::char::from_u32(ch).unwrap_or('?').encode_utf8(&mut buf).as_bytes());
+        } else {
+            bytes.push(if ch > 0xff { b'?' } else { ch as u8 });
+        }
+        for b in bytes {
+            if *pos >= self.data.len() {
+                self.data.push(b);
+            } else {
+                self.data[*pos] = b;
+            }
+            *pos += 1;
+        }
+    }
+
+    fn get(&self, pos: &mut usize) -> Option<u32> {
+        let len = match self.data.get(*pos) {
+            None => return None,
+            Some(_) if self.unicode && !self.text => 4,
+            Some(&b) if self.unicode && b >= 0xf0 => 4,
+            Some(&b) if self.unicode && b >= 0xe0 => 3,
+            Some(&b) if self.unicode && b >= 0xc0 => 2,
+            Some(_) => 1,
+        };
+        if *pos + len > self.data.len() {
+            return None;
+        }
+        let bytes = &self.data[*pos .. *pos + len];
+        *pos += len;
+        if self.unicode && !self.text {
+            Some(bytes.iter().fold(0, |ch, &b| ch << 8 | b as u32))
+        } else if self.unicode {
+            Some(std::str::from_utf8(bytes).ok().and_then(|s| s.chars().next()).unwrap_or('?') as u32)
+        } else {
+            Some(bytes[0] as u32)
+        }
+    }
+
+    // Positions in binary Unicode files count characters rather than bytes.
+    fn scale(&self) -> usize {
+        if self.unicode && !self.text { 4 } else { 1 }
+    }
+}
+
+// The contents of a grid window, a row of characters per line.
+struct Grid {
+    width: usize,
+    height: usize,
+    rows: Vec<Vec<char>>,
+    x: usize,
+    y: usize,
+}
+
+impl Grid {
+    fn new(width: u32, height: u32) -> Self {
+        let mut grid = Grid{ width: 0, height: 0, rows: vec![], x: 0, y: 0 };
+        grid.resize(width, height);
+        grid
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as usize;
+        self.height = height as usize;
+        self.rows.resize(self.height, vec![]);
+        for row in self.rows.iter_mut() {
+            row.resize(width as usize, ' ');
+        }
+    }
+
+    fn clear(&mut self) {
+        for row in self.rows.iter_mut() {
+            for ch in row.iter_mut() {
+                *ch = ' ';
+            }
+        }
+        self.x = 0;
+        self.y = 0;
+    }
+
+    fn move_cursor(&mut self, xpos: u32, ypos: u32) {
+        self.x = xpos as usize;
+        self.y = ypos as usize;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+    }
+
+    fn put(&mut self, ch: char) {
+        if ch == '\n' {
+            self.x = 0;
+            self.y += 1;
+            return;
+        }
+        if self.y >= self.height {
+            return;
+        }
+        self.rows[self.y][self.x] = ch;
+        self.x += 1;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+    }
+}
+
+enum LineBuf {
+    Latin1((u32,Box<[u8]>)),
+    Uni((u32,Box<[u32]>)),
+}
+
+struct LineInput {
+    win: usize,
+    buf: LineBuf,
+    typed: Vec<u32>,
+}
+
+// A file reference, naming a file in the file store.
 struct TestFile {
     rock: u32,
-    data: Box<[u8]>,
+    usage: u32,
+    name: String,
 }
 
 pub struct GlkTest<'a> {
-    root: usize,
-    rock: u32,
+    windows: Windows,
+    grids: Vec<Option<Grid>>,
+    arrange: bool,
     current: usize,
     streams: Vec<Option<TestStream>>,
     out: String,
-    line_input: Option<(u32,Box<[u8]>)>,
-    char_input: bool,
+    out_runs: Vec<(u32,String)>,
+    runs: HashMap<usize,Vec<(u32,String)>>,
+    styles: HashMap<usize,u32>,
+    hints: HashMap<(u32,u32,u32),i32>,
+    line_input: Option<LineInput>,
+    char_input: Option<(usize,bool)>,
+    terminators: HashMap<usize,Vec<u32>>,
+    timer: u32,
+    time: TimeVal,
+    time_zone: i64,
+    mouse_input: Vec<usize>,
+    hyperlink_input: Vec<usize>,
     files: Vec<Option<TestFile>>,
-
-    test: Vec<(TestOutput<'a>,&'a str)>,
+    store: BTreeMap<String,Vec<u8>>,
+    prompts: Vec<&'a str>,
+    temp_count: u32,
+    garglk_text: Vec<GarglkText>,
+    resources: Option<Blorb>,
+
+    test: Vec<(TestOutput<'a>,TestInput<'a>)>,
+    step: usize,
+    failure: Option<TestFailure>,
 }
 
 impl<'a> GlkTest<'a> {
     pub fn new(test: Vec<(TestOutput<'a>,&'a str)>) -> Self {
+        GlkTest::scripted(test.into_iter().map(|(output,text)| (output,TestInput::Text(text))).collect())
+    }
+
+    pub fn scripted(test: Vec<(TestOutput<'a>,TestInput<'a>)>) -> Self {
         GlkTest{
-            root: 0,
-            rock: 0,
+            windows: Windows::new(screen(80, 24)),
+            grids: vec![None],
+            arrange: false,
             current: 0,
             streams: vec![None],
             out: String::new(),
+            out_runs: vec![],
+            runs: HashMap::new(),
+            styles: HashMap::new(),
+            hints: HashMap::new(),
             line_input: None,
-            char_input: false,
+            char_input: None,
+            terminators: HashMap::new(),
+            timer: 0,
+            time: TimeVal::from_secs(0, 0),
+            time_zone: 0,
+            mouse_input: vec![],
+            hyperlink_input: vec![],
             files: vec![None],
+            store: BTreeMap::new(),
+            prompts: vec![],
+            temp_count: 0,
+            garglk_text: vec![],
+            resources: None,
             test: test,
+            step: 0,
+            failure: None,
         }
     }
 
-    pub fn output(self) -> String {
-        self.out
+    pub fn output(mut self) -> String {
+        ::std::mem::replace(&mut self.out, String::new())
+    }
+
+    // Once the result is taken, a failure no longer panics when the
+    // GlkTest is dropped.
+    pub fn result(&mut self) -> ::std::result::Result<(),TestFailure> {
+        match self.failure.take() {
+            Some(failure) => Err(failure),
+            None => Ok(()),
+        }
+    }
+
+    // The output since the last select, as runs of text in each style.
+    pub fn styled_output(&self) -> Vec<(u32,&str)> {
+        self.out_runs.iter().map(|&(style,ref text)| (style,text.as_str())).collect()
+    }
+
+    // What was written to a window since the last select, including grid
+    // windows, as runs of text in each style.
+    pub fn window_output(&self, win: &WinId) -> Vec<(u32,&str)> {
+        match self.runs.get(&win.0) {
+            Some(runs) => runs.iter().map(|&(style,ref text)| (style,text.as_str())).collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn stylehint(&self, wintype: u32, styl: u32, hint: u32) -> Option<i32> {
+        self.hints.get(&(wintype,styl,hint)).cloned()
+    }
+
+    pub fn garglk_text(&self) -> &[GarglkText] {
+        &self.garglk_text
+    }
+
+    // Puts a file in the file store.  Files created by name are named by
+    // glk::streams::file_name, and files created by prompt get the answer.
+    pub fn set_file(&mut self, name: &str, data: &[u8]) {
+        self.store.insert(name.to_string(), data.to_vec());
+    }
+
+    pub fn file(&self, name: &str) -> Option<&[u8]> {
+        self.store.get(name).map(|data| &data[..])
+    }
+
+    pub fn file_names(&self) -> Vec<&str> {
+        self.store.keys().map(|name| name.as_str()).collect()
+    }
+
+    // Answers to file prompts, in order.  An empty answer, or running out
+    // of answers, cancels the prompt.
+    pub fn set_prompt_answers(&mut self, answers: Vec<&'a str>) {
+        self.prompts = answers;
+    }
+
+    // The clock starts at the epoch and only moves when set, advanced, or
+    // on each Timer step by the timer interval.
+    pub fn set_time(&mut self, time: TimeVal) {
+        self.time = time;
+    }
+
+    pub fn advance_time(&mut self, microsecs: i64) {
+        let micros = self.time.microsec as i64 + microsecs;
+        let secs = self.time.secs() + micros.div_euclid(1000000);
+        self.time = TimeVal::from_secs(secs, micros.rem_euclid(1000000) as i32);
+    }
+
+    // The offset of local time, in seconds east of UTC.
+    pub fn set_time_zone(&mut self, offset: i64) {
+        self.time_zone = offset;
+    }
+
+    // Resizes the screen, which is 80x24 to start with.  The next select
+    // returns an arrange event if there are windows.
+    pub fn set_screen_size(&mut self, width: u32, height: u32) {
+        self.windows.set_metrics(screen(width, height));
+        self.relayout();
+        self.arrange = !self.windows.root().is_null();
+    }
+
+    // The contents of a grid window, a line per row.
+    pub fn grid(&self, win: &WinId) -> Option<Vec<String>> {
+        match self.grids.get(win.0) {
+            Some(&Some(ref grid)) => Some(grid.rows.iter().map(|row| row.iter().collect()).collect()),
+            _ => None,
+        }
+    }
+
+    fn relayout(&mut self) {
+        for i in 0 .. self.grids.len() {
+            let (width,height) = self.windows.size(&glk::windows::WinId(i));
+            if let Some(ref mut grid) = self.grids[i] {
+                grid.resize(width, height);
+            }
+        }
+    }
+
+    // The window of a window stream.
+    fn stream_window(&self, str: usize) -> Option<usize> {
+        let mut win = glk::windows::WinId(0);
+        loop {
+            win = self.windows.iterate(&win).0;
+            match self.windows.get(&win) {
+                Some(w) if w.str.0 == str => return Some(win.0),
+                Some(_) => (),
+                None => return None,
+            }
+        }
+    }
+
+    // Text in buffer windows goes to the output.
+    fn put_window(&mut self, win: usize, ch: char) {
+        let style = self.styles.get(&win).cloned().unwrap_or(glk::style_Normal);
+        match self.windows.get(&glk::windows::WinId(win)).map(|w| w.wintype) {
+            Some(glk::wintype_TextBuffer) => {
+                self.out.push(ch);
+                push_run(&mut self.out_runs, style, ch);
+            },
+            Some(glk::wintype_TextGrid) => {
+                if let Some(&mut Some(ref mut grid)) = self.grids.get_mut(win) {
+                    grid.put(ch);
+                }
+            },
+            _ => return,
+        }
+        push_run(self.runs.entry(win).or_insert(vec![]), style, ch);
+    }
+
+    fn request_line(&mut self, win: &WinId, buf: LineBuf, initlen: u32) {
+        if self.windows.get(&glk::windows::WinId(win.0)).is_none() {
+            return;
+        }
+        let typed = match buf {
+            LineBuf::Latin1((_,ref buf)) => buf.iter().take(initlen as usize).map(|&b| b as u32).collect(),
+            LineBuf::Uni((_,ref buf)) => buf.iter().take(initlen as usize).cloned().collect(),
+        };
+        self.line_input = Some(LineInput{ win: win.0, buf: buf, typed: typed });
+    }
+
+    fn type_line(&mut self, text: &str) {
+        let line = self.line_input.as_mut().expect("no line input requested");
+        line.typed.extend(text.chars().map(|ch| ch as u32));
+    }
+
+    fn finish_line(&mut self, terminator: u32) -> Event {
+        let line = match self.line_input.take() {
+            Some(line) => line,
+            None => return Event::none(),
+        };
+        let mut event = Event::new(glk::evtype_LineInput, line.win, 0, terminator);
+        match line.buf {
+            LineBuf::Latin1((addr,mut buf)) => {
+                let len = std::cmp::min(buf.len(), line.typed.len());
+                for i in 0 .. len {
+                    buf[i] = if line.typed[i] > 0xff { b'?' } else { line.typed[i] as u8 };
+                }
+                event.val1 = len as u32;
+                event.buf = Some((addr,buf));
+            },
+            LineBuf::Uni((addr,mut buf)) => {
+                let len = std::cmp::min(buf.len(), line.typed.len());
+                buf[.. len].copy_from_slice(&line.typed[.. len]);
+                event.val1 = len as u32;
+                event.buf_uni = Some((addr,buf));
+            },
+        }
+        event
+    }
+
+    fn char_event(&mut self, ch: u32) -> Event {
+        let (win,uni) = self.char_input.take().expect("no char input requested");
+        let ch = if !uni && ch > 0xff && ch < glk::keycode_Func12 { glk::keycode_Unknown } else { ch };
+        Event::new(glk::evtype_CharInput, win, ch, 0)
+    }
+
+    fn create_fileref(&mut self, usage: u32, name: String, rock: u32) -> FRefId {
+        let mut index = 0;
+        for i in 1 .. self.files.len() {
+            if self.files[i].is_none() {
+                index = i;
+                break;
+            }
+        }
+        if index == 0 {
+            index = self.files.len();
+            self.files.push(None);
+        }
+        self.files[index] = Some(TestFile{
+                rock: rock,
+                usage: usage,
+                name: name,
+            });
+        FRefId(index)
+    }
+
+    fn file_name(&self, fref: &FRefId) -> Option<&str> {
+        match self.files.get(fref.0) {
+            Some(&Some(ref file)) => Some(&file.name),
+            _ => None,
+        }
+    }
+
+    fn open_file(&mut self, fileref: &FRefId, fmode: u32, rock: u32, unicode: bool) -> StrId {
+        let (name,text) = match self.files.get(fileref.0) {
+            Some(&Some(ref file)) => (file.name.clone(),file.usage & glk::fileusage_TextMode != 0),
+            _ => return StrId(0),
+        };
+        let data = match fmode {
+            glk::filemode_Read => match self.store.get(&name) {
+                Some(data) => data.clone(),
+                None => return StrId(0),
+            },
+            glk::filemode_Write => vec![],
+            glk::filemode_ReadWrite | glk::filemode_WriteAppend => self.store.get(&name).cloned().unwrap_or(vec![]),
+            _ => return StrId(0),
+        };
+        if fmode != glk::filemode_Read {
+            self.store.insert(name.clone(), data.clone());
+        }
+        let index = self.open_stream();
+        let stream = self.streams[index].as_mut().unwrap();
+        stream.rock = rock;
+        stream.pos = if fmode == glk::filemode_WriteAppend { data.len() } else { 0 };
+        stream.end = data.len();
+        stream.file = Some(FileStream{ name, data, text, unicode });
+        StrId(index)
+    }
+
+    fn open_stream(&mut self) -> usize {
+        let mut index = 0;
+        for i in 1 .. self.streams.len() {
+            if self.streams[i].is_none() {
+                index = i;
+                break;
+            }
+        }
+        if index == 0 {
+            index = self.streams.len();
+            self.streams.push(None);
+        }
+        self.streams[index] = Some(TestStream{
+                rock: 0,
+                readcount: 0,
+                writecount: 0,
+                out: None,
+                out_uni: None,
+                file: None,
+                pos: 0,
+                end: 0,
+            });
+        index
+    }
+
+    // A description of how the output fails the expectation, if it does.
+    fn check_output(&self, test: &TestOutput) -> Option<String> {
+        match *test {
+            TestOutput::Match(expected) => {
+                if expected == self.out {
+                    None
+                } else {
+                    Some(format!("output differs\n{}", diff(expected, &self.out)))
+                }
+            },
+            TestOutput::Check(f) => {
+                if f(&self.out) {
+                    None
+                } else {
+                    Some(format!("output rejected by check:\n{}", self.out))
+                }
+            },
+            TestOutput::Styled(expected) => {
+                let actual = self.styled_output();
+                if expected == &actual[..] {
+                    None
+                } else {
+                    Some(format!("styled output differs\nexpected: {:?}\nactual:   {:?}", expected, actual))
+                }
+            },
+            TestOutput::Regex(pattern) => {
+                match Regex::new(&format!(r"\A(?:{})\z", pattern)) {
+                    Ok(ref regex) if regex.is_match(&self.out) => None,
+                    Ok(_) => Some(format!("output does not match /{}/:\n{}", pattern, self.out)),
+                    Err(err) => Some(format!("bad regex /{}/: {}", pattern, err)),
+                }
+            },
+            TestOutput::Snapshot(path) => check_snapshot(path, &self.out),
+        }
+    }
+}
+
+impl<'a> Drop for GlkTest<'a> {
+    fn drop(&mut self) {
+        if !::std::thread::panicking() {
+            if let Some(failure) = self.failure.take() {
+                panic!("{}", failure);
+            }
+        }
+    }
+}
+
+fn check_snapshot(path: &str, output: &str) -> Option<String> {
+    if ::std::env::var_os("GLKTEST_BLESS").is_some() {
+        return match File::create(path).and_then(|mut file| file.write_all(output.as_bytes())) {
+            Ok(()) => None,
+            Err(err) => Some(format!("cannot write {}: {}", path, err)),
+        };
+    }
+    let mut expected = String::new();
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut expected)) {
+        Ok(_) if expected == output => None,
+        Ok(_) => Some(format!("output differs from {}\n{}", path, diff(&expected, output))),
+        Err(err) => Some(format!("cannot read {}: {}, set GLKTEST_BLESS to create it", path, err)),
+    }
+}
+
+// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+// A unified diff of the expected and actual output.  Lines are split at
+// each newline, so a missing final newline shows as an empty line.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.split('\n').collect();
+    let new: Vec<&str> = actual.split('\n').collect();
+    // Length of the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0 .. old.len()).rev() {
+        for j in (0 .. new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i+1][j+1] + 1 } else { ::std::cmp::max(lcs[i+1][j], lcs[i][j+1]) };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i,mut j) = (0,0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ',i,j,old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i+1][j] >= lcs[i][j+1]) {
+            lines.push(('-',i,j,old[i]));
+            i += 1;
+        } else {
+            lines.push(('+',i,j,new[j]));
+            j += 1;
+        }
+    }
+    let mut result = String::from("--- expected\n+++ actual\n");
+    let mut k = 0;
+    while k < lines.len() {
+        if lines[k].0 == ' ' {
+            k += 1;
+            continue;
+        }
+        // Changes with no more than twice the context between them share
+        // a hunk.
+        let mut last = k;
+        let mut m = k + 1;
+        while m < lines.len() && m - last <= 2 * CONTEXT + 1 {
+            if lines[m].0 != ' ' {
+                last = m;
+            }
+            m += 1;
+        }
+        let hunk = &lines[k.saturating_sub(CONTEXT) .. ::std::cmp::min(last + CONTEXT + 1, lines.len())];
+        let old_count = hunk.iter().filter(|line| line.0 != '+').count();
+        let new_count = hunk.iter().filter(|line| line.0 != '-').count();
+        result.push_str(&format!("@@ -{} +{} @@\n", hunk_range(hunk[0].1, old_count), hunk_range(hunk[0].2, new_count)));
+        for &(tag,_,_,line) in hunk {
+            result.push(tag);
+            result.push_str(line);
+            result.push('\n');
+        }
+        k = last + 1;
+    }
+    result
+}
+
+// An empty range starts at the line before it.
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+// Adds to the last run if it is in the same style.
+fn push_run(runs: &mut Vec<(u32,String)>, style: u32, ch: char) {
+    if let Some(&mut (last,ref mut text)) = runs.last_mut() {
+        if last == style {
+            text.push(ch);
+            return;
+        }
     }
+    runs.push((style,ch.to_string()));
+}
+
+// A screen of character cells, without space for borders.
+fn screen(width: u32, height: u32) -> Metrics {
+    Metrics{ spacing: 0, .. Metrics::cells(width, height) }
 }
 
 impl<'a> Glk<'a> for GlkTest<'a> {
@@ -69,7 +716,7 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     type IOStream = IOStream<'a>;
 
     fn exit(&mut self) -> ! {
-        std::process::exit(0);
+        session::end(Ok(()))
     }
 
     fn set_interrupt_handler(&mut self, _handler: extern fn()) {
@@ -82,6 +729,7 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
         match sel {
             glk::gestalt_Version => 0x00000705,
+            glk::gestalt_CharInput => if (val >= 32 && val < 127) || val >= glk::keycode_Func12 { 1 } else { 0 },
             glk::gestalt_LineInput => if val >= 32 && val < 127 { 1 } else { 0 },
             glk::gestalt_CharOutput => {
                 if val >= 32 && val < 127 {
@@ -90,7 +738,17 @@ impl<'a> Glk<'a> for GlkTest<'a> {
                     glk::gestalt_CharOutput_CannotPrint
                 }
             },
+            glk::gestalt_MouseInput => 1,
+            glk::gestalt_Timer => 1,
             glk::gestalt_Unicode => 1,
+            glk::gestalt_UnicodeNorm => 1,
+            glk::gestalt_DateTime => 1,
+            glk::gestalt_Hyperlinks => 1,
+            glk::gestalt_HyperlinkInput => 1,
+            glk::gestalt_LineTerminators => 1,
+            glk::gestalt_LineTerminatorKey => if val == glk::keycode_Escape || (val >= glk::keycode_Func12 && val <= glk::keycode_Func1) { 1 } else { 0 },
+            glk::gestalt_ResourceStream => 1,
+            glk::gestalt_GarglkText => 1,
             _ => 0,
         }
     }
@@ -98,6 +756,7 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
         match sel {
             glk::gestalt_Version => 0x00000705,
+            glk::gestalt_CharInput => if (val >= 32 && val < 127) || val >= glk::keycode_Func12 { 1 } else { 0 },
             glk::gestalt_LineInput => if val >= 32 && val < 127 { 1 } else { 0 },
             glk::gestalt_CharOutput => {
                 if val >= 32 && val < 127 {
@@ -109,109 +768,132 @@ impl<'a> Glk<'a> for GlkTest<'a> {
                     glk::gestalt_CharOutput_CannotPrint
                 }
             },
+            glk::gestalt_MouseInput => 1,
+            glk::gestalt_Timer => 1,
             glk::gestalt_Unicode => 1,
+            glk::gestalt_UnicodeNorm => 1,
+            glk::gestalt_DateTime => 1,
+            glk::gestalt_Hyperlinks => 1,
+            glk::gestalt_HyperlinkInput => 1,
+            glk::gestalt_LineTerminators => 1,
+            glk::gestalt_LineTerminatorKey => if val == glk::keycode_Escape || (val >= glk::keycode_Func12 && val <= glk::keycode_Func1) { 1 } else { 0 },
+            glk::gestalt_ResourceStream => 1,
+            glk::gestalt_GarglkText => 1,
             _ => 0,
         }
     }
 
 
     fn char_to_lower(&mut self, ch: u8) -> u8 {
-        (ch as char).to_lowercase().next().unwrap() as u8
+        glk::unicode::latin1_to_lower(ch)
     }
 
     fn char_to_upper(&mut self, ch: u8) -> u8 {
-        (ch as char).to_uppercase().next().unwrap() as u8
+        glk::unicode::latin1_to_upper(ch)
     }
 
 
     fn window_get_root(&mut self) -> Self::WinId {
-        WinId(self.root)
+        WinId(self.windows.root().0)
     }
 
-    fn window_open(&mut self, _split: &Self::WinId, _method: u32, _size: u32, wintype: u32, rock: u32) -> Self::WinId {
-        if self.root != 0 || wintype != glk::wintype_TextBuffer {
-            WinId(0)
-        } else {
-            let mut index = 0;
-            for i in 1 .. self.streams.len() {
-                if self.streams[i].is_none() {
-                    index = i;
-                    break;
-                }
-            }
-            if index == 0 {
-                index = self.streams.len();
-                self.streams.push(None);
-            }
-            self.streams[index] = Some(TestStream{
-                    rock: 0,
-                    readcount: 0,
-                    writecount: 0,
-                    out: None,
-                    out_uni: None,
-                    file: None,
-                    pos: 0,
-                    end: 0,
-                });
-            self.root = index;
-            self.rock = rock;
-            WinId(index)
+    fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
+        if wintype == glk::wintype_Pair {
+            return WinId(0);
+        }
+        let (win,pair) = match self.windows.open(&glk::windows::WinId(split.0), method, size, wintype, rock) {
+            Some(opened) => opened,
+            None => return WinId(0),
+        };
+        for &win in [Some(win),pair].iter().flat_map(|win| win) {
+            let str = self.open_stream();
+            self.windows.get_mut(&win).unwrap().str = glk::streams::StrId(str);
+        }
+        if self.grids.len() <= win.0 {
+            self.grids.resize_with(win.0 + 1, || None);
+        }
+        if wintype == glk::wintype_TextGrid {
+            self.grids[win.0] = Some(Grid::new(0, 0));
         }
+        self.relayout();
+        WinId(win.0)
     }
 
     fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
-        if self.root == 0 || win.0 != self.root {
-            (0,0,None,None)
-        } else {
-            let mut str = StrId(self.root);
-            self.stream_close(&mut str)
+        let mut result = (0,0,None,None);
+        for (id,w) in self.windows.close(&glk::windows::WinId(win.0)) {
+            let closed = self.stream_close(&mut StrId(w.str.0));
+            if id.0 == win.0 {
+                result = closed;
+            }
+            if let Some(grid) = self.grids.get_mut(id.0) {
+                *grid = None;
+            }
+            if self.line_input.as_ref().map(|line| line.win == id.0).unwrap_or(false) {
+                self.line_input = None;
+            }
+            if self.char_input.map(|(win,_)| win == id.0).unwrap_or(false) {
+                self.char_input = None;
+            }
+            self.terminators.remove(&id.0);
+            self.styles.remove(&id.0);
+            self.mouse_input.retain(|&win| win != id.0);
+            self.hyperlink_input.retain(|&win| win != id.0);
         }
+        self.relayout();
+        result
     }
 
-    fn window_get_size(&mut self, _win: &Self::WinId) -> (u32,u32) {
-        (80,24)
+    fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
+        self.windows.size(&glk::windows::WinId(win.0))
     }
 
-    fn window_set_arrangement(&mut self, _win: &Self::WinId, _method: u32, _size: u32, _keywin: &Self::WinId) {
+    fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
+        self.windows.set_arrangement(&glk::windows::WinId(win.0), method, size, &glk::windows::WinId(keywin.0));
+        self.relayout();
     }
 
-    fn window_get_arrangement(&mut self, _win: &Self::WinId) -> (u32,u32,Self::WinId) {
-        (0,0,WinId(0))
+    fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
+        let (method,size,keywin) = self.windows.get_arrangement(&glk::windows::WinId(win.0));
+        (method,size,WinId(keywin.0))
     }
 
     fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
-        if win.0 == 0 {
-            (WinId(self.root),self.rock)
-        } else {
-            (WinId(0),0)
-        }
+        let (win,rock) = self.windows.iterate(&glk::windows::WinId(win.0));
+        (WinId(win.0),rock)
     }
 
     fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
-        if win.0 == self.root { self.rock } else { 0 }
+        self.windows.get(&glk::windows::WinId(win.0)).map(|w| w.rock).unwrap_or(0)
     }
 
     fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
-        if win.0 == self.root { glk::wintype_TextBuffer } else { 0 }
+        self.windows.get(&glk::windows::WinId(win.0)).map(|w| w.wintype).unwrap_or(0)
     }
 
-    fn window_get_parent(&mut self, _win: &Self::WinId) -> Self::WinId {
-        WinId(0)
+    fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
+        WinId(self.windows.get(&glk::windows::WinId(win.0)).map(|w| w.parent.0).unwrap_or(0))
     }
 
-    fn window_get_sibling(&mut self, _win: &Self::WinId) -> Self::WinId {
-        WinId(0)
+    fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
+        WinId(self.windows.sibling(&glk::windows::WinId(win.0)).0)
     }
 
-    fn window_clear(&mut self, _win: &Self::WinId) {
+    fn window_clear(&mut self, win: &Self::WinId) {
+        if let Some(&mut Some(ref mut grid)) = self.grids.get_mut(win.0) {
+            grid.clear();
+        }
     }
 
-    fn window_move_cursor(&mut self, _win: &Self::WinId, _xpos: u32, _ypos: u32) {
+    fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
+        if let Some(&mut Some(ref mut grid)) = self.grids.get_mut(win.0) {
+            grid.move_cursor(xpos, ypos);
+        }
     }
 
 
     fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
-        StrId(win.0)
+        StrId(self.windows.get(&glk::windows::WinId(win.0)).map(|w| w.str.0).unwrap_or(0))
     }
 
     fn window_set_echo_stream(&mut self, _win: &Self::WinId, _str: &Self::StrId) {
@@ -222,41 +904,12 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
     fn set_window(&mut self, win: &Self::WinId) {
-        self.current = win.0;
+        self.current = self.window_get_stream(win).0;
     }
 
 
     fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
-        let contents = if let Some(&Some(TestFile{ rock:_, ref data })) = self.files.get(fileref.0) {
-            let mut contents = vec![];
-            contents.extend_from_slice(&data);
-            contents
-        } else {
-            return StrId(0);
-        };
-        let mut index = 0;
-        for i in 1 .. self.streams.len() {
-            if self.streams[i].is_none() {
-                index = i;
-                break;
-            }
-        }
-        if index == 0 {
-            index = self.streams.len();
-            self.streams.push(None);
-        }
-        let end = if fmode == glk::filemode_Write { 0 } else { contents.len() };
-        self.streams[index] = Some(TestStream{
-                rock: rock,
-                readcount: 0,
-                writecount: 0,
-                out: None,
-                out_uni: None,
-                file: Some((fileref.0,contents)),
-                pos: 0,
-                end: end,
-            });
-        StrId(index)
+        self.open_file(fileref, fmode, rock, false)
     }
 
     fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
@@ -290,10 +943,8 @@ impl<'a> Glk<'a> for GlkTest<'a> {
             return (0,0,None,None);
         }
         let stream = self.streams[str.0].take().unwrap();
-        if let Some((fref,stream_data)) = stream.file {
-            if let Some(&mut Some(TestFile{ rock:_, ref mut data })) = self.files.get_mut(fref) {
-                *data = stream_data.into_boxed_slice();
-            }
+        if let Some(file) = stream.file {
+            self.store.insert(file.name, file.data);
         }
         (stream.readcount,stream.writecount,stream.out,stream.out_uni)
     }
@@ -318,7 +969,8 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
     fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
-        if let Some(&mut Some(TestStream{ rock:_, readcount:_, writecount:_, out:_, out_uni:_, file:_, pos:ref mut stream_pos, end })) = self.streams.get_mut(str.0) {
+        if let Some(&mut Some(TestStream{ rock:_, readcount:_, writecount:_, out:_, out_uni:_, ref file, pos:ref mut stream_pos, end })) = self.streams.get_mut(str.0) {
+            let pos = pos * file.as_ref().map(|file| file.scale()).unwrap_or(1) as i32;
             match seekmode {
                 glk::seekmode_Start => {
                     *stream_pos = pos as usize;
@@ -338,8 +990,8 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
     fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
-        if let Some(&Some(TestStream{ rock:_, readcount:_, writecount:_, out:_, out_uni:_, file:_, pos, end:_ })) = self.streams.get(str.0) {
-            pos as u32
+        if let Some(&Some(TestStream{ rock:_, readcount:_, writecount:_, out:_, out_uni:_, ref file, pos, end:_ })) = self.streams.get(str.0) {
+            (pos / file.as_ref().map(|file| file.scale()).unwrap_or(1)) as u32
         } else {
             0
         }
@@ -360,9 +1012,13 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
     fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
+        let window = self.stream_window(str.0);
+        if let Some(win) = window {
+            self.put_window(win, ch as char);
+        }
         if let Some(&mut Some(TestStream{ rock:_, readcount:_, ref mut writecount, ref mut out, ref mut out_uni, ref mut file, ref mut pos, ref mut end })) = self.streams.get_mut(str.0) {
-            if str.0 == self.root {
-                self.out.push(ch as char);
+            if window.is_some() {
+                // Written to the window above.
             } else if let &mut Some((_,ref mut mem)) = out {
                 if *pos < mem.len() {
                     mem[*pos] = ch;
@@ -373,14 +1029,8 @@ impl<'a> Glk<'a> for GlkTest<'a> {
                     mem[*pos as usize] = ch as u32;
                     *pos += 1;
                 }
-            } else if let &mut Some((_,ref mut data)) = file {
-                if *pos >= data.len() {
-                    data.push(ch);
-                    *pos = data.len();
-                } else {
-                    data[*pos] = ch;
-                    *pos += 1;
-                }
+            } else if let &mut Some(ref mut file) = file {
+                file.put(pos, ch as u32);
             }
             *end = std::cmp::max(*end, *pos);
             *writecount += 1;
@@ -411,16 +1061,22 @@ impl<'a> Glk<'a> for GlkTest<'a> {
         }
     }
 
-    fn set_style(&mut self, _styl: u32) {
+    fn set_style(&mut self, styl: u32) {
+        let str = StrId(self.current);
+        self.set_style_stream(&str, styl);
     }
 
-    fn set_style_stream(&mut self, _str: &Self::StrId, _styl: u32) {
+    fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
+        if let Some(win) = self.stream_window(str.0) {
+            self.styles.insert(win, styl);
+        }
     }
 
 
     fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
+        let window = self.stream_window(str.0);
         if let Some(&mut Some(TestStream{ rock:_, ref mut readcount, writecount:_, ref out, ref out_uni, ref file, ref mut pos, end:_ })) = self.streams.get_mut(str.0) {
-            if str.0 == self.root {
+            if window.is_some() {
                 panic!();
             } else if let &Some((_,ref mem)) = out {
                 if *pos < mem.len() {
@@ -440,14 +1096,13 @@ impl<'a> Glk<'a> for GlkTest<'a> {
                 } else {
                     -1
                 }
-            } else if let &Some((_,ref data)) = file {
-                if *pos < data.len() {
-                    let ch = data[*pos];
-                    *pos += 1;
-                    *readcount += 1;
-                    ch as i32
-                } else {
-                    -1
+            } else if let &Some(ref file) = file {
+                match file.get(pos) {
+                    Some(ch) => {
+                        *readcount += 1;
+                        if ch > 0xff { '?' as i32 } else { ch as i32 }
+                    },
+                    None => -1,
                 }
             } else {
                 -1
@@ -480,54 +1135,65 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
 
-    fn stylehint_set(&mut self, _wintype: u32, _styl: u32, _hint: u32, _val: i32) {
+    fn stylehint_set(&mut self, wintype: u32, styl: u32, hint: u32, val: i32) {
+        for &t in [glk::wintype_TextBuffer,glk::wintype_TextGrid].iter() {
+            if wintype == glk::wintype_AllTypes || wintype == t {
+                self.hints.insert((t,styl,hint), val);
+            }
+        }
     }
 
-    fn stylehint_clear(&mut self, _wintype: u32, _styl: u32, _hint: u32) {
+    fn stylehint_clear(&mut self, wintype: u32, styl: u32, hint: u32) {
+        for &t in [glk::wintype_TextBuffer,glk::wintype_TextGrid].iter() {
+            if wintype == glk::wintype_AllTypes || wintype == t {
+                self.hints.remove(&(t,styl,hint));
+            }
+        }
     }
 
     fn style_distinguish(&mut self, _win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
         styl1 != styl2
     }
 
-    fn style_measure(&mut self, _win: &Self::WinId, _styl: u32, _hint: u32) -> (bool,u32) {
-        (false,0)
+    fn style_measure(&mut self, win: &Self::WinId, styl: u32, hint: u32) -> (bool,u32) {
+        let wintype = self.window_get_type(win);
+        match self.hints.get(&(wintype,styl,hint)) {
+            Some(&val) => (true,val as u32),
+            None => (false,0),
+        }
     }
 
 
-    fn fileref_create_temp(&mut self, _usage: u32, rock: u32) -> Self::FRefId {
-        let mut index = 0;
-        for i in 1 .. self.files.len() {
-            if self.files[i].is_none() {
-                index = i;
-                break;
-            }
-        }
-        if index == 0 {
-            index = self.files.len();
-            self.files.push(None);
-        }
-        self.files[index] = Some(TestFile{
-                rock: rock,
-                data: vec![].into_boxed_slice(),
-            });
-        FRefId(index)
+    fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
+        self.temp_count += 1;
+        let name = format!("temp{}", self.temp_count);
+        self.store.insert(name.clone(), vec![]);
+        self.create_fileref(usage, name, rock)
     }
 
-    fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, _usage: u32, _name: S, _rock: u32) -> Self::FRefId {
-        FRefId(0)
+    fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
+        let name = glk::streams::file_name(usage, name);
+        self.create_fileref(usage, name, rock)
     }
 
-    fn fileref_create_by_prompt(&mut self, _usage: u32, _fmode: u32, _rock: u32) -> Self::FRefId {
-        FRefId(0)
+    fn fileref_create_by_prompt(&mut self, usage: u32, _fmode: u32, rock: u32) -> Self::FRefId {
+        if self.prompts.is_empty() || self.prompts[0].is_empty() {
+            self.prompts.clear();
+            return FRefId(0);
+        }
+        let name = self.prompts.remove(0).to_string();
+        self.create_fileref(usage, name, rock)
     }
 
-    fn fileref_create_from_fileref(&mut self, _usage: u32, _fref: &Self::FRefId, _rock: u32) -> Self::FRefId {
-        FRefId(0)
+    fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
+        match self.file_name(fref).map(|name| name.to_string()) {
+            Some(name) => self.create_fileref(usage, name, rock),
+            None => FRefId(0),
+        }
     }
 
     fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
-        if let Some(mut file) = self.files.get_mut(fref.0) {
+        if let Some(file) = self.files.get_mut(fref.0) {
             *file = None;
         }
     }
@@ -535,7 +1201,7 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
         if fref.0 + 1 < self.files.len() {
             for i in fref.0 + 1 .. self.files.len() {
-                if let &Some(TestFile{ rock, data:_ }) = &self.files[i] {
+                if let &Some(TestFile{ rock, usage:_, name:_ }) = &self.files[i] {
                     return (FRefId(i),rock);
                 }
             }
@@ -544,52 +1210,86 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
     fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
-        if let Some(&Some(TestFile{ rock, data:_ })) = self.files.get(fref.0) {
+        if let Some(&Some(TestFile{ rock, usage:_, name:_ })) = self.files.get(fref.0) {
             rock
         } else {
             0
         }
     }
 
-    fn fileref_delete_file(&mut self, _fref: &Self::FRefId) {
+    fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
+        if let Some(name) = self.file_name(fref).map(|name| name.to_string()) {
+            self.store.remove(&name);
+        }
     }
 
     fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
-        if let Some(&Some(_)) = self.files.get(fref.0) {
-            true
-        } else {
-            false
+        match self.file_name(fref) {
+            Some(name) => self.store.contains_key(name),
+            None => false,
         }
     }
 
 
     fn select(&mut self) -> Self::Event {
-        if self.line_input.is_none() && !self.char_input {
-            return Event::None;
+        if self.arrange {
+            self.arrange = false;
+            return Event::new(glk::evtype_Arrange, 0, 0, 0);
         }
-        let (test,line) = self.test.remove(0);
-        match test {
-            TestOutput::Match(expected) => {
-                assert_eq!(expected, self.out.as_str());
-            },
-            TestOutput::Check(f) => {
-                assert!(f(&self.out));
-            },
+        if self.line_input.is_none() && self.char_input.is_none() && self.timer == 0 && self.mouse_input.is_empty() && self.hyperlink_input.is_empty() {
+            return Event::none();
         }
-        self.out.clear();
-        if self.char_input {
-            assert_eq!(line.len(), 1);
-            self.char_input = false;
-            Event::Char(self.root,line.chars().next().unwrap() as u32)
-        } else {
-            let mut input = self.line_input.take().unwrap();
-            {
-                let mut buf = &mut input.1;
-                for i in 0 .. std::cmp::min(buf.len(), line.as_bytes().len()) {
-                    buf[i] = line.as_bytes()[i];
+        loop {
+            if self.test.is_empty() {
+                if let Some(failure) = self.failure.take() {
+                    panic!("{}", failure);
+                }
+                panic!("unexpected prompt after all {} steps of the script, with output:\n{}", self.step, self.out);
+            }
+            let (test,input) = self.test.remove(0);
+            self.step += 1;
+            if self.failure.is_none() {
+                if let Some(message) = self.check_output(&test) {
+                    self.failure = Some(TestFailure{ step: self.step, input: input.to_string(), message: message });
                 }
             }
-            Event::Line((self.root,line.as_bytes().len() as u32,Some(input)))
+            self.out.clear();
+            self.out_runs.clear();
+            self.runs.clear();
+            match input {
+                TestInput::Text(text) => {
+                    if self.char_input.is_some() {
+                        assert_eq!(1, text.chars().count());
+                        return self.char_event(text.chars().next().unwrap() as u32);
+                    }
+                    self.type_line(text);
+                    return self.finish_line(0);
+                },
+                TestInput::Terminated(text,keycode) => {
+                    self.type_line(text);
+                    let win = self.line_input.as_ref().unwrap().win;
+                    assert!(self.terminators.get(&win).map(|keycodes| keycodes.contains(&keycode)).unwrap_or(false), "{:x} is not a line terminator", keycode);
+                    return self.finish_line(keycode);
+                },
+                TestInput::Key(keycode) => return self.char_event(keycode),
+                TestInput::Typed(text) => self.type_line(text),
+                TestInput::Timer => {
+                    assert!(self.timer != 0, "no timer events requested");
+                    let interval = self.timer as i64 * 1000;
+                    self.advance_time(interval);
+                    return Event::new(glk::evtype_Timer, 0, 0, 0);
+                },
+                TestInput::Mouse(x,y) => {
+                    assert!(!self.mouse_input.is_empty(), "no mouse input requested");
+                    let win = self.mouse_input.remove(0);
+                    return Event::new(glk::evtype_MouseInput, win, x, y);
+                },
+                TestInput::Hyperlink(linkval) => {
+                    assert!(!self.hyperlink_input.is_empty(), "no hyperlink input requested");
+                    let win = self.hyperlink_input.remove(0);
+                    return Event::new(glk::evtype_Hyperlink, win, linkval, 0);
+                },
+            }
         }
     }
 
@@ -598,34 +1298,44 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
 
-    fn request_timer_events(&mut self, _millisecs: u32) {
+    fn request_timer_events(&mut self, millisecs: u32) {
+        self.timer = millisecs;
     }
 
 
-    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), _initlen: u32) {
-        if win.0 == self.root && self.root != 0 {
-            self.line_input = Some(buf);
-        }
+    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
+        self.request_line(win, LineBuf::Latin1(buf), initlen);
     }
 
     fn request_char_event(&mut self, win: &Self::WinId) {
-        if win.0 == self.root && self.root != 0 {
-            self.char_input = true;
+        if self.windows.get(&glk::windows::WinId(win.0)).is_some() {
+            self.char_input = Some((win.0,false));
         }
     }
 
-    fn request_mouse_event(&mut self, _win: &Self::WinId) {
+    fn request_mouse_event(&mut self, win: &Self::WinId) {
+        if self.windows.get(&glk::windows::WinId(win.0)).is_some() && !self.mouse_input.contains(&win.0) {
+            self.mouse_input.push(win.0);
+        }
     }
 
 
-    fn cancel_line_event(&mut self, _win: &Self::WinId) -> Self::Event {
-        Event::None
+    fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
+        if self.line_input.as_ref().map(|line| line.win == win.0).unwrap_or(false) {
+            self.finish_line(0)
+        } else {
+            Event::none()
+        }
     }
 
-    fn cancel_char_event(&mut self, _win: &Self::WinId) {
+    fn cancel_char_event(&mut self, win: &Self::WinId) {
+        if self.char_input.map(|(id,_)| id == win.0).unwrap_or(false) {
+            self.char_input = None;
+        }
     }
 
-    fn cancel_mouse_event(&mut self, _win: &Self::WinId) {
+    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
+        self.mouse_input.retain(|&id| id != win.0);
     }
 
 
@@ -633,53 +1343,21 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
 
-    fn set_terminators_line_event(&mut self, _win: &Self::WinId, _keycodes: &[u32]) {
+    fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
+        self.terminators.insert(win.0, keycodes.to_vec());
     }
 
 
     fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        let mut chars: Vec<char> = vec![];
-        for i in 0 .. numchars as usize {
-            if let Some(ch) = std::char::from_u32(buf[i]) {
-                chars.extend(ch.to_lowercase());
-            }
-        }
-        for i in 0 .. std::cmp::min(chars.len(), buf.len()) {
-            buf[i] = chars[i] as u32;
-        }
-        chars.len() as u32
+        glk::unicode::to_lower_case(buf, numchars)
     }
 
     fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
-        let mut chars: Vec<char> = vec![];
-        for i in 0 .. numchars as usize {
-            if let Some(ch) = std::char::from_u32(buf[i]) {
-                chars.extend(ch.to_uppercase());
-            }
-        }
-        for i in 0 .. std::cmp::min(chars.len(), buf.len()) {
-            buf[i] = chars[i] as u32;
-        }
-        chars.len() as u32
+        glk::unicode::to_upper_case(buf, numchars)
     }
 
     fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
-        let mut chars: Vec<char> = vec![];
-        for i in 0 .. numchars as usize {
-            if let Some(ch) = std::char::from_u32(buf[i]) {
-                if i == 0 {
-                    chars.extend(ch.to_uppercase());
-                } else if lowerrest != 0 {
-                    chars.extend(ch.to_lowercase());
-                } else {
-                    chars.push(ch);
-                }
-            }
-        }
-        for i in 0 .. std::cmp::min(chars.len(), buf.len()) {
-            buf[i] = chars[i] as u32;
-        }
-        chars.len() as u32
+        glk::unicode::to_title_case(buf, numchars, lowerrest != 0)
     }
 
 
@@ -701,11 +1379,13 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
     fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
+        let window = self.stream_window(str.0);
+        if let (Some(win),Some(c)) = (window,std::char::from_u32(ch)) {
+            self.put_window(win, c);
+        }
         if let Some(&mut Some(TestStream{ rock:_, readcount:_, ref mut writecount, ref mut out, ref mut out_uni, ref mut file, ref mut pos, ref mut end })) = self.streams.get_mut(str.0) {
-            if str.0 == self.root {
-                if let Some(c) = std::char::from_u32(ch) {
-                    self.out.push(c);
-                }
+            if window.is_some() {
+                // Written to the window above.
             } else if let &mut Some((_,ref mut mem)) = out {
                 if *pos < mem.len() {
                     mem[*pos] = ch as u8;
@@ -716,14 +1396,8 @@ impl<'a> Glk<'a> for GlkTest<'a> {
                     mem[*pos] = ch;
                     *pos += 1;
                 }
-            } else if let &mut Some((_,ref mut data)) = file {
-                if *pos >= data.len() {
-                    data.push(ch as u8);
-                    *pos = data.len();
-                } else {
-                    data[*pos] = ch as u8;
-                    *pos += 1;
-                }
+            } else if let &mut Some(ref mut file) = file {
+                file.put(pos, ch);
             }
             *end = std::cmp::max(*end, *pos);
             *writecount += 1;
@@ -744,8 +1418,9 @@ impl<'a> Glk<'a> for GlkTest<'a> {
 
 
     fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
+        let window = self.stream_window(str.0);
         if let Some(&mut Some(TestStream{ rock:_, ref mut readcount, writecount:_, ref out, ref out_uni, ref file, ref mut pos, end:_ })) = self.streams.get_mut(str.0) {
-            if str.0 == self.root {
+            if window.is_some() {
                 panic!();
             } else if let &Some((_,ref mem)) = out {
                 if *pos < mem.len() {
@@ -765,14 +1440,13 @@ impl<'a> Glk<'a> for GlkTest<'a> {
                 } else {
                     -1
                 }
-            } else if let &Some((_,ref data)) = file {
-                if *pos < data.len() {
-                    let ch = data[*pos];
-                    *pos += 1;
-                    *readcount += 1;
-                    ch as i32
-                } else {
-                    -1
+            } else if let &Some(ref file) = file {
+                match file.get(pos) {
+                    Some(ch) => {
+                        *readcount += 1;
+                        ch as i32
+                    },
+                    None => -1,
                 }
             } else {
                 -1
@@ -805,8 +1479,8 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
 
-    fn stream_open_file_uni(&mut self, _fileref: &Self::FRefId, _fmode: u32, _rock: u32) -> Self::StrId {
-        StrId(0)
+    fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.open_file(fileref, fmode, rock, true)
     }
 
     fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
@@ -836,20 +1510,23 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
 
-    fn request_char_event_uni(&mut self, _win: &Self::WinId) {
+    fn request_char_event_uni(&mut self, win: &Self::WinId) {
+        if self.windows.get(&glk::windows::WinId(win.0)).is_some() {
+            self.char_input = Some((win.0,true));
+        }
     }
 
-    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), _initlen: u32) {
-        let _ = (win,buf);
+    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
+        self.request_line(win, LineBuf::Uni(buf), initlen);
     }
 
 
-    fn buffer_canon_decompose_uni(&mut self, _buf: &mut [u32], _numchars: u32) -> u32 {
-        0
+    fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        glk::unicode::canon_decompose(buf, numchars)
     }
 
-    fn buffer_canon_normalize_uni(&mut self, _buf: &mut [u32], _numchars: u32) -> u32 {
-        0
+    fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        glk::unicode::canon_normalize(buf, numchars)
     }
 
 
@@ -861,8 +1538,11 @@ impl<'a> Glk<'a> for GlkTest<'a> {
         false
     }
 
-    fn image_get_info(&mut self, _image: u32) -> (bool,u32,u32) {
-        (false,0,0)
+    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
+        match self.resources.as_ref().and_then(|blorb| blorb.image_size(image)) {
+            Some((width,height)) => (true,width,height),
+            None => (false,0,0),
+        }
     }
 
 
@@ -880,6 +1560,11 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     }
 
 
+    fn image_draw_scaled_ext(&mut self, _win: &Self::WinId, _image: u32, _val1: i32, _val2: i32, _width: u32, _height: u32, _imagerule: u32, _maxwidth: u32) -> bool {
+        false
+    }
+
+
     fn schannel_create(&mut self, _rock: u32) -> Self::SChanId {
         SChanId(())
     }
@@ -939,66 +1624,105 @@ impl<'a> Glk<'a> for GlkTest<'a> {
     fn set_hyperlink_stream(&mut self, _str: &Self::StrId, _linkval: u32) {
     }
 
-    fn request_hyperlink_event(&mut self, _win: &Self::WinId) {
+    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
+        if self.windows.get(&glk::windows::WinId(win.0)).is_some() && !self.hyperlink_input.contains(&win.0) {
+            self.hyperlink_input.push(win.0);
+        }
     }
 
-    fn cancel_hyperlink_event(&mut self, _win: &Self::WinId) {
+    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
+        self.hyperlink_input.retain(|&id| id != win.0);
     }
 
 
     fn current_time(&mut self) -> Self::TimeVal {
-        TimeVal(())
+        self.time
     }
 
-    fn current_simple_time(&mut self, _factor: u32) -> i32 {
-        0
+    fn current_simple_time(&mut self, factor: u32) -> i32 {
+        self.time.to_simple(factor)
     }
 
-    fn time_to_date_utc(&mut self, _time: &Self::TimeVal) -> Self::Date {
-        Date(())
+    fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
+        Date::from_time(time, 0)
     }
 
-    fn time_to_date_local(&mut self, _time: &Self::TimeVal) -> Self::Date {
-        Date(())
+    fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
+        Date::from_time(time, self.time_zone)
     }
 
-    fn simple_time_to_date_utc(&mut self, _time: i32, _factor: u32) -> Self::Date {
-        Date(())
+    fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
     }
 
-    fn simple_time_to_date_local(&mut self, _time: i32, _factor: u32) -> Self::Date {
-        Date(())
+    fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
+        Date::from_time(&TimeVal::from_simple(time, factor), self.time_zone)
     }
 
-    fn date_to_time_utc(&mut self, _date: &Self::Date) -> Self::TimeVal {
-        TimeVal(())
+    fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
+        date.to_time(0)
     }
 
-    fn date_to_time_local(&mut self, _date: &Self::Date) -> Self::TimeVal {
-        TimeVal(())
+    fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
+        date.to_time(self.time_zone)
     }
 
-    fn date_to_simple_time_utc(&mut self, _date: &Self::Date, _factor: u32) -> i32 {
-        0
+    fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        date.to_time(0).to_simple(factor)
     }
 
-    fn date_to_simple_time_local(&mut self, _date: &Self::Date, _factor: u32) -> i32 {
-        0
+    fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        date.to_time(self.time_zone).to_simple(factor)
     }
 
 
-    fn stream_open_resource(&mut self, _filenum: u32, _rock: u32) -> Self::StrId {
-        StrId(0)
+    fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        let buf = if let Some(resource) = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum)) {
+            resource.data.to_vec().into_boxed_slice()
+        } else {
+            return StrId(0);
+        };
+        self.stream_open_memory((0,buf), glk::filemode_Read, rock)
     }
 
-    fn stream_open_resource_uni(&mut self, _filenum: u32, _rock: u32) -> Self::StrId {
-        StrId(0)
+    fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        let buf: Vec<u32> = if let Some(resource) = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum)) {
+            if &resource.chunk_type == b"TEXT" {
+                String::from_utf8_lossy(resource.data).chars().map(|ch| ch as u32).collect()
+            } else {
+                resource.data.chunks(4).map(|b| b.iter().fold(0, |ch, &b| ch << 8 | b as u32)).collect()
+            }
+        } else {
+            return StrId(0);
+        };
+        self.stream_open_memory_uni((0,buf.into_boxed_slice()), glk::filemode_Read, rock)
     }
 
 
     fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
         IOStream(str.clone(),self)
     }
+
+    fn set_resource_map(&mut self, blorb: Blorb) {
+        self.resources = Some(blorb);
+    }
+
+
+    fn garglk_set_zcolors(&mut self, fg: u32, bg: u32) {
+        self.garglk_text.push(GarglkText::ZColors(self.current, fg, bg));
+    }
+
+    fn garglk_set_zcolors_stream(&mut self, str: &Self::StrId, fg: u32, bg: u32) {
+        self.garglk_text.push(GarglkText::ZColors(str.0, fg, bg));
+    }
+
+    fn garglk_set_reversevideo(&mut self, reverse: u32) {
+        self.garglk_text.push(GarglkText::ReverseVideo(self.current, reverse));
+    }
+
+    fn garglk_set_reversevideo_stream(&mut self, str: &Self::StrId, reverse: u32) {
+        self.garglk_text.push(GarglkText::ReverseVideo(str.0, reverse));
+    }
 }
 
 #[derive(Clone,Eq,Hash,PartialEq)]
@@ -1097,110 +1821,47 @@ impl IdType for SChanId {
     }
 }
 
-pub enum Event {
-    Line((usize,u32,Option<(u32,Box<[u8]>)>)),
-    Char(usize,u32),
-    None,
+pub struct Event {
+    pub evtype: u32,
+    pub win: usize,
+    pub val1: u32,
+    pub val2: u32,
+    pub buf: Option<(u32,Box<[u8]>)>,
+    pub buf_uni: Option<(u32,Box<[u32]>)>,
+}
+
+impl Event {
+    fn new(evtype: u32, win: usize, val1: u32, val2: u32) -> Self {
+        Event{ evtype, win, val1, val2, buf: None, buf_uni: None }
+    }
+
+    fn none() -> Self {
+        Event::new(glk::evtype_None, 0, 0, 0)
+    }
 }
 
 impl EventType<WinId> for Event {
     fn evtype(&self) -> u32 {
-        match self {
-            &Event::Line(_) => glk::evtype_LineInput,
-            &Event::Char(_,_) => glk::evtype_CharInput,
-            &Event::None => glk::evtype_None,
-        }
+        self.evtype
     }
 
     fn win(&self) -> WinId {
-        match self {
-            &Event::Line((win,_,_)) => WinId(win),
-            &Event::Char(win,_) => WinId(win),
-            &Event::None => WinId(0),
-        }
+        WinId(self.win)
     }
 
     fn val1(&self) -> u32 {
-        match self {
-            &Event::Line((_,val,_)) => val,
-            &Event::Char(_,val) => val,
-            &Event::None => 0,
-        }
+        self.val1
     }
 
     fn val2(&self) -> u32 {
-        0
+        self.val2
     }
 
     fn buf(&mut self) -> Option<(u32,Box<[u8]>)> {
-        match self {
-            &mut Event::Line((_,_,ref mut buf)) => buf.take(),
-            &mut Event::Char(_,_) => None,
-            &mut Event::None => None,
-        }
+        self.buf.take()
     }
 
     fn buf_uni(&mut self) -> Option<(u32,Box<[u32]>)> {
-        None
-    }
-}
-
-pub struct TimeVal(());
-
-impl TimeValType for TimeVal {
-    fn new(_high_sec: i32, _low_sec: u32, _microsec: i32) -> Self {
-        TimeVal(())
-    }
-
-    fn high_sec(&self) -> i32 {
-        0
-    }
-
-    fn low_sec(&self) -> u32 {
-        0
-    }
-
-    fn microsec(&self) -> i32 {
-        0
-    }
-}
-
-pub struct Date(());
-
-impl DateType for Date {
-     fn new(_year: i32, _month: i32, _day: i32, _weekday: i32, _hour: i32, _minute: i32, _second: i32, _microsec: i32) -> Self {
-        Date(())
-    }
-
-   fn year(&self) -> i32 {
-        0
-    }
-
-    fn month(&self) -> i32 {
-        0
-    }
-
-    fn day(&self) -> i32 {
-        0
-    }
-
-    fn weekday(&self) -> i32 {
-        0
-    }
-
-    fn hour(&self) -> i32 {
-        0
-    }
-
-    fn minute(&self) -> i32 {
-        0
-    }
-
-    fn second(&self) -> i32 {
-        0
-    }
-
-    fn microsec(&self) -> i32 {
-        0
+        self.buf_uni.take()
     }
 }
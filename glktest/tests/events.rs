@@ -0,0 +1,96 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::{GlkTest,TestInput};
+use glktest::TestOutput::Match;
+use glk::{Glk,EventType,IdType};
+
+#[test]
+fn timer_and_keys() {
+    let mut glk = GlkTest::scripted(vec![
+                (Match(""),TestInput::Timer),
+                (Match("tick\n"),TestInput::Key(glk::keycode_Left)),
+                (Match(""),TestInput::Text("\u{3b1}")),
+                (Match(""),TestInput::Text("\u{3b1}")),
+                ]);
+    let win = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    assert_eq!(1, glk.gestalt(glk::gestalt_Timer, 0));
+    assert_eq!(1, glk.gestalt(glk::gestalt_CharInput, glk::keycode_Func3));
+    glk.request_timer_events(100);
+    glk.request_char_event(&win);
+    let event = glk.select();
+    assert_eq!(glk::evtype_Timer, event.evtype());
+    glk.request_timer_events(0);
+    glk.put_string("tick\n");
+
+    let event = glk.select();
+    assert_eq!(glk::evtype_CharInput, event.evtype());
+    assert!(event.win() == win);
+    assert_eq!(glk::keycode_Left, event.val1());
+
+    glk.request_char_event(&win);
+    assert_eq!(glk::keycode_Unknown, glk.select().val1());
+    glk.request_char_event_uni(&win);
+    assert_eq!(0x3b1, glk.select().val1());
+}
+
+#[test]
+fn terminators_and_partial_input() {
+    let mut glk = GlkTest::scripted(vec![
+                (Match(">"),TestInput::Terminated("north",glk::keycode_Func1)),
+                (Match(">"),TestInput::Typed("ea")),
+                (Match(""),TestInput::Timer),
+                ]);
+    let win = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    assert_eq!(1, glk.gestalt(glk::gestalt_LineTerminatorKey, glk::keycode_Func1));
+    assert_eq!(0, glk.gestalt(glk::gestalt_LineTerminatorKey, glk::keycode_Left));
+    glk.set_terminators_line_event(&win, &[glk::keycode_Func1]);
+    glk.put_string(">");
+    glk.request_line_event_uni(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+    let mut event = glk.select();
+    assert_eq!(glk::evtype_LineInput, event.evtype());
+    assert_eq!((5,glk::keycode_Func1), (event.val1(),event.val2()));
+    assert_eq!(&[0x6e, 0x6f, 0x72, 0x74, 0x68], &event.buf_uni().unwrap().1[..5]);
+
+    glk.put_string(">");
+    glk.request_timer_events(50);
+    glk.request_line_event(&win, (0,b"go         ".to_vec().into_boxed_slice()), 3);
+    assert_eq!(glk::evtype_Timer, glk.select().evtype());
+    let mut event = glk.cancel_line_event(&win);
+    assert_eq!(glk::evtype_LineInput, event.evtype());
+    assert_eq!((5,0), (event.val1(),event.val2()));
+    assert_eq!(b"go ea", &event.buf().unwrap().1[..5]);
+    assert_eq!(glk::evtype_None, glk.cancel_line_event(&win).evtype());
+}
+
+#[test]
+fn mouse_and_hyperlinks() {
+    let mut glk = GlkTest::scripted(vec![
+                (Match(""),TestInput::Mouse(3,0)),
+                (Match("Go west."),TestInput::Hyperlink(7)),
+                ]);
+    let mainwin = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    let statuswin = glk.window_open(&mainwin, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 2);
+    assert_eq!(1, glk.gestalt(glk::gestalt_MouseInput, glk::wintype_TextGrid));
+    assert_eq!(1, glk.gestalt(glk::gestalt_HyperlinkInput, 0));
+    glk.request_mouse_event(&statuswin);
+    glk.request_hyperlink_event(&mainwin);
+    let event = glk.select();
+    assert_eq!(glk::evtype_MouseInput, event.evtype());
+    assert!(event.win() == statuswin);
+    assert_eq!((3,0), (event.val1(),event.val2()));
+
+    glk.set_window(&mainwin);
+    glk.set_hyperlink(7);
+    glk.put_string("Go west.");
+    glk.set_hyperlink(0);
+    let event = glk.select();
+    assert_eq!(glk::evtype_Hyperlink, event.evtype());
+    assert!(event.win() == mainwin);
+    assert_eq!(7, event.val1());
+
+    glk.cancel_hyperlink_event(&mainwin);
+    assert_eq!(glk::evtype_None, glk.select().evtype());
+}
@@ -0,0 +1,94 @@
+extern crate glktest;
+extern crate glk;
+
+use std::env;
+use std::fs;
+
+use glktest::{GlkTest,WinId};
+use glktest::TestOutput::{Match,Regex,Snapshot};
+use glk::{Glk,IdType};
+
+fn prompt(glk: &mut GlkTest, win: &WinId, text: &str) {
+    glk.put_string(text);
+    glk.request_line_event(win, (0,vec![0; 10].into_boxed_slice()), 0);
+    glk.select();
+}
+
+#[test]
+fn regex_and_failures() {
+    let mut glk = GlkTest::new(vec![
+                (Regex(r"Turn \d+\n>"),"wait"),
+                (Match("One\nTwo\nThree\n>"),"look"),
+                (Regex("Four"),"quit"),
+                ]);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    prompt(&mut glk, &win, "Turn 12\n>");
+    prompt(&mut glk, &win, "One\n2\nThree\n>");
+    prompt(&mut glk, &win, "Five");
+    let failure = glk.result().unwrap_err();
+    assert_eq!(2, failure.step);
+    assert_eq!("\"look\"", failure.input);
+    assert_eq!("output differs\n--- expected\n+++ actual\n@@ -1,4 +1,4 @@\n One\n-Two\n+2\n Three\n >\n", failure.message);
+    assert!(glk.result().is_ok());
+}
+
+#[test]
+fn snapshots() {
+    let path = env::temp_dir().join(format!("glktest-snapshot-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+
+    let mut glk = GlkTest::new(vec![(Snapshot(path),"look")]);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    prompt(&mut glk, &win, "Room\n>");
+    assert!(glk.result().unwrap_err().message.contains("set GLKTEST_BLESS to create it"));
+
+    env::set_var("GLKTEST_BLESS", "1");
+    let mut glk = GlkTest::new(vec![(Snapshot(path),"look")]);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    prompt(&mut glk, &win, "Room\n>");
+    env::remove_var("GLKTEST_BLESS");
+    assert!(glk.result().is_ok());
+    assert_eq!("Room\n>", fs::read_to_string(path).unwrap());
+
+    let mut glk = GlkTest::new(vec![(Snapshot(path),"look"),(Snapshot(path),"quit")]);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    prompt(&mut glk, &win, "Room\n>");
+    prompt(&mut glk, &win, "Hall\n>");
+    let failure = glk.result().unwrap_err();
+    assert_eq!(2, failure.step);
+    assert!(failure.message.ends_with("@@ -1,2 +1,2 @@\n-Room\n+Hall\n >\n"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "unexpected prompt after all 1 steps of the script, with output:\nAgain?")]
+fn extra_prompt() {
+    let mut glk = GlkTest::new(vec![(Match(">"),"quit")]);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    prompt(&mut glk, &win, ">");
+    prompt(&mut glk, &win, "Again?");
+}
+
+#[test]
+#[should_panic(expected = "step 1 (before input \"quit\"): output differs")]
+fn unchecked_failure() {
+    let mut glk = GlkTest::new(vec![(Match(">"),"quit")]);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    prompt(&mut glk, &win, "?");
+    glk.output();
+}
+
+#[test]
+fn diff() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+    let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n";
+    assert_eq!("--- expected\n+++ actual\n@@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n@@ -10,4 +10,5 @@\n 10\n 11\n 12\n+13\n \n", glktest::diff(old, new));
+    assert_eq!("--- expected\n+++ actual\n", glktest::diff(old, old));
+}
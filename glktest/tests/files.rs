@@ -0,0 +1,74 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::GlkTest;
+use glk::{Glk,IdType};
+
+#[test]
+fn named_files() {
+    let mut glk = GlkTest::new(vec![]);
+    let fref = glk.fileref_create_by_name(glk::fileusage_Data | glk::fileusage_BinaryMode, "scores.dat", 1);
+    assert!(!fref.is_null());
+    assert!(!glk.fileref_does_file_exist(&fref));
+    let mut str = glk.stream_open_file(&fref, glk::filemode_Write, 0);
+    glk.put_string_stream(&str, "abc");
+    glk.stream_close(&mut str);
+    assert!(glk.fileref_does_file_exist(&fref));
+    assert_eq!(vec!["scores.glkdata"], glk.file_names());
+    assert_eq!(Some(&b"abc"[..]), glk.file("scores.glkdata"));
+
+    let mut str = glk.stream_open_file(&fref, glk::filemode_WriteAppend, 0);
+    glk.put_string_stream(&str, "de");
+    glk.stream_close(&mut str);
+    let mut str = glk.stream_open_file(&fref, glk::filemode_Read, 0);
+    let mut buf = [0; 10];
+    assert_eq!(5, glk.get_buffer_stream(&str, &mut buf));
+    assert_eq!(b"abcde", &buf[..5]);
+    glk.stream_close(&mut str);
+
+    glk.fileref_delete_file(&fref);
+    assert!(!glk.fileref_does_file_exist(&fref));
+    assert!(glk.stream_open_file(&fref, glk::filemode_Read, 0).is_null());
+}
+
+#[test]
+fn prompts() {
+    let mut glk = GlkTest::new(vec![]);
+    glk.set_file("game.sav", b"saved");
+    glk.set_prompt_answers(vec!["game.sav", ""]);
+    let fref = glk.fileref_create_by_prompt(glk::fileusage_SavedGame, glk::filemode_Read, 0);
+    let str = glk.stream_open_file(&fref, glk::filemode_Read, 0);
+    let mut buf = [0; 10];
+    assert_eq!(5, glk.get_buffer_stream(&str, &mut buf));
+    assert_eq!(b"saved", &buf[..5]);
+    assert!(glk.fileref_create_by_prompt(glk::fileusage_SavedGame, glk::filemode_Write, 0).is_null());
+    assert!(glk.fileref_create_by_prompt(glk::fileusage_SavedGame, glk::filemode_Write, 0).is_null());
+}
+
+#[test]
+fn unicode_files() {
+    let mut glk = GlkTest::new(vec![]);
+    let text = glk.fileref_create_by_name(glk::fileusage_Transcript | glk::fileusage_TextMode, "log", 0);
+    let mut str = glk.stream_open_file_uni(&text, glk::filemode_Write, 0);
+    glk.put_string_stream_uni(&str, &[0x3b1, 0x62]);
+    glk.put_char_stream(&str, 0xe9);
+    glk.stream_close(&mut str);
+    assert_eq!(Some("\u{3b1}b\u{e9}".as_bytes()), glk.file("log.txt"));
+
+    let binary = glk.fileref_create_from_fileref(glk::fileusage_Data | glk::fileusage_BinaryMode, &text, 0);
+    let mut str = glk.stream_open_file_uni(&binary, glk::filemode_Write, 0);
+    glk.put_char_stream_uni(&str, 0x3b1);
+    glk.put_char_stream_uni(&str, 0x62);
+    assert_eq!(2, glk.stream_get_position(&str));
+    glk.stream_close(&mut str);
+    assert_eq!(Some(&[0, 0, 3, 0xb1, 0, 0, 0, 0x62][..]), glk.file("log.txt"));
+
+    let mut str = glk.stream_open_file_uni(&binary, glk::filemode_Read, 0);
+    glk.stream_set_position(&str, 1, glk::seekmode_Start);
+    assert_eq!(0x62, glk.get_char_stream_uni(&str));
+    glk.stream_set_position(&str, 0, glk::seekmode_Start);
+    assert_eq!('?' as i32, glk.get_char_stream(&str));
+    assert_eq!(0x62, glk.get_char_stream_uni(&str));
+    assert_eq!(-1, glk.get_char_stream_uni(&str));
+    glk.stream_close(&mut str);
+}
@@ -4,6 +4,7 @@ extern crate glk;
 use glktest::GlkTest;
 use glktest::TestOutput::Match;
 use glk::{Glk,EventType,IdType};
+use glk::session;
 
 // model.c: Model program for Glk API, version 0.5.
 //  Designed by Andrew Plotkin <erkyrath@eblong.com>
@@ -16,14 +17,18 @@ use glk::{Glk,EventType,IdType};
 
 #[test]
 fn main() {
-    Model::new(GlkTest::new(vec![
-                (Match("Model Glk Program\nAn Interactive Model Glk Program\nBy Andrew Plotkin.\nRelease 7.\nType \"help\" for a list of commands.\n\nThe Room\nYou're in a room of some sort.\n\n>"),"yada"),
-                (Match("Gafiggle wob shim fleb moobosh fonk wabble gazoon ting floo zonk loof lob figgle shim moobosh wabble. Boting zonk lob wob fleb fonk gazoon floo loof figgle fleb wabble floo lob shim fonk ting. Waloof wob moobosh gazoon zonk figgle moobosh ting lob fleb gazoon loof shim wabble zonk wob fonk. Mufloo figgle fonk zonk shim gazoon lob moobosh floo wob wabble loof fleb ting figgle wabble lob. Bifonk loof moobosh zonk fleb floo shim ting wob gazoon figgle gazoon wob ting shim floo fleb.\n\n>"),"move"),
-                (Match("You walk for a while.\n\nA Different Room\nYou're in a room of some sort.\n\n>"),"jump"),
-                (Match("You jump on the fruit, spotlessly.\n\n>"),"get swword"),
-                (Match("I don't understand the command \"get swword\".\n\n>"),"quit"),
-                (Match("Are you sure you want to quit? "),"y"),
-                ])).glk_main();
+    let result = session::run(|| {
+        Model::new(GlkTest::new(vec![
+                    (Match("Model Glk Program\nAn Interactive Model Glk Program\nBy Andrew Plotkin.\nRelease 7.\nType \"help\" for a list of commands.\n\nThe Room\nYou're in a room of some sort.\n\n>"),"yada"),
+                    (Match("Gafiggle wob shim fleb moobosh fonk wabble gazoon ting floo zonk loof lob figgle shim moobosh wabble. Boting zonk lob wob fleb fonk gazoon floo loof figgle fleb wabble floo lob shim fonk ting. Waloof wob moobosh gazoon zonk figgle moobosh ting lob fleb gazoon loof shim wabble zonk wob fonk. Mufloo figgle fonk zonk shim gazoon lob moobosh floo wob wabble loof fleb ting figgle wabble lob. Bifonk loof moobosh zonk fleb floo shim ting wob gazoon figgle gazoon wob ting shim floo fleb.\n\n>"),"move"),
+                    (Match("You walk for a while.\n\nA Different Room\nYou're in a room of some sort.\n\n>"),"jump"),
+                    (Match("You jump on the fruit, spotlessly.\n\n>"),"get swword"),
+                    (Match("I don't understand the command \"get swword\".\n\n>"),"quit"),
+                    (Match("Are you sure you want to quit? "),"y"),
+                    ])).glk_main();
+        Ok(())
+    });
+    assert!(result.is_ok());
 }
 
 pub struct Model<'a,G: Glk<'a>> {
@@ -381,7 +386,7 @@ impl<'a,G: Glk<'a>> Model<'a,G> {
         if self.yes_or_no() {
             self.glk.put_string("Thanks for playing.\n");
             self.glk.exit();
-            // glk_exit() actually stops the process; it does not return.
+            // glk_exit() actually ends the session; it does not return.
         }
     }
 
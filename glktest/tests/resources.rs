@@ -0,0 +1,120 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::GlkTest;
+use glk::Glk;
+use glk::blorb::{Blorb,Usage};
+
+fn push_u32(bytes: &mut Vec<u8>, val: u32) {
+    bytes.extend_from_slice(&[(val >> 24) as u8, (val >> 16) as u8, (val >> 8) as u8, val as u8]);
+}
+
+fn blorb(resources: &[(&[u8;4],u32,&[u8;4],&[u8])], others: &[(&[u8;4],&[u8])]) -> Vec<u8> {
+    let mut offset = 12 + 8 + 4 + 12*resources.len();
+    let mut ridx = vec![];
+    let mut chunks = vec![];
+    push_u32(&mut ridx, resources.len() as u32);
+    for &(usage,number,id,data) in resources {
+        ridx.extend_from_slice(usage);
+        push_u32(&mut ridx, number);
+        push_u32(&mut ridx, offset as u32);
+        chunks.extend_from_slice(id);
+        push_u32(&mut chunks, data.len() as u32);
+        chunks.extend_from_slice(data);
+        if data.len() % 2 != 0 {
+            chunks.push(0);
+        }
+        offset += 8 + data.len() + data.len() % 2;
+    }
+    for &(id,data) in others {
+        chunks.extend_from_slice(id);
+        push_u32(&mut chunks, data.len() as u32);
+        chunks.extend_from_slice(data);
+        if data.len() % 2 != 0 {
+            chunks.push(0);
+        }
+    }
+    let mut form = vec![];
+    form.extend_from_slice(b"FORM");
+    push_u32(&mut form, (4 + 8 + ridx.len() + chunks.len()) as u32);
+    form.extend_from_slice(b"IFRS");
+    form.extend_from_slice(b"RIdx");
+    push_u32(&mut form, ridx.len() as u32);
+    form.extend_from_slice(&ridx);
+    form.extend_from_slice(&chunks);
+    form
+}
+
+const PNG: &'static [u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x01\x40\x00\x00\x00\xf0\x08\x02\x00\x00\x00";
+
+#[test]
+fn test() {
+    let blorb = Blorb::new(blorb(&[
+                (b"Exec", 0, b"GLUL", b"Glul"),
+                (b"Pict", 1, b"PNG ", PNG),
+                (b"Data", 2, b"TEXT", "h\u{e9}llo\n".as_bytes()),
+                (b"Data", 3, b"BINA", b"\x00\x00\x00\xe9\x00\x00\x00\x21"),
+                ], &[])).unwrap();
+    assert_eq!(b"Glul", blorb.exec().unwrap().data);
+    assert_eq!(Usage::Pict, blorb.resource(Usage::Pict, 1).unwrap().usage);
+    assert!(blorb.resource(Usage::Pict, 2).is_none());
+    assert_eq!(2, blorb.resources(Usage::Data).len());
+
+    let mut glk = GlkTest::new(vec![]);
+    glk.set_resource_map(blorb);
+    assert_eq!((true,320,240), glk.image_get_info(1));
+    assert_eq!((false,0,0), glk.image_get_info(2));
+
+    let mut str = glk.stream_open_resource_uni(2, 0);
+    let mut buf = [0; 10];
+    assert_eq!(5, glk.get_line_stream_uni(&str, &mut buf));
+    assert_eq!(&['h' as u32,0xe9,'l' as u32,'l' as u32,'o' as u32], &buf[0..5]);
+    glk.stream_close(&mut str);
+
+    let mut str = glk.stream_open_resource(3, 0);
+    let mut buf = [0; 10];
+    assert_eq!(8, glk.get_buffer_stream(&str, &mut buf));
+    glk.stream_close(&mut str);
+
+    let mut str = glk.stream_open_resource_uni(3, 0);
+    let mut buf = [0; 10];
+    assert_eq!(2, glk.get_buffer_stream_uni(&str, &mut buf));
+    assert_eq!(&[0xe9,0x21], &buf[0..2]);
+    glk.stream_close(&mut str);
+}
+
+const IFICTION: &'static str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<ifindex version=\"1.0\" xmlns=\"http://babel.ifarchive.org/protocol/iFiction/\">
+  <story>
+    <identification>
+      <ifid  >0E2B6F1C-8D47-4B1E-9C0F-2B5B0A7B4D11</ifid>
+      <format>glulx</format>
+    </identification>
+    <bibliographic>
+      <titles/>
+      <title lang=\"en\">Fish &amp; Chips</title>
+      <author>A. N. Author</author>
+      <headline>An Interactive Lunch</headline>
+    </bibliographic>
+  </story>
+</ifindex>
+";
+
+#[test]
+fn metadata() {
+    let blorb = Blorb::new(blorb(&[
+                (b"Exec", 0, b"GLUL", b"Glul"),
+                (b"Pict", 1, b"PNG ", PNG),
+                ], &[
+                (b"IFmd", IFICTION.as_bytes()),
+                (b"Fspc", b"\x00\x00\x00\x01"),
+                (b"RelN", b"\x00\x07"),
+                ])).unwrap();
+    let metadata = blorb.metadata();
+    assert_eq!(Some("Fish & Chips".to_string()), metadata.title);
+    assert_eq!(Some("A. N. Author".to_string()), metadata.author);
+    assert_eq!(Some("An Interactive Lunch".to_string()), metadata.headline);
+    assert_eq!(Some(7), metadata.release);
+    assert_eq!(Some("0E2B6F1C-8D47-4B1E-9C0F-2B5B0A7B4D11".to_string()), metadata.ifid);
+    assert_eq!(Some(1), metadata.frontispiece);
+}
@@ -0,0 +1,57 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::GlkTest;
+use glktest::TestOutput::{Match,Styled};
+use glk::{Glk,IdType};
+
+#[test]
+fn styled_steps() {
+    let mut glk = GlkTest::new(vec![
+                (Styled(&[(glk::style_Header,"Room"),(glk::style_Normal,"\nYou are here.\n"),(glk::style_Input,">")]),"look"),
+                (Match("Room\n>"),"quit"),
+                ]);
+    let win = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    glk.set_window(&win);
+    glk.set_style(glk::style_Header);
+    glk.put_string("Room");
+    glk.set_style(glk::style_Normal);
+    glk.put_string("\nYou are here.\n");
+    glk.set_style(glk::style_Input);
+    glk.put_string(">");
+    glk.request_line_event(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+    glk.select();
+
+    glk.set_style(glk::style_Header);
+    glk.put_string("Room\n");
+    glk.set_style(glk::style_Header);
+    glk.put_string(">");
+    assert_eq!(vec![(glk::style_Header,"Room\n>")], glk.styled_output());
+    glk.request_line_event(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+    glk.select();
+    assert!(glk.styled_output().is_empty());
+}
+
+#[test]
+fn windows_and_hints() {
+    let mut glk = GlkTest::new(vec![]);
+    glk.stylehint_set(glk::wintype_AllTypes, glk::style_Alert, glk::stylehint_Weight, 1);
+    glk.stylehint_set(glk::wintype_TextGrid, glk::style_Alert, glk::stylehint_ReverseColor, 1);
+    glk.stylehint_clear(glk::wintype_TextBuffer, glk::style_Alert, glk::stylehint_Weight);
+    assert_eq!(None, glk.stylehint(glk::wintype_TextBuffer, glk::style_Alert, glk::stylehint_Weight));
+    assert_eq!(Some(1), glk.stylehint(glk::wintype_TextGrid, glk::style_Alert, glk::stylehint_Weight));
+
+    let mainwin = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    let statuswin = glk.window_open(&mainwin, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 2);
+    assert_eq!((true,1), glk.style_measure(&statuswin, glk::style_Alert, glk::stylehint_ReverseColor));
+    assert_eq!((false,0), glk.style_measure(&mainwin, glk::style_Alert, glk::stylehint_ReverseColor));
+
+    let str = glk.window_get_stream(&statuswin);
+    glk.set_style_stream(&str, glk::style_Alert);
+    glk.put_string_stream(&str, "Dark");
+    glk.set_window(&mainwin);
+    glk.put_string("It is dark.");
+    assert_eq!(vec![(glk::style_Alert,"Dark")], glk.window_output(&statuswin));
+    assert_eq!(vec![(glk::style_Normal,"It is dark.")], glk.window_output(&mainwin));
+    assert_eq!(vec![(glk::style_Normal,"It is dark.")], glk.styled_output());
+}
@@ -0,0 +1,56 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::{Date,GlkTest,TestInput,TimeVal};
+use glktest::TestOutput::Match;
+use glk::{Glk,DateType,EventType,IdType,TimeValType};
+
+#[test]
+fn clock() {
+    let mut glk = GlkTest::new(vec![]);
+    assert_eq!(TimeVal::new(0, 0, 0), glk.current_time());
+    glk.set_time(TimeVal::from_secs(1700000000, 999999));
+    glk.advance_time(2);
+    assert_eq!(TimeVal::from_secs(1700000001, 1), glk.current_time());
+    assert_eq!(28333333, glk.current_simple_time(60));
+    glk.advance_time(-2000001);
+    assert_eq!(TimeVal::from_secs(1699999999, 0), glk.current_time());
+}
+
+#[test]
+fn time_zones() {
+    let mut glk = GlkTest::new(vec![]);
+    glk.set_time(TimeVal::from_secs(1700000000, 250));
+    glk.set_time_zone(-5 * 3600);
+    let now = glk.current_time();
+    let utc = glk.time_to_date_utc(&now);
+    assert_eq!((2023,11,14,2), (utc.year(),utc.month(),utc.day(),utc.weekday()));
+    assert_eq!((22,13,20,250), (utc.hour(),utc.minute(),utc.second(),utc.microsec()));
+    let local = glk.time_to_date_local(&now);
+    assert_eq!((2023,11,14,17,13), (local.year(),local.month(),local.day(),local.hour(),local.minute()));
+    assert_eq!(now, glk.date_to_time_local(&local));
+    assert_eq!(now, glk.date_to_time_utc(&utc));
+
+    let midnight = Date::new(2024, 2, 30, 0, 0, 0, 0, 0);
+    let time = glk.date_to_time_local(&midnight);
+    let date = glk.simple_time_to_date_utc(time.to_simple(1), 1);
+    assert_eq!((3,1,5), (date.month(),date.day(),date.hour()));
+    assert_eq!(time.to_simple(60), glk.date_to_simple_time_local(&midnight, 60));
+    let date = glk.simple_time_to_date_local(time.to_simple(60), 60);
+    assert_eq!((2024,3,1,0,0), (date.year(),date.month(),date.day(),date.hour(),date.minute()));
+}
+
+#[test]
+fn timer_advances_clock() {
+    let mut glk = GlkTest::scripted(vec![
+                (Match(""),TestInput::Timer),
+                (Match(""),TestInput::Timer),
+                ]);
+    let win = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    assert!(!win.is_null());
+    glk.request_timer_events(1500);
+    assert_eq!(glk::evtype_Timer, glk.select().evtype());
+    assert_eq!(TimeVal::from_secs(1, 500000), glk.current_time());
+    assert_eq!(glk::evtype_Timer, glk.select().evtype());
+    assert_eq!(3, glk.current_simple_time(1));
+}
@@ -0,0 +1,44 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::{GlkTest,WinId};
+use glktest::TestOutput::Match;
+use glk::{Glk,IdType};
+use glk::transcript::{Format,TranscriptGlk};
+
+fn play(format: Format, include_input: bool) -> String {
+    let mut glk = TranscriptGlk::new(GlkTest::new(vec![(Match("West of House\nYou are <here>.\n>"),"look")]), vec![], format);
+    glk.set_include_input(include_input);
+    let win = glk.window_open(&WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    let status = glk.window_open(&win, glk::winmethod_Above|glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 2);
+    glk.set_window(&status);
+    glk.put_string("Score: 0");
+    glk.set_window(&win);
+    glk.set_style(glk::style_Header);
+    glk.put_string("West of House");
+    glk.set_style(glk::style_Normal);
+    glk.put_string("\nYou are ");
+    glk.set_style(glk::style_Emphasized);
+    glk.put_string_uni(&['<' as u32,'h' as u32,'e' as u32,'r' as u32,'e' as u32,'>' as u32][..]);
+    glk.set_style(glk::style_Normal);
+    glk.put_string(".\n>");
+    glk.request_line_event(&win, (0,vec![0; 10].into_boxed_slice()), 0);
+    glk.select();
+    glk.put_string("Done.\n");
+    let (_,transcript) = glk.finish();
+    String::from_utf8(transcript.unwrap()).unwrap()
+}
+
+#[test]
+fn markdown() {
+    assert_eq!("**West of House**  \nYou are *\\<here\\>*.  \n\\>**look**  \nDone.  \n", play(Format::Markdown, true));
+    assert_eq!("**West of House**  \nYou are *\\<here\\>*.  \n\\>Done.  \n", play(Format::Markdown, false));
+}
+
+#[test]
+fn html() {
+    let transcript = play(Format::Html, true);
+    assert!(transcript.starts_with("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Transcript</title>\n"));
+    assert!(transcript.contains("<div class=\"transcript\">\n<span class=\"header\">West of House</span>\nYou are <span class=\"emphasized\">&lt;here&gt;</span>.\n&gt;<span class=\"input\">look\n</span>Done.\n</div>\n</body>\n</html>\n"));
+    assert!(!transcript.contains("Score"));
+}
@@ -0,0 +1,70 @@
+extern crate glktest;
+extern crate glk;
+
+use std::io::SeekFrom;
+
+use glktest::GlkTest;
+use glktest::TestOutput::Match;
+use glk::typed::{Direction,Event,FileMode,Key,Size,Style,TypedGlk,WinMethod,WinType};
+
+#[test]
+fn test() {
+    let mut glk = TypedGlk::new(GlkTest::new(vec![
+                (Match("h\u{e9}llo\n>"),"look"),
+                ]));
+    assert!(glk.window_get_root().is_none());
+    let mainwin = glk.window_open(None, WinType::TextBuffer, 1).unwrap();
+    assert_eq!(Some(WinType::TextBuffer), glk.window_get_type(&mainwin));
+    assert_eq!(1, glk.window_get_rock(&mainwin));
+    assert!(glk.window_get_parent(&mainwin).is_none());
+    let statuswin = glk.window_open(Some((&mainwin,WinMethod::new(Direction::Above, Size::Fixed(1)))), WinType::TextGrid, 2).unwrap();
+    assert!(glk.window_get_parent(&statuswin).is_some());
+    assert!(glk.window_get_parent(&statuswin) == glk.window_get_parent(&mainwin));
+    assert!(glk.window_iterate(None) == Some((mainwin.clone(),1)));
+    assert!(glk.window_iterate(Some(&mainwin)) == Some((statuswin.clone(),2)));
+
+    glk.set_window(Some(&mainwin));
+    glk.set_style(Style::Emphasized);
+    glk.put_str("h\u{e9}llo\n>");
+    glk.set_style(Style::Normal);
+
+    glk.request_line_event(&mainwin, vec![0; 20].into_boxed_slice(), 0);
+    match glk.select() {
+        Event::LineInput{ win, buf, len, terminator } => {
+            assert!(win == mainwin);
+            assert_eq!(b"look", &buf[..len]);
+            assert_eq!(None, terminator);
+        },
+        _ => panic!("expected line input"),
+    }
+
+    let str = glk.stream_open_memory(vec![0; 8].into_boxed_slice(), FileMode::ReadWrite, 3).unwrap();
+    glk.put_string_stream(&str, "abcd");
+    glk.stream_set_position(&str, SeekFrom::Start(1)).unwrap();
+    assert_eq!(Some(b'b'), glk.get_char_stream(&str));
+    assert!(glk.stream_set_position(&str, SeekFrom::Start(0x100000000)).is_err());
+    assert!(glk.stream_set_position(&str, SeekFrom::Current(-0x80000001)).is_err());
+    assert_eq!(Some(b'c'), glk.get_char_stream(&str));
+    glk.stream_set_position(&str, SeekFrom::End(0)).unwrap();
+    assert_eq!(None, glk.get_char_stream(&str));
+    let result = glk.stream_close(str);
+    assert_eq!(2, result.readcount);
+    assert_eq!(4, result.writecount);
+    assert_eq!(b"abcd", &result.buf.unwrap()[0..4]);
+    assert!(result.buf_uni.is_none());
+}
+
+#[test]
+fn conversions() {
+    let method = WinMethod{ direction: Direction::Left, size: Size::Proportional(40), border: false };
+    assert_eq!((glk::winmethod_Left|glk::winmethod_Proportional|glk::winmethod_NoBorder,40), method.to_u32());
+    assert_eq!(Some(method), WinMethod::from_u32(method.to_u32().0, 40));
+    assert_eq!(None, WinMethod::from_u32(glk::winmethod_Left|0x70, 40));
+    assert_eq!(None, WinType::from_u32(99));
+
+    for &keycode in &[glk::keycode_Func1,glk::keycode_Func12,glk::keycode_Return,'x' as u32] {
+        assert_eq!(keycode, Key::from_u32(keycode).to_u32());
+    }
+    assert_eq!(Key::Func(12), Key::from_u32(glk::keycode_Func12));
+    assert_eq!(Key::Unknown, Key::from_u32(0xd800));
+}
@@ -0,0 +1,75 @@
+extern crate glktest;
+extern crate glk;
+
+use glktest::GlkTest;
+use glktest::TestOutput::Match;
+use glk::{Glk,EventType,IdType};
+
+#[test]
+fn status_line() {
+    let mut glk = GlkTest::new(vec![
+                (Match("Room\n>"),"look"),
+                ]);
+    let mainwin = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    let statuswin = glk.window_open(&mainwin, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 2);
+    assert!(!statuswin.is_null());
+    assert_eq!((80,1), glk.window_get_size(&statuswin));
+    assert_eq!((80,23), glk.window_get_size(&mainwin));
+
+    let pair = glk.window_get_parent(&mainwin);
+    assert!(pair == glk.window_get_parent(&statuswin));
+    assert!(pair == glk.window_get_root());
+    assert!(glk.window_get_sibling(&mainwin) == statuswin);
+    assert_eq!(glk::wintype_Pair, glk.window_get_type(&pair));
+    let (method,size,keywin) = glk.window_get_arrangement(&pair);
+    assert_eq!((glk::winmethod_Above | glk::winmethod_Fixed,1), (method,size));
+    assert!(keywin == statuswin);
+
+    glk.set_window(&statuswin);
+    glk.put_string(" Room");
+    glk.window_move_cursor(&statuswin, 70, 0);
+    glk.put_string("Moves: 1");
+    glk.set_window(&mainwin);
+    glk.put_string("Room\n>");
+    assert_eq!(Some(vec![format!(" Room{}Moves: 1  ", " ".repeat(65))]), glk.grid(&statuswin));
+    assert_eq!(None, glk.grid(&mainwin));
+
+    glk.set_screen_size(40, 10);
+    glk.request_line_event(&mainwin, (0,vec![0; 10].into_boxed_slice()), 0);
+    let event = glk.select();
+    assert_eq!(glk::evtype_Arrange, event.evtype());
+    assert_eq!((40,1), glk.window_get_size(&statuswin));
+    assert_eq!((40,9), glk.window_get_size(&mainwin));
+    assert_eq!(Some(vec![format!(" Room{}", " ".repeat(35))]), glk.grid(&statuswin));
+
+    let event = glk.select();
+    assert_eq!(glk::evtype_LineInput, event.evtype());
+    assert!(event.win() == mainwin);
+}
+
+#[test]
+fn split_and_close() {
+    let mut glk = GlkTest::new(vec![]);
+    glk.set_screen_size(60, 20);
+    assert_eq!(glk::evtype_None, glk.select().evtype());
+    let mainwin = glk.window_open(&glktest::WinId::null(), 0, 0, glk::wintype_TextBuffer, 1);
+    let mut sidewin = glk.window_open(&mainwin, glk::winmethod_Right | glk::winmethod_Proportional, 25, glk::wintype_TextGrid, 2);
+    assert_eq!((15,20), glk.window_get_size(&sidewin));
+    assert_eq!((45,20), glk.window_get_size(&mainwin));
+
+    let pair = glk.window_get_parent(&sidewin);
+    glk.window_set_arrangement(&pair, glk::winmethod_Right | glk::winmethod_Fixed, 10, &sidewin);
+    assert_eq!((10,20), glk.window_get_size(&sidewin));
+    let str = glk.window_get_stream(&sidewin);
+    glk.put_string_stream(&str, "one two three");
+    assert_eq!(Some("one two th".to_string()), glk.grid(&sidewin).map(|rows| rows[0].clone()));
+    assert_eq!(Some("ree       ".to_string()), glk.grid(&sidewin).map(|rows| rows[1].clone()));
+    glk.window_clear(&sidewin);
+    assert_eq!(Some(" ".repeat(10)), glk.grid(&sidewin).map(|rows| rows[1].clone()));
+
+    glk.window_close(&mut sidewin);
+    assert!(glk.window_get_root() == mainwin);
+    assert!(glk.window_get_parent(&mainwin).is_null());
+    assert_eq!((60,20), glk.window_get_size(&mainwin));
+    assert!(glk.window_iterate(&mainwin).0.is_null());
+}
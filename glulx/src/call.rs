@@ -168,6 +168,11 @@ pub fn ret<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, val: u32) -> Next {
         let frame_ptr = exec.state.frame_ptr;
         exec.state.stack.truncate(frame_ptr);
     }
+    pop_stub(exec, val)
+}
+
+// Pops the call stub on top of the stack and stores val where it says.
+pub fn pop_stub<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, val: u32) -> Next {
     trace::call_stub(exec);
     match exec.state.stack.pop() {
         None => return NEXT_QUIT,
@@ -1,8 +1,10 @@
 use rand;
 use std::cmp::min;
+use std::fs::{self,File};
+use std::path::PathBuf;
 use glk::Glk;
 
-use super::{accel,call,gestalt,glk_dispatch,iosys,malloc,opcode,operand,search,state};
+use super::{accel,call,gestalt,glk_dispatch,iosys,malloc,opcode,operand,save,search,state};
 use super::state::{read_u8,read_u16,read_u32,write_u16,write_u32,State};
 
 #[derive(Eq,PartialEq)]
@@ -11,15 +13,21 @@ pub struct Next(pub u64);
 pub const NEXT_EXEC: Next = Next(0x100000000);
 pub const NEXT_QUIT: Next = Next(0x200000000);
 
-const MAX_UNDO_DEPTH: usize = 2;
+// Where a suspendable VM hands control back to the host.
+pub enum Suspend<W> {
+    Select(usize),
+    LineRequest(W),
+}
 
 pub struct Execute<'a,G: Glk<'a>> {
     pub state: State,
 
     pub undo_state: Vec<state::UndoState<operand::Mode>>,
+    pub undo_depth: usize,
     pub protected_range: (usize,usize),
     pub protected_tmp: Vec<u8>,
     pub rng: rand::XorShiftRng,
+    pub seeded: bool,
     pub stringtbl: usize,
     pub call_args: Vec<u32>,
     pub iosys: iosys::IOSys,
@@ -33,25 +41,36 @@ pub struct Execute<'a,G: Glk<'a>> {
 
     pub glk: G,
 
+    pub suspendable: bool,
+    pub suspended: Option<Suspend<G::WinId>>,
+
+    pub memory_limit: usize,
+    pub strict: bool,
+    // The autosave file, and whether it is still to be resumed from.
+    pub autosave: Option<PathBuf>,
+    pub resume: bool,
+
     pub trace: super::trace::Trace,
 }
 
 impl<'a,G: Glk<'a>> Execute<'a,G> {
-    pub fn new(state: State, glk: G) -> Self {
+    pub fn new(state: State, glk: G, options: super::Options<'a,G>) -> Self {
         let stringtbl = read_u32(&state.rom, 28) as usize;
         let ram_start = read_u32(&state.rom, 8) as usize;
         let mut exec = Execute{
             state: state,
 
             undo_state: Vec::new(),
+            undo_depth: options.undo_depth,
             protected_range: (0,0),
             protected_tmp: Vec::new(),
-            rng: rand::SeedableRng::from_seed(rand::random()),
+            rng: rand::SeedableRng::from_seed(if options.random_seed == 0 { rand::random() } else { [options.random_seed; 4] }),
+            seeded: options.random_seed != 0,
             stringtbl: stringtbl,
             call_args: Vec::new(),
             iosys: iosys::IOSys::new(),
             accel: accel::Accel::new(),
-            dispatch: glk_dispatch::Dispatch::new(),
+            dispatch: glk_dispatch::Dispatch::new(options.selector_handlers, options.unknown_selector_handler),
 
             ram_start: ram_start,
             frame_locals: 0,
@@ -59,7 +78,15 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
 
             glk: glk,
 
-            trace: super::trace::Trace::new(),
+            suspendable: options.suspendable,
+            suspended: None,
+
+            memory_limit: options.memory_limit,
+            strict: options.strict,
+            resume: options.autosave.as_ref().map(|path| path.exists()).unwrap_or(false),
+            autosave: options.autosave,
+
+            trace: super::trace::Trace::new(options.trace),
         };
         exec.start();
         exec
@@ -399,18 +426,22 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             },
             opcode::STREAMCHAR => {
                 let l1 = self.l1();
+                self.glk.set_call_address(opcode_addr as u32);
                 return iosys::streamchar(self, l1 as u8, false);
             },
             opcode::STREAMNUM => {
                 let l1 = self.l1();
+                self.glk.set_call_address(opcode_addr as u32);
                 return iosys::streamnum(self, l1 as i32, false);
             },
             opcode::STREAMSTR => {
                 let l1 = self.l1();
+                self.glk.set_call_address(opcode_addr as u32);
                 return iosys::streamstr(self, l1 as usize, false);
             },
             opcode::STREAMUNICHAR => {
                 let l1 = self.l1();
+                self.glk.set_call_address(opcode_addr as u32);
                 return iosys::streamunichar(self, l1, false);
             },
             opcode::GESTALT => {
@@ -430,8 +461,13 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             opcode::SETMEMSIZE => {
                 let (l1,s1) = self.l1s1();
                 let end_mem = read_u32(&self.state.mem, 16);
+                if self.strict && (l1 % 256 != 0 || l1 < end_mem) {
+                    panic!("{:x}: invalid setmemsize {:x}", opcode_addr, l1);
+                }
                 if !self.state.heap.is_empty() && l1 < end_mem {
                     s1.store(self, 1);
+                } else if self.memory_limit != 0 && l1 as usize > self.memory_limit {
+                    s1.store(self, 1);
                 } else {
                     self.state.mem.resize(l1 as usize, 0);
                     s1.store(self, 0);
@@ -455,13 +491,20 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
                 s1.store(self, val);
             },
             opcode::SETRANDOM => {
-                use rand::SeedableRng;
+                use rand::{Rng,SeedableRng};
                 let l1 = self.l1();
-                self.rng.reseed(if l1 == 0 { rand::random() } else { [l1; 4] });
+                let seed = if l1 != 0 {
+                    [l1; 4]
+                } else if self.seeded {
+                    [self.rng.next_u32(),self.rng.next_u32(),self.rng.next_u32(),self.rng.next_u32() | 1]
+                } else {
+                    rand::random()
+                };
+                self.rng.reseed(seed);
             },
             opcode::QUIT => {
                 super::trace::frame(self);
-                return NEXT_QUIT;
+                return self.quit();
             },
             opcode::VERIFY => {
                 let s1 = self.s1();
@@ -476,6 +519,7 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             },
             opcode::SAVE => {
                 let (l1,s1) = self.l1s1();
+                self.glk.set_call_address(opcode_addr as u32);
                 match iosys::save(self, l1) {
                     Ok(()) => s1.store(self, 0),
                     _ => s1.store(self, 1),
@@ -483,6 +527,7 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             },
             opcode::RESTORE => {
                 let (l1,s1) = self.l1s1();
+                self.glk.set_call_address(opcode_addr as u32);
                 match iosys::restore(self, l1) {
                     Ok(()) => {
                         return Next(0xffffffff);
@@ -492,6 +537,15 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             },
             opcode::SAVEUNDO => {
                 let s1 = self.s1();
+                if self.autosave.is_some() {
+                    if self.resume {
+                        self.resume = false;
+                        if let Some(next) = self.resume_autosave() {
+                            return next;
+                        }
+                    }
+                    self.autosave(s1);
+                }
                 let mut result = 1;
                 for mut undo_state in &mut self.undo_state {
                     if undo_state.save(&self.state, s1) {
@@ -499,8 +553,8 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
                         break;
                     }
                 }
-                if result != 0 {
-                    let mut undo_state = if self.undo_state.len() < MAX_UNDO_DEPTH {
+                if result != 0 && self.undo_depth > 0 {
+                    let mut undo_state = if self.undo_state.len() < self.undo_depth {
                         state::UndoState::new()
                     } else {
                         self.undo_state.remove(0)
@@ -537,10 +591,16 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             },
             opcode::GLK => {
                 let (l1,l2,s1) = self.l1l2s1();
+                self.glk.set_call_address(opcode_addr as u32);
                 self.call_args.clear();
                 for _ in 0 .. l2 {
                     self.call_args.push(self.state.stack.pop().unwrap());
                 }
+                // glk_exit ends the game as @quit does, so that run returns
+                // and Glk carries on for the host.
+                if l1 == super::glk_selector::EXIT {
+                    return self.quit();
+                }
                 let result = glk_dispatch::dispatch(self, l1);
                 s1.store(self, result);
             },
@@ -638,7 +698,7 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
             },
             opcode::MALLOC => {
                 let (l1,s1) = self.l1s1();
-                let addr = malloc::malloc(&mut self.state, l1 as usize);
+                let addr = malloc::malloc(&mut self.state, l1 as usize, self.memory_limit);
                 s1.store(self, addr as u32);
             },
             opcode::MFREE => {
@@ -835,6 +895,41 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
         NEXT_EXEC
     }
 
+    // Writes a save file at a @saveundo, with a call stub for its store
+    // operand like @save.
+    fn autosave(&mut self, s1: operand::Mode) {
+        let pc = self.state.pc;
+        let (dest_type,dest_addr) = s1.result_dest(self);
+        call::push_stub(self, dest_type, dest_addr);
+        if let Some(ref path) = self.autosave {
+            if let Ok(mut file) = File::create(path) {
+                let _ = save::write(&self.state, &mut file);
+            }
+        }
+        let len = self.state.stack.len();
+        self.state.stack.truncate(len - 4);
+        self.state.pc = pc;
+    }
+
+    // Continues from the autosave file as if its @saveundo had just been
+    // undone, so that the game finds its Glk objects again.
+    fn resume_autosave(&mut self) -> Option<Next> {
+        let mut backup = state::UndoState::new();
+        backup.save(&self.state, ());
+        self.state.heap.clear();
+        self.state.heap_ptr = 0;
+        let result = match self.autosave {
+            Some(ref path) => File::open(path).and_then(|mut file| save::read(&mut self.state, &mut file)),
+            None => Ok(()),
+        };
+        if result.is_err() {
+            backup.restore(&mut self.state);
+            return None;
+        }
+        self.undo_state.clear();
+        Some(call::pop_stub(self, 0xffffffff))
+    }
+
     fn start(&mut self) {
         let start_func = read_u32(&self.state.rom, 24) as usize;
         self.call_args.clear();
@@ -1063,6 +1158,13 @@ impl<'a,G: Glk<'a>> Execute<'a,G> {
     fn tick(&mut self) {
         self.glk.tick();
     }
+
+    fn quit(&mut self) -> Next {
+        if let Some(ref path) = self.autosave {
+            let _ = fs::remove_file(path);
+        }
+        NEXT_QUIT
+    }
 }
 
 fn to_f32(val: u32) -> f32 {
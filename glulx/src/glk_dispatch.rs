@@ -1,9 +1,17 @@
 use glk::{Glk,DateType,EventType,IdType,TimeValType};
 
 use super::glk_selector;
-use super::execute::Execute;
+use super::execute::{Execute,Suspend};
 use super::state::{cstr,read_arr8,read_arr32,read_u32,write_arr8,write_arr32,write_u32};
 
+// Called with the Glk implementation, the selector, the call arguments,
+// and VM memory.  The return value is passed back to the game.
+pub type SelectorHandler<'a,G> = Box<FnMut(&mut G, u32, &[u32], &mut [u8]) -> u32 + Send + 'a>;
+
+// Called with the selector and call arguments of any selector that is
+// neither built in nor covered by a registered handler.
+pub type UnknownSelectorHandler<'a> = Box<FnMut(u32, &[u32]) + Send + 'a>;
+
 pub struct Dispatch<'a,G: Glk<'a>> {
     winids: Registry<G::WinId>,
     strids: Registry<G::StrId>,
@@ -12,10 +20,13 @@ pub struct Dispatch<'a,G: Glk<'a>> {
 
     buffer8: Option<Vec<u8>>,
     buffer32: Option<Vec<u32>>,
+
+    selector_handlers: Vec<(u32,u32,SelectorHandler<'a,G>)>,
+    unknown_selector_handler: Option<UnknownSelectorHandler<'a>>,
 }
 
 impl<'a,G: Glk<'a>> Dispatch<'a,G> {
-    pub fn new() -> Self {
+    pub fn new(selector_handlers: Vec<(u32,u32,SelectorHandler<'a,G>)>, unknown_selector_handler: Option<UnknownSelectorHandler<'a>>) -> Self {
         Dispatch{
             winids: Registry::new(),
             strids: Registry::new(),
@@ -24,6 +35,9 @@ impl<'a,G: Glk<'a>> Dispatch<'a,G> {
 
             buffer8: None,
             buffer32: None,
+
+            selector_handlers,
+            unknown_selector_handler,
         }
     }
 
@@ -473,6 +487,10 @@ pub fn dispatch<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, glksel: u32) -> u32 {
         },
         glk_selector::SELECT => {
             let addr = exec.call_args[0] as usize;
+            if exec.suspendable {
+                exec.suspended = Some(Suspend::Select(addr));
+                return 0;
+            }
             let event = exec.glk.select();
             write_event(exec, addr, event);
             0
@@ -490,6 +508,9 @@ pub fn dispatch<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, glksel: u32) -> u32 {
             let initlen = exec.call_args[3];
             let buf = read_arrayref8(exec, bufaddr, buflen);
             exec.glk.request_line_event(&win, (bufaddr as u32,buf), initlen);
+            if exec.suspendable {
+                exec.suspended = Some(Suspend::LineRequest(win));
+            }
             0
         },
         glk_selector::CANCEL_LINE_EVENT => {
@@ -579,6 +600,17 @@ pub fn dispatch<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, glksel: u32) -> u32 {
             exec.glk.window_set_background_color(&win, color);
             0
         },
+        glk_selector::IMAGE_DRAW_SCALED_EXT => {
+            let win = exec.dispatch.winids.get(exec.call_args[0]);
+            let image = exec.call_args[1];
+            let val1 = exec.call_args[2] as i32;
+            let val2 = exec.call_args[3] as i32;
+            let width = exec.call_args[4];
+            let height = exec.call_args[5];
+            let imagerule = exec.call_args[6];
+            let maxwidth = exec.call_args[7];
+            if exec.glk.image_draw_scaled_ext(&win, image, val1, val2, width, height, imagerule, maxwidth) { 1 } else { 0 }
+        },
         glk_selector::SCHANNEL_ITERATE => {
             let chan = exec.dispatch.schanids.get(exec.call_args[0]);
             let rockaddr = exec.call_args[1] as usize;
@@ -841,6 +873,9 @@ pub fn dispatch<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, glksel: u32) -> u32 {
             let initlen = exec.call_args[3];
             let buf = read_arrayref32(exec, bufaddr, buflen);
             exec.glk.request_line_event_uni(&win, (bufaddr as u32,buf), initlen);
+            if exec.suspendable {
+                exec.suspended = Some(Suspend::LineRequest(win));
+            }
             0
         },
         glk_selector::SET_ECHO_LINE_EVENT => {
@@ -928,8 +963,47 @@ pub fn dispatch<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, glksel: u32) -> u32 {
             let date = read_date(exec, dateaddr);
             exec.glk.date_to_simple_time_local(&date, factor) as u32
         },
-        _ => 0,
+        glk_selector::GARGLK_SET_ZCOLORS => {
+            let fg = exec.call_args[0];
+            let bg = exec.call_args[1];
+            exec.glk.garglk_set_zcolors(fg, bg);
+            0
+        },
+        glk_selector::GARGLK_SET_ZCOLORS_STREAM => {
+            let str = exec.dispatch.strids.get(exec.call_args[0]);
+            let fg = exec.call_args[1];
+            let bg = exec.call_args[2];
+            exec.glk.garglk_set_zcolors_stream(&str, fg, bg);
+            0
+        },
+        glk_selector::GARGLK_SET_REVERSEVIDEO => {
+            let reverse = exec.call_args[0];
+            exec.glk.garglk_set_reversevideo(reverse);
+            0
+        },
+        glk_selector::GARGLK_SET_REVERSEVIDEO_STREAM => {
+            let str = exec.dispatch.strids.get(exec.call_args[0]);
+            let reverse = exec.call_args[1];
+            exec.glk.garglk_set_reversevideo_stream(&str, reverse);
+            0
+        },
+        _ => dispatch_extension(exec, glksel),
+    }
+}
+
+fn dispatch_extension<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, glksel: u32) -> u32 {
+    for &mut (first,last,ref mut handler) in exec.dispatch.selector_handlers.iter_mut() {
+        if first <= glksel && glksel <= last {
+            return handler(&mut exec.glk, glksel, &exec.call_args, &mut exec.state.mem);
+        }
+    }
+    if let Some(ref mut handler) = exec.dispatch.unknown_selector_handler {
+        handler(glksel, &exec.call_args);
+    }
+    if exec.strict {
+        panic!("{:x}: unknown glk selector {:x}", exec.state.pc, glksel);
     }
+    0
 }
 
 fn read_arrayref8<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, addr: usize, len: usize) -> Box<[u8]> {
@@ -1020,7 +1094,7 @@ fn write_stream_result<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, addr: usize, str
     }
 }
 
-fn write_event<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, addr: usize, mut event: G::Event) {
+pub fn write_event<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, addr: usize, mut event: G::Event) {
     let mut arr = exec.dispatch.get_buffer32(4);
     arr[0] = event.evtype();
     arr[1] = exec.dispatch.winids.get_index(event.win());
@@ -72,6 +72,7 @@ pub const WINDOW_FLOW_BREAK: u32 = 0x00e8;
 pub const WINDOW_ERASE_RECT: u32 = 0x00e9;
 pub const WINDOW_FILL_RECT: u32 = 0x00ea;
 pub const WINDOW_SET_BACKGROUND_COLOR: u32 = 0x00eb;
+pub const IMAGE_DRAW_SCALED_EXT: u32 = 0x00ec;
 pub const SCHANNEL_ITERATE: u32 = 0x00f0;
 pub const SCHANNEL_GET_ROCK: u32 = 0x00f1;
 pub const SCHANNEL_CREATE: u32 = 0x00f2;
@@ -121,3 +122,7 @@ pub const DATE_TO_TIME_UTC: u32 = 0x016c;
 pub const DATE_TO_TIME_LOCAL: u32 = 0x016d;
 pub const DATE_TO_SIMPLE_TIME_UTC: u32 = 0x016e;
 pub const DATE_TO_SIMPLE_TIME_LOCAL: u32 = 0x016f;
+pub const GARGLK_SET_ZCOLORS: u32 = 0x1100;
+pub const GARGLK_SET_ZCOLORS_STREAM: u32 = 0x1101;
+pub const GARGLK_SET_REVERSEVIDEO: u32 = 0x1102;
+pub const GARGLK_SET_REVERSEVIDEO_STREAM: u32 = 0x1103;
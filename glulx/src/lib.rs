@@ -19,11 +19,101 @@ mod search;
 mod state;
 mod trace;
 
+pub use glk_dispatch::{SelectorHandler,UnknownSelectorHandler};
+
+pub struct Options<'a,G: Glk<'a>> {
+    selector_handlers: Vec<(u32,u32,SelectorHandler<'a,G>)>,
+    unknown_selector_handler: Option<UnknownSelectorHandler<'a>>,
+    suspendable: bool,
+    random_seed: u32,
+    undo_depth: usize,
+    memory_limit: usize,
+    strict: bool,
+    autosave: Option<std::path::PathBuf>,
+    trace: Option<std::fs::File>,
+}
+
+impl<'a,G: Glk<'a>> Options<'a,G> {
+    pub fn new() -> Self {
+        Options{
+            selector_handlers: Vec::new(),
+            unknown_selector_handler: None,
+            suspendable: false,
+            random_seed: 0,
+            undo_depth: 2,
+            memory_limit: 0,
+            strict: false,
+            autosave: None,
+            trace: None,
+        }
+    }
+
+    // Handle Glk selectors from first to last, inclusive, that are not
+    // built in.  Earlier registrations take precedence.
+    pub fn add_selector_handler(&mut self, first: u32, last: u32, handler: SelectorHandler<'a,G>) {
+        self.selector_handlers.push((first,last,handler));
+    }
+
+    pub fn set_unknown_selector_handler(&mut self, handler: UnknownSelectorHandler<'a>) {
+        self.unknown_selector_handler = Some(handler);
+    }
+
+    // A nonzero seed makes @random repeatable, as if the game started with
+    // @setrandom seed, and @setrandom 0 then reseeds from the same
+    // sequence.
+    pub fn set_random_seed(&mut self, seed: u32) {
+        self.random_seed = seed;
+    }
+
+    // The number of @saveundo states kept.  0 makes @saveundo fail.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+    }
+
+    // The most memory the game can have, in bytes, or 0 for no limit.
+    // @setmemsize and @malloc fail beyond it, and games whose ENDMEM is
+    // larger don't start.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory_limit = limit;
+    }
+
+    // Stop with an error on unknown Glk selectors and invalid
+    // @setmemsize sizes instead of carrying on.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // Save the game to the file at each @saveundo, and resume from it at
+    // the first @saveundo when it already exists, which is after the game
+    // has set up its windows.  The file is removed when the game quits.
+    pub fn set_autosave(&mut self, path: std::path::PathBuf) {
+        self.autosave = Some(path);
+    }
+
+    // Write an instruction trace to the file.  Only debug builds trace,
+    // and GLULX_TRACE names the file when this isn't set.
+    pub fn set_trace(&mut self, out: std::fs::File) {
+        self.trace = Some(out);
+    }
+}
+
+fn new_state<'a,G: Glk<'a>, R: std::io::Read>(r: &mut R, options: &Options<'a,G>) -> Result<state::State,std::io::Error> {
+    let state = state::State::new(r)?;
+    if options.memory_limit != 0 && state::read_u32(&state.rom, 16) as usize > options.memory_limit {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "ENDMEM exceeds the memory limit"));
+    }
+    Ok(state)
+}
+
 pub fn run<'a,G: Glk<'a>, R: std::io::Read>(glk: G, r: &mut R) -> (G,Result<(),std::io::Error>) {
-    match state::State::new(r) {
+    run_with_options(glk, r, Options::new())
+}
+
+pub fn run_with_options<'a,G: Glk<'a>, R: std::io::Read>(glk: G, r: &mut R, options: Options<'a,G>) -> (G,Result<(),std::io::Error>) {
+    match new_state(r, &options) {
         Err(cause) => (glk,Err(cause)),
         Ok(state) => {
-            let mut exec = execute::Execute::new(state, glk);
+            let mut exec = execute::Execute::new(state, glk, options);
             let mut next = execute::NEXT_EXEC;
             while next != execute::NEXT_QUIT {
                 next = exec.next(next);
@@ -32,3 +122,67 @@ pub fn run<'a,G: Glk<'a>, R: std::io::Read>(glk: G, r: &mut R) -> (G,Result<(),s
         },
     }
 }
+
+pub enum Status<W> {
+    // The game called glk_select.  Resume with the event.
+    Select,
+    // The game requested line input in the window.  Continue with run.
+    LineRequest(W),
+    Quit,
+}
+
+// A VM that returns control to the host instead of blocking in
+// glk_select, for hosts driven by an event loop.
+pub struct Vm<'a,G: Glk<'a>> {
+    exec: execute::Execute<'a,G>,
+    next: execute::Next,
+    select_addr: Option<usize>,
+}
+
+impl<'a,G: Glk<'a>> Vm<'a,G> {
+    pub fn new<R: std::io::Read>(glk: G, r: &mut R, mut options: Options<'a,G>) -> Result<Self,std::io::Error> {
+        let state = new_state(r, &options)?;
+        options.suspendable = true;
+        Ok(Vm{
+            exec: execute::Execute::new(state, glk, options),
+            next: execute::NEXT_EXEC,
+            select_addr: None,
+        })
+    }
+
+    pub fn glk(&mut self) -> &mut G {
+        &mut self.exec.glk
+    }
+
+    pub fn into_glk(self) -> G {
+        self.exec.glk
+    }
+
+    // Run until the game waits for input or quits.
+    pub fn run(&mut self) -> Status<G::WinId> {
+        if self.select_addr.is_some() {
+            return Status::Select;
+        }
+        while self.next != execute::NEXT_QUIT {
+            let next = std::mem::replace(&mut self.next, execute::NEXT_QUIT);
+            self.next = self.exec.next(next);
+            match self.exec.suspended.take() {
+                Some(execute::Suspend::Select(addr)) => {
+                    self.select_addr = Some(addr);
+                    return Status::Select;
+                },
+                Some(execute::Suspend::LineRequest(win)) => return Status::LineRequest(win),
+                None => (),
+            }
+        }
+        Status::Quit
+    }
+
+    // Complete the pending glk_select with the event and run on.
+    pub fn resume(&mut self, event: G::Event) -> Status<G::WinId> {
+        if let Some(addr) = self.select_addr.take() {
+            glk_dispatch::write_event(&mut self.exec, addr, event);
+        }
+        self.run()
+    }
+}
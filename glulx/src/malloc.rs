@@ -1,10 +1,14 @@
 use super::state::{MemoryBlock,State};
 
-pub fn malloc(state: &mut State, size: usize) -> usize {
+// Fails with 0 when the memory would grow past a nonzero limit.
+pub fn malloc(state: &mut State, size: usize, limit: usize) -> usize {
     if size == 0 {
         0
     } else if state.heap.is_empty() {
         let addr = state.mem.len();
+        if limit != 0 && addr + size > limit {
+            return 0;
+        }
         state.heap_ptr = addr;
         state.mem.resize(addr + size, 0);
         state.heap.push(MemoryBlock{ addr, size });
@@ -21,6 +25,9 @@ pub fn malloc(state: &mut State, size: usize) -> usize {
             }
             addr = block.addr + block.size;
         }
+        if limit != 0 && addr + size > limit.max(state.mem.len()) {
+            return 0;
+        }
         state.heap.insert(index, MemoryBlock{ addr, size });
         if addr + size > state.mem.len() {
             state.mem.resize(addr + size, 0);
@@ -2,6 +2,7 @@ pub use self::internal::{Trace,opcode,iosys,operand,frame,push_call_stub,call_st
 
 #[cfg(not(debug_assertions))]
 mod internal {
+    use std::fs::File;
     use glk::Glk;
     use super::super::execute::Execute;
     use super::super::operand::Mode;
@@ -10,7 +11,7 @@ mod internal {
     }
 
     impl Trace {
-        pub fn new() -> Self {
+        pub fn new(_out: Option<File>) -> Self {
             Trace{}
         }
     }
@@ -59,8 +60,10 @@ mod internal {
     }
 
     impl Trace {
-        pub fn new() -> Self {
-            let out = if let Ok(file) = env::var("GLULX_TRACE") {
+        pub fn new(out: Option<File>) -> Self {
+            let out = if out.is_some() {
+                out
+            } else if let Ok(file) = env::var("GLULX_TRACE") {
                 if let Ok(f) = File::create(file) {
                     Some(f)
                 } else {
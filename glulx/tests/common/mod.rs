@@ -1,10 +1,11 @@
 use std::env::current_exe;
 use std::fs::File;
-use std::io::{Error,ErrorKind,Read,Result};
+use std::io::{Error,ErrorKind,Result};
 
+use glk::Glk;
+use glk::blorb::Blorb;
 use glktest;
 use glulx;
-use iff;
 
 fn testdata(name: &'static str) -> Result<File> {
     let mut path = current_exe()?;
@@ -30,22 +31,44 @@ pub fn run_test<'a>(ulx_file: &'static str, test: Vec<(glktest::TestOutput<'a>,&
 
 #[allow(dead_code)]
 pub fn run_blorb<'a>(blorb_file: &'static str, test: Vec<(glktest::TestOutput<'a>,&'a str)>) -> Result<String> {
-    let mut blorb = testdata(blorb_file)?;
-    let mut buf = vec![];
-    blorb.read_to_end(&mut buf)?;
-    if let iff::Chunk::Envelope { envelope_id:_, id, chunks } = iff::Chunk::new(&buf)? {
-        if id == From::from(b"IFRS") {
-            for chunk in chunks {
-                if let iff::Chunk::Data { id, mut data } = chunk {
-                    if id == From::from(b"GLUL") {
-                        let glk = glktest::GlkTest::new(test);
-                        let (glk,result) = glulx::run(glk, &mut data);
-                        result?;
-                        return Ok(glk.output());
-                    }
-                }
-            }
-        }
+    let blorb = Blorb::read(&mut testdata(blorb_file)?)?;
+    let ulx = match blorb.exec() {
+        Some(ref exec) if &exec.chunk_type == b"GLUL" => exec.data.to_vec(),
+        _ => return Err(Error::new(ErrorKind::InvalidData, "invalid file")),
+    };
+    let mut glk = glktest::GlkTest::new(test);
+    glk.set_resource_map(blorb);
+    let (glk,result) = glulx::run(glk, &mut &ulx[..]);
+    result?;
+    Ok(glk.output())
+}
+
+// Wrap code for a single C1 start function into a minimal game file.
+#[allow(dead_code)]
+pub fn image(code: &[u8]) -> Vec<u8> {
+    let ram_start = (48 + 3 + code.len() + 255) / 256 * 256;
+    let mut rom = vec![0; ram_start + 256];
+    rom[0..4].copy_from_slice(b"Glul");
+    put_u32(&mut rom, 4, 0x00030102);
+    put_u32(&mut rom, 8, ram_start as u32);
+    let ext_start = rom.len() as u32;
+    put_u32(&mut rom, 12, ext_start);
+    put_u32(&mut rom, 16, ext_start);
+    put_u32(&mut rom, 20, 0x1000);
+    put_u32(&mut rom, 24, 48);
+    rom[48] = 0xc1;
+    rom[51..51+code.len()].copy_from_slice(code);
+    let mut sum = 0u32;
+    for i in 0 .. rom.len()/4 {
+        sum = sum.wrapping_add((rom[4*i] as u32) << 24 | (rom[4*i+1] as u32) << 16 | (rom[4*i+2] as u32) << 8 | rom[4*i+3] as u32);
     }
-    Err(Error::new(ErrorKind::InvalidData, "invalid file"))
+    put_u32(&mut rom, 32, sum);
+    rom
+}
+
+fn put_u32(rom: &mut [u8], addr: usize, val: u32) {
+    rom[addr] = (val >> 24) as u8;
+    rom[addr+1] = (val >> 16) as u8;
+    rom[addr+2] = (val >> 8) as u8;
+    rom[addr+3] = val as u8;
 }
@@ -0,0 +1,36 @@
+extern crate glk;
+extern crate glktest;
+extern crate glulx;
+
+use glktest::GarglkText::{ReverseVideo,ZColors};
+
+mod common;
+
+const CODE: &'static [u8] = &[
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x03,                           // copy 3 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x81, 0x30, 0x11, 0x08, 0x23, 0x05,         // glk $23 5 sp (window_open)
+    0x81, 0x30, 0x11, 0x00, 0x2f, 0x01,         // glk $2f 1 0 (set_window)
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x82, 0x11, 0x00,                     // copy $1100 sp
+    0x81, 0x30, 0x11, 0x08, 0x04, 0x02,         // glk $4 2 sp (gestalt)
+    0x10, 0x18, 0x08, 0x30,                     // add sp 48 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0x80, 0x01,   // glk $80 1 0 (put_char)
+    0x40, 0x81, 0xff,                           // copy -1 sp
+    0x40, 0x83, 0x00, 0xff, 0x00, 0x00,         // copy $ff0000 sp
+    0x81, 0x30, 0x12, 0x00, 0x11, 0x00, 0x02,   // glk $1100 2 0 (garglk_set_zcolors)
+    0x40, 0x81, 0x01,                           // copy 1 sp
+    0x81, 0x30, 0x12, 0x00, 0x11, 0x02, 0x01,   // glk $1102 1 0 (garglk_set_reversevideo)
+    0x81, 0x20,                                 // quit
+];
+
+#[test]
+fn test() {
+    let (glk,result) = glulx::run(glktest::GlkTest::new(vec![]), &mut &common::image(CODE)[..]);
+    assert!(result.is_ok());
+    assert_eq!(&[ZColors(1,0xff0000,glk::zcolor_Default),ReverseVideo(1,1)], glk.garglk_text());
+    assert_eq!("1", glk.output());
+}
@@ -1,6 +1,6 @@
+extern crate glk;
 extern crate glktest;
 extern crate glulx;
-extern crate iff;
 
 use glktest::TestOutput::{Check,Match};
 
@@ -0,0 +1,79 @@
+extern crate glk;
+extern crate glktest;
+extern crate glulx;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+mod common;
+
+const CODE: &'static [u8] = &[
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x03,                           // copy 3 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x81, 0x30, 0x11, 0x08, 0x23, 0x05,         // glk $23 5 sp (window_open)
+    0x81, 0x30, 0x11, 0x00, 0x2f, 0x01,         // glk $2f 1 0 (set_window)
+    0x81, 0x49, 0x01, 0x02,                     // setiosys 2 0
+    0x81, 0x25, 0x08,                           // saveundo sp
+    0x71, 0x08,                                 // streamnum sp
+    0x70, 0x01, 0x20,                           // streamchar ' '
+    0x81, 0x03, 0x83, 0x00, 0x01, 0x00, 0x00,   // setmemsize $10000 sp
+    0x71, 0x08,                                 // streamnum sp
+    0x31, 0x00,                                 // return 0
+];
+
+fn run<'a>(code: &[u8], options: glulx::Options<'a,glktest::GlkTest<'a>>) -> String {
+    let glk = glktest::GlkTest::new(vec![]);
+    let (glk,result) = glulx::run_with_options(glk, &mut &common::image(code)[..], options);
+    assert!(result.is_ok());
+    glk.output()
+}
+
+#[test]
+fn undo_depth_and_memory_limit() {
+    assert_eq!("0 0", run(CODE, glulx::Options::new()));
+    let mut options = glulx::Options::new();
+    options.set_undo_depth(0);
+    options.set_memory_limit(0x8000);
+    assert_eq!("1 1", run(CODE, options));
+
+    let mut options = glulx::Options::new();
+    options.set_memory_limit(0x100);
+    let (_,result) = glulx::run_with_options(glktest::GlkTest::new(vec![]), &mut &common::image(CODE)[..], options);
+    assert!(result.is_err());
+}
+
+#[test]
+#[should_panic(expected = "invalid setmemsize 10001")]
+fn strict() {
+    let mut code = CODE.to_vec();
+    code[41] = 0x01;
+    let mut options = glulx::Options::new();
+    options.set_strict(true);
+    run(&code, options);
+}
+
+#[test]
+fn autosave() {
+    let path = env::temp_dir().join(format!("glulx-autosave-{}.glksave", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let options = |path: &PathBuf| {
+        let mut options = glulx::Options::new();
+        options.set_autosave(path.clone());
+        options
+    };
+
+    assert_eq!("0 0", run(CODE, options(&path)));
+    assert!(path.exists());
+    assert_eq!("-1 0", run(CODE, options(&path)));
+
+    // A different game doesn't resume, and quitting removes the file.
+    let mut code = CODE.to_vec();
+    let len = code.len();
+    code[len-2 ..].copy_from_slice(&[0x81, 0x20]);  // quit
+    assert_eq!("0 0", run(&code, options(&path)));
+    assert!(!path.exists());
+}
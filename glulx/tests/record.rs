@@ -0,0 +1,92 @@
+extern crate glk;
+extern crate glktest;
+extern crate glulx;
+
+use glk::Glk;
+use glk::record::{RecordingGlk,ReplayGlk};
+use glk::session;
+use glktest::TestOutput::Match;
+
+mod common;
+
+const CODE: &'static [u8] = &[
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x03,                           // copy 3 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x81, 0x30, 0x11, 0x08, 0x23, 0x05,         // glk $23 5 sp (window_open)
+    0x81, 0x30, 0x11, 0x00, 0x2f, 0x01,         // glk $2f 1 0 (set_window)
+    0x40, 0x81, 0x3e,                           // copy '>' sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0x80, 0x01,   // glk $80 1 0 (put_char)
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x20,                           // copy 32 sp
+    0x40, 0x82, 0x01, 0x10,                     // copy $110 sp
+    0x40, 0x81, 0x01,                           // copy 1 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0xd0, 0x04,   // glk $d0 4 0 (request_line_event)
+    0x40, 0x82, 0x01, 0x00,                     // copy $100 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0xc0, 0x01,   // glk $c0 1 0 (select)
+    0x40, 0x8d, 0x08,                           // copy *($100+8) sp
+    0x40, 0x82, 0x01, 0x10,                     // copy $110 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0x84, 0x02,   // glk $84 2 0 (put_buffer)
+    0x81, 0x10, 0x81, 0x1a,                     // random 26 sp
+    0x10, 0x18, 0x08, 0x61,                     // add sp 'a' sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0x80, 0x01,   // glk $80 1 0 (put_char)
+    0x81, 0x20,                                 // quit
+];
+
+fn record() -> Vec<u8> {
+    let glk = RecordingGlk::new(glktest::GlkTest::new(vec![(Match(">"),"look")]), vec![], 1234);
+    let mut options = glulx::Options::new();
+    options.set_random_seed(1234);
+    let (glk,result) = glulx::run_with_options(glk, &mut &common::image(CODE)[..], options);
+    assert!(result.is_ok());
+    let (glk,recording) = glk.finish();
+    assert!(glk.output().starts_with("look"));
+    recording.unwrap()
+}
+
+fn replay(recording: &[u8], code: &[u8]) -> ReplayGlk {
+    let glk = ReplayGlk::new(recording).unwrap();
+    let mut options = glulx::Options::new();
+    options.set_random_seed(glk.seed());
+    let (glk,result) = glulx::run_with_options(glk, &mut &common::image(code)[..], options);
+    assert!(result.is_ok());
+    glk
+}
+
+#[test]
+fn record_and_replay() {
+    let recording = record();
+    assert_eq!(recording, record());
+    let text = String::from_utf8(recording.clone()).unwrap();
+    assert!(text.starts_with("seed 1234\n"));
+    assert!(text.contains(" window_open 0 0 0 3 0 -> 1\n"));
+    assert!(text.contains(" select -> 3 1 4 0 "));
+    assert_eq!(0, replay(&recording, CODE).remaining());
+}
+
+#[test]
+#[should_panic(expected = "replay diverged at call 3, pc $")]
+fn divergence() {
+    let recording = record();
+    let mut code = CODE.to_vec();
+    code[25] = b'?';
+    replay(&recording, &code);
+}
+
+#[test]
+fn exit() {
+    let mut glk = ReplayGlk::new(&b"seed 1\n10 exit ->\n"[..]).unwrap();
+    assert!(session::run(|| glk.exit()).is_ok());
+    let mut glk = ReplayGlk::new(&b"seed 1\n10 exit ->\n20 tick ->\n"[..]).unwrap();
+    let err = session::run(|| glk.exit()).unwrap_err();
+    assert!(err.to_string().starts_with("replay ended at call 1, but the recording has 1 more, starting with tick at pc $20"));
+}
+
+#[test]
+fn end_of_input() {
+    let mut glk = ReplayGlk::new(&b"seed 1\n10 put_char 97 ->\n"[..]).unwrap();
+    glk.put_char(b'a');
+    assert!(session::run(|| { glk.select(); Ok(()) }).is_ok());
+}
@@ -0,0 +1,37 @@
+extern crate glk;
+extern crate glktest;
+extern crate glulx;
+
+use std::sync::Mutex;
+
+mod common;
+
+const CODE: &'static [u8] = &[
+    0x40, 0x81, 0x07,                           // copy 7 sp
+    0x40, 0x81, 0x05,                           // copy 5 sp
+    0x81, 0x30, 0x12, 0x08, 0x12, 0x34, 0x02,   // glk $1234 2 sp
+    0x81, 0x30, 0x12, 0x00, 0x12, 0x35, 0x01,   // glk $1235 1 0
+    0x81, 0x30, 0x02, 0x00, 0x23, 0x45,         // glk $2345 0 0
+    0x81, 0x20,                                 // quit
+];
+
+#[test]
+fn test() {
+    let calls = Mutex::new(Vec::new());
+    let unknown = Mutex::new(Vec::new());
+    {
+        let mut options = glulx::Options::new();
+        options.add_selector_handler(0x1234, 0x12ff, Box::new(|_, sel, args: &[u32], mem: &mut [u8]| {
+            calls.lock().unwrap().push((sel,args.to_vec()));
+            assert_eq!(b"Glul", &mem[0..4]);
+            args.iter().sum()
+        }));
+        options.set_unknown_selector_handler(Box::new(|sel, args: &[u32]| {
+            unknown.lock().unwrap().push((sel,args.to_vec()));
+        }));
+        let (_,result) = glulx::run_with_options(glktest::GlkTest::new(vec![]), &mut &common::image(CODE)[..], options);
+        assert!(result.is_ok());
+    }
+    assert_eq!(vec![(0x1234,vec![5,7]),(0x1235,vec![12])], calls.into_inner().unwrap());
+    assert_eq!(vec![(0x2345,vec![])], unknown.into_inner().unwrap());
+}
@@ -0,0 +1,56 @@
+extern crate glk;
+extern crate glktest;
+extern crate glulx;
+
+use glk::Glk;
+use glktest::TestOutput::Match;
+use glulx::Status;
+
+mod common;
+
+const CODE: &'static [u8] = &[
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x03,                           // copy 3 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x81, 0x30, 0x11, 0x08, 0x23, 0x05,         // glk $23 5 sp (window_open)
+    0x81, 0x30, 0x11, 0x00, 0x2f, 0x01,         // glk $2f 1 0 (set_window)
+    0x40, 0x81, 0x3e,                           // copy '>' sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0x80, 0x01,   // glk $80 1 0 (put_char)
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x20,                           // copy 32 sp
+    0x40, 0x82, 0x01, 0x10,                     // copy $110 sp
+    0x40, 0x81, 0x01,                           // copy 1 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0xd0, 0x04,   // glk $d0 4 0 (request_line_event)
+    0x40, 0x82, 0x01, 0x00,                     // copy $100 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0xc0, 0x01,   // glk $c0 1 0 (select)
+    0x40, 0x8d, 0x08,                           // copy *($100+8) sp
+    0x40, 0x82, 0x01, 0x10,                     // copy $110 sp
+    0x81, 0x30, 0x12, 0x00, 0x00, 0x84, 0x02,   // glk $84 2 0 (put_buffer)
+    0x81, 0x20,                                 // quit
+];
+
+#[test]
+fn test() {
+    let glk = glktest::GlkTest::new(vec![(Match(">"),"look")]);
+    let mut vm = glulx::Vm::new(glk, &mut &common::image(CODE)[..], glulx::Options::new()).unwrap();
+    match vm.run() {
+        Status::LineRequest(_) => (),
+        _ => panic!("expected line request"),
+    }
+    match vm.run() {
+        Status::Select => (),
+        _ => panic!("expected select"),
+    }
+    match vm.run() {
+        Status::Select => (),
+        _ => panic!("expected select to still be pending"),
+    }
+    let event = vm.glk().select();
+    match vm.resume(event) {
+        Status::Quit => (),
+        _ => panic!("expected quit"),
+    }
+    assert_eq!("look", vm.into_glk().output());
+}
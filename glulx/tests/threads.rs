@@ -0,0 +1,82 @@
+extern crate glk;
+extern crate glktest;
+extern crate glulx;
+
+use std::thread;
+
+mod common;
+
+const CODE: &'static [u8] = &[
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x03,                           // copy 3 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x81, 0x30, 0x11, 0x08, 0x23, 0x05,         // glk $23 5 sp (window_open)
+    0x81, 0x30, 0x11, 0x00, 0x2f, 0x01,         // glk $2f 1 0 (set_window)
+    0x81, 0x49, 0x01, 0x02,                     // setiosys 2 0
+    0x81, 0x10, 0x82, 0x03, 0xe8,               // random 1000 sp
+    0x71, 0x08,                                 // streamnum sp
+    0x70, 0x01, 0x20,                           // streamchar ' '
+    0x81, 0x10, 0x82, 0x03, 0xe8,               // random 1000 sp
+    0x71, 0x08,                                 // streamnum sp
+    0x31, 0x00,                                 // return 0
+];
+
+const EXIT: &'static [u8] = &[
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x81, 0x03,                           // copy 3 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x40, 0x80,                                 // copy 0 sp
+    0x81, 0x30, 0x11, 0x08, 0x23, 0x05,         // glk $23 5 sp (window_open)
+    0x81, 0x30, 0x11, 0x00, 0x2f, 0x01,         // glk $2f 1 0 (set_window)
+    0x81, 0x49, 0x01, 0x02,                     // setiosys 2 0
+    0x70, 0x01, 0x61,                           // streamchar 'a'
+    0x81, 0x30, 0x01, 0x00, 0x01,               // glk $01 0 (exit)
+    0x70, 0x01, 0x62,                           // streamchar 'b'
+    0x31, 0x00,                                 // return 0
+];
+
+fn run(seed: u32) -> String {
+    let mut options = glulx::Options::new();
+    options.set_random_seed(seed);
+    let (glk,result) = glulx::run_with_options(glktest::GlkTest::new(vec![]), &mut &common::image(CODE)[..], options);
+    assert!(result.is_ok());
+    glk.output()
+}
+
+fn assert_send<T: Send>() {
+}
+
+#[test]
+fn send() {
+    assert_send::<glktest::GlkTest<'static>>();
+    assert_send::<glulx::Options<'static,glktest::GlkTest<'static>>>();
+    assert_send::<glulx::Vm<'static,glktest::GlkTest<'static>>>();
+}
+
+// Games on their own threads don't share any state, so each gives what
+// it gives alone.
+#[test]
+fn concurrent() {
+    let expected: Vec<String> = (1 .. 17).map(run).collect();
+    let threads: Vec<thread::JoinHandle<String>> = (1 .. 17).map(|seed| thread::spawn(move || run(seed))).collect();
+    let outputs: Vec<String> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+    assert_eq!(expected, outputs);
+}
+
+// A game calling glk_exit ends its own run, while the others go on.
+#[test]
+fn exit() {
+    let exits = thread::spawn(|| {
+        let (glk,result) = glulx::run(glktest::GlkTest::new(vec![]), &mut &common::image(EXIT)[..]);
+        assert!(result.is_ok());
+        glk.output()
+    });
+    let runs: Vec<thread::JoinHandle<String>> = (1 .. 9).map(|seed| thread::spawn(move || run(seed))).collect();
+    assert_eq!("a", exits.join().unwrap());
+    for (seed,thread) in (1 .. 9).zip(runs) {
+        assert_eq!(run(seed), thread.join().unwrap());
+    }
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::io::{Error,ErrorKind,Result};
+
+use super::glk::babel;
+
+// grue babel [--ifid | --format | --ifiction] STORY-FILE
+//
+// Prints the story's IFID, its format, or its iFiction record, as the
+// Treaty of Babel's babel tool does.  The IFID is the default.
+
+const USAGE: &'static str = "usage: grue babel [--ifid | --format | --ifiction] STORY-FILE";
+
+// Handle grue babel without starting Glk.
+pub fn babel(args: &[String]) -> Option<Result<()>> {
+    if args.len() < 2 || args[1] != "babel" {
+        return None;
+    }
+    let (option,path) = match args.len() {
+        3 => ("--ifid",&args[2]),
+        4 => (args[2].as_str(),&args[3]),
+        _ => return Some(Err(invalid_input(USAGE.to_string()))),
+    };
+    Some(print(option, path))
+}
+
+fn print(option: &str, path: &str) -> Result<()> {
+    let story = fs::read(path)?;
+    let text = match option {
+        "--ifid" => babel::ifid(&story),
+        "--format" => babel::format(&story).map(|format| format.name().to_string()),
+        "--ifiction" => babel::ifiction(&story).map(|ifiction| ifiction.trim_end().to_string()),
+        _ => return Err(invalid_input(USAGE.to_string())),
+    };
+    match text {
+        Some(text) => {
+            println!("{}", text);
+            Ok(())
+        },
+        None => Err(Error::new(ErrorKind::InvalidData, format!("{}: not a Glulx or Z-code story", path))),
+    }
+}
+
+fn invalid_input(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
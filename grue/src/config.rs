@@ -0,0 +1,266 @@
+// grue's settings, from its options and from a config file with the
+// settings for all stories, then a section for each story named by its
+// IFID:
+//
+//     undo = 10
+//
+//     [GLULX-1-161114-7A4A7A2E]
+//     seed = 42
+//     strict
+//
+// The names are those of the options without the dashes, and lines
+// starting with # are comments.  Options override the story's section,
+// which overrides the settings for all stories.  The file is --config
+// FILE, or else $XDG_CONFIG_HOME/grue/grue.conf or ~/.config/grue/grue.conf
+// if it exists.  The backend is chosen before the story is read, so it is
+// only taken from the settings for all stories.
+
+use std::env;
+use std::fs;
+use std::io::{Error,ErrorKind,Result};
+use std::path::PathBuf;
+
+#[derive(Clone,Default)]
+pub struct Settings {
+    pub backend: Option<String>,
+    pub undo: Option<usize>,
+    pub seed: Option<u32>,
+    pub trace: Option<String>,
+    pub transcript: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub autosave: Option<String>,
+    pub memory_limit: Option<usize>,
+    pub strict: Option<bool>,
+    pub config: Option<String>,
+}
+
+impl Settings {
+    // Parses the options in the program's arguments.  Returns the settings
+    // and the story file.
+    pub fn from_args(args: &[String]) -> Result<(Settings,Option<String>)> {
+        let mut settings = Settings::default();
+        let mut story = None;
+        let mut i = 1;
+        while i < args.len() {
+            let arg = &args[i];
+            i += 1;
+            if !arg.starts_with("--") {
+                story = Some(arg.clone());
+            } else if arg == "--strict" {
+                settings.set("strict", None).map_err(invalid_input)?;
+            } else {
+                let value = args.get(i).map(|value| value.as_str());
+                i += 1;
+                settings.set(&arg[2..], value).map_err(invalid_input)?;
+            }
+        }
+        Ok((settings,story))
+    }
+
+    // Sets a setting by its option name.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> ::std::result::Result<(),String> {
+        match (name,value) {
+            ("backend",Some(value)) => self.backend = Some(value.to_string()),
+            ("undo",Some(value)) => self.undo = Some(value.parse().map_err(|_| bad_value(name, value))?),
+            ("seed",Some(value)) => self.seed = Some(value.parse().map_err(|_| bad_value(name, value))?),
+            ("trace",Some(value)) => self.trace = Some(value.to_string()),
+            ("transcript",Some(value)) => self.transcript = Some(value.to_string()),
+            ("record",Some(value)) => self.record = Some(value.to_string()),
+            ("replay",Some(value)) => self.replay = Some(value.to_string()),
+            ("autosave",Some(value)) => self.autosave = Some(value.to_string()),
+            ("memory-limit",Some(value)) => self.memory_limit = Some(size(value).ok_or(bad_value(name, value))?),
+            ("strict",None) => self.strict = Some(true),
+            ("strict",Some(value)) => self.strict = Some(match value {
+                "yes" | "true" | "1" => true,
+                "no" | "false" | "0" => false,
+                _ => return Err(bad_value(name, value)),
+            }),
+            ("config",Some(value)) => self.config = Some(value.to_string()),
+            ("backend",None) | ("undo",None) | ("seed",None) | ("trace",None) | ("transcript",None)
+                | ("record",None) | ("replay",None) | ("autosave",None) | ("memory-limit",None) | ("config",None) => return Err(format!("{} needs a value", name)),
+            _ => return Err(format!("unknown setting {}", name)),
+        }
+        Ok(())
+    }
+
+    // These settings, or other's where these have none.
+    pub fn or(self, other: Settings) -> Settings {
+        Settings{
+            backend: self.backend.or(other.backend),
+            undo: self.undo.or(other.undo),
+            seed: self.seed.or(other.seed),
+            trace: self.trace.or(other.trace),
+            transcript: self.transcript.or(other.transcript),
+            record: self.record.or(other.record),
+            replay: self.replay.or(other.replay),
+            autosave: self.autosave.or(other.autosave),
+            memory_limit: self.memory_limit.or(other.memory_limit),
+            strict: self.strict.or(other.strict),
+            config: self.config.or(other.config),
+        }
+    }
+}
+
+// Reads the settings for all stories and for the story with the IFID
+// from the config file, or the default one if path is None.
+pub fn load(path: Option<&str>, ifid: Option<&str>) -> Result<Settings> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match default_path() {
+            Some(ref path) if path.exists() => path.clone(),
+            _ => return Ok(Settings::default()),
+        },
+    };
+    let text = fs::read_to_string(&path)?;
+    parse(&text, ifid).map_err(|msg| Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), msg)))
+}
+
+fn default_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("grue").join("grue.conf"))
+}
+
+pub fn parse(text: &str, ifid: Option<&str>) -> ::std::result::Result<Settings,String> {
+    let mut all = Settings::default();
+    let mut story = Settings::default();
+    let mut other = Settings::default();
+    let mut section: Option<&str> = None;
+    for (i,line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#") {
+            continue;
+        }
+        if line.starts_with("[") && line.ends_with("]") {
+            section = Some(line[1 .. line.len()-1].trim());
+            continue;
+        }
+        let (name,value) = match line.find('=') {
+            Some(index) => (line[.. index].trim(),Some(line[index+1 ..].trim())),
+            None => (line,None),
+        };
+        let settings = match (section,ifid) {
+            (None,_) => &mut all,
+            (Some(section),Some(ifid)) if section.eq_ignore_ascii_case(ifid) => &mut story,
+            _ => &mut other,
+        };
+        settings.set(name, value).map_err(|msg| format!("line {}: {}", i+1, msg))?;
+    }
+    story.backend = None;
+    Ok(story.or(all))
+}
+
+// Sizes in bytes, or with a K, M or G suffix.
+fn size(value: &str) -> Option<usize> {
+    let (digits,unit) = match value.chars().last()? {
+        'K' | 'k' => (&value[.. value.len()-1],1 << 10),
+        'M' | 'm' => (&value[.. value.len()-1],1 << 20),
+        'G' | 'g' => (&value[.. value.len()-1],1 << 30),
+        _ => (value,1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+fn bad_value(name: &str, value: &str) -> String {
+    format!("bad {} value {}", name, value)
+}
+
+fn invalid_input(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use glk::args;
+    use run;
+    use super::{parse,size,Settings};
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    const CONFIG: &'static str = "
+# comment
+backend = cheap
+undo = 10
+seed = 1
+memory-limit = 1M
+
+[glulx-1-2-3]
+backend = remglk
+seed = 42
+strict
+
+[OTHER]
+undo = 3
+";
+
+    #[test]
+    fn precedence() {
+        let settings = parse(CONFIG, Some("GLULX-1-2-3")).unwrap();
+        assert_eq!(Some("cheap".to_string()), settings.backend);
+        assert_eq!(Some(10), settings.undo);
+        assert_eq!(Some(42), settings.seed);
+        assert_eq!(Some(1 << 20), settings.memory_limit);
+        assert_eq!(Some(true), settings.strict);
+        let settings = parse(CONFIG, None).unwrap();
+        assert_eq!(Some(1), settings.seed);
+        assert_eq!(None, settings.strict);
+
+        let (options,story) = Settings::from_args(&strings(&["grue", "--seed", "7", "--strict", "story.ulx"])).unwrap();
+        assert_eq!(Some("story.ulx".to_string()), story);
+        let settings = options.or(parse(CONFIG, Some("GLULX-1-2-3")).unwrap());
+        assert_eq!(Some(7), settings.seed);
+        assert_eq!(Some(10), settings.undo);
+        let (options,_) = Settings::from_args(&strings(&["grue", "--strict"])).unwrap();
+        let settings = options.or(parse("strict = no\n", None).unwrap());
+        assert_eq!(Some(true), settings.strict);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(Some(100), size("100"));
+        assert_eq!(Some(64 << 10), size("64K"));
+        assert_eq!(Some(2 << 20), size("2m"));
+        assert_eq!(Some(1 << 30), size("1G"));
+        for value in ["", "K", "1.5M", "-1", "12Q", "M1", "99999999999999999999G"].iter() {
+            assert_eq!(None, size(value), "{}", value);
+        }
+        assert_eq!("line 2: bad memory-limit value 12Q", parse("undo = 1\nmemory-limit = 12Q\n", None).err().unwrap());
+        assert!(Settings::from_args(&strings(&["grue", "--memory-limit", "lots"])).is_err());
+    }
+
+    #[test]
+    fn unknown_options() {
+        assert_eq!("unknown setting bogus", Settings::from_args(&strings(&["grue", "--bogus", "1"])).err().unwrap().to_string());
+        assert_eq!("line 1: unknown setting bogus", parse("bogus = 1\n", None).err().unwrap());
+        assert_eq!("undo needs a value", Settings::from_args(&strings(&["grue", "--undo"])).err().unwrap().to_string());
+        assert_eq!("bad strict value maybe", Settings::default().set("strict", Some("maybe")).unwrap_err());
+    }
+
+    // glkunix_arguments was limited to 10 arguments.
+    #[test]
+    fn more_than_ten() {
+        let args = strings(&["grue", "--backend", "cheap", "--undo", "5", "--seed", "3", "--trace", "trace.txt",
+                          "--transcript", "game.md", "--record", "game.log", "--autosave", "saves",
+                          "--memory-limit", "4M", "--strict", "--config", "grue.conf", "story.ulx"]);
+        let (settings,story) = Settings::from_args(&args).unwrap();
+        assert_eq!(Some("story.ulx".to_string()), story);
+        assert_eq!(Some("cheap".to_string()), settings.backend);
+        assert_eq!(Some(5), settings.undo);
+        assert_eq!(Some(3), settings.seed);
+        assert_eq!(Some("trace.txt".to_string()), settings.trace);
+        assert_eq!(Some("game.md".to_string()), settings.transcript);
+        assert_eq!(Some("game.log".to_string()), settings.record);
+        assert_eq!(Some("saves".to_string()), settings.autosave);
+        assert_eq!(Some(4 << 20), settings.memory_limit);
+        assert_eq!(Some(true), settings.strict);
+        assert_eq!(Some("grue.conf".to_string()), settings.config);
+        let arguments = run::arguments();
+        assert!(arguments.len() > 10);
+        assert_eq!(args, args::parse(&arguments, &[], &args).unwrap().0);
+    }
+}
@@ -1,18 +1,94 @@
-extern crate iff;
+extern crate cheapglk;
 extern crate glk;
 extern crate glkterm;
+extern crate glktest;
 extern crate glulx;
+extern crate remglk;
+extern crate zmachine;
 
-use glkterm::{init,set_arguments,Argument,GlkTerm};
+use std::io::{stdin,stdout,BufReader};
 
+use cheapglk::CheapGlk;
+use glkterm::GlkTerm;
+use remglk::RemGlk;
+
+mod babel;
+mod config;
 mod run;
+mod test;
 
 fn main() {
-    set_arguments(vec![Argument::ValueFollows("".to_string(), "STORY-FILE".to_string())]);
-    init(glk_main);
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(result) = run::info(&args) {
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(result) = babel::babel(&args) {
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(result) = test::test(&args) {
+        match result {
+            Ok(passed) => std::process::exit(if passed { 0 } else { 1 }),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            },
+        }
+    }
+    let backend = match run::backend(&args) {
+        Ok(backend) => backend,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
+    };
+    match backend.as_str() {
+        "term" => glkterm::init(run::arguments(), term_main),
+        "cheap" => cheapglk::init(run::arguments(), cheap_main),
+        "remglk" => {
+            let args = match glk::args::parse(&run::arguments(), &[], &args) {
+                Some((args,_)) => args,
+                None => {
+                    eprint!("{}", glk::args::usage(&args[0], &run::arguments(), &[]));
+                    std::process::exit(1);
+                },
+            };
+            let glk = match RemGlk::new(BufReader::new(stdin()), stdout()) {
+                Ok(glk) => glk,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                },
+            };
+            exit_on_error(glk::session::run(|| run::grue(glk, args)));
+        },
+        _ => {
+            eprintln!("unknown backend {}, expected term, cheap or remglk", backend);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn term_main(glk: GlkTerm, args: Vec<String>) {
+    exit_on_error(run::grue(glk, args));
 }
 
-fn glk_main(glk: GlkTerm, args: Vec<String>) {
-    run::grue(glk, args).unwrap();
+fn cheap_main(glk: CheapGlk, args: Vec<String>) {
+    exit_on_error(run::grue(glk, args));
 }
 
+// Glk is gone by the time run::grue returns, so the terminal is usable
+// for the message.
+fn exit_on_error(result: std::io::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -1,30 +1,225 @@
 use std;
-use super::{glk,glulx,iff};
+use super::{glk,glulx,zmachine};
+use std::fs::{self,File};
+use std::io::{BufReader,BufWriter};
+use std::time::{SystemTime,UNIX_EPOCH};
+use std::path::Path;
+use glk::args::Argument;
+use glk::babel;
+use glk::blorb::Blorb;
+use glk::record::{RecordingGlk,ReplayGlk};
+use glk::transcript::{Format,TranscriptGlk};
 
+use super::config::{self,Settings};
+
+// grue [OPTIONS] STORY-FILE, see config.rs for the config file.
+pub fn arguments() -> Vec<Argument> {
+    vec![
+        Argument::ValueFollows("".to_string(), "STORY-FILE".to_string()),
+        Argument::ValueFollows("--backend".to_string(), "term (default), cheap or remglk".to_string()),
+        Argument::NumberValue("--undo".to_string(), "number of undo states to keep (default 2)".to_string()),
+        Argument::NumberValue("--seed".to_string(), "random number seed, for repeatable games".to_string()),
+        Argument::ValueFollows("--trace".to_string(), "write an instruction trace to FILE (debug builds only)".to_string()),
+        Argument::ValueFollows("--transcript".to_string(), "write a transcript to FILE, as Markdown if it ends in .md, else HTML".to_string()),
+        Argument::ValueFollows("--record".to_string(), "log the game's Glk calls to FILE, for bug reports".to_string()),
+        Argument::ValueFollows("--replay".to_string(), "play the game against a log from --record instead of the backend".to_string()),
+        Argument::ValueFollows("--autosave".to_string(), "save each turn in DIR, and resume from there".to_string()),
+        Argument::ValueFollows("--memory-limit".to_string(), "most memory the game can use, in bytes or with a K, M or G suffix".to_string()),
+        Argument::NoValue("--strict".to_string(), "stop on unknown Glk calls and bad memory sizes".to_string()),
+        Argument::ValueFollows("--config".to_string(), "read settings from FILE".to_string()),
+    ]
+}
+
+// The backend named by --backend or the config file's settings for all
+// stories, before Glk starts.
+pub fn backend(args: &[String]) -> std::io::Result<String> {
+    let option = |name: &str| args.iter().position(|arg| arg == name).and_then(|index| args.get(index+1)).map(|value| value.as_str());
+    let backend = match option("--backend") {
+        Some(backend) => Some(backend.to_string()),
+        None => config::load(option("--config"), None)?.backend,
+    };
+    Ok(backend.unwrap_or("term".to_string()))
+}
+
+// --transcript writes the main window to FILE with the player's input,
+// as Markdown if FILE ends in .md and as HTML otherwise.
 pub fn grue<'a,G: glk::Glk<'a>>(glk: G, args: Vec<String>) -> std::io::Result<()> {
-    if args.len() < 2 {
+    let (settings,story) = Settings::from_args(&args)?;
+    let story = match story {
+        Some(story) => story,
+        None => return Ok(()),
+    };
+    let data = fs::read(&story)?;
+    let ifid = babel::ifid(&data);
+    let settings = {
+        let path = settings.config.clone();
+        settings.or(config::load(path.as_ref().map(|path| path.as_str()), ifid.as_ref().map(|ifid| ifid.as_str()))?)
+    };
+    // Release builds of the interpreter can't trace.
+    if settings.trace.is_some() && !cfg!(debug_assertions) {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--trace needs a debug build"));
+    }
+    match settings.transcript.clone() {
+        Some(path) => {
+            let format = if path.ends_with(".md") { Format::Markdown } else { Format::Html };
+            let mut glk = TranscriptGlk::new(glk, BufWriter::new(File::create(&path)?), format);
+            glk.set_include_input(true);
+            let (_,result) = record(glk, data, &story, &settings)?.finish();
+            result?;
+        },
+        None => {
+            record(glk, data, &story, &settings)?;
+        },
+    }
+    Ok(())
+}
+
+// --record logs the calls the story makes to FILE with its random seed,
+// which is --seed or else one from the clock.  --replay plays the story
+// against such a log instead of glk, failing if it makes different calls
+// or stops before the log's last call.
+fn record<'a,G: glk::Glk<'a>>(glk: G, data: Vec<u8>, story: &str, settings: &Settings) -> std::io::Result<G> {
+    let mut settings = settings.clone();
+    if let Some(path) = settings.replay.clone() {
+        let replay = ReplayGlk::new(BufReader::new(File::open(&path)?))?;
+        settings.seed = Some(replay.seed());
+        // glk_exit ends only the replay's session, after checking the log.
+        let mut remaining = 0;
+        glk::session::run(|| {
+            remaining = play(replay, data, story, &settings)?.remaining();
+            Ok(())
+        })?;
+        if remaining > 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: the story ended with {} recorded calls left", path, remaining)));
+        }
+        return Ok(glk);
+    }
+    match settings.record.clone() {
+        Some(path) => {
+            // A seed of 0 is a random one, which the replay couldn't repeat.
+            let seed = match settings.seed {
+                Some(seed) if seed != 0 => seed,
+                _ => SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0) | 1,
+            };
+            settings.seed = Some(seed);
+            let glk = RecordingGlk::new(glk, File::create(&path)?, seed);
+            let (glk,result) = play(glk, data, story, &settings)?.finish();
+            result?;
+            Ok(glk)
+        },
+        None => play(glk, data, story, &settings),
+    }
+}
+
+// Glulx stories and Z-code stories, versions 1 to 8, alone or in a
+// blorb.  Z-code doesn't use the trace, autosave and memory limit settings.
+fn play<'a,G: glk::Glk<'a>>(mut glk: G, data: Vec<u8>, story: &str, settings: &Settings) -> std::io::Result<G> {
+    if data.starts_with(b"Glul") {
+        let (glk,result) = glulx::run_with_options(glk, &mut &data[..], glulx_options(story, settings)?);
+        result.map(|()| glk)
+    } else if data.starts_with(b"FORM") {
+        let blorb = Blorb::new(data)?;
+        let (chunk_type,exec) = match blorb.exec() {
+            Some(ref exec) => (exec.chunk_type,exec.data.to_vec()),
+            None => return Ok(glk),
+        };
+        if let Some(title) = blorb.metadata().title {
+            glk.set_story_title(&title);
+        }
+        glk.set_resource_map(blorb);
+        let (glk,result) = match &chunk_type {
+            b"GLUL" => glulx::run_with_options(glk, &mut &exec[..], glulx_options(story, settings)?),
+            b"ZCOD" => zmachine::run_with_options(glk, &mut &exec[..], zmachine_options(settings)),
+            _ => return Ok(glk),
+        };
+        result.map(|()| glk)
+    } else if data.len() >= 64 && data[0] >= 1 && data[0] <= 8 {
+        let (glk,result) = zmachine::run_with_options(glk, &mut &data[..], zmachine_options(settings));
+        result.map(|()| glk)
+    } else {
+        Ok(glk)
+    }
+}
+
+fn glulx_options<'a,G: glk::Glk<'a>>(story: &str, settings: &Settings) -> std::io::Result<glulx::Options<'a,G>> {
+    let mut options = glulx::Options::new();
+    if let Some(undo) = settings.undo {
+        options.set_undo_depth(undo);
+    }
+    if let Some(seed) = settings.seed {
+        options.set_random_seed(seed);
+    }
+    if let Some(ref path) = settings.trace {
+        options.set_trace(File::create(path)?);
+    }
+    if let Some(ref dir) = settings.autosave {
+        fs::create_dir_all(dir)?;
+        let name = Path::new(story).file_stem().map(|name| name.to_string_lossy().into_owned()).unwrap_or("story".to_string());
+        options.set_autosave(Path::new(dir).join(format!("{}.glksave", name)));
+    }
+    if let Some(limit) = settings.memory_limit {
+        options.set_memory_limit(limit);
+    }
+    if let Some(strict) = settings.strict {
+        options.set_strict(strict);
+    }
+    Ok(options)
+}
+
+fn zmachine_options(settings: &Settings) -> zmachine::Options {
+    let mut options = zmachine::Options::new();
+    if let Some(undo) = settings.undo {
+        options.set_undo_depth(undo);
+    }
+    if let Some(seed) = settings.seed {
+        options.set_random_seed(seed);
+    }
+    if let Some(strict) = settings.strict {
+        options.set_strict(strict);
+    }
+    options
+}
+
+// Handle --info STORY-FILE without starting Glk.
+pub fn info(args: &[String]) -> Option<std::io::Result<()>> {
+    if args.len() != 3 || args[1] != "--info" {
+        return None;
+    }
+    Some(print_info(&args[2]))
+}
+
+// Stories that aren't in a blorb have no metadata, so they only have the
+// format and the IFID from their header.
+fn print_info(path: &str) -> std::io::Result<()> {
+    let data = fs::read(path).map_err(|err| std::io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+    let format = match babel::format(&data) {
+        Some(format) => format,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: not a Glulx or Z-code story", path))),
+    };
+    println!("Format: {}", format.name());
+    if !data.starts_with(b"FORM") {
+        if let Some(ifid) = babel::ifid(&data) {
+            println!("IFID: {}", ifid);
+        }
         return Ok(());
     }
-    let mut file = std::fs::File::open(&args[1])?;
-    let mut buf = vec![0,0,0,0];
-    use std::io::{Read,Seek};
-    file.read(&mut buf)?;
-    if buf[..] == b"Glul"[..] {
-        file.seek(std::io::SeekFrom::Current(-4))?;
-        glulx::run(glk, &mut file);
-    } else if buf[..] == b"FORM"[..] {
-        file.read_to_end(&mut buf)?;
-        if let iff::Chunk::Envelope { envelope_id:_, id, chunks } = iff::Chunk::new(&buf)? {
-            if id == From::from(b"IFRS") {
-                for chunk in chunks {
-                    if let iff::Chunk::Data { id, mut data } = chunk {
-                        if id == From::from(b"GLUL") {
-                            glulx::run(glk, &mut data);
-                            break;
-                        }
-                    }
-                }
-            }
+    let ifid = babel::ifid(&data);
+    let blorb = Blorb::new(data)?;
+    let metadata = blorb.metadata();
+    let fields = [("Title",metadata.title),
+                  ("Author",metadata.author),
+                  ("Headline",metadata.headline),
+                  ("Release",metadata.release.map(|release| release.to_string())),
+                  ("IFID",ifid)];
+    for &(name,ref value) in fields.iter() {
+        if let &Some(ref value) = value {
+            println!("{}: {}", name, value);
+        }
+    }
+    if let Some(pict) = metadata.frontispiece {
+        match blorb.image_size(pict) {
+            Some((width,height)) => println!("Frontispiece: Pict {} ({}x{})", pict, width, height),
+            None => println!("Frontispiece: Pict {}", pict),
         }
     }
     Ok(())
@@ -0,0 +1,206 @@
+use std;
+use std::fs::File;
+use std::io::{Cursor,Error,ErrorKind,Read,Result,Write};
+use std::path::{Path,PathBuf};
+use std::process::{Command,Stdio};
+use std::sync::{Arc,Mutex};
+
+use super::cheapglk::CheapGlk;
+use super::glk::session;
+use super::glk::streams::Encoding;
+use super::glktest;
+use super::run;
+
+// grue test [--bless] [-j JOBS] STORY-FILE SCRIPT...
+//
+// A script is a file of commands, one per line, where lines starting
+// with # are comments.  A directory stands for the NAME.script files in
+// it.  The story is run with each script as its input, and the transcript
+// is written to NAME.transcript and compared with NAME.golden.  --bless
+// replaces NAME.golden with the new transcript.
+//
+// Each script is run in a directory of its own, so that files saved by
+// one aren't seen by another, with an empty config file and a fixed seed
+// so that the user's settings and chance don't change the transcript.
+
+const USAGE: &'static str = "usage: grue test [--bless] [-j JOBS] STORY-FILE SCRIPT...";
+
+const CONFIG: &'static str = "grue.conf";
+const SEED: &'static str = "1";
+
+// Handle grue test without starting Glk.  Returns whether all the
+// transcripts match.
+pub fn test(args: &[String]) -> Option<Result<bool>> {
+    if args.len() < 2 || args[1] != "test" {
+        return None;
+    }
+    if args.len() == 5 && args[2] == "--run" {
+        run_script(&args[0], &args[3], &args[4]);
+    }
+    Some(run_tests(&args[2 ..]))
+}
+
+// Runs the story in this process with output to stdout, which the parent
+// process collects as the transcript.
+fn run_script(program: &str, story: &str, script: &str) -> ! {
+    let input = match read_script(Path::new(script)) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}: {}", script, err);
+            std::process::exit(2);
+        },
+    };
+    let mut glk = CheapGlk::new(Box::new(Cursor::new(input)), Box::new(std::io::stdout()));
+    glk.set_encoding(Encoding::Utf8);
+    glk.set_echo_input(true);
+    if let Err(err) = session::run(|| run::grue(glk, vec![program.to_string(), "--config".to_string(), CONFIG.to_string(), "--seed".to_string(), SEED.to_string(), story.to_string()])) {
+        eprintln!("{}: {}", story, err);
+        std::process::exit(2);
+    }
+    std::process::exit(0);
+}
+
+fn read_script(path: &Path) -> Result<Vec<u8>> {
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    let mut input = String::new();
+    for line in text.lines().filter(|line| !line.starts_with("#")) {
+        input.push_str(line);
+        input.push_str("\n");
+    }
+    Ok(input.into_bytes())
+}
+
+enum Outcome {
+    Passed,
+    Blessed,
+    Failed(String),
+    Error(String),
+}
+
+fn run_tests(args: &[String]) -> Result<bool> {
+    let mut bless = false;
+    let mut jobs = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with("-") {
+        match args[i].as_str() {
+            "--bless" => bless = true,
+            "-j" if i + 1 < args.len() => {
+                i += 1;
+                jobs = args[i].parse().map_err(|_| Error::new(ErrorKind::InvalidInput, USAGE))?;
+            },
+            _ => return Err(Error::new(ErrorKind::InvalidInput, USAGE)),
+        }
+        i += 1;
+    }
+    if args.len() < i + 2 {
+        return Err(Error::new(ErrorKind::InvalidInput, USAGE));
+    }
+    let story = std::fs::canonicalize(&args[i])?;
+    let mut scripts = Vec::new();
+    for arg in &args[i + 1 ..] {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            let mut found = Vec::new();
+            for entry in std::fs::read_dir(&path)? {
+                let entry = entry?.path();
+                if entry.extension().map(|ext| ext == "script").unwrap_or(false) {
+                    found.push(entry);
+                }
+            }
+            found.sort();
+            scripts.extend(found);
+        } else {
+            scripts.push(path);
+        }
+    }
+
+    let program = std::env::current_exe()?;
+    let queue = Arc::new(Mutex::new((0,Vec::new())));
+    let scripts = Arc::new(scripts);
+    let mut threads = Vec::new();
+    for _ in 0 .. jobs.max(1).min(scripts.len()) {
+        let (queue,scripts,program,story) = (queue.clone(),scripts.clone(),program.clone(),story.clone());
+        threads.push(std::thread::spawn(move || {
+            loop {
+                let index = {
+                    let mut queue = queue.lock().unwrap();
+                    if queue.0 >= scripts.len() {
+                        break;
+                    }
+                    queue.0 += 1;
+                    queue.0 - 1
+                };
+                let outcome = match check(&program, &story, &scripts[index], index, bless) {
+                    Ok(outcome) => outcome,
+                    Err(err) => Outcome::Error(err.to_string()),
+                };
+                queue.lock().unwrap().1.push((index,outcome));
+            }
+        }));
+    }
+    for thread in threads {
+        let _ = thread.join();
+    }
+
+    let mut outcomes = std::mem::replace(&mut queue.lock().unwrap().1, Vec::new());
+    outcomes.sort_by_key(|&(index,_)| index);
+    let (mut passed,mut blessed,mut failed,mut errors) = (0,0,0,0);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for (index,outcome) in outcomes {
+        let name = scripts[index].display();
+        match outcome {
+            Outcome::Passed => {
+                passed += 1;
+                writeln!(out, "ok      {}", name)?;
+            },
+            Outcome::Blessed => {
+                blessed += 1;
+                writeln!(out, "blessed {}", name)?;
+            },
+            Outcome::Failed(diff) => {
+                failed += 1;
+                writeln!(out, "FAILED  {}", name)?;
+                out.write_all(diff.as_bytes())?;
+            },
+            Outcome::Error(err) => {
+                errors += 1;
+                writeln!(out, "ERROR   {}: {}", name, err)?;
+            },
+        }
+    }
+    writeln!(out, "{} scripts: {} passed, {} failed, {} errors, {} blessed", scripts.len(), passed, failed, errors, blessed)?;
+    Ok(failed == 0 && errors == 0)
+}
+
+fn check(program: &Path, story: &Path, script: &Path, index: usize, bless: bool) -> Result<Outcome> {
+    let dir = std::env::temp_dir().join(format!("grue-test-{}-{}", std::process::id(), index));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    File::create(dir.join(CONFIG))?;
+    let output = Command::new(program).arg("test").arg("--run").arg(story).arg(script.canonicalize()?)
+        .current_dir(&dir).stdin(Stdio::null()).output();
+    let _ = std::fs::remove_dir_all(&dir);
+    let output = output?;
+    if !output.status.success() {
+        return Ok(Outcome::Error(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    let transcript = String::from_utf8_lossy(&output.stdout).into_owned();
+    File::create(script.with_extension("transcript"))?.write_all(transcript.as_bytes())?;
+    let golden_path = script.with_extension("golden");
+    if bless {
+        File::create(golden_path)?.write_all(transcript.as_bytes())?;
+        return Ok(Outcome::Blessed);
+    }
+    let mut golden = String::new();
+    match File::open(&golden_path) {
+        Ok(mut file) => file.read_to_string(&mut golden)?,
+        Err(_) => return Ok(Outcome::Error(format!("no {}, run with --bless to create it", golden_path.display()))),
+    };
+    if golden == transcript {
+        Ok(Outcome::Passed)
+    } else {
+        Ok(Outcome::Failed(glktest::diff(&golden, &transcript)))
+    }
+}
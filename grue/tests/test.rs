@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::{Path,PathBuf};
+use std::process::{Command,Output};
+
+const STORY: &'static str = "../glulx/tests/testdata/glulxercise.ulx";
+
+// A directory with the script NAME.script.
+fn script(name: &str, script: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("grue-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(format!("{}.script", name)), script).unwrap();
+    dir
+}
+
+fn grue_test(args: &[&str], dir: &Path) -> (bool,String) {
+    let story = fs::canonicalize(STORY).unwrap();
+    let Output{ status, stdout, .. } = Command::new(env!("CARGO_BIN_EXE_grue")).arg("test").args(args).arg(story).arg(dir).output().unwrap();
+    (status.success(),String::from_utf8(stdout).unwrap())
+}
+
+#[test]
+fn pass() {
+    let dir = script("pass", "# repeat the welcome\nhelp\n");
+    let (passed,out) = grue_test(&["--bless"], &dir);
+    assert!(passed);
+    assert!(out.ends_with("1 scripts: 0 passed, 0 failed, 0 errors, 1 blessed\n"), "{}", out);
+    let golden = fs::read_to_string(dir.join("pass.golden")).unwrap();
+    assert!(golden.contains("\n>help\n"), "{}", golden);
+    assert!(!golden.contains("repeat the welcome"));
+    let (passed,out) = grue_test(&[], &dir);
+    assert!(passed);
+    assert!(out.starts_with("ok      "), "{}", out);
+    assert_eq!(golden, fs::read_to_string(dir.join("pass.transcript")).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mismatch() {
+    let dir = script("mismatch", "help\n");
+    assert!(grue_test(&["--bless"], &dir).0);
+    let golden = fs::read_to_string(dir.join("mismatch.golden")).unwrap();
+    fs::write(dir.join("mismatch.golden"), golden.replacen(">help", ">hint", 1)).unwrap();
+    let (passed,out) = grue_test(&[], &dir);
+    assert!(!passed);
+    assert!(out.starts_with("FAILED  "), "{}", out);
+    assert!(out.contains("\n--- expected\n+++ actual\n@@ "), "{}", out);
+    assert!(out.contains("\n->hint\n+>help\n"), "{}", out);
+    assert!(out.ends_with("1 scripts: 0 passed, 1 failed, 0 errors, 0 blessed\n"), "{}", out);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// The story is stopped at the first input it asks for after the script.
+#[test]
+fn out_of_commands() {
+    let dir = script("out", "# nothing to type\n");
+    assert!(grue_test(&["--bless"], &dir).0);
+    let golden = fs::read_to_string(dir.join("out.golden")).unwrap();
+    assert!(golden.starts_with("\nGlulxercise: A Glulx interpreter unit test\n"), "{}", golden);
+    assert!(golden.ends_with("\n>"), "{}", golden);
+    assert!(grue_test(&[], &dir).0);
+    fs::remove_dir_all(&dir).unwrap();
+}
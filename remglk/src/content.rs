@@ -0,0 +1,369 @@
+use serde_json::{Map,Value};
+
+use glk;
+use glk::streams::WindowSink;
+use glk::windows::WinId;
+
+const STYLE_NAMES: [&'static str; 11] = [
+    "normal", "emphasized", "preformatted", "header", "subheader", "alert",
+    "note", "blockquote", "input", "user1", "user2",
+];
+
+pub fn style_name(styl: u32) -> &'static str {
+    STYLE_NAMES.get(styl as usize).cloned().unwrap_or("normal")
+}
+
+enum Run {
+    Text{ style: u32, hyperlink: u32, text: String },
+    Image(Value),
+}
+
+struct Line {
+    append: bool,
+    runs: Vec<Run>,
+}
+
+#[derive(Clone,PartialEq)]
+struct Cell {
+    ch: char,
+    style: u32,
+    hyperlink: u32,
+}
+
+const BLANK: Cell = Cell{ ch: ' ', style: 0, hyperlink: 0 };
+
+enum Text {
+    Buffer{ clear: bool, lines: Vec<Line> },
+    Grid{ width: usize, height: usize, cells: Vec<Vec<Cell>>, dirty: Vec<bool>, x: usize, y: usize },
+    Graphics{ draw: Vec<Value> },
+    None,
+}
+
+pub enum LineBuf {
+    Latin1((u32,Box<[u8]>)),
+    Uni((u32,Box<[u32]>)),
+}
+
+pub struct LineRequest {
+    pub buf: LineBuf,
+    pub initlen: u32,
+    pub gen: u32,
+}
+
+pub struct Window {
+    pub style: u32,
+    pub hyperlink: u32,
+    pub line_request: Option<LineRequest>,
+    // Whether the request is for Unicode, and its generation.
+    pub char_request: Option<(bool,u32)>,
+    pub mouse_request: bool,
+    pub hyperlink_request: bool,
+    pub echo_line: bool,
+    pub terminators: Vec<u32>,
+    pub partial: Option<String>,
+    text: Text,
+}
+
+impl Window {
+    pub fn new(wintype: u32) -> Self {
+        Window{
+            style: glk::style_Normal,
+            hyperlink: 0,
+            line_request: None,
+            char_request: None,
+            mouse_request: false,
+            hyperlink_request: false,
+            echo_line: true,
+            terminators: Vec::new(),
+            partial: None,
+            text: match wintype {
+                glk::wintype_TextBuffer => Text::Buffer{ clear: false, lines: Vec::new() },
+                glk::wintype_TextGrid => Text::Grid{ width: 0, height: 0, cells: Vec::new(), dirty: Vec::new(), x: 0, y: 0 },
+                glk::wintype_Graphics => Text::Graphics{ draw: Vec::new() },
+                _ => Text::None,
+            },
+        }
+    }
+
+    pub fn put(&mut self, s: &[u32]) {
+        let (style,hyperlink) = (self.style,self.hyperlink);
+        match self.text {
+            Text::Buffer{ ref mut lines, .. } => {
+                for &ch in s {
+                    if lines.is_empty() {
+                        lines.push(Line{ append: true, runs: Vec::new() });
+                    }
+                    if ch == '\n' as u32 {
+                        lines.push(Line{ append: false, runs: Vec::new() });
+                        continue;
+                    }
+                    let ch = ::std::char::from_u32(ch).unwrap_or('?');
+                    let runs = &mut lines.last_mut().unwrap().runs;
+                    if let Some(&mut Run::Text{ style: s, hyperlink: h, ref mut text }) = runs.last_mut() {
+                        if s == style && h == hyperlink {
+                            text.push(ch);
+                            continue;
+                        }
+                    }
+                    runs.push(Run::Text{ style, hyperlink, text: ch.to_string() });
+                }
+            },
+            Text::Grid{ width, height, ref mut cells, ref mut dirty, ref mut x, ref mut y } => {
+                for &ch in s {
+                    if ch == '\n' as u32 {
+                        *x = 0;
+                        *y += 1;
+                        continue;
+                    }
+                    if *x >= width {
+                        *x = 0;
+                        *y += 1;
+                    }
+                    if *y >= height {
+                        break;
+                    }
+                    cells[*y][*x] = Cell{ ch: ::std::char::from_u32(ch).unwrap_or('?'), style, hyperlink };
+                    dirty[*y] = true;
+                    *x += 1;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self.text {
+            Text::Buffer{ ref mut clear, ref mut lines } => {
+                *clear = true;
+                lines.clear();
+            },
+            Text::Grid{ ref mut cells, ref mut dirty, ref mut x, ref mut y, .. } => {
+                for line in cells.iter_mut() {
+                    for cell in line.iter_mut() {
+                        *cell = BLANK;
+                    }
+                }
+                for d in dirty.iter_mut() {
+                    *d = true;
+                }
+                *x = 0;
+                *y = 0;
+            },
+            Text::Graphics{ ref mut draw } => {
+                draw.clear();
+                draw.push(json!({"special": "fill"}));
+            },
+            Text::None => (),
+        }
+    }
+
+    pub fn move_cursor(&mut self, xpos: u32, ypos: u32) {
+        if let Text::Grid{ ref mut x, ref mut y, .. } = self.text {
+            *x = xpos as usize;
+            *y = ypos as usize;
+        }
+    }
+
+    pub fn cursor(&self) -> Option<(usize,usize)> {
+        match self.text {
+            Text::Grid{ x, y, .. } => Some((x,y)),
+            _ => None,
+        }
+    }
+
+    pub fn resize_grid(&mut self, new_width: u32, new_height: u32) {
+        if let Text::Grid{ ref mut width, ref mut height, ref mut cells, ref mut dirty, .. } = self.text {
+            *width = new_width as usize;
+            *height = new_height as usize;
+            cells.resize(*height, Vec::new());
+            for line in cells.iter_mut() {
+                line.resize(*width, BLANK);
+            }
+            *dirty = vec![true; *height];
+        }
+    }
+
+    pub fn draw(&mut self, op: Value) {
+        if let Text::Graphics{ ref mut draw } = self.text {
+            draw.push(op);
+        }
+    }
+
+    // An image in the text of a buffer window.
+    pub fn put_image(&mut self, image: Value) {
+        if let Text::Buffer{ ref mut lines, .. } = self.text {
+            if lines.is_empty() {
+                lines.push(Line{ append: true, runs: Vec::new() });
+            }
+            lines.last_mut().unwrap().runs.push(Run::Image(image));
+        }
+    }
+
+    pub fn flow_break(&mut self) {
+        self.put_image(json!({"special": "flowbreak"}));
+    }
+
+    // The content update since the last call, if any.
+    pub fn take_content(&mut self, id: usize) -> Option<Value> {
+        let mut content = Map::new();
+        content.insert("id".to_string(), json!(id));
+        match self.text {
+            Text::Buffer{ ref mut clear, ref mut lines } => {
+                if !*clear && lines.is_empty() {
+                    return None;
+                }
+                if *clear {
+                    content.insert("clear".to_string(), json!(true));
+                }
+                let text: Vec<Value> = lines.drain(..).map(|line| {
+                    let mut obj = Map::new();
+                    if line.append {
+                        obj.insert("append".to_string(), json!(true));
+                    }
+                    if !line.runs.is_empty() {
+                        obj.insert("content".to_string(), Value::Array(line.runs.iter().map(run_json).collect()));
+                    }
+                    Value::Object(obj)
+                }).collect();
+                content.insert("text".to_string(), Value::Array(text));
+                *clear = false;
+            },
+            Text::Grid{ ref cells, ref mut dirty, .. } => {
+                if !dirty.iter().any(|&d| d) {
+                    return None;
+                }
+                let mut lines = Vec::new();
+                for (i,line) in cells.iter().enumerate() {
+                    if !dirty[i] {
+                        continue;
+                    }
+                    let mut runs: Vec<Run> = Vec::new();
+                    for cell in line {
+                        if let Some(&mut Run::Text{ style, hyperlink, ref mut text }) = runs.last_mut() {
+                            if style == cell.style && hyperlink == cell.hyperlink {
+                                text.push(cell.ch);
+                                continue;
+                            }
+                        }
+                        runs.push(Run::Text{ style: cell.style, hyperlink: cell.hyperlink, text: cell.ch.to_string() });
+                    }
+                    lines.push(json!({"line": i, "content": runs.iter().map(run_json).collect::<Vec<Value>>()}));
+                }
+                for d in dirty.iter_mut() {
+                    *d = false;
+                }
+                content.insert("lines".to_string(), Value::Array(lines));
+            },
+            Text::Graphics{ ref mut draw } => {
+                if draw.is_empty() {
+                    return None;
+                }
+                content.insert("draw".to_string(), Value::Array(draw.drain(..).collect()));
+            },
+            Text::None => return None,
+        }
+        Some(Value::Object(content))
+    }
+}
+
+fn run_json(run: &Run) -> Value {
+    match run {
+        &Run::Text{ style, hyperlink, ref text } => {
+            let mut obj = Map::new();
+            obj.insert("style".to_string(), json!(style_name(style)));
+            obj.insert("text".to_string(), json!(text));
+            if hyperlink != 0 {
+                obj.insert("hyperlink".to_string(), json!(hyperlink));
+            }
+            Value::Object(obj)
+        },
+        &Run::Image(ref image) => image.clone(),
+    }
+}
+
+// The content of all windows, indexed by window id.
+pub struct Contents(pub Vec<Option<Window>>);
+
+impl Contents {
+    pub fn get_mut(&mut self, win: &WinId) -> Option<&mut Window> {
+        self.0.get_mut(win.0).and_then(|w| w.as_mut())
+    }
+
+    pub fn get(&self, win: &WinId) -> Option<&Window> {
+        self.0.get(win.0).and_then(|w| w.as_ref())
+    }
+
+    pub fn insert(&mut self, win: &WinId, window: Window) {
+        if self.0.len() <= win.0 {
+            self.0.resize_with(win.0 + 1, || None);
+        }
+        self.0[win.0] = Some(window);
+    }
+
+    pub fn remove(&mut self, win: &WinId) -> Option<Window> {
+        self.0.get_mut(win.0).and_then(|w| w.take())
+    }
+}
+
+impl WindowSink<WinId> for Contents {
+    fn put_window(&mut self, win: &WinId, s: &[u32]) {
+        if let Some(w) = self.get_mut(win) {
+            w.put(s);
+        }
+    }
+
+    fn set_window_style(&mut self, win: &WinId, styl: u32) {
+        if let Some(w) = self.get_mut(win) {
+            w.style = styl;
+        }
+    }
+
+    fn set_window_hyperlink(&mut self, win: &WinId, linkval: u32) {
+        if let Some(w) = self.get_mut(win) {
+            w.hyperlink = linkval;
+        }
+    }
+}
+
+const KEY_NAMES: [(&'static str,u32); 24] = [
+    ("left", glk::keycode_Left),
+    ("right", glk::keycode_Right),
+    ("up", glk::keycode_Up),
+    ("down", glk::keycode_Down),
+    ("return", glk::keycode_Return),
+    ("delete", glk::keycode_Delete),
+    ("escape", glk::keycode_Escape),
+    ("tab", glk::keycode_Tab),
+    ("pageup", glk::keycode_PageUp),
+    ("pagedown", glk::keycode_PageDown),
+    ("home", glk::keycode_Home),
+    ("end", glk::keycode_End),
+    ("func1", glk::keycode_Func1),
+    ("func2", glk::keycode_Func2),
+    ("func3", glk::keycode_Func3),
+    ("func4", glk::keycode_Func4),
+    ("func5", glk::keycode_Func5),
+    ("func6", glk::keycode_Func6),
+    ("func7", glk::keycode_Func7),
+    ("func8", glk::keycode_Func8),
+    ("func9", glk::keycode_Func9),
+    ("func10", glk::keycode_Func10),
+    ("func11", glk::keycode_Func11),
+    ("func12", glk::keycode_Func12),
+];
+
+pub fn key_name(keycode: u32) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|&&(_,k)| k == keycode).map(|&(name,_)| name)
+}
+
+// A special key name, or a single character.
+pub fn keycode(name: &str) -> u32 {
+    if let Some(&(_,k)) = KEY_NAMES.iter().find(|&&(n,_)| n == name) {
+        return k;
+    }
+    let mut chars = name.chars();
+    match (chars.next(),chars.next()) {
+        (Some(ch),None) => ch as u32,
+        _ => glk::keycode_Unknown,
+    }
+}
@@ -0,0 +1,1120 @@
+extern crate glk;
+#[macro_use]
+extern crate serde_json;
+
+use std::io;
+use std::io::{Error,ErrorKind,Read,Write};
+use std::path::{Component,Path,PathBuf};
+
+use serde_json::{Map,Value};
+
+use glk::{Glk,IdType};
+use glk::blorb::{Blorb,Usage};
+use glk::datetime::{Date,TimeVal};
+use glk::session;
+use glk::streams::{FRefId,FileRefs,IOStream,StrId,Streams};
+use glk::windows::{Event,Metrics,WinId,Windows};
+
+mod content;
+
+use content::{Contents,LineBuf,LineRequest,Window};
+
+// A Glk implementation speaking the RemGlk JSON protocol, as used by
+// GlkOte, with the frontend on input and output.
+pub struct RemGlk<R,W> {
+    input: R,
+    output: W,
+    gen: u32,
+    windows: Windows,
+    contents: Contents,
+    streams: Streams<WinId>,
+    frefs: FileRefs,
+    timer: Option<u32>,
+    timer_changed: bool,
+    windows_changed: bool,
+    resources: Option<Blorb>,
+}
+
+impl<R: Read,W: Write> RemGlk<R,W> {
+    // Waits for the init message from the frontend.
+    pub fn new(input: R, output: W) -> io::Result<Self> {
+        let mut remglk = RemGlk{
+            input,
+            output,
+            gen: 0,
+            windows: Windows::new(Metrics::cells(80, 24)),
+            contents: Contents(Vec::new()),
+            streams: Streams::new(),
+            frefs: FileRefs::new(PathBuf::from(".")),
+            timer: None,
+            timer_changed: false,
+            windows_changed: false,
+            resources: None,
+        };
+        loop {
+            match remglk.read_message()? {
+                Some(ref msg) if msg["type"] == "init" => {
+                    let metrics = parse_metrics(&msg["metrics"], remglk.windows.metrics());
+                    remglk.windows.set_metrics(metrics);
+                    return Ok(remglk);
+                },
+                Some(_) => (),
+                None => return Err(Error::new(ErrorKind::UnexpectedEof, "no init message")),
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> (R,W) {
+        (self.input,self.output)
+    }
+
+    fn read_message(&mut self) -> io::Result<Option<Value>> {
+        let mut messages = serde_json::Deserializer::from_reader(&mut self.input).into_iter::<Value>();
+        match messages.next() {
+            Some(Ok(msg)) => Ok(Some(msg)),
+            Some(Err(ref e)) if e.is_eof() => Ok(None),
+            Some(Err(e)) => Err(Error::new(ErrorKind::InvalidData, e)),
+            None => Ok(None),
+        }
+    }
+
+    // The next message from the frontend, ending the session at the end
+    // of input, or with an error if the message isn't JSON.
+    fn next_message(&mut self) -> Value {
+        match self.read_message() {
+            Ok(Some(msg)) => msg,
+            Ok(None) => session::end(Ok(())),
+            Err(err) => session::end(Err(err)),
+        }
+    }
+
+    fn send_update(&mut self, special: Option<Value>, input: bool) {
+        self.gen += 1;
+        let mut update = Map::new();
+        update.insert("type".to_string(), json!("update"));
+        update.insert("gen".to_string(), json!(self.gen));
+        if self.windows_changed {
+            update.insert("windows".to_string(), Value::Array(self.window_list()));
+            self.windows_changed = false;
+        }
+        let mut content = Vec::new();
+        for (i,w) in self.contents.0.iter_mut().enumerate() {
+            if let &mut Some(ref mut w) = w {
+                if let Some(c) = w.take_content(i) {
+                    content.push(c);
+                }
+            }
+        }
+        if !content.is_empty() {
+            update.insert("content".to_string(), Value::Array(content));
+        }
+        if input {
+            update.insert("input".to_string(), Value::Array(self.input_list()));
+        } else {
+            update.insert("disable".to_string(), json!(true));
+        }
+        if self.timer_changed {
+            update.insert("timer".to_string(), json!(self.timer));
+            self.timer_changed = false;
+        }
+        if let Some(special) = special {
+            update.insert("specialinput".to_string(), special);
+        }
+        let _ = writeln!(self.output, "{}", Value::Object(update));
+        let _ = self.output.flush();
+    }
+
+    fn window_list(&self) -> Vec<Value> {
+        let mut list = Vec::new();
+        let mut win = WinId(0);
+        loop {
+            win = self.windows.iterate(&win).0;
+            let w = match self.windows.get(&win) {
+                Some(w) => w,
+                None => return list,
+            };
+            let wintype = match w.wintype {
+                glk::wintype_TextBuffer => "buffer",
+                glk::wintype_TextGrid => "grid",
+                glk::wintype_Graphics => "graphics",
+                _ => continue,
+            };
+            let mut desc = Map::new();
+            desc.insert("id".to_string(), json!(win.0));
+            desc.insert("type".to_string(), json!(wintype));
+            desc.insert("rock".to_string(), json!(w.rock));
+            desc.insert("left".to_string(), json!(w.rect.left));
+            desc.insert("top".to_string(), json!(w.rect.top));
+            desc.insert("width".to_string(), json!(w.rect.width));
+            desc.insert("height".to_string(), json!(w.rect.height));
+            let (width,height) = self.windows.size(&win);
+            match w.wintype {
+                glk::wintype_TextGrid => {
+                    desc.insert("gridwidth".to_string(), json!(width));
+                    desc.insert("gridheight".to_string(), json!(height));
+                },
+                glk::wintype_Graphics => {
+                    desc.insert("graphwidth".to_string(), json!(width));
+                    desc.insert("graphheight".to_string(), json!(height));
+                },
+                _ => (),
+            }
+            list.push(Value::Object(desc));
+        }
+    }
+
+    fn input_list(&self) -> Vec<Value> {
+        let mut list = Vec::new();
+        for (i,w) in self.contents.0.iter().enumerate() {
+            let w = match w {
+                &Some(ref w) => w,
+                &None => continue,
+            };
+            let mut input = Map::new();
+            input.insert("id".to_string(), json!(i));
+            if let Some(ref request) = w.line_request {
+                let initial: String = match request.buf {
+                    LineBuf::Latin1((_,ref buf)) => buf.iter().take(request.initlen as usize).map(|&b| b as char).collect(),
+                    LineBuf::Uni((_,ref buf)) => buf.iter().take(request.initlen as usize).map(|&ch| std::char::from_u32(ch).unwrap_or('?')).collect(),
+                };
+                let maxlen = match request.buf {
+                    LineBuf::Latin1((_,ref buf)) => buf.len(),
+                    LineBuf::Uni((_,ref buf)) => buf.len(),
+                };
+                input.insert("type".to_string(), json!("line"));
+                input.insert("gen".to_string(), json!(request.gen));
+                input.insert("maxlen".to_string(), json!(maxlen));
+                if !initial.is_empty() {
+                    input.insert("initial".to_string(), json!(initial));
+                }
+                if !w.terminators.is_empty() {
+                    let names: Vec<&str> = w.terminators.iter().filter_map(|&k| content::key_name(k)).collect();
+                    input.insert("terminators".to_string(), json!(names));
+                }
+            } else if let Some((_,gen)) = w.char_request {
+                input.insert("type".to_string(), json!("char"));
+                input.insert("gen".to_string(), json!(gen));
+            } else if !w.mouse_request && !w.hyperlink_request {
+                continue;
+            }
+            if let Some((x,y)) = w.cursor() {
+                input.insert("xpos".to_string(), json!(x));
+                input.insert("ypos".to_string(), json!(y));
+            }
+            if w.hyperlink_request {
+                input.insert("hyperlink".to_string(), json!(true));
+            }
+            if w.mouse_request {
+                input.insert("mouse".to_string(), json!(true));
+            }
+            list.push(Value::Object(input));
+        }
+        list
+    }
+
+    // The event for a message, or None if it is not acceptable.
+    fn accept(&mut self, msg: Value) -> Option<Event> {
+        if let Some(partial) = msg["partial"].as_object() {
+            for (id,text) in partial {
+                let win = WinId(id.parse().unwrap_or(0));
+                if let (Some(w),Some(text)) = (self.contents.get_mut(&win),text.as_str()) {
+                    w.partial = Some(text.to_string());
+                }
+            }
+        }
+        if msg["type"] != "arrange" && msg["gen"].as_u64() != Some(self.gen as u64) {
+            return None;
+        }
+        let win = WinId(msg["window"].as_u64().unwrap_or(0) as usize);
+        match msg["type"].as_str() {
+            Some("arrange") => {
+                let metrics = parse_metrics(&msg["metrics"], self.windows.metrics());
+                self.windows.set_metrics(metrics);
+                self.relayout();
+                Some(Event::new(glk::evtype_Arrange, WinId(0), 0, 0))
+            },
+            Some("redraw") => Some(Event::new(glk::evtype_Redraw, win, 0, 0)),
+            Some("timer") => Some(Event::new(glk::evtype_Timer, WinId(0), 0, 0)),
+            Some("line") => {
+                let text = msg["value"].as_str().unwrap_or("").to_string();
+                let terminator = msg["terminator"].as_str().map(content::keycode).unwrap_or(0);
+                self.accept_line(&win, &text, terminator)
+            },
+            Some("char") => {
+                let keycode = content::keycode(msg["value"].as_str().unwrap_or(""));
+                let uni = match self.contents.get_mut(&win).and_then(|w| w.char_request.take()) {
+                    Some((uni,_)) => uni,
+                    None => return None,
+                };
+                let keycode = if !uni && keycode > 0xff && keycode < 0x110000 { glk::keycode_Unknown } else { keycode };
+                Some(Event::new(glk::evtype_CharInput, win, keycode, 0))
+            },
+            Some("hyperlink") => {
+                match self.contents.get_mut(&win) {
+                    Some(ref mut w) if w.hyperlink_request => w.hyperlink_request = false,
+                    _ => return None,
+                }
+                Some(Event::new(glk::evtype_Hyperlink, win, msg["value"].as_u64().unwrap_or(0) as u32, 0))
+            },
+            Some("mouse") => {
+                match self.contents.get_mut(&win) {
+                    Some(ref mut w) if w.mouse_request => w.mouse_request = false,
+                    _ => return None,
+                }
+                let x = msg["x"].as_u64().unwrap_or(0) as u32;
+                let y = msg["y"].as_u64().unwrap_or(0) as u32;
+                Some(Event::new(glk::evtype_MouseInput, win, x, y))
+            },
+            _ => None,
+        }
+    }
+
+    fn accept_line(&mut self, win: &WinId, text: &str, terminator: u32) -> Option<Event> {
+        let (request,echo) = match self.contents.get_mut(win) {
+            Some(w) => {
+                w.partial = None;
+                (w.line_request.take()?,w.echo_line)
+            },
+            None => return None,
+        };
+        let mut event = Event::new(glk::evtype_LineInput, *win, 0, terminator);
+        let mut echoed = Vec::new();
+        match request.buf {
+            LineBuf::Latin1((addr,mut buf)) => {
+                for (i,ch) in text.chars().take(buf.len()).enumerate() {
+                    buf[i] = if ch as u32 > 0xff { b'?' } else { ch as u8 };
+                    echoed.push(buf[i] as u32);
+                }
+                event.buf = Some((addr,buf));
+            },
+            LineBuf::Uni((addr,mut buf)) => {
+                for (i,ch) in text.chars().take(buf.len()).enumerate() {
+                    buf[i] = ch as u32;
+                    echoed.push(ch as u32);
+                }
+                event.buf_uni = Some((addr,buf));
+            },
+        }
+        event.val1 = echoed.len() as u32;
+        if echo {
+            let str = self.windows.get(win).map(|w| w.str).unwrap_or(StrId(0));
+            let style = self.contents.get(win).map(|w| w.style).unwrap_or(0);
+            self.streams.set_style(&mut self.contents, &str, glk::style_Input);
+            echoed.push('\n' as u32);
+            self.streams.put(&mut self.contents, &str, &echoed);
+            self.streams.set_style(&mut self.contents, &str, style);
+        }
+        Some(event)
+    }
+
+    fn relayout(&mut self) {
+        self.windows_changed = true;
+        for i in 0 .. self.contents.0.len() {
+            let win = WinId(i);
+            let (width,height) = self.windows.size(&win);
+            if let Some(w) = self.contents.get_mut(&win) {
+                w.resize_grid(width, height);
+            }
+        }
+    }
+
+    fn image_size(&self, image: u32) -> Option<(u32,u32)> {
+        self.resources.as_ref().and_then(|blorb| blorb.image_size(image))
+    }
+
+    fn draw_image(&mut self, win: &WinId, image: u32, val1: i32, val2: i32, size: Option<(u32,u32)>) -> bool {
+        let (width,height) = match size.or_else(|| self.image_size(image)) {
+            Some(size) => size,
+            None => return false,
+        };
+        let wintype = self.windows.get(win).map(|w| w.wintype).unwrap_or(0);
+        let w = match self.contents.get_mut(win) {
+            Some(w) => w,
+            None => return false,
+        };
+        match wintype {
+            glk::wintype_Graphics => {
+                w.draw(json!({"special": "image", "image": image, "x": val1, "y": val2, "width": width, "height": height}));
+                true
+            },
+            glk::wintype_TextBuffer => {
+                let alignment = match val1 as u32 {
+                    glk::imagealign_InlineDown => "inlinedown",
+                    glk::imagealign_InlineCenter => "inlinecenter",
+                    glk::imagealign_MarginLeft => "marginleft",
+                    glk::imagealign_MarginRight => "marginright",
+                    _ => "inlineup",
+                };
+                w.put_image(json!({"special": "image", "image": image, "alignment": alignment, "width": width, "height": height}));
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn file_prompt(&mut self, usage: u32, fmode: u32) -> Option<String> {
+        let filetype = match usage & glk::fileusage_TypeMask {
+            glk::fileusage_SavedGame => "save",
+            glk::fileusage_Transcript => "transcript",
+            glk::fileusage_InputRecord => "command",
+            _ => "data",
+        };
+        let filemode = match fmode {
+            glk::filemode_Read => "read",
+            glk::filemode_ReadWrite => "readwrite",
+            glk::filemode_WriteAppend => "writeappend",
+            _ => "write",
+        };
+        self.send_update(Some(json!({"type": "fileref_prompt", "filetype": filetype, "filemode": filemode})), false);
+        loop {
+            let msg = self.next_message();
+            if msg["type"] == "specialresponse" && msg["response"] == "fileref_prompt" {
+                return match msg["value"] {
+                    Value::String(ref name) if !name.is_empty() => Some(name.clone()),
+                    Value::Object(ref obj) => obj.get("filename").and_then(|name| name.as_str()).map(|name| name.to_string()),
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    fn close_window_tree(&mut self, win: &WinId) -> (u32,u32) {
+        let mut result = (0,0);
+        for (id,w) in self.windows.close(win) {
+            let (readcount,writecount,_,_) = self.streams.close(&w.str);
+            if id == *win {
+                result = (readcount,writecount);
+            }
+            self.contents.remove(&id);
+        }
+        self.relayout();
+        result
+    }
+}
+
+fn parse_metrics(value: &Value, default: Metrics) -> Metrics {
+    let get = |name: &str, default: u32| value[name].as_f64().map(|v| v as u32).unwrap_or(default);
+    let charwidth = get("charwidth", default.buffer_char_width);
+    let charheight = get("charheight", default.buffer_char_height);
+    Metrics{
+        width: get("width", default.width),
+        height: get("height", default.height),
+        spacing: get("inspacing", default.spacing),
+        grid_char_width: get("gridcharwidth", charwidth),
+        grid_char_height: get("gridcharheight", charheight),
+        grid_margin_x: get("gridmarginx", default.grid_margin_x),
+        grid_margin_y: get("gridmarginy", default.grid_margin_y),
+        buffer_char_width: get("buffercharwidth", charwidth),
+        buffer_char_height: get("buffercharheight", charheight),
+        buffer_margin_x: get("buffermarginx", default.buffer_margin_x),
+        buffer_margin_y: get("buffermarginy", default.buffer_margin_y),
+        graphics_margin_x: get("graphicsmarginx", default.graphics_margin_x),
+        graphics_margin_y: get("graphicsmarginy", default.graphics_margin_y),
+    }
+}
+
+fn to_chars(s: &[u8]) -> Vec<u32> {
+    s.iter().map(|&ch| ch as u32).collect()
+}
+
+impl<'a,R: Read + 'a,W: Write + 'a> Glk<'a> for RemGlk<R,W> {
+    type WinId = WinId;
+    type StrId = StrId;
+    type FRefId = FRefId;
+    type SChanId = SChanId;
+    type Event = Event;
+    type TimeVal = TimeVal;
+    type Date = Date;
+    type IOStream = IOStream<WinId>;
+
+    fn exit(&mut self) -> ! {
+        self.send_update(None, false);
+        session::end(Ok(()))
+    }
+
+    fn set_interrupt_handler(&mut self, _handler: extern fn()) {
+    }
+
+    fn tick(&mut self) {
+    }
+
+
+    fn gestalt(&mut self, sel: u32, val: u32) -> u32 {
+        self.gestalt_ext(sel, val, &mut [])
+    }
+
+    fn gestalt_ext(&mut self, sel: u32, val: u32, arr: &mut [u32]) -> u32 {
+        match sel {
+            glk::gestalt_Version => 0x00070500,
+            glk::gestalt_CharInput => if val < 0x110000 || content::key_name(val).is_some() { 1 } else { 0 },
+            glk::gestalt_LineInput => if val >= 32 && val < 0x110000 && (val < 127 || val > 159) { 1 } else { 0 },
+            glk::gestalt_CharOutput => {
+                if arr.len() > 0 {
+                    arr[0] = 1;
+                }
+                if val >= 32 && val < 0x110000 && (val < 127 || val > 159) {
+                    glk::gestalt_CharOutput_ExactPrint
+                } else {
+                    glk::gestalt_CharOutput_CannotPrint
+                }
+            },
+            glk::gestalt_MouseInput => if val == glk::wintype_TextGrid || val == glk::wintype_Graphics { 1 } else { 0 },
+            glk::gestalt_Timer => 1,
+            glk::gestalt_Graphics => 1,
+            glk::gestalt_DrawImage => if val == glk::wintype_TextBuffer || val == glk::wintype_Graphics { 1 } else { 0 },
+            glk::gestalt_GraphicsTransparency => 1,
+            glk::gestalt_Hyperlinks => 1,
+            glk::gestalt_HyperlinkInput => if val == glk::wintype_TextBuffer || val == glk::wintype_TextGrid { 1 } else { 0 },
+            glk::gestalt_Unicode => 1,
+            glk::gestalt_UnicodeNorm => 1,
+            glk::gestalt_LineInputEcho => 1,
+            glk::gestalt_LineTerminators => 1,
+            glk::gestalt_LineTerminatorKey => if val == glk::keycode_Escape || (val <= glk::keycode_Func1 && val >= glk::keycode_Func12) { 1 } else { 0 },
+            glk::gestalt_DateTime => 1,
+            glk::gestalt_ResourceStream => 1,
+            glk::gestalt_DrawImageScale => 1,
+            _ => 0,
+        }
+    }
+
+
+    fn char_to_lower(&mut self, ch: u8) -> u8 {
+        glk::unicode::latin1_to_lower(ch)
+    }
+
+    fn char_to_upper(&mut self, ch: u8) -> u8 {
+        glk::unicode::latin1_to_upper(ch)
+    }
+
+
+    fn window_get_root(&mut self) -> Self::WinId {
+        self.windows.root()
+    }
+
+    fn window_open(&mut self, split: &Self::WinId, method: u32, size: u32, wintype: u32, rock: u32) -> Self::WinId {
+        match wintype {
+            glk::wintype_Blank | glk::wintype_TextBuffer | glk::wintype_TextGrid | glk::wintype_Graphics => (),
+            _ => return WinId(0),
+        }
+        let (win,pair) = match self.windows.open(split, method, size, wintype, rock) {
+            Some(opened) => opened,
+            None => return WinId(0),
+        };
+        let str = self.streams.open_window(win);
+        self.windows.get_mut(&win).unwrap().str = str;
+        self.contents.insert(&win, Window::new(wintype));
+        if let Some(pair) = pair {
+            let str = self.streams.open_window(pair);
+            self.windows.get_mut(&pair).unwrap().str = str;
+            self.contents.insert(&pair, Window::new(glk::wintype_Pair));
+        }
+        self.relayout();
+        win
+    }
+
+    fn window_close(&mut self, win: &mut Self::WinId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        let (readcount,writecount) = self.close_window_tree(win);
+        (readcount,writecount,None,None)
+    }
+
+    fn window_get_size(&mut self, win: &Self::WinId) -> (u32,u32) {
+        self.windows.size(win)
+    }
+
+    fn window_set_arrangement(&mut self, win: &Self::WinId, method: u32, size: u32, keywin: &Self::WinId) {
+        self.windows.set_arrangement(win, method, size, keywin);
+        self.relayout();
+    }
+
+    fn window_get_arrangement(&mut self, win: &Self::WinId) -> (u32,u32,Self::WinId) {
+        self.windows.get_arrangement(win)
+    }
+
+    fn window_iterate(&mut self, win: &Self::WinId) -> (Self::WinId,u32) {
+        self.windows.iterate(win)
+    }
+
+    fn window_get_rock(&mut self, win: &Self::WinId) -> u32 {
+        self.windows.get(win).map(|w| w.rock).unwrap_or(0)
+    }
+
+    fn window_get_type(&mut self, win: &Self::WinId) -> u32 {
+        self.windows.get(win).map(|w| w.wintype).unwrap_or(0)
+    }
+
+    fn window_get_parent(&mut self, win: &Self::WinId) -> Self::WinId {
+        self.windows.get(win).map(|w| w.parent).unwrap_or(WinId(0))
+    }
+
+    fn window_get_sibling(&mut self, win: &Self::WinId) -> Self::WinId {
+        self.windows.sibling(win)
+    }
+
+    fn window_clear(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.clear();
+        }
+    }
+
+    fn window_move_cursor(&mut self, win: &Self::WinId, xpos: u32, ypos: u32) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.move_cursor(xpos, ypos);
+        }
+    }
+
+
+    fn window_get_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        self.windows.get(win).map(|w| w.str).unwrap_or(StrId(0))
+    }
+
+    fn window_set_echo_stream(&mut self, win: &Self::WinId, str: &Self::StrId) {
+        let winstr = self.window_get_stream(win);
+        self.streams.set_echo(&winstr, str);
+    }
+
+    fn window_get_echo_stream(&mut self, win: &Self::WinId) -> Self::StrId {
+        let winstr = self.window_get_stream(win);
+        self.streams.echo(&winstr)
+    }
+
+    fn set_window(&mut self, win: &Self::WinId) {
+        let str = self.window_get_stream(win);
+        self.streams.set_current(&str);
+    }
+
+
+    fn stream_open_file(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.streams.open_file(&self.frefs, fileref, fmode, rock, false)
+    }
+
+    fn stream_open_memory(&mut self, buf: (u32,Box<[u8]>), fmode: u32, rock: u32) -> Self::StrId {
+        self.streams.open_memory(buf, fmode, rock)
+    }
+
+    fn stream_close(&mut self, str: &mut Self::StrId) -> (u32,u32,Option<(u32,Box<[u8]>)>,Option<(u32,Box<[u32]>)>) {
+        // Window streams are closed with their windows.
+        if self.streams.window(str).is_some() {
+            return (0,0,None,None);
+        }
+        self.streams.close(str)
+    }
+
+    fn stream_iterate(&mut self, str: &Self::StrId) -> (Self::StrId,u32) {
+        self.streams.iterate(str)
+    }
+
+    fn stream_get_rock(&mut self, str: &Self::StrId) -> u32 {
+        self.streams.rock(str)
+    }
+
+    fn stream_set_position(&mut self, str: &Self::StrId, pos: i32, seekmode: u32) {
+        self.streams.set_position(str, pos, seekmode);
+    }
+
+    fn stream_get_position(&mut self, str: &Self::StrId) -> u32 {
+        self.streams.position(str)
+    }
+
+    fn stream_set_current(&mut self, str: &Self::StrId) {
+        self.streams.set_current(str);
+    }
+
+    fn stream_get_current(&mut self) -> Self::StrId {
+        self.streams.current()
+    }
+
+
+    fn put_char(&mut self, ch: u8) {
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &[ch as u32]);
+    }
+
+    fn put_char_stream(&mut self, str: &Self::StrId, ch: u8) {
+        self.streams.put(&mut self.contents, str, &[ch as u32]);
+    }
+
+    fn put_string<S: AsRef<[u8]>>(&mut self, s: S) {
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &to_chars(s.as_ref()));
+    }
+
+    fn put_string_stream<S: AsRef<[u8]>>(&mut self, str: &Self::StrId, s: S) {
+        self.streams.put(&mut self.contents, str, &to_chars(s.as_ref()));
+    }
+
+    fn put_buffer(&mut self, buf: &[u8]) {
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &to_chars(buf));
+    }
+
+    fn put_buffer_stream(&mut self, str: &Self::StrId, buf: &[u8]) {
+        self.streams.put(&mut self.contents, str, &to_chars(buf));
+    }
+
+    fn set_style(&mut self, styl: u32) {
+        let str = self.streams.current();
+        self.streams.set_style(&mut self.contents, &str, styl);
+    }
+
+    fn set_style_stream(&mut self, str: &Self::StrId, styl: u32) {
+        self.streams.set_style(&mut self.contents, str, styl);
+    }
+
+
+    fn get_char_stream(&mut self, str: &Self::StrId) -> i32 {
+        self.streams.get_char(str, false)
+    }
+
+    fn get_line_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        self.streams.get_line(str, buf)
+    }
+
+    fn get_buffer_stream(&mut self, str: &Self::StrId, buf: &mut [u8]) -> u32 {
+        self.streams.get_buffer(str, buf)
+    }
+
+
+    fn stylehint_set(&mut self, _wintype: u32, _styl: u32, _hint: u32, _val: i32) {
+    }
+
+    fn stylehint_clear(&mut self, _wintype: u32, _styl: u32, _hint: u32) {
+    }
+
+    fn style_distinguish(&mut self, _win: &Self::WinId, styl1: u32, styl2: u32) -> bool {
+        styl1 != styl2
+    }
+
+    fn style_measure(&mut self, _win: &Self::WinId, _styl: u32, _hint: u32) -> (bool,u32) {
+        (false,0)
+    }
+
+
+    fn fileref_create_temp(&mut self, usage: u32, rock: u32) -> Self::FRefId {
+        self.frefs.create_temp(usage, rock)
+    }
+
+    fn fileref_create_by_name<S: AsRef<[u8]>>(&mut self, usage: u32, name: S, rock: u32) -> Self::FRefId {
+        self.frefs.create_by_name(usage, name, rock)
+    }
+
+    fn fileref_create_by_prompt(&mut self, usage: u32, fmode: u32, rock: u32) -> Self::FRefId {
+        // The name comes from the frontend, so it is kept to the
+        // directory for files created by name.
+        match self.file_prompt(usage, fmode) {
+            Some(ref name) if Path::new(name).is_absolute() || Path::new(name).components().any(|c| c == Component::ParentDir) => FRefId(0),
+            Some(name) => self.frefs.create_by_name(usage, name, rock),
+            None => FRefId(0),
+        }
+    }
+
+    fn fileref_create_from_fileref(&mut self, usage: u32, fref: &Self::FRefId, rock: u32) -> Self::FRefId {
+        self.frefs.create_from_fileref(usage, fref, rock)
+    }
+
+    fn fileref_destroy(&mut self, fref: &mut Self::FRefId) {
+        self.frefs.destroy(fref);
+    }
+
+    fn fileref_iterate(&mut self, fref: &Self::FRefId) -> (Self::FRefId,u32) {
+        self.frefs.iterate(fref)
+    }
+
+    fn fileref_get_rock(&mut self, fref: &Self::FRefId) -> u32 {
+        self.frefs.rock(fref)
+    }
+
+    fn fileref_delete_file(&mut self, fref: &Self::FRefId) {
+        self.frefs.delete_file(fref);
+    }
+
+    fn fileref_does_file_exist(&mut self, fref: &Self::FRefId) -> bool {
+        self.frefs.does_file_exist(fref)
+    }
+
+
+    fn select(&mut self) -> Self::Event {
+        self.send_update(None, true);
+        loop {
+            let msg = self.next_message();
+            if let Some(event) = self.accept(msg) {
+                return event;
+            }
+        }
+    }
+
+    // Input is only read in select.
+    fn select_poll(&mut self) -> Self::Event {
+        Event::none()
+    }
+
+
+    fn request_timer_events(&mut self, millisecs: u32) {
+        self.timer = if millisecs == 0 { None } else { Some(millisecs) };
+        self.timer_changed = true;
+    }
+
+
+    fn request_line_event(&mut self, win: &Self::WinId, buf: (u32,Box<[u8]>), initlen: u32) {
+        let gen = self.gen + 1;
+        if let Some(w) = self.contents.get_mut(win) {
+            w.line_request = Some(LineRequest{ buf: LineBuf::Latin1(buf), initlen, gen });
+        }
+    }
+
+    fn request_char_event(&mut self, win: &Self::WinId) {
+        let gen = self.gen + 1;
+        if let Some(w) = self.contents.get_mut(win) {
+            w.char_request = Some((false,gen));
+        }
+    }
+
+    fn request_mouse_event(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.mouse_request = true;
+        }
+    }
+
+
+    fn cancel_line_event(&mut self, win: &Self::WinId) -> Self::Event {
+        let partial = match self.contents.get_mut(win) {
+            Some(ref mut w) if w.line_request.is_some() => w.partial.take().unwrap_or(String::new()),
+            _ => return Event::none(),
+        };
+        self.accept_line(win, &partial, 0).unwrap_or(Event::none())
+    }
+
+    fn cancel_char_event(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.char_request = None;
+        }
+    }
+
+    fn cancel_mouse_event(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.mouse_request = false;
+        }
+    }
+
+
+    fn set_echo_line_event(&mut self, win: &Self::WinId, val: u32) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.echo_line = val != 0;
+        }
+    }
+
+
+    fn set_terminators_line_event(&mut self, win: &Self::WinId, keycodes: &[u32]) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.terminators = keycodes.to_vec();
+        }
+    }
+
+
+    fn buffer_to_lower_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        glk::unicode::to_lower_case(buf, numchars)
+    }
+
+    fn buffer_to_upper_case_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        glk::unicode::to_upper_case(buf, numchars)
+    }
+
+    fn buffer_to_title_case_uni(&mut self, buf: &mut [u32], numchars: u32, lowerrest: u32) -> u32 {
+        glk::unicode::to_title_case(buf, numchars, lowerrest != 0)
+    }
+
+
+    fn put_char_uni(&mut self, ch: u32) {
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, &[ch]);
+    }
+
+    fn put_string_uni<SU: AsRef<[u32]>>(&mut self, s: SU) {
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, s.as_ref());
+    }
+
+    fn put_buffer_uni(&mut self, buf: &[u32]) {
+        let str = self.streams.current();
+        self.streams.put(&mut self.contents, &str, buf);
+    }
+
+    fn put_char_stream_uni(&mut self, str: &Self::StrId, ch: u32) {
+        self.streams.put(&mut self.contents, str, &[ch]);
+    }
+
+    fn put_string_stream_uni<SU: AsRef<[u32]>>(&mut self, str: &Self::StrId, s: SU) {
+        self.streams.put(&mut self.contents, str, s.as_ref());
+    }
+
+    fn put_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &[u32]) {
+        self.streams.put(&mut self.contents, str, buf);
+    }
+
+
+    fn get_char_stream_uni(&mut self, str: &Self::StrId) -> i32 {
+        self.streams.get_char(str, true)
+    }
+
+    fn get_buffer_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        self.streams.get_buffer_uni(str, buf)
+    }
+
+    fn get_line_stream_uni(&mut self, str: &Self::StrId, buf: &mut [u32]) -> u32 {
+        self.streams.get_line_uni(str, buf)
+    }
+
+
+    fn stream_open_file_uni(&mut self, fileref: &Self::FRefId, fmode: u32, rock: u32) -> Self::StrId {
+        self.streams.open_file(&self.frefs, fileref, fmode, rock, true)
+    }
+
+    fn stream_open_memory_uni(&mut self, buf: (u32,Box<[u32]>), fmode: u32, rock: u32) -> Self::StrId {
+        self.streams.open_memory_uni(buf, fmode, rock)
+    }
+
+
+    fn request_char_event_uni(&mut self, win: &Self::WinId) {
+        let gen = self.gen + 1;
+        if let Some(w) = self.contents.get_mut(win) {
+            w.char_request = Some((true,gen));
+        }
+    }
+
+    fn request_line_event_uni(&mut self, win: &Self::WinId, buf: (u32,Box<[u32]>), initlen: u32) {
+        let gen = self.gen + 1;
+        if let Some(w) = self.contents.get_mut(win) {
+            w.line_request = Some(LineRequest{ buf: LineBuf::Uni(buf), initlen, gen });
+        }
+    }
+
+
+    fn buffer_canon_decompose_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        glk::unicode::canon_decompose(buf, numchars)
+    }
+
+    fn buffer_canon_normalize_uni(&mut self, buf: &mut [u32], numchars: u32) -> u32 {
+        glk::unicode::canon_normalize(buf, numchars)
+    }
+
+
+    fn image_draw(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32) -> bool {
+        self.draw_image(win, image, val1, val2, None)
+    }
+
+    fn image_draw_scaled(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> bool {
+        self.draw_image(win, image, val1, val2, Some((width,height)))
+    }
+
+    fn image_get_info(&mut self, image: u32) -> (bool,u32,u32) {
+        match self.image_size(image) {
+            Some((width,height)) => (true,width,height),
+            None => (false,0,0),
+        }
+    }
+
+
+    fn window_flow_break(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.flow_break();
+        }
+    }
+
+
+    fn window_erase_rect(&mut self, win: &Self::WinId, left: i32, top: i32, width: u32, height: u32) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.draw(json!({"special": "fill", "x": left, "y": top, "width": width, "height": height}));
+        }
+    }
+
+    fn window_fill_rect(&mut self, win: &Self::WinId, color: u32, left: i32, top: i32, width: u32, height: u32) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.draw(json!({"special": "fill", "color": format!("#{:06X}", color & 0xffffff), "x": left, "y": top, "width": width, "height": height}));
+        }
+    }
+
+    fn window_set_background_color(&mut self, win: &Self::WinId, color: u32) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.draw(json!({"special": "setcolor", "color": format!("#{:06X}", color & 0xffffff)}));
+        }
+    }
+
+
+    fn image_draw_scaled_ext(&mut self, win: &Self::WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32, imagerule: u32, maxwidth: u32) -> bool {
+        let (orig_width,orig_height) = match self.image_size(image) {
+            Some(size) => size,
+            None => return false,
+        };
+        let win_width = self.windows.get(win).map(|w| w.rect.width).unwrap_or(0) as u64;
+        // Ratios are 16.16 fixed point.
+        let mut w = match imagerule & glk::imagerule_WidthMask {
+            glk::imagerule_WidthFixed => width as u64,
+            glk::imagerule_WidthRatio => win_width * width as u64 / 0x10000,
+            _ => orig_width as u64,
+        };
+        let aspect = imagerule & glk::imagerule_HeightMask == glk::imagerule_AspectRatio;
+        let mut h = match imagerule & glk::imagerule_HeightMask {
+            glk::imagerule_HeightFixed => height as u64,
+            glk::imagerule_AspectRatio if orig_width > 0 => w * orig_height as u64 * height as u64 / orig_width as u64 / 0x10000,
+            _ => orig_height as u64,
+        };
+        let max = win_width * maxwidth as u64 / 0x10000;
+        if maxwidth != 0 && w > max {
+            if aspect && w > 0 {
+                h = h * max / w;
+            }
+            w = max;
+        }
+        self.draw_image(win, image, val1, val2, Some((w as u32,h as u32)))
+    }
+
+
+    fn schannel_create(&mut self, _rock: u32) -> Self::SChanId {
+        SChanId(())
+    }
+
+    fn schannel_destroy(&mut self, _chan: &mut Self::SChanId) {
+    }
+
+    fn schannel_iterate(&mut self, _chan: &Self::SChanId) -> (Self::SChanId,u32) {
+        (SChanId(()),0)
+    }
+
+    fn schannel_get_rock(&mut self, _chan: &Self::SChanId) -> u32 {
+        0
+    }
+
+
+    fn schannel_play(&mut self, _chan: &Self::SChanId, _snd: u32) -> bool {
+        false
+    }
+
+    fn schannel_play_ext(&mut self, _chan: &Self::SChanId, _snd: u32, _repeat: u32, _notify: u32) -> bool {
+        false
+    }
+
+    fn schannel_stop(&mut self, _chan: &Self::SChanId) {
+    }
+
+    fn schannel_set_volume(&mut self, _chan: &Self::SChanId, _vol: u32) {
+    }
+
+
+    fn sound_load_hint(&mut self, _snd: u32, _flag: u32) {
+    }
+
+
+    fn schannel_create_ext(&mut self, _rock: u32, _volume: u32) -> Self::SChanId {
+        SChanId(())
+    }
+
+    fn schannel_play_multi(&mut self, _chanarray: &[Self::SChanId], _sndarray: &[u32], _notify: u32) -> bool {
+        false
+    }
+
+    fn schannel_pause(&mut self, _chan: &Self::SChanId) {
+    }
+
+    fn schannel_unpause(&mut self, _chan: &Self::SChanId) {
+    }
+
+    fn schannel_set_volume_ext(&mut self, _chan: &Self::SChanId, _vol: u32, _duration: u32, _notify: u32) {
+    }
+
+
+    fn set_hyperlink(&mut self, linkval: u32) {
+        let str = self.streams.current();
+        self.streams.set_hyperlink(&mut self.contents, &str, linkval);
+    }
+
+    fn set_hyperlink_stream(&mut self, str: &Self::StrId, linkval: u32) {
+        self.streams.set_hyperlink(&mut self.contents, str, linkval);
+    }
+
+    fn request_hyperlink_event(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.hyperlink_request = true;
+        }
+    }
+
+    fn cancel_hyperlink_event(&mut self, win: &Self::WinId) {
+        if let Some(w) = self.contents.get_mut(win) {
+            w.hyperlink_request = false;
+        }
+    }
+
+
+    // There is no time zone information, so local time is UTC.
+    fn current_time(&mut self) -> Self::TimeVal {
+        TimeVal::now()
+    }
+
+    fn current_simple_time(&mut self, factor: u32) -> i32 {
+        TimeVal::now().to_simple(factor)
+    }
+
+    fn time_to_date_utc(&mut self, time: &Self::TimeVal) -> Self::Date {
+        Date::from_time(time, 0)
+    }
+
+    fn time_to_date_local(&mut self, time: &Self::TimeVal) -> Self::Date {
+        Date::from_time(time, 0)
+    }
+
+    fn simple_time_to_date_utc(&mut self, time: i32, factor: u32) -> Self::Date {
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
+    }
+
+    fn simple_time_to_date_local(&mut self, time: i32, factor: u32) -> Self::Date {
+        Date::from_time(&TimeVal::from_simple(time, factor), 0)
+    }
+
+    fn date_to_time_utc(&mut self, date: &Self::Date) -> Self::TimeVal {
+        date.to_time(0)
+    }
+
+    fn date_to_time_local(&mut self, date: &Self::Date) -> Self::TimeVal {
+        date.to_time(0)
+    }
+
+    fn date_to_simple_time_utc(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        date.to_time(0).to_simple(factor)
+    }
+
+    fn date_to_simple_time_local(&mut self, date: &Self::Date, factor: u32) -> i32 {
+        date.to_time(0).to_simple(factor)
+    }
+
+
+    fn stream_open_resource(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        let resource = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum));
+        self.streams.open_resource(resource, rock, false)
+    }
+
+    fn stream_open_resource_uni(&mut self, filenum: u32, rock: u32) -> Self::StrId {
+        let resource = self.resources.as_ref().and_then(|blorb| blorb.resource(Usage::Data, filenum));
+        self.streams.open_resource(resource, rock, true)
+    }
+
+
+    fn io_stream(&mut self, str: &mut Self::StrId) -> Self::IOStream {
+        self.streams.io_stream(str)
+    }
+
+
+    fn set_resource_map(&mut self, blorb: Blorb) {
+        self.resources = Some(blorb);
+    }
+}
+
+#[derive(Clone,Eq,Hash,PartialEq)]
+pub struct SChanId(());
+
+impl IdType for SChanId {
+    fn null() -> Self {
+        SChanId(())
+    }
+
+    fn is_null(&self) -> bool {
+        true
+    }
+}
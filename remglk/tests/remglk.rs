@@ -0,0 +1,158 @@
+extern crate glk;
+extern crate remglk;
+extern crate serde_json;
+
+use std::io::{self,Cursor,ErrorKind,Read,Write};
+use std::sync::{Arc,Mutex};
+use std::thread;
+
+use glk::{Glk,IdType};
+use glk::session;
+use remglk::RemGlk;
+use serde_json::Value;
+
+const INIT: &'static str = r#"{"type":"init","gen":0,"metrics":{"width":800,"height":480,"charwidth":10,"charheight":20}}"#;
+
+fn updates(output: &[u8]) -> Vec<Value> {
+    serde_json::Deserializer::from_slice(output).into_iter::<Value>().map(|v| v.unwrap()).collect()
+}
+
+#[test]
+fn line_input() {
+    let input = format!("{}\n{}\n", INIT, r#"{"type":"line","gen":1,"window":1,"value":"look"}"#);
+    let mut glk = RemGlk::new(Cursor::new(input.into_bytes()), Vec::new()).unwrap();
+    let mainwin = glk.window_open(&glk::windows::WinId(0), 0, 0, glk::wintype_TextBuffer, 7);
+    assert_eq!(glk::windows::WinId(1), mainwin);
+    assert_eq!((80,24), glk.window_get_size(&mainwin));
+    glk.set_window(&mainwin);
+    glk.set_style(glk::style_Emphasized);
+    glk.put_string("Hello");
+    glk.set_style(glk::style_Normal);
+    glk.put_string("\n>");
+    glk.request_line_event(&mainwin, (0,vec![0; 10].into_boxed_slice()), 0);
+    let event = glk.select();
+    assert_eq!(glk::evtype_LineInput, event.evtype);
+    assert_eq!(mainwin, event.win);
+    assert_eq!(4, event.val1);
+    assert_eq!(b"look", &event.buf.unwrap().1[..4]);
+
+    let (_,output) = glk.into_inner();
+    let updates = updates(&output);
+    assert_eq!(1, updates.len());
+    let update = &updates[0];
+    assert_eq!("update", update["type"]);
+    assert_eq!(1, update["gen"]);
+    assert_eq!(serde_json::json!([{"id": 1, "type": "buffer", "rock": 7, "left": 0, "top": 0, "width": 800, "height": 480}]), update["windows"]);
+    assert_eq!(serde_json::json!([{"id": 1, "text": [
+        {"append": true, "content": [{"style": "emphasized", "text": "Hello"}]},
+        {"content": [{"style": "normal", "text": ">"}]},
+    ]}]), update["content"]);
+    assert_eq!(serde_json::json!([{"id": 1, "type": "line", "gen": 1, "maxlen": 10}]), update["input"]);
+}
+
+#[test]
+fn grid_and_char_input() {
+    let input = format!("{}\n{}\n{}\n{}\n", INIT,
+                        r#"{"type":"char","gen":0,"window":2,"value":"x"}"#,
+                        r#"{"type":"arrange","gen":1,"metrics":{"width":400,"height":480,"charwidth":10,"charheight":20}}"#,
+                        r#"{"type":"char","gen":2,"window":2,"value":"left"}"#);
+    let mut glk = RemGlk::new(Cursor::new(input.into_bytes()), Vec::new()).unwrap();
+    let mainwin = glk.window_open(&glk::windows::WinId(0), 0, 0, glk::wintype_TextBuffer, 0);
+    let statuswin = glk.window_open(&mainwin, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 0);
+    assert_eq!((80,1), glk.window_get_size(&statuswin));
+    glk.window_move_cursor(&statuswin, 2, 0);
+    let str = glk.window_get_stream(&statuswin);
+    glk.put_string_stream(&str, "Room");
+    glk.request_char_event(&statuswin);
+
+    // The stale char event is ignored.
+    let event = glk.select();
+    assert_eq!(glk::evtype_Arrange, event.evtype);
+    assert_eq!((40,1), glk.window_get_size(&statuswin));
+    let event = glk.select();
+    assert_eq!(glk::evtype_CharInput, event.evtype);
+    assert_eq!(statuswin, event.win);
+    assert_eq!(glk::keycode_Left, event.val1);
+
+    let (_,output) = glk.into_inner();
+    let updates = updates(&output);
+    assert_eq!(2, updates.len());
+    assert_eq!(serde_json::json!([{"id": 2, "lines": [
+        {"line": 0, "content": [{"style": "normal", "text": format!("  Room{}", " ".repeat(74))}]},
+    ]}]), updates[0]["content"]);
+    assert_eq!(serde_json::json!([{"id": 2, "type": "char", "gen": 1, "xpos": 6, "ypos": 0}]), updates[0]["input"]);
+    assert_eq!(40, updates[1]["windows"][1]["gridwidth"]);
+    assert_eq!(serde_json::json!([{"id": 2, "type": "char", "gen": 1, "xpos": 6, "ypos": 0}]), updates[1]["input"]);
+}
+
+#[test]
+fn prompted_file_names() {
+    let prompt = |name: &str| format!("{}\n{}\n", INIT, serde_json::json!({"type": "specialresponse", "gen": 1, "response": "fileref_prompt", "value": name}));
+    for &(name,ok) in &[("game", true), ("../../tmp/game", false), ("/tmp/game", false), ("saves/../../game", false)] {
+        let mut glk = RemGlk::new(Cursor::new(prompt(name).into_bytes()), Vec::new()).unwrap();
+        let fref = glk.fileref_create_by_prompt(glk::fileusage_SavedGame, glk::filemode_Write, 0);
+        assert_eq!(ok, !fref.is_null(), "{}", name);
+    }
+}
+
+// Echoes lines until the player types quit.
+fn echo<R: Read,W: Write>(mut glk: RemGlk<R,W>) -> io::Result<()> {
+    let mainwin = glk.window_open(&glk::windows::WinId(0), 0, 0, glk::wintype_TextBuffer, 0);
+    glk.set_window(&mainwin);
+    loop {
+        glk.request_line_event(&mainwin, (0,vec![0; 10].into_boxed_slice()), 0);
+        let event = glk.select();
+        let line = event.buf.unwrap().1[.. event.val1 as usize].to_vec();
+        if line == b"quit" {
+            glk.exit();
+        }
+        glk.put_buffer(&line);
+    }
+}
+
+#[derive(Clone)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// A session on a thread of its own, with the lines as its input.
+fn run_echo(lines: &[&str]) -> (thread::JoinHandle<io::Result<()>>,Output) {
+    let mut input = format!("{}\n", INIT);
+    for (i,line) in lines.iter().enumerate() {
+        input.push_str(&format!("{}\n", serde_json::json!({"type": "line", "gen": i + 1, "window": 1, "value": line})));
+    }
+    let output = Output(Arc::new(Mutex::new(Vec::new())));
+    let glk = RemGlk::new(Cursor::new(input.into_bytes()), output.clone()).unwrap();
+    (thread::spawn(move || session::run(|| echo(glk))),output)
+}
+
+// Sessions end at glk_exit or the end of their input, leaving the process
+// and the other sessions running.
+#[test]
+fn sessions() {
+    let (quits,quits_output) = run_echo(&["hello", "quit"]);
+    let (closes,closes_output) = run_echo(&["hello"]);
+    assert!(quits.join().unwrap().is_ok());
+    let quit_updates = updates(&quits_output.0.lock().unwrap());
+    assert_eq!(3, quit_updates.len());
+    assert_eq!(true, quit_updates[2]["disable"]);
+    assert!(closes.join().unwrap().is_ok());
+    let close_updates = updates(&closes_output.0.lock().unwrap());
+    assert_eq!(2, close_updates.len());
+    assert_eq!(serde_json::json!([{"id": 1, "text": [
+        {"append": true, "content": [{"style": "input", "text": "hello"}]},
+        {"content": [{"style": "normal", "text": "hello"}]},
+    ]}]), close_updates[1]["content"]);
+
+    let input = format!("{}\n{{bad}}\n", INIT);
+    let glk = RemGlk::new(Cursor::new(input.into_bytes()), Vec::new()).unwrap();
+    assert_eq!(ErrorKind::InvalidData, session::run(|| echo(glk)).unwrap_err().kind());
+}
@@ -0,0 +1,929 @@
+use rand;
+use glk::Glk;
+
+use super::{object,save,screen,text};
+use super::object::Link;
+use super::state::{read_u16,write_u16,Frame,State,UndoState,GLOBALS,INITIAL_PC};
+
+pub struct Execute<'a,G: Glk<'a>> {
+    pub state: State,
+    pub glk: G,
+    pub screen: screen::Screen<'a,G>,
+
+    pub undo_state: Vec<UndoState>,
+    pub undo_depth: usize,
+    pub rng: rand::XorShiftRng,
+    pub seeded: bool,
+    pub strict: bool,
+
+    pub quit: bool,
+    // Counts restarts and restores, which abandon interrupt routines.
+    pub generation: u32,
+    pub interrupt_result: Option<u16>,
+}
+
+// How an instruction's opcode was encoded.
+#[derive(Clone,Copy,Eq,PartialEq)]
+enum Form {
+    Op0,
+    Op1,
+    Op2,
+    Var,
+    Ext,
+}
+
+impl<'a,G: Glk<'a>> Execute<'a,G> {
+    pub fn new(state: State, glk: G, options: super::Options) -> Self {
+        let mut exec = Execute{
+            state: state,
+            glk: glk,
+            screen: screen::Screen::new(),
+
+            undo_state: Vec::new(),
+            undo_depth: options.undo_depth,
+            rng: rand::SeedableRng::from_seed(if options.random_seed == 0 { rand::random() } else { [options.random_seed; 4] }),
+            seeded: options.random_seed != 0,
+            strict: options.strict,
+
+            quit: false,
+            generation: 0,
+            interrupt_result: None,
+        };
+        exec.open_windows();
+        exec.start();
+        exec
+    }
+
+    fn start(&mut self) {
+        self.init_header();
+        self.state.stack.clear();
+        self.state.frames.clear();
+        self.state.frames.push(Frame{
+            return_pc: 0,
+            store: None,
+            args: 0,
+            locals: Vec::new(),
+            stack_base: 0,
+            interrupt: false,
+        });
+        let initial_pc = read_u16(&self.state.mem, INITIAL_PC);
+        if self.state.version == 6 {
+            self.state.pc = 0;
+            self.call(initial_pc, &[], None, false);
+        } else {
+            self.state.pc = initial_pc as usize;
+        }
+    }
+
+    pub fn run(&mut self) {
+        while !self.quit {
+            self.step();
+        }
+    }
+
+    fn next_u8(&mut self) -> u8 {
+        let val = self.state.mem[self.state.pc];
+        self.state.pc += 1;
+        val
+    }
+
+    fn next_u16(&mut self) -> u16 {
+        let val = read_u16(&self.state.mem, self.state.pc);
+        self.state.pc += 2;
+        val
+    }
+
+    // Operands of the types in a type byte, stopping at the first omitted.
+    fn operands(&mut self, types: &[u8], operands: &mut Vec<u16>) {
+        for &byte in types {
+            for i in 0 .. 4 {
+                match (byte >> (6 - 2*i)) & 3 {
+                    0 => {
+                        let val = self.next_u16();
+                        operands.push(val);
+                    },
+                    1 => {
+                        let val = self.next_u8() as u16;
+                        operands.push(val);
+                    },
+                    2 => {
+                        let var = self.next_u8();
+                        let val = self.read_var(var);
+                        operands.push(val);
+                    },
+                    _ => return,
+                }
+            }
+        }
+    }
+
+    pub fn read_var(&mut self, var: u8) -> u16 {
+        match var {
+            0 => {
+                let base = self.state.frames.last().map(|frame| frame.stack_base).unwrap_or(0);
+                if self.state.stack.len() <= base {
+                    panic!("{:x}: stack underflow", self.state.pc);
+                }
+                self.state.stack.pop().unwrap()
+            },
+            1 ..= 15 => self.local(var),
+            _ => read_u16(&self.state.mem, read_u16(&self.state.mem, GLOBALS) as usize + 2*(var as usize - 16)),
+        }
+    }
+
+    pub fn write_var(&mut self, var: u8, val: u16) {
+        match var {
+            0 => self.state.stack.push(val),
+            1 ..= 15 => self.set_local(var, val),
+            _ => {
+                let addr = read_u16(&self.state.mem, GLOBALS) as usize + 2*(var as usize - 16);
+                write_u16(&mut self.state.mem, addr, val);
+            },
+        }
+    }
+
+    // The variable operands of inc, dec, load, store and pull read and
+    // write the top of the stack in place.
+    fn read_var_indirect(&mut self, var: u8) -> u16 {
+        if var == 0 {
+            match self.state.stack.last() {
+                Some(&val) => val,
+                None => panic!("{:x}: stack underflow", self.state.pc),
+            }
+        } else {
+            self.read_var(var)
+        }
+    }
+
+    fn write_var_indirect(&mut self, var: u8, val: u16) {
+        if var == 0 {
+            match self.state.stack.last_mut() {
+                Some(top) => *top = val,
+                None => panic!("{:x}: stack underflow", self.state.pc),
+            }
+        } else {
+            self.write_var(var, val);
+        }
+    }
+
+    fn local(&self, var: u8) -> u16 {
+        match self.state.frames.last().and_then(|frame| frame.locals.get(var as usize - 1)) {
+            Some(&val) => val,
+            None => panic!("{:x}: invalid local variable {}", self.state.pc, var),
+        }
+    }
+
+    fn set_local(&mut self, var: u8, val: u16) {
+        let pc = self.state.pc;
+        match self.state.frames.last_mut().and_then(|frame| frame.locals.get_mut(var as usize - 1)) {
+            Some(local) => *local = val,
+            None => panic!("{:x}: invalid local variable {}", pc, var),
+        }
+    }
+
+    pub fn store(&mut self, val: u16) {
+        let var = self.next_u8();
+        self.write_var(var, val);
+    }
+
+    pub fn branch(&mut self, cond: bool) {
+        let byte = self.next_u8();
+        let offset = if byte & 0x40 != 0 {
+            (byte & 0x3f) as i32
+        } else {
+            let offset = ((byte as i32 & 0x3f) << 8) | self.next_u8() as i32;
+            if offset & 0x2000 != 0 { offset - 0x4000 } else { offset }
+        };
+        if cond == (byte & 0x80 != 0) {
+            match offset {
+                0 | 1 => self.ret(offset as u16),
+                _ => self.state.pc = (self.state.pc as i32 + offset - 2) as usize,
+            }
+        }
+    }
+
+    pub fn call(&mut self, routine: u16, args: &[u16], store: Option<u8>, interrupt: bool) {
+        if routine == 0 {
+            if let Some(var) = store {
+                self.write_var(var, 0);
+            }
+            return;
+        }
+        let mut addr = self.state.routine_addr(routine);
+        let count = self.state.mem[addr] as usize;
+        addr += 1;
+        let mut locals = vec![0; count];
+        if self.state.version <= 4 {
+            for i in 0 .. count {
+                locals[i] = read_u16(&self.state.mem, addr);
+                addr += 2;
+            }
+        }
+        for i in 0 .. ::std::cmp::min(count, args.len()) {
+            locals[i] = args[i];
+        }
+        self.state.frames.push(Frame{
+            return_pc: self.state.pc,
+            store: store,
+            args: args.len(),
+            locals: locals,
+            stack_base: self.state.stack.len(),
+            interrupt: interrupt,
+        });
+        self.state.pc = addr;
+    }
+
+    pub fn ret(&mut self, val: u16) {
+        if self.state.frames.len() <= 1 {
+            if self.state.version == 6 {
+                self.quit = true;
+                return;
+            }
+            panic!("{:x}: return from the main routine", self.state.pc);
+        }
+        let frame = self.state.frames.pop().unwrap();
+        self.state.stack.truncate(frame.stack_base);
+        self.state.pc = frame.return_pc;
+        if let Some(var) = frame.store {
+            self.write_var(var, val);
+        }
+        if frame.interrupt {
+            self.interrupt_result = Some(val);
+        }
+    }
+
+    // Runs an interrupt routine, such as a timed input routine, to its
+    // return.  Returns None if the game quit, restarted or restored
+    // instead.
+    pub fn call_interrupt(&mut self, routine: u16) -> Option<u16> {
+        let depth = self.state.frames.len();
+        let generation = self.generation;
+        self.interrupt_result = None;
+        self.call(routine, &[], None, true);
+        while self.state.frames.len() > depth && !self.quit && self.generation == generation {
+            self.step();
+        }
+        if self.quit || self.generation != generation {
+            None
+        } else {
+            self.interrupt_result.take()
+        }
+    }
+
+    // Calls the routine and stores its result when it returns.
+    fn call_store(&mut self, operands: &[u16]) {
+        let var = self.next_u8();
+        self.call(operands[0], &operands[1 ..], Some(var), false);
+    }
+
+    fn check_object(&self, obj: u16) {
+        if self.strict && obj == 0 {
+            panic!("{:x}: invalid object 0", self.state.pc);
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: usize, val: u8) {
+        if addr >= self.state.dynamic_size() {
+            if self.strict {
+                panic!("{:x}: write to static memory {:x}", self.state.pc, addr);
+            }
+            return;
+        }
+        self.state.mem[addr] = val;
+        if addr == super::state::FLAGS2 + 1 {
+            self.sync_transcript();
+        }
+    }
+
+    pub fn write_word(&mut self, addr: usize, val: u16) {
+        self.write_byte(addr, (val >> 8) as u8);
+        self.write_byte(addr + 1, val as u8);
+    }
+
+    fn print_string(&mut self, addr: usize) -> usize {
+        let (zscii,end) = text::decode(&self.state, addr);
+        for ch in zscii {
+            self.print_zscii(ch);
+        }
+        end
+    }
+
+    pub fn print_str(&mut self, s: &str) {
+        for ch in s.chars() {
+            self.print_zscii(if ch == '\n' { 13 } else { ch as u16 });
+        }
+    }
+
+    fn random(&mut self, range: u16) -> u16 {
+        use rand::{Rng,SeedableRng};
+        let range = range as i16;
+        if range > 0 {
+            return self.rng.gen_range(0, range as u32) as u16 + 1;
+        }
+        let seed = if range < 0 {
+            [-(range as i32) as u32; 4]
+        } else if self.seeded {
+            [self.rng.next_u32(),self.rng.next_u32(),self.rng.next_u32(),self.rng.next_u32() | 1]
+        } else {
+            rand::random()
+        };
+        self.rng.reseed(seed);
+        0
+    }
+
+    pub fn restart(&mut self) {
+        let flags2 = self.state.mem[super::state::FLAGS2 + 1] & 3;
+        self.state.reset_mem();
+        self.state.mem[super::state::FLAGS2 + 1] |= flags2;
+        self.generation += 1;
+        self.reset_windows();
+        self.start();
+    }
+
+    // Header values the interpreter fills in at the start and after
+    // restarts and restores.
+    pub fn init_header(&mut self) {
+        use super::state::*;
+        let version = self.state.version;
+        let mut flags1 = self.state.mem[FLAGS1];
+        if version <= 3 {
+            // Status line and split screen available, no variable pitch font.
+            flags1 &= !0x70;
+            flags1 |= 0x20;
+        } else {
+            // Colours, bold, italic, fixed space and timed input, but no
+            // pictures or sound effects in version 6.
+            flags1 = (flags1 | 0x9d) & !0x22;
+        }
+        self.state.mem[FLAGS1] = flags1;
+        // No pictures, mouse or menus, and undo if it's kept.
+        self.state.mem[FLAGS2] &= !0x01;
+        let mut flags2 = self.state.mem[FLAGS2 + 1] & !0xa8;
+        if self.undo_depth == 0 {
+            flags2 &= !0x10;
+        }
+        if self.glk.gestalt(::glk::gestalt_Sound, 0) != 0 {
+            flags2 |= self.state.mem[FLAGS2 + 1] & 0x80;
+        }
+        self.state.mem[FLAGS2 + 1] = flags2;
+        if version >= 4 {
+            self.state.mem[INTERPRETER_NUMBER] = 1;
+            self.state.mem[INTERPRETER_VERSION] = b'A';
+        }
+        self.update_screen_size();
+        if version >= 5 {
+            write_u16(&mut self.state.mem, FONT_WIDTH, 0x0101);
+            self.state.mem[DEFAULT_BACKGROUND] = 9;
+            self.state.mem[DEFAULT_FOREGROUND] = 2;
+            self.state.set_header_extension(4, 0);
+        }
+        write_u16(&mut self.state.mem, STANDARD_REVISION, 0x0101);
+    }
+
+    pub fn step(&mut self) {
+        let opcode_addr = self.state.pc;
+        let opcode = self.next_u8();
+        let mut operands = Vec::with_capacity(8);
+        let (form,number) = match opcode {
+            0x00 ..= 0x7f => {
+                // Bits 6 and 5 give the operand types: variable or small constant.
+                let first = if opcode & 0x40 != 0 { 0x80 } else { 0x40 };
+                let second = if opcode & 0x20 != 0 { 0x2f } else { 0x1f };
+                let types = [first | second];
+                self.operands(&types, &mut operands);
+                (Form::Op2,opcode & 0x1f)
+            },
+            0xbe if self.state.version >= 5 => {
+                let number = self.next_u8();
+                let types = [self.next_u8()];
+                self.operands(&types, &mut operands);
+                (Form::Ext,number)
+            },
+            0x80 ..= 0xbf => {
+                let types = [(opcode << 2) | 0x3f];
+                self.operands(&types, &mut operands);
+                (if opcode & 0x30 == 0x30 { Form::Op0 } else { Form::Op1 },opcode & 0x0f)
+            },
+            _ => {
+                // call_vs2 and call_vn2 have two type bytes.
+                let types = if opcode == 0xec || opcode == 0xfa {
+                    vec![self.next_u8(),self.next_u8()]
+                } else {
+                    vec![self.next_u8()]
+                };
+                self.operands(&types, &mut operands);
+                (if opcode & 0x20 == 0 { Form::Op2 } else { Form::Var },opcode & 0x1f)
+            },
+        };
+        let ops = &operands;
+        let op = |i: usize| ops.get(i).cloned().unwrap_or(0);
+        let version = self.state.version;
+        match (form,number) {
+            (Form::Op2,0x01) => {
+                let cond = ops[1 ..].iter().any(|&val| val == op(0));
+                self.branch(cond);
+            },
+            (Form::Op2,0x02) => self.branch((op(0) as i16) < (op(1) as i16)),
+            (Form::Op2,0x03) => self.branch((op(0) as i16) > (op(1) as i16)),
+            (Form::Op2,0x04) => {
+                let val = self.read_var_indirect(op(0) as u8).wrapping_sub(1);
+                self.write_var_indirect(op(0) as u8, val);
+                self.branch((val as i16) < (op(1) as i16));
+            },
+            (Form::Op2,0x05) => {
+                let val = self.read_var_indirect(op(0) as u8).wrapping_add(1);
+                self.write_var_indirect(op(0) as u8, val);
+                self.branch((val as i16) > (op(1) as i16));
+            },
+            (Form::Op2,0x06) => {
+                self.check_object(op(0));
+                let parent = object::get(&self.state, op(0), Link::Parent);
+                self.branch(parent == op(1));
+            },
+            (Form::Op2,0x07) => self.branch(op(0) & op(1) == op(1)),
+            (Form::Op2,0x08) => self.store(op(0) | op(1)),
+            (Form::Op2,0x09) => self.store(op(0) & op(1)),
+            (Form::Op2,0x0a) => {
+                self.check_object(op(0));
+                let attr = object::attr(&self.state, op(0), op(1));
+                self.branch(attr);
+            },
+            (Form::Op2,0x0b) => {
+                self.check_object(op(0));
+                object::set_attr(&mut self.state, op(0), op(1), true);
+            },
+            (Form::Op2,0x0c) => {
+                self.check_object(op(0));
+                object::set_attr(&mut self.state, op(0), op(1), false);
+            },
+            (Form::Op2,0x0d) => self.write_var_indirect(op(0) as u8, op(1)),
+            (Form::Op2,0x0e) => {
+                self.check_object(op(0));
+                self.check_object(op(1));
+                object::insert(&mut self.state, op(0), op(1));
+            },
+            (Form::Op2,0x0f) => {
+                let val = read_u16(&self.state.mem, op(0).wrapping_add(2*op(1)) as usize);
+                self.store(val);
+            },
+            (Form::Op2,0x10) => {
+                let val = self.state.mem[op(0).wrapping_add(op(1)) as usize] as u16;
+                self.store(val);
+            },
+            (Form::Op2,0x11) => {
+                self.check_object(op(0));
+                let val = object::get_prop(&self.state, op(0), op(1));
+                self.store(val);
+            },
+            (Form::Op2,0x12) => {
+                self.check_object(op(0));
+                let val = object::get_prop_addr(&self.state, op(0), op(1));
+                self.store(val);
+            },
+            (Form::Op2,0x13) => {
+                self.check_object(op(0));
+                let val = object::get_next_prop(&self.state, op(0), op(1));
+                self.store(val);
+            },
+            (Form::Op2,0x14) => self.store(op(0).wrapping_add(op(1))),
+            (Form::Op2,0x15) => self.store(op(0).wrapping_sub(op(1))),
+            (Form::Op2,0x16) => self.store(op(0).wrapping_mul(op(1))),
+            (Form::Op2,0x17) => {
+                if op(1) == 0 {
+                    panic!("{:x}: division by zero", opcode_addr);
+                }
+                self.store((op(0) as i16).wrapping_div(op(1) as i16) as u16);
+            },
+            (Form::Op2,0x18) => {
+                if op(1) == 0 {
+                    panic!("{:x}: division by zero", opcode_addr);
+                }
+                self.store((op(0) as i16).wrapping_rem(op(1) as i16) as u16);
+            },
+            (Form::Op2,0x19) if version >= 4 => self.call_store(ops),
+            (Form::Op2,0x1a) if version >= 5 => self.call(op(0), &ops[1 ..], None, false),
+            (Form::Op2,0x1b) if version >= 5 => self.set_colour(op(0), op(1)),
+            (Form::Op2,0x1c) if version >= 5 => {
+                self.state.frames.truncate(op(1) as usize);
+                self.ret(op(0));
+            },
+
+            (Form::Op1,0x00) => self.branch(op(0) == 0),
+            (Form::Op1,0x01) | (Form::Op1,0x02) => {
+                self.check_object(op(0));
+                let link = if number == 1 { Link::Sibling } else { Link::Child };
+                let val = object::get(&self.state, op(0), link);
+                self.store(val);
+                self.branch(val != 0);
+            },
+            (Form::Op1,0x03) => {
+                self.check_object(op(0));
+                let val = object::get(&self.state, op(0), Link::Parent);
+                self.store(val);
+            },
+            (Form::Op1,0x04) => {
+                let val = object::get_prop_len(&self.state, op(0));
+                self.store(val);
+            },
+            (Form::Op1,0x05) => {
+                let val = self.read_var_indirect(op(0) as u8).wrapping_add(1);
+                self.write_var_indirect(op(0) as u8, val);
+            },
+            (Form::Op1,0x06) => {
+                let val = self.read_var_indirect(op(0) as u8).wrapping_sub(1);
+                self.write_var_indirect(op(0) as u8, val);
+            },
+            (Form::Op1,0x07) => {
+                self.print_string(op(0) as usize);
+            },
+            (Form::Op1,0x08) if version >= 4 => self.call_store(ops),
+            (Form::Op1,0x09) => {
+                self.check_object(op(0));
+                object::remove(&mut self.state, op(0));
+            },
+            (Form::Op1,0x0a) => {
+                self.check_object(op(0));
+                if op(0) != 0 {
+                    let addr = object::name(&self.state, op(0));
+                    self.print_string(addr);
+                }
+            },
+            (Form::Op1,0x0b) => self.ret(op(0)),
+            (Form::Op1,0x0c) => self.state.pc = (self.state.pc as i32 + (op(0) as i16) as i32 - 2) as usize,
+            (Form::Op1,0x0d) => {
+                let addr = self.state.string_addr(op(0));
+                self.print_string(addr);
+            },
+            (Form::Op1,0x0e) => {
+                let val = self.read_var_indirect(op(0) as u8);
+                self.store(val);
+            },
+            (Form::Op1,0x0f) if version <= 4 => self.store(!op(0)),
+            (Form::Op1,0x0f) => self.call(op(0), &[], None, false),
+
+            (Form::Op0,0x00) => self.ret(1),
+            (Form::Op0,0x01) => self.ret(0),
+            (Form::Op0,0x02) => {
+                let pc = self.state.pc;
+                self.state.pc = self.print_string(pc);
+            },
+            (Form::Op0,0x03) => {
+                let pc = self.state.pc;
+                self.state.pc = self.print_string(pc);
+                self.print_zscii(13);
+                self.ret(1);
+            },
+            (Form::Op0,0x04) => (),
+            (Form::Op0,0x05) if version <= 4 => {
+                let result = self.save();
+                if version <= 3 {
+                    self.branch(result);
+                } else {
+                    self.store(result as u16);
+                }
+            },
+            (Form::Op0,0x06) if version <= 4 => {
+                if !self.restore() {
+                    if version <= 3 {
+                        self.branch(false);
+                    } else {
+                        self.store(0);
+                    }
+                }
+            },
+            (Form::Op0,0x07) => self.restart(),
+            (Form::Op0,0x08) => {
+                let val = self.read_var(0);
+                self.ret(val);
+            },
+            (Form::Op0,0x09) if version <= 4 => {
+                self.read_var(0);
+            },
+            (Form::Op0,0x09) => {
+                let frame = self.state.frames.len() as u16;
+                self.store(frame);
+            },
+            (Form::Op0,0x0a) => self.quit = true,
+            (Form::Op0,0x0b) => self.print_zscii(13),
+            (Form::Op0,0x0c) => {
+                if version <= 3 {
+                    self.show_status();
+                }
+            },
+            (Form::Op0,0x0d) => {
+                let result = self.verify();
+                self.branch(result);
+            },
+            (Form::Op0,0x0f) => self.branch(true),
+
+            (Form::Var,0x00) => self.call_store(ops),
+            (Form::Var,0x01) => self.write_word(op(0).wrapping_add(2*op(1)) as usize, op(2)),
+            (Form::Var,0x02) => self.write_byte(op(0).wrapping_add(op(1)) as usize, op(2) as u8),
+            (Form::Var,0x03) => {
+                self.check_object(op(0));
+                if !object::put_prop(&mut self.state, op(0), op(1), op(2)) && self.strict {
+                    panic!("{:x}: object {} has no property {}", opcode_addr, op(0), op(1));
+                }
+            },
+            (Form::Var,0x04) => {
+                let terminator = self.read_line(op(0) as usize, op(1) as usize, op(2), op(3));
+                if let Some(terminator) = terminator {
+                    if version >= 5 {
+                        self.store(terminator);
+                    }
+                }
+            },
+            (Form::Var,0x05) => self.print_zscii(op(0)),
+            (Form::Var,0x06) => {
+                let num = (op(0) as i16).to_string();
+                self.print_str(&num);
+            },
+            (Form::Var,0x07) => {
+                let val = self.random(op(0));
+                self.store(val);
+            },
+            (Form::Var,0x08) => self.write_var(0, op(0)),
+            (Form::Var,0x09) if version == 6 => {
+                let val = self.pop_user_stack(op(0), ops.len());
+                self.store(val);
+            },
+            (Form::Var,0x09) => {
+                let val = self.read_var(0);
+                self.write_var_indirect(op(0) as u8, val);
+            },
+            (Form::Var,0x0a) => self.split_window(op(0)),
+            (Form::Var,0x0b) => self.set_window(op(0)),
+            (Form::Var,0x0c) => self.call_store(ops),
+            (Form::Var,0x0d) => self.erase_window(op(0) as i16),
+            (Form::Var,0x0e) => {
+                if op(0) == 1 {
+                    self.erase_line();
+                }
+            },
+            (Form::Var,0x0f) => self.set_cursor(op(0) as i16, op(1)),
+            (Form::Var,0x10) => {
+                let (x,y) = self.screen.cursor;
+                self.write_word(op(0) as usize, y + 1);
+                self.write_word(op(0) as usize + 2, x + 1);
+            },
+            (Form::Var,0x11) => self.set_text_style(op(0)),
+            (Form::Var,0x12) => (),
+            (Form::Var,0x13) => self.output_stream(op(0) as i16, op(1) as usize),
+            (Form::Var,0x14) => self.input_stream(op(0)),
+            (Form::Var,0x15) => self.sound_effect(op(0), op(1), op(2), op(3)),
+            (Form::Var,0x16) => {
+                if let Some(ch) = self.read_char(op(1), op(2)) {
+                    self.store(ch);
+                }
+            },
+            (Form::Var,0x17) => {
+                let form = if ops.len() > 3 { op(3) } else { 0x82 };
+                let addr = self.scan_table(op(0), op(1) as usize, op(2) as usize, form);
+                self.store(addr);
+                self.branch(addr != 0);
+            },
+            (Form::Var,0x18) => self.store(!op(0)),
+            (Form::Var,0x19) | (Form::Var,0x1a) => self.call(op(0), &ops[1 ..], None, false),
+            (Form::Var,0x1b) => self.tokenise(op(0) as usize, op(1) as usize, op(2) as usize, op(3) != 0),
+            (Form::Var,0x1c) => self.encode_text(op(0) as usize, op(1) as usize, op(2) as usize, op(3) as usize),
+            (Form::Var,0x1d) => self.copy_table(op(0) as usize, op(1) as usize, op(2) as i16),
+            (Form::Var,0x1e) => {
+                let height = if ops.len() > 2 { op(2) } else { 1 };
+                self.print_table(op(0) as usize, op(1), height, op(3));
+            },
+            (Form::Var,0x1f) => {
+                let args = self.state.frames.last().map(|frame| frame.args).unwrap_or(0);
+                self.branch(op(0) as usize <= args);
+            },
+
+            (Form::Ext,0x00) => {
+                let result = if ops.is_empty() { self.save() as u16 } else { self.save_table(op(0) as usize, op(1) as usize, op(2) as usize) };
+                self.store(result);
+            },
+            (Form::Ext,0x01) => {
+                if ops.is_empty() {
+                    if !self.restore() {
+                        self.store(0);
+                    }
+                } else {
+                    let result = self.restore_table(op(0) as usize, op(1) as usize, op(2) as usize);
+                    self.store(result);
+                }
+            },
+            (Form::Ext,0x02) => {
+                let places = op(1) as i16;
+                let val = if places >= 16 || places <= -16 {
+                    0
+                } else if places >= 0 {
+                    op(0) << places
+                } else {
+                    op(0) >> -places
+                };
+                self.store(val);
+            },
+            (Form::Ext,0x03) => {
+                let places = op(1) as i16;
+                let val = if places >= 16 {
+                    0
+                } else if places >= 0 {
+                    op(0) << places
+                } else if places <= -16 {
+                    ((op(0) as i16) >> 15) as u16
+                } else {
+                    ((op(0) as i16) >> -places) as u16
+                };
+                self.store(val);
+            },
+            (Form::Ext,0x04) => {
+                let font = self.set_font(op(0));
+                self.store(font);
+            },
+            (Form::Ext,0x09) => {
+                let result = self.save_undo();
+                self.store(result);
+            },
+            (Form::Ext,0x0a) => {
+                if !self.restore_undo() {
+                    self.store(0);
+                }
+            },
+            (Form::Ext,0x0b) => self.print_unicode(op(0) as u32),
+            (Form::Ext,0x0c) => {
+                let result = self.check_unicode(op(0) as u32);
+                self.store(result);
+            },
+            (Form::Ext,0x0d) => self.set_true_colour(op(0) as i16, op(1) as i16),
+
+            // Version 6 is played as text, without pictures, windows
+            // beyond the first two or the mouse.
+            (Form::Ext,0x05) | (Form::Ext,0x07) | (Form::Ext,0x08) | (Form::Ext,0x10) | (Form::Ext,0x11)
+                | (Form::Ext,0x12) | (Form::Ext,0x14) | (Form::Ext,0x17) | (Form::Ext,0x19) | (Form::Ext,0x1c) => (),
+            (Form::Ext,0x06) | (Form::Ext,0x1b) => self.branch(false),
+            (Form::Ext,0x13) | (Form::Ext,0x1d) => self.store(0),
+            (Form::Ext,0x15) => {
+                for _ in 0 .. op(0) {
+                    self.pop_user_stack(op(1), ops.len());
+                }
+            },
+            (Form::Ext,0x16) => {
+                for i in 0 .. 4 {
+                    self.write_word(op(0) as usize + 2*i, 0);
+                }
+            },
+            (Form::Ext,0x18) => {
+                let result = self.push_user_stack(op(0), op(1) as usize);
+                self.branch(result);
+            },
+            (Form::Ext,0x1a) => self.print_form(op(0) as usize),
+
+            _ => panic!("{:x}: unknown opcode {:x}", opcode_addr, opcode),
+        }
+    }
+
+    fn verify(&self) -> bool {
+        let length = ::std::cmp::min(super::state::file_length(&self.state.rom), self.state.rom.len());
+        let mut sum = 0u16;
+        for i in 0x40 .. length {
+            sum = sum.wrapping_add(self.state.rom[i] as u16);
+        }
+        sum == read_u16(&self.state.rom, super::state::CHECKSUM)
+    }
+
+    fn scan_table(&self, val: u16, table: usize, len: usize, form: u16) -> u16 {
+        let size = (form & 0x7f) as usize;
+        for i in 0 .. len {
+            let addr = table + i*size;
+            let entry = if form & 0x80 != 0 { read_u16(&self.state.mem, addr) } else { self.state.mem[addr] as u16 };
+            if entry == val {
+                return addr as u16;
+            }
+        }
+        0
+    }
+
+    fn copy_table(&mut self, first: usize, second: usize, size: i16) {
+        if second == 0 {
+            for i in 0 .. (size as i32).abs() as usize {
+                self.write_byte(first + i, 0);
+            }
+        } else if size < 0 || first > second {
+            for i in 0 .. (size as i32).abs() as usize {
+                let val = self.state.mem[first + i];
+                self.write_byte(second + i, val);
+            }
+        } else {
+            for i in (0 .. size as usize).rev() {
+                let val = self.state.mem[first + i];
+                self.write_byte(second + i, val);
+            }
+        }
+    }
+
+    fn encode_text(&mut self, text: usize, len: usize, from: usize, coded: usize) {
+        let zscii: Vec<u16> = self.state.mem[text + from .. text + from + len].iter().map(|&b| b as u16).collect();
+        let encoded = text::encode(&self.state, &zscii);
+        for (i,b) in encoded.into_iter().enumerate() {
+            self.write_byte(coded + i, b);
+        }
+    }
+
+    fn pop_user_stack(&mut self, stack: u16, count: usize) -> u16 {
+        if count < 1 || stack == 0 {
+            return self.read_var(0);
+        }
+        let stack = stack as usize;
+        let free = read_u16(&self.state.mem, stack) + 1;
+        self.write_word(stack, free);
+        read_u16(&self.state.mem, stack + 2*free as usize)
+    }
+
+    fn push_user_stack(&mut self, val: u16, stack: usize) -> bool {
+        let free = read_u16(&self.state.mem, stack);
+        if free == 0 {
+            return false;
+        }
+        self.write_word(stack + 2*free as usize, val);
+        self.write_word(stack, free - 1);
+        true
+    }
+
+    fn print_form(&mut self, table: usize) {
+        let mut addr = table;
+        loop {
+            let len = read_u16(&self.state.mem, addr) as usize;
+            if len == 0 {
+                break;
+            }
+            for i in 0 .. len {
+                let ch = self.state.mem[addr + 2 + i] as u16;
+                self.print_zscii(ch);
+            }
+            self.print_zscii(13);
+            addr += 2 + len;
+        }
+    }
+
+    pub fn save_undo(&mut self) -> u16 {
+        if self.undo_depth == 0 {
+            return 0xffff;
+        }
+        if self.undo_state.len() >= self.undo_depth {
+            self.undo_state.remove(0);
+        }
+        let undo_state = UndoState::new(&self.state);
+        self.undo_state.push(undo_state);
+        1
+    }
+
+    // Restores the state at the last save_undo, which then stores 2.
+    pub fn restore_undo(&mut self) -> bool {
+        match self.undo_state.pop() {
+            Some(undo_state) => {
+                let flags2 = self.state.mem[super::state::FLAGS2 + 1] & 3;
+                undo_state.restore(&mut self.state);
+                self.state.mem[super::state::FLAGS2 + 1] = self.state.mem[super::state::FLAGS2 + 1] & !3 | flags2;
+                self.generation += 1;
+                self.store(2);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn save(&mut self) -> bool {
+        save::save(self)
+    }
+
+    // Continues from the restored save instruction, or returns false.
+    fn restore(&mut self) -> bool {
+        if !save::restore(self) {
+            return false;
+        }
+        self.generation += 1;
+        self.init_header();
+        if self.state.version <= 3 {
+            self.branch(true);
+        } else {
+            self.store(2);
+        }
+        true
+    }
+
+    fn save_table(&mut self, table: usize, bytes: usize, name: usize) -> u16 {
+        let data = self.state.mem[table .. table + bytes].to_vec();
+        save::save_data(self, &data, name) as u16
+    }
+
+    fn restore_table(&mut self, table: usize, bytes: usize, name: usize) -> u16 {
+        match save::restore_data(self, name) {
+            Some(data) => {
+                let len = ::std::cmp::min(bytes, data.len());
+                for i in 0 .. len {
+                    self.write_byte(table + i, data[i]);
+                }
+                len as u16
+            },
+            None => 0,
+        }
+    }
+}
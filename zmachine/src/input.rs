@@ -0,0 +1,346 @@
+use std::cmp::min;
+
+use glk;
+use glk::{Glk,EventType,IdType};
+
+use super::execute::Execute;
+use super::state::{read_u16,DICTIONARY,TERMINATING_CHARS};
+use super::text;
+
+// The ZSCII of a special key.
+fn keycode_zscii(keycode: u32) -> Option<u16> {
+    match keycode {
+        glk::keycode_Delete => Some(8),
+        glk::keycode_Return => Some(13),
+        glk::keycode_Escape => Some(27),
+        glk::keycode_Up => Some(129),
+        glk::keycode_Down => Some(130),
+        glk::keycode_Left => Some(131),
+        glk::keycode_Right => Some(132),
+        glk::keycode_Func12 ..= glk::keycode_Func1 => Some(133 + (glk::keycode_Func1 - keycode) as u16),
+        _ => None,
+    }
+}
+
+fn zscii_keycode(zscii: u16) -> Option<u32> {
+    match zscii {
+        129 => Some(glk::keycode_Up),
+        130 => Some(glk::keycode_Down),
+        131 => Some(glk::keycode_Left),
+        132 => Some(glk::keycode_Right),
+        133 ..= 144 => Some(glk::keycode_Func1 - (zscii as u32 - 133)),
+        _ => None,
+    }
+}
+
+// What ended a wait for line input.
+enum Wait {
+    Line(u16),
+    Timer,
+    Sound(u16),
+}
+
+impl<'a,G: Glk<'a>> Execute<'a,G> {
+    // Reads a line into the text buffer and tokenises it into the parse
+    // buffer, if any.  Returns the ZSCII that ended the line, 0 if a timed
+    // routine ended it, or None if the game quit, restarted or restored
+    // meanwhile.
+    pub fn read_line(&mut self, text: usize, parse: usize, time: u16, routine: u16) -> Option<u16> {
+        if self.state.version <= 3 {
+            self.show_status();
+        }
+        self.show_upper();
+        let version = self.state.version;
+        let (max,start) = if version <= 4 {
+            (self.state.mem[text].saturating_sub(1) as usize,text + 1)
+        } else {
+            (self.state.mem[text] as usize,text + 2)
+        };
+        let (line,terminator) = match self.read_input_line(max) {
+            Some(line) => (line,13),
+            None => {
+                let initial = if version >= 5 {
+                    let len = min(self.state.mem[text + 1] as usize, max);
+                    self.state.mem[start .. start + len].iter().filter_map(|&ch| text::to_unicode(&self.state, ch as u16)).collect()
+                } else {
+                    Vec::new()
+                };
+                self.keyboard_line(max, initial, time, routine)?
+            },
+        };
+        if terminator != 0 && !self.screen.commands.is_null() {
+            let commands = self.screen.commands.clone();
+            self.glk.put_buffer_stream_uni(&commands, &line);
+            self.glk.put_char_stream_uni(&commands, 10);
+        }
+
+        let zscii: Vec<u16> = line.iter().take(max).map(|&ch| {
+            let ch = ::std::char::from_u32(ch).and_then(|ch| ch.to_lowercase().next()).map(|ch| ch as u32).unwrap_or(ch);
+            text::from_unicode(&self.state, ch).unwrap_or(b'?' as u16)
+        }).collect();
+        for (i,&ch) in zscii.iter().enumerate() {
+            self.write_byte(start + i, ch as u8);
+        }
+        if version <= 4 {
+            self.write_byte(start + zscii.len(), 0);
+        } else {
+            self.write_byte(text + 1, zscii.len() as u8);
+        }
+        if parse != 0 {
+            self.tokenise(text, parse, 0, false);
+        }
+        Some(terminator)
+    }
+
+    // A line from input stream 1, which is echoed, until it runs out.
+    fn read_input_line(&mut self, max: usize) -> Option<Vec<u32>> {
+        if self.screen.input.is_null() {
+            return None;
+        }
+        let input = self.screen.input.clone();
+        let mut buf = vec![0; max + 2];
+        let len = self.glk.get_line_stream_uni(&input, &mut buf) as usize;
+        if len == 0 {
+            self.close_input();
+            return None;
+        }
+        let mut line = buf[.. len].to_vec();
+        while line.last() == Some(&10) || line.last() == Some(&13) {
+            line.pop();
+        }
+        for &ch in &line {
+            self.print_unicode(ch);
+        }
+        self.print_zscii(13);
+        Some(line)
+    }
+
+    fn keyboard_line(&mut self, max: usize, initial: Vec<u32>, time: u16, routine: u16) -> Option<(Vec<u32>,u16)> {
+        let lower = self.screen.lower.clone();
+        // Input is echoed here when Glk can be told not to, so it isn't
+        // echoed again each time a timed routine interrupts it.
+        let echo = self.glk.gestalt(glk::gestalt_LineInputEcho, 0) != 0;
+        if echo {
+            self.glk.set_echo_line_event(&lower, 0);
+        }
+        if self.state.version >= 5 && self.glk.gestalt(glk::gestalt_LineTerminators, 0) != 0 {
+            let terminators = self.terminators();
+            self.glk.set_terminators_line_event(&lower, &terminators);
+        }
+        let timed = time != 0 && routine != 0 && self.glk.gestalt(glk::gestalt_Timer, 0) != 0;
+        if timed {
+            self.glk.request_timer_events(time as u32*100);
+        }
+        let mut typed = initial;
+        typed.truncate(max);
+        let result = loop {
+            let mut buf = vec![0; max].into_boxed_slice();
+            buf[.. typed.len()].copy_from_slice(&typed);
+            self.glk.request_line_event_uni(&lower, (0,buf), typed.len() as u32);
+            let wait = loop {
+                let mut event = self.glk.select();
+                match event.evtype() {
+                    glk::evtype_LineInput if event.win() == lower => {
+                        let len = event.val1() as usize;
+                        typed = event.buf_uni().map(|(_,buf)| buf[.. min(len, buf.len())].to_vec()).unwrap_or(Vec::new());
+                        break Wait::Line(if event.val2() == 0 { 13 } else { keycode_zscii(event.val2()).unwrap_or(13) });
+                    },
+                    glk::evtype_Timer if timed => break Wait::Timer,
+                    glk::evtype_SoundNotify if self.screen.sound_routine != 0 => {
+                        break Wait::Sound(::std::mem::replace(&mut self.screen.sound_routine, 0));
+                    },
+                    glk::evtype_Arrange => self.update_screen_size(),
+                    _ => (),
+                }
+            };
+            let routine = match wait {
+                Wait::Line(terminator) => break Some((typed,terminator)),
+                Wait::Timer => routine,
+                Wait::Sound(routine) => routine,
+            };
+
+            // Glk doesn't allow printing in the window while it waits for
+            // the line, so the routine runs between requests.
+            let mut event = self.glk.cancel_line_event(&lower);
+            let len = event.val1() as usize;
+            if let Some((_,buf)) = event.buf_uni() {
+                typed = buf[.. min(len, buf.len())].to_vec();
+            }
+            match (self.call_interrupt(routine),wait) {
+                (None,_) => break None,
+                (Some(0),_) | (_,Wait::Sound(_)) => (),
+                (Some(_),_) => break Some((typed,0)),
+            }
+        };
+        if timed {
+            self.glk.request_timer_events(0);
+        }
+        if echo {
+            if let Some((ref line,_)) = result {
+                let str = self.glk.window_get_stream(&lower);
+                self.glk.set_style_stream(&str, glk::style_Input);
+                self.glk.put_buffer_stream_uni(&str, line);
+                self.glk.put_char_stream_uni(&str, 10);
+                self.glk.set_style_stream(&str, glk::style_Normal);
+                self.apply_style();
+            }
+        }
+        result
+    }
+
+    // The Glk keycodes of the terminating characters table.
+    fn terminators(&mut self) -> Vec<u32> {
+        let mut addr = read_u16(&self.state.mem, TERMINATING_CHARS) as usize;
+        let mut keycodes = Vec::new();
+        if addr == 0 {
+            return keycodes;
+        }
+        loop {
+            let zscii = self.state.mem[addr] as u16;
+            addr += 1;
+            if zscii == 0 {
+                break;
+            } else if zscii == 255 {
+                keycodes = (129 .. 145).filter_map(zscii_keycode).collect();
+                break;
+            } else if let Some(keycode) = zscii_keycode(zscii) {
+                keycodes.push(keycode);
+            }
+        }
+        keycodes.retain(|&keycode| self.glk.gestalt(glk::gestalt_LineTerminatorKey, keycode) != 0);
+        keycodes
+    }
+
+    // Reads a key as ZSCII, 0 if a timed routine ended the wait, or None
+    // if the game quit, restarted or restored meanwhile.
+    pub fn read_char(&mut self, time: u16, routine: u16) -> Option<u16> {
+        self.show_upper();
+        if !self.screen.input.is_null() {
+            let input = self.screen.input.clone();
+            match self.glk.get_char_stream_uni(&input) {
+                -1 => self.close_input(),
+                10 | 13 => return Some(13),
+                ch => return Some(text::from_unicode(&self.state, ch as u32).unwrap_or(b'?' as u16)),
+            }
+        }
+        let lower = self.screen.lower.clone();
+        let timed = time != 0 && routine != 0 && self.glk.gestalt(glk::gestalt_Timer, 0) != 0;
+        if timed {
+            self.glk.request_timer_events(time as u32*100);
+        }
+        self.glk.request_char_event_uni(&lower);
+        let result = loop {
+            let event = self.glk.select();
+            match event.evtype() {
+                glk::evtype_CharInput if event.win() == lower => {
+                    let key = match keycode_zscii(event.val1()) {
+                        Some(zscii) => Some(zscii),
+                        None => text::from_unicode(&self.state, event.val1()),
+                    };
+                    match key {
+                        Some(key) => break Some(key),
+                        None => self.glk.request_char_event_uni(&lower),
+                    }
+                },
+                glk::evtype_Timer if timed => {
+                    match self.call_interrupt(routine) {
+                        None => {
+                            self.glk.cancel_char_event(&lower);
+                            break None;
+                        },
+                        Some(0) => (),
+                        Some(_) => {
+                            self.glk.cancel_char_event(&lower);
+                            break Some(0);
+                        },
+                    }
+                },
+                glk::evtype_SoundNotify if self.screen.sound_routine != 0 => {
+                    let routine = ::std::mem::replace(&mut self.screen.sound_routine, 0);
+                    if self.call_interrupt(routine).is_none() {
+                        self.glk.cancel_char_event(&lower);
+                        break None;
+                    }
+                },
+                glk::evtype_Arrange => self.update_screen_size(),
+                _ => (),
+            }
+        };
+        if timed {
+            self.glk.request_timer_events(0);
+        }
+        result
+    }
+
+    // Splits the text buffer into words, and writes each word's dictionary
+    // entry, length and position to the parse buffer.  With skip_unknown,
+    // the entries of words not in the dictionary are left alone.
+    pub fn tokenise(&mut self, text: usize, parse: usize, dictionary: usize, skip_unknown: bool) {
+        let dictionary = if dictionary == 0 { read_u16(&self.state.mem, DICTIONARY) as usize } else { dictionary };
+        let (start,len) = if self.state.version <= 4 {
+            let len = self.state.mem[text + 1 ..].iter().position(|&ch| ch == 0).unwrap_or(0);
+            (text + 1,len)
+        } else {
+            (text + 2,self.state.mem[text + 1] as usize)
+        };
+        let chars: Vec<u16> = self.state.mem[start .. start + len].iter().map(|&ch| ch as u16).collect();
+        let separator_count = self.state.mem[dictionary] as usize;
+        let separators: Vec<u16> = self.state.mem[dictionary + 1 .. dictionary + 1 + separator_count].iter().map(|&ch| ch as u16).collect();
+
+        let mut words = Vec::new();
+        let mut word_start = None;
+        for (i,&ch) in chars.iter().enumerate() {
+            if ch == b' ' as u16 || separators.contains(&ch) {
+                if let Some(word_start) = word_start.take() {
+                    words.push((word_start,i - word_start));
+                }
+                if ch != b' ' as u16 {
+                    words.push((i,1));
+                }
+            } else if word_start.is_none() {
+                word_start = Some(i);
+            }
+        }
+        if let Some(word_start) = word_start {
+            words.push((word_start,chars.len() - word_start));
+        }
+
+        let count = min(words.len(), self.state.mem[parse] as usize);
+        self.write_byte(parse + 1, count as u8);
+        for (i,&(pos,len)) in words.iter().take(count).enumerate() {
+            let entry = self.lookup(dictionary, &chars[pos .. pos + len]);
+            if entry == 0 && skip_unknown {
+                continue;
+            }
+            let addr = parse + 2 + 4*i;
+            self.write_word(addr, entry);
+            self.write_byte(addr + 2, len as u8);
+            self.write_byte(addr + 3, (pos + start - text) as u8);
+        }
+    }
+
+    // The address of the word's dictionary entry, or 0.  A negative
+    // number of entries means they aren't sorted.
+    fn lookup(&self, dictionary: usize, word: &[u16]) -> u16 {
+        let encoded = text::encode(&self.state, word);
+        let separator_count = self.state.mem[dictionary] as usize;
+        let entry_len = self.state.mem[dictionary + 1 + separator_count] as usize;
+        let count = read_u16(&self.state.mem, dictionary + 2 + separator_count) as i16;
+        let entries = dictionary + 4 + separator_count;
+        let entry = |i: usize| &self.state.mem[entries + i*entry_len .. entries + i*entry_len + encoded.len()];
+        if count < 0 {
+            return (0 .. (-(count as i32)) as usize).find(|&i| entry(i) == &encoded[..])
+                .map(|i| (entries + i*entry_len) as u16).unwrap_or(0);
+        }
+        let (mut low,mut high) = (0,count as usize);
+        while low < high {
+            let mid = (low + high)/2;
+            match entry(mid).cmp(&encoded[..]) {
+                ::std::cmp::Ordering::Equal => return (entries + mid*entry_len) as u16,
+                ::std::cmp::Ordering::Less => low = mid + 1,
+                ::std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        0
+    }
+}
@@ -0,0 +1,65 @@
+extern crate glk;
+extern crate iff;
+extern crate rand;
+
+use glk::Glk;
+
+mod execute;
+mod input;
+mod object;
+mod save;
+mod screen;
+mod state;
+mod text;
+
+pub struct Options {
+    random_seed: u32,
+    undo_depth: usize,
+    strict: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options{
+            random_seed: 0,
+            undo_depth: 2,
+            strict: false,
+        }
+    }
+
+    // A nonzero seed makes @random repeatable, as if the game started in
+    // predictable mode with the seed.
+    pub fn set_random_seed(&mut self, seed: u32) {
+        self.random_seed = seed;
+    }
+
+    // The number of @save_undo states kept.  0 makes @save_undo report
+    // that undo isn't available.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+    }
+
+    // Stop with an error on object 0, writes outside dynamic memory and
+    // @put_prop of missing properties instead of carrying on.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+}
+
+// Runs Z-code, versions 1 to 8.  Version 6 plays as text, without
+// pictures, its extra windows or the mouse.
+pub fn run<'a,G: Glk<'a>, R: std::io::Read>(glk: G, r: &mut R) -> (G,Result<(),std::io::Error>) {
+    run_with_options(glk, r, Options::new())
+}
+
+pub fn run_with_options<'a,G: Glk<'a>, R: std::io::Read>(glk: G, r: &mut R, options: Options) -> (G,Result<(),std::io::Error>) {
+    match state::State::new(r) {
+        Err(cause) => (glk,Err(cause)),
+        Ok(state) => {
+            let mut exec = execute::Execute::new(state, glk, options);
+            exec.run();
+            exec.close_input();
+            (exec.glk,Ok(()))
+        },
+    }
+}
@@ -0,0 +1,198 @@
+use super::state::{read_u16,write_u16,State,OBJECTS};
+
+// The object table: property defaults, then the objects, which are 9
+// bytes in versions 1 to 3, with byte-sized object numbers, and 14 bytes
+// after, with word-sized object numbers.
+
+fn defaults_len(state: &State) -> usize {
+    if state.version <= 3 { 31 } else { 63 }
+}
+
+fn object_addr(state: &State, obj: u16) -> usize {
+    let entry_len = if state.version <= 3 { 9 } else { 14 };
+    read_u16(&state.mem, OBJECTS) as usize + 2*defaults_len(state) + entry_len*(obj as usize - 1)
+}
+
+pub fn attr(state: &State, obj: u16, attr: u16) -> bool {
+    if obj == 0 {
+        return false;
+    }
+    let addr = object_addr(state, obj) + attr as usize/8;
+    state.mem[addr] & (0x80 >> (attr % 8)) != 0
+}
+
+pub fn set_attr(state: &mut State, obj: u16, attr: u16, val: bool) {
+    if obj == 0 {
+        return;
+    }
+    let addr = object_addr(state, obj) + attr as usize/8;
+    if val {
+        state.mem[addr] |= 0x80 >> (attr % 8);
+    } else {
+        state.mem[addr] &= !(0x80 >> (attr % 8));
+    }
+}
+
+#[derive(Clone,Copy)]
+pub enum Link {
+    Parent,
+    Sibling,
+    Child,
+}
+
+pub fn get(state: &State, obj: u16, link: Link) -> u16 {
+    if obj == 0 {
+        return 0;
+    }
+    let addr = object_addr(state, obj);
+    if state.version <= 3 {
+        state.mem[addr + 4 + link as usize] as u16
+    } else {
+        read_u16(&state.mem, addr + 6 + 2*link as usize)
+    }
+}
+
+fn set(state: &mut State, obj: u16, link: Link, val: u16) {
+    let addr = object_addr(state, obj);
+    if state.version <= 3 {
+        state.mem[addr + 4 + link as usize] = val as u8;
+    } else {
+        write_u16(&mut state.mem, addr + 6 + 2*link as usize, val);
+    }
+}
+
+pub fn remove(state: &mut State, obj: u16) {
+    if obj == 0 {
+        return;
+    }
+    let parent = get(state, obj, Link::Parent);
+    if parent == 0 {
+        return;
+    }
+    let sibling = get(state, obj, Link::Sibling);
+    let mut child = get(state, parent, Link::Child);
+    if child == obj {
+        set(state, parent, Link::Child, sibling);
+    } else {
+        while child != 0 {
+            let next = get(state, child, Link::Sibling);
+            if next == obj {
+                set(state, child, Link::Sibling, sibling);
+                break;
+            }
+            child = next;
+        }
+    }
+    set(state, obj, Link::Parent, 0);
+    set(state, obj, Link::Sibling, 0);
+}
+
+pub fn insert(state: &mut State, obj: u16, dest: u16) {
+    if obj == 0 || dest == 0 {
+        return;
+    }
+    remove(state, obj);
+    let child = get(state, dest, Link::Child);
+    set(state, obj, Link::Sibling, child);
+    set(state, obj, Link::Parent, dest);
+    set(state, dest, Link::Child, obj);
+}
+
+fn properties(state: &State, obj: u16) -> usize {
+    let addr = object_addr(state, obj) + if state.version <= 3 { 7 } else { 12 };
+    read_u16(&state.mem, addr) as usize
+}
+
+// The address of the object's short name.
+pub fn name(state: &State, obj: u16) -> usize {
+    properties(state, obj) + 1
+}
+
+// The property number, the address of its data and its length, for the
+// property at addr, or None at the end of the list.
+fn property(state: &State, addr: usize) -> Option<(u16,usize,usize)> {
+    let size = state.mem[addr];
+    if size == 0 {
+        None
+    } else if state.version <= 3 {
+        Some(((size & 31) as u16,addr + 1,(size >> 5) as usize + 1))
+    } else if size & 0x80 != 0 {
+        let len = (state.mem[addr + 1] & 63) as usize;
+        Some(((size & 63) as u16,addr + 2,if len == 0 { 64 } else { len }))
+    } else {
+        Some(((size & 63) as u16,addr + 1,if size & 0x40 != 0 { 2 } else { 1 }))
+    }
+}
+
+fn first_property(state: &State, obj: u16) -> usize {
+    let addr = properties(state, obj);
+    addr + 1 + 2*state.mem[addr] as usize
+}
+
+fn find_property(state: &State, obj: u16, prop: u16) -> Option<(usize,usize)> {
+    if obj == 0 {
+        return None;
+    }
+    let mut addr = first_property(state, obj);
+    while let Some((number,data,len)) = property(state, addr) {
+        if number == prop {
+            return Some((data,len));
+        } else if number < prop {
+            return None;
+        }
+        addr = data + len;
+    }
+    None
+}
+
+pub fn get_prop(state: &State, obj: u16, prop: u16) -> u16 {
+    match find_property(state, obj, prop) {
+        Some((data,1)) => state.mem[data] as u16,
+        Some((data,_)) => read_u16(&state.mem, data),
+        None => read_u16(&state.mem, read_u16(&state.mem, OBJECTS) as usize + 2*(prop as usize - 1)),
+    }
+}
+
+pub fn get_prop_addr(state: &State, obj: u16, prop: u16) -> u16 {
+    find_property(state, obj, prop).map(|(data,_)| data as u16).unwrap_or(0)
+}
+
+pub fn get_prop_len(state: &State, addr: u16) -> u16 {
+    if addr == 0 {
+        return 0;
+    }
+    let size = state.mem[addr as usize - 1];
+    if state.version <= 3 {
+        (size >> 5) as u16 + 1
+    } else if size & 0x80 != 0 {
+        match size & 63 { 0 => 64, len => len as u16 }
+    } else if size & 0x40 != 0 {
+        2
+    } else {
+        1
+    }
+}
+
+pub fn get_next_prop(state: &State, obj: u16, prop: u16) -> u16 {
+    if obj == 0 {
+        return 0;
+    }
+    let mut addr = first_property(state, obj);
+    if prop != 0 {
+        match find_property(state, obj, prop) {
+            Some((data,len)) => addr = data + len,
+            None => return 0,
+        }
+    }
+    property(state, addr).map(|(number,_,_)| number).unwrap_or(0)
+}
+
+// Returns false if the object has no such property.
+pub fn put_prop(state: &mut State, obj: u16, prop: u16, val: u16) -> bool {
+    match find_property(state, obj, prop) {
+        Some((data,1)) => state.mem[data] = val as u8,
+        Some((data,_)) => write_u16(&mut state.mem, data, val),
+        None => return false,
+    }
+    true
+}
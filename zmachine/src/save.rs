@@ -0,0 +1,260 @@
+use std::io;
+use std::io::{Error,ErrorKind,Read,Write};
+
+use glk;
+use glk::{Glk,IdType};
+use iff::Chunk;
+
+use super::execute::Execute;
+use super::state::{read_u16,Frame,State,CHECKSUM,FLAGS2,RELEASE,SERIAL};
+
+// Saves are Quetzal files.
+
+#[allow(non_upper_case_globals)]
+mod ids {
+    use iff::TypeID;
+    pub use iff::FORM;
+
+    pub const IFZS: TypeID = TypeID([b'I',b'F',b'Z',b'S']);
+    pub const IFhd: TypeID = TypeID([b'I',b'F',b'h',b'd']);
+    pub const CMem: TypeID = TypeID([b'C',b'M',b'e',b'm']);
+    pub const UMem: TypeID = TypeID([b'U',b'M',b'e',b'm']);
+    pub const Stks: TypeID = TypeID([b'S',b't',b'k',b's']);
+}
+
+fn ifhd(state: &State, pc: usize) -> Vec<u8> {
+    let mut ifhd = Vec::with_capacity(13);
+    ifhd.extend_from_slice(&state.rom[RELEASE .. RELEASE + 2]);
+    ifhd.extend_from_slice(&state.rom[SERIAL .. SERIAL + 6]);
+    ifhd.extend_from_slice(&state.rom[CHECKSUM .. CHECKSUM + 2]);
+    ifhd.push((pc >> 16) as u8);
+    ifhd.push((pc >> 8) as u8);
+    ifhd.push(pc as u8);
+    ifhd
+}
+
+pub fn write<W: Write>(state: &State, w: &mut W) -> io::Result<()> {
+    let mut cmem = Vec::new();
+    let mut run_len: usize = 0;
+    for i in 0 .. state.dynamic_size() {
+        let b = state.mem[i] ^ state.rom[i];
+        if b == 0 {
+            run_len += 1;
+            continue;
+        }
+        while run_len > 0 {
+            cmem.push(0);
+            if run_len <= 256 {
+                cmem.push((run_len - 1) as u8);
+                run_len = 0;
+            } else {
+                cmem.push(255);
+                run_len -= 256;
+            }
+        }
+        cmem.push(b);
+    }
+
+    let mut stks = Vec::new();
+    for (i,frame) in state.frames.iter().enumerate() {
+        let stack_end = state.frames.get(i + 1).map(|next| next.stack_base).unwrap_or(state.stack.len());
+        stks.push((frame.return_pc >> 16) as u8);
+        stks.push((frame.return_pc >> 8) as u8);
+        stks.push(frame.return_pc as u8);
+        let discard = if frame.store.is_none() && i > 0 { 0x10 } else { 0 };
+        stks.push(frame.locals.len() as u8 | discard);
+        stks.push(frame.store.unwrap_or(0));
+        stks.push(((1u16 << frame.args) - 1) as u8);
+        push_u16(&mut stks, (stack_end - frame.stack_base) as u16);
+        for &local in &frame.locals {
+            push_u16(&mut stks, local);
+        }
+        for &val in &state.stack[frame.stack_base .. stack_end] {
+            push_u16(&mut stks, val);
+        }
+    }
+
+    let ifhd = ifhd(state, state.pc);
+    let mut chunk = Chunk::create(ids::FORM, ids::IFZS);
+    chunk.append_data(ids::IFhd, &ifhd);
+    chunk.append_data(ids::CMem, &cmem);
+    chunk.append_data(ids::Stks, &stks);
+    chunk.write(w)
+}
+
+pub fn read<R: Read>(state: &mut State, r: &mut R) -> io::Result<()> {
+    let mut vec = Vec::new();
+    r.read_to_end(&mut vec)?;
+    let chunk = Chunk::new(&vec[..])?;
+    if !chunk.has_envelope_type(ids::FORM, ids::IFZS) {
+        return Err(invalid_data("invalid save data"));
+    }
+    let chunks = chunk.data_chunks();
+    let ifhd = match chunks.iter().find(|&&(id,_)| id == ids::IFhd) {
+        Some(&(_,data)) if data.len() >= 13 => data,
+        _ => return Err(invalid_data("invalid save data")),
+    };
+    if ifhd[.. 10] != self::ifhd(state, 0)[.. 10] {
+        return Err(invalid_data("save data is from a different story"));
+    }
+    let mut mem = None;
+    let mut stks = None;
+    for &(id,data) in &chunks {
+        match id {
+            ids::CMem => mem = Some(read_cmem(state, data)?),
+            ids::UMem if data.len() == state.dynamic_size() => mem = Some(data.to_vec()),
+            ids::Stks => stks = Some(read_stks(data)?),
+            _ => (),
+        }
+    }
+    match (mem,stks) {
+        (Some(mem),Some((stack,frames))) => {
+            // The transcript and fixed pitch bits stay as they are.
+            let flags2 = state.mem[FLAGS2 + 1] & 3;
+            state.mem[.. mem.len()].copy_from_slice(&mem);
+            state.mem[FLAGS2 + 1] = state.mem[FLAGS2 + 1] & !3 | flags2;
+            state.stack = stack;
+            state.frames = frames;
+            state.pc = (ifhd[10] as usize) << 16 | (ifhd[11] as usize) << 8 | ifhd[12] as usize;
+            Ok(())
+        },
+        _ => Err(invalid_data("invalid save data")),
+    }
+}
+
+fn read_cmem(state: &State, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut mem = state.rom[.. state.dynamic_size()].to_vec();
+    let mut i = 0;
+    let mut iter = data.iter();
+    while let Some(&b) = iter.next() {
+        if b == 0 {
+            i += 1 + *iter.next().unwrap_or(&0) as usize;
+        } else if i < mem.len() {
+            mem[i] ^= b;
+            i += 1;
+        } else {
+            return Err(invalid_data("invalid CMem chunk"));
+        }
+    }
+    Ok(mem)
+}
+
+fn read_stks(data: &[u8]) -> io::Result<(Vec<u16>,Vec<Frame>)> {
+    let mut stack = Vec::new();
+    let mut frames = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        if i + 8 > data.len() {
+            return Err(invalid_data("invalid Stks chunk"));
+        }
+        let return_pc = (data[i] as usize) << 16 | (data[i+1] as usize) << 8 | data[i+2] as usize;
+        let flags = data[i+3];
+        let store = data[i+4];
+        let args = (data[i+5] as u16 + 1).trailing_zeros() as usize;
+        let eval_count = read_u16(data, i+6) as usize;
+        let local_count = (flags & 15) as usize;
+        i += 8;
+        if i + 2*(local_count + eval_count) > data.len() {
+            return Err(invalid_data("invalid Stks chunk"));
+        }
+        let locals = (0 .. local_count).map(|j| read_u16(data, i + 2*j)).collect();
+        i += 2*local_count;
+        let stack_base = stack.len();
+        for _ in 0 .. eval_count {
+            stack.push(read_u16(data, i));
+            i += 2;
+        }
+        frames.push(Frame{
+            return_pc: return_pc,
+            store: if flags & 0x10 != 0 || frames.is_empty() { None } else { Some(store) },
+            args: args,
+            locals: locals,
+            stack_base: stack_base,
+            interrupt: false,
+        });
+    }
+    if frames.is_empty() {
+        return Err(invalid_data("invalid Stks chunk"));
+    }
+    Ok((stack,frames))
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn push_u16(bytes: &mut Vec<u8>, val: u16) {
+    bytes.push((val >> 8) as u8);
+    bytes.push(val as u8);
+}
+
+// Opens a file the player chooses, or the named file.
+fn open<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, usage: u32, fmode: u32, name: Option<String>) -> G::StrId {
+    let mut fref = match name {
+        Some(name) => exec.glk.fileref_create_by_name(usage, name, 0),
+        None => exec.glk.fileref_create_by_prompt(usage, fmode, 0),
+    };
+    if fref.is_null() {
+        return G::StrId::null();
+    }
+    let str = if fmode == glk::filemode_Read && !exec.glk.fileref_does_file_exist(&fref) {
+        G::StrId::null()
+    } else {
+        exec.glk.stream_open_file(&fref, fmode, 0)
+    };
+    exec.glk.fileref_destroy(&mut fref);
+    str
+}
+
+pub fn save<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>) -> bool {
+    let mut str = open(exec, glk::fileusage_SavedGame | glk::fileusage_BinaryMode, glk::filemode_Write, None);
+    if str.is_null() {
+        return false;
+    }
+    let result = write(&exec.state, &mut exec.glk.io_stream(&mut str));
+    exec.glk.stream_close(&mut str);
+    result.is_ok()
+}
+
+pub fn restore<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>) -> bool {
+    let mut str = open(exec, glk::fileusage_SavedGame | glk::fileusage_BinaryMode, glk::filemode_Read, None);
+    if str.is_null() {
+        return false;
+    }
+    let result = read(&mut exec.state, &mut exec.glk.io_stream(&mut str));
+    exec.glk.stream_close(&mut str);
+    result.is_ok()
+}
+
+// The file name of the auxiliary files of version 5 save and restore,
+// which is a string with its length first, if any.
+fn data_name(state: &State, name: usize) -> Option<String> {
+    if name == 0 {
+        return None;
+    }
+    let len = state.mem[name] as usize;
+    Some(state.mem[name + 1 .. name + 1 + len].iter().map(|&b| b as char).collect())
+}
+
+pub fn save_data<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, data: &[u8], name: usize) -> bool {
+    let name = data_name(&exec.state, name);
+    let mut str = open(exec, glk::fileusage_Data | glk::fileusage_BinaryMode, glk::filemode_Write, name);
+    if str.is_null() {
+        return false;
+    }
+    let result = exec.glk.io_stream(&mut str).write_all(data);
+    exec.glk.stream_close(&mut str);
+    result.is_ok()
+}
+
+pub fn restore_data<'a,G: Glk<'a>>(exec: &mut Execute<'a,G>, name: usize) -> Option<Vec<u8>> {
+    let name = data_name(&exec.state, name);
+    let mut str = open(exec, glk::fileusage_Data | glk::fileusage_BinaryMode, glk::filemode_Read, name);
+    if str.is_null() {
+        return None;
+    }
+    let mut data = Vec::new();
+    let result = exec.glk.io_stream(&mut str).read_to_end(&mut data);
+    exec.glk.stream_close(&mut str);
+    result.ok().map(|_| data)
+}
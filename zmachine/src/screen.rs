@@ -0,0 +1,525 @@
+use glk;
+use glk::{Glk,IdType};
+
+use super::execute::Execute;
+use super::object;
+use super::state::{read_u16,write_u16,FLAGS1,FLAGS2,GLOBALS,SCREEN_HEIGHT,SCREEN_HEIGHT_UNITS,SCREEN_WIDTH,SCREEN_WIDTH_UNITS};
+use super::text;
+
+// The lower window is a text buffer, and the upper window, which the
+// game splits off the top of the screen, is a text grid.  Versions 1 to
+// 3 also have a status line above both.
+pub struct Screen<'a,G: Glk<'a>> {
+    pub lower: G::WinId,
+    pub upper: G::WinId,
+    pub status: G::WinId,
+    pub window: u16,
+    // The upper window's height as the game last set it and as shown.
+    // Shrinking is put off until the next input so the player can read
+    // what the game printed there, such as a quotation box.
+    upper_height: u16,
+    upper_shown: u16,
+    // The cursor in the upper window, from 0.
+    pub cursor: (u16,u16),
+    width: u16,
+    style: u16,
+    font: u16,
+    // Output streams 1, 2 and 4, and the tables of stream 3.
+    pub screen_output: bool,
+    transcript: G::StrId,
+    pub commands: G::StrId,
+    memory: Vec<usize>,
+    // Input stream 1, if commands are read from a file.
+    pub input: G::StrId,
+    sound: G::SChanId,
+    pub sound_routine: u16,
+}
+
+impl<'a,G: Glk<'a>> Screen<'a,G> {
+    pub fn new() -> Self {
+        Screen{
+            lower: G::WinId::null(),
+            upper: G::WinId::null(),
+            status: G::WinId::null(),
+            window: 0,
+            upper_height: 0,
+            upper_shown: 0,
+            cursor: (0,0),
+            width: 80,
+            style: 0,
+            font: 1,
+            screen_output: true,
+            transcript: G::StrId::null(),
+            commands: G::StrId::null(),
+            memory: Vec::new(),
+            input: G::StrId::null(),
+            sound: G::SChanId::null(),
+            sound_routine: 0,
+        }
+    }
+}
+
+// ZSCII colours 2 to 12 as 0xRRGGBB.
+const COLOURS: [u32; 11] = [
+    0x000000, 0xef0000, 0x00d600, 0xefef00, 0x006bb5, 0xff00ff, 0x00efef, 0xffffff,
+    0xb5b5b5, 0x8c8c8c, 0x5a5a5a,
+];
+
+impl<'a,G: Glk<'a>> Execute<'a,G> {
+    pub fn open_windows(&mut self) {
+        self.screen.lower = self.glk.window_open(&G::WinId::null(), 0, 0, glk::wintype_TextBuffer, 0);
+        if self.screen.lower.is_null() {
+            panic!("unable to open the main window");
+        }
+        self.glk.set_window(&self.screen.lower);
+        if self.state.version <= 3 {
+            self.glk.stylehint_set(glk::wintype_TextGrid, glk::style_Normal, glk::stylehint_ReverseColor, 1);
+            self.screen.status = self.glk.window_open(&self.screen.lower, glk::winmethod_Above | glk::winmethod_Fixed, 1, glk::wintype_TextGrid, 0);
+            self.glk.stylehint_clear(glk::wintype_TextGrid, glk::style_Normal, glk::stylehint_ReverseColor);
+        }
+        self.screen.width = self.glk.window_get_size(&self.screen.lower).0 as u16;
+    }
+
+    pub fn reset_windows(&mut self) {
+        self.erase_window(-1);
+        self.screen.style = 0;
+        self.screen.font = 1;
+        self.screen.memory.clear();
+        self.glk.set_style(glk::style_Normal);
+    }
+
+    // The screen size in the header, from the lower window's width and the
+    // height of all the windows.
+    pub fn update_screen_size(&mut self) {
+        let (width,mut height) = self.glk.window_get_size(&self.screen.lower);
+        height += self.screen.upper_shown as u32;
+        if !self.screen.status.is_null() {
+            height += 1;
+        }
+        let width = ::std::cmp::min(width, 255);
+        let height = ::std::cmp::min(height, 254);
+        self.screen.width = width as u16;
+        if self.state.version >= 4 {
+            self.state.mem[SCREEN_HEIGHT] = height as u8;
+            self.state.mem[SCREEN_WIDTH] = width as u8;
+        }
+        if self.state.version >= 5 {
+            write_u16(&mut self.state.mem, SCREEN_WIDTH_UNITS, width as u16);
+            write_u16(&mut self.state.mem, SCREEN_HEIGHT_UNITS, height as u16);
+        }
+    }
+
+    // Output stream 2 is on while bit 0 of Flags 2 is set, which the game
+    // may set itself.
+    pub fn sync_transcript(&mut self) {
+        let on = self.state.mem[FLAGS2 + 1] & 1 != 0;
+        if on != self.screen.transcript.is_null() {
+            return;
+        }
+        if on {
+            let fref = self.glk.fileref_create_by_prompt(glk::fileusage_Transcript | glk::fileusage_TextMode, glk::filemode_WriteAppend, 0);
+            if !fref.is_null() {
+                self.screen.transcript = self.glk.stream_open_file_uni(&fref, glk::filemode_WriteAppend, 0);
+                let mut fref = fref;
+                self.glk.fileref_destroy(&mut fref);
+            }
+            if self.screen.transcript.is_null() {
+                self.state.mem[FLAGS2 + 1] &= !1;
+                return;
+            }
+        } else {
+            let mut transcript = ::std::mem::replace(&mut self.screen.transcript, G::StrId::null());
+            self.glk.stream_close(&mut transcript);
+        }
+        let transcript = self.screen.transcript.clone();
+        self.glk.window_set_echo_stream(&self.screen.lower, &transcript);
+    }
+
+    pub fn print_zscii(&mut self, ch: u16) {
+        if let Some(&table) = self.screen.memory.last() {
+            if ch == 0 {
+                return;
+            }
+            let len = read_u16(&self.state.mem, table);
+            self.state.mem[table + 2 + len as usize] = ch as u8;
+            write_u16(&mut self.state.mem, table, len + 1);
+            return;
+        }
+        if let Some(ch) = text::to_unicode(&self.state, ch) {
+            self.print_char(ch);
+        }
+    }
+
+    pub fn print_unicode(&mut self, ch: u32) {
+        if !self.screen.memory.is_empty() {
+            let zscii = text::from_unicode(&self.state, ch).unwrap_or(b'?' as u16);
+            self.print_zscii(zscii);
+        } else {
+            self.print_char(ch);
+        }
+    }
+
+    fn print_char(&mut self, ch: u32) {
+        if !self.screen.screen_output {
+            return;
+        }
+        if self.screen.window == 1 {
+            if self.screen.upper.is_null() {
+                return;
+            }
+            let (x,y) = self.screen.cursor;
+            if y >= self.screen.upper_height {
+                return;
+            }
+            self.screen.cursor = if ch == 10 || x + 1 >= self.screen.width { (0,y + 1) } else { (x + 1,y) };
+        }
+        self.glk.put_char_uni(ch);
+    }
+
+    pub fn check_unicode(&mut self, ch: u32) -> u16 {
+        let output = if self.glk.gestalt(glk::gestalt_CharOutput, ch) != glk::gestalt_CharOutput_CannotPrint { 1 } else { 0 };
+        let input = if text::from_unicode(&self.state, ch).is_some() { 2 } else { 0 };
+        output | input
+    }
+
+    pub fn split_window(&mut self, lines: u16) {
+        if self.screen.upper.is_null() {
+            if lines == 0 {
+                return;
+            }
+            self.screen.upper = self.glk.window_open(&self.screen.lower, glk::winmethod_Above | glk::winmethod_Fixed, lines as u32, glk::wintype_TextGrid, 0);
+            self.screen.upper_shown = lines;
+        } else if lines > self.screen.upper_shown || self.state.version <= 3 {
+            self.resize_upper(lines);
+        }
+        self.screen.upper_height = lines;
+        if self.state.version == 3 {
+            self.glk.window_clear(&self.screen.upper);
+        }
+        if self.screen.cursor.1 >= lines {
+            self.screen.cursor = (0,0);
+            let upper = self.screen.upper.clone();
+            self.glk.window_move_cursor(&upper, 0, 0);
+        }
+        self.update_screen_size();
+    }
+
+    fn resize_upper(&mut self, lines: u16) {
+        let parent = self.glk.window_get_parent(&self.screen.upper);
+        let upper = self.screen.upper.clone();
+        self.glk.window_set_arrangement(&parent, glk::winmethod_Above | glk::winmethod_Fixed, lines as u32, &upper);
+        self.screen.upper_shown = lines;
+    }
+
+    // Shrinks the upper window to the game's height before input.
+    pub fn show_upper(&mut self) {
+        if !self.screen.upper.is_null() && self.screen.upper_shown > self.screen.upper_height {
+            let lines = self.screen.upper_height;
+            self.resize_upper(lines);
+            self.update_screen_size();
+        }
+    }
+
+    pub fn set_window(&mut self, window: u16) {
+        // Version 6 windows beyond the upper window print in the lower.
+        self.screen.window = if window == 1 { 1 } else { 0 };
+        if window == 1 && !self.screen.upper.is_null() {
+            self.screen.cursor = (0,0);
+            let upper = self.screen.upper.clone();
+            self.glk.window_move_cursor(&upper, 0, 0);
+        }
+        self.select_window();
+    }
+
+    fn select_window(&mut self) {
+        let win = if self.screen.window == 1 && !self.screen.upper.is_null() { self.screen.upper.clone() } else { self.screen.lower.clone() };
+        self.glk.set_window(&win);
+        self.apply_style();
+    }
+
+    pub fn erase_window(&mut self, window: i16) {
+        match window {
+            -1 => {
+                if !self.screen.upper.is_null() {
+                    self.resize_upper(0);
+                    self.screen.upper_height = 0;
+                    self.glk.window_clear(&self.screen.upper);
+                }
+                self.glk.window_clear(&self.screen.lower);
+                self.screen.cursor = (0,0);
+                self.set_window(0);
+                self.update_screen_size();
+            },
+            -2 => {
+                if !self.screen.upper.is_null() {
+                    self.glk.window_clear(&self.screen.upper);
+                }
+                self.glk.window_clear(&self.screen.lower);
+                self.screen.cursor = (0,0);
+            },
+            1 => {
+                if !self.screen.upper.is_null() {
+                    self.glk.window_clear(&self.screen.upper);
+                }
+                self.screen.cursor = (0,0);
+            },
+            _ => self.glk.window_clear(&self.screen.lower),
+        }
+    }
+
+    pub fn erase_line(&mut self) {
+        if self.screen.window != 1 || self.screen.upper.is_null() {
+            return;
+        }
+        let (x,y) = self.screen.cursor;
+        for _ in x .. self.screen.width {
+            self.glk.put_char(b' ');
+        }
+        self.screen.cursor = (x,y);
+        let upper = self.screen.upper.clone();
+        self.glk.window_move_cursor(&upper, x as u32, y as u32);
+    }
+
+    pub fn set_cursor(&mut self, line: i16, column: u16) {
+        if self.screen.window != 1 || self.screen.upper.is_null() || line < 1 || column < 1 {
+            return;
+        }
+        let (x,y) = (column - 1,line as u16 - 1);
+        // Games may print in the upper window beyond its height, which
+        // then grows to fit.
+        if y >= self.screen.upper_height {
+            self.screen.upper_height = y + 1;
+            if y >= self.screen.upper_shown {
+                self.resize_upper(y + 1);
+                self.update_screen_size();
+            }
+        }
+        self.screen.cursor = (x,y);
+        let upper = self.screen.upper.clone();
+        self.glk.window_move_cursor(&upper, x as u32, y as u32);
+    }
+
+    pub fn set_text_style(&mut self, style: u16) {
+        if style == 0 {
+            self.screen.style = 0;
+        } else {
+            self.screen.style |= style;
+        }
+        self.apply_style();
+    }
+
+    pub fn set_font(&mut self, font: u16) -> u16 {
+        let previous = self.screen.font;
+        match font {
+            0 => (),
+            1 | 4 => {
+                self.screen.font = font;
+                self.apply_style();
+            },
+            _ => return 0,
+        }
+        previous
+    }
+
+    // Reverse video goes to the Gargoyle extension, and the other styles
+    // to the Glk style closest to each combination.
+    pub fn apply_style(&mut self) {
+        let style = self.screen.style;
+        let fixed = style & 8 != 0 || self.screen.font == 4 || read_u16(&self.state.mem, FLAGS2) & 2 != 0;
+        let glk_style = match (style & 2 != 0,style & 4 != 0) {
+            _ if fixed => glk::style_Preformatted,
+            (true,true) => glk::style_Alert,
+            (true,false) => glk::style_Subheader,
+            (false,true) => glk::style_Emphasized,
+            (false,false) => glk::style_Normal,
+        };
+        self.glk.set_style(glk_style);
+        self.glk.garglk_set_reversevideo(if style & 1 != 0 { 1 } else { 0 });
+    }
+
+    pub fn set_colour(&mut self, foreground: u16, background: u16) {
+        let colour = |colour: u16| match colour {
+            0 => glk::zcolor_Current,
+            2 ..= 12 => COLOURS[colour as usize - 2],
+            _ => glk::zcolor_Default,
+        };
+        self.glk.garglk_set_zcolors(colour(foreground), colour(background));
+    }
+
+    // Colours as 15-bit RGB, or -1 for the default and -2 for the current.
+    pub fn set_true_colour(&mut self, foreground: i16, background: i16) {
+        let colour = |colour: i16| match colour {
+            -2 => glk::zcolor_Current,
+            -1 => glk::zcolor_Default,
+            _ => {
+                let component = |shift: u32| {
+                    let c = (colour as u32 >> shift) & 31;
+                    (c << 3) | (c >> 2)
+                };
+                component(0) << 16 | component(5) << 8 | component(10)
+            },
+        };
+        self.glk.garglk_set_zcolors(colour(foreground), colour(background));
+    }
+
+    pub fn output_stream(&mut self, stream: i16, table: usize) {
+        match stream {
+            1 => self.screen.screen_output = true,
+            -1 => self.screen.screen_output = false,
+            2 => {
+                self.state.mem[FLAGS2 + 1] |= 1;
+                self.sync_transcript();
+            },
+            -2 => {
+                self.state.mem[FLAGS2 + 1] &= !1;
+                self.sync_transcript();
+            },
+            3 => {
+                if self.screen.memory.len() >= 16 {
+                    panic!("{:x}: output stream 3 nested too deeply", self.state.pc);
+                }
+                write_u16(&mut self.state.mem, table, 0);
+                self.screen.memory.push(table);
+            },
+            -3 => {
+                self.screen.memory.pop();
+            },
+            4 => {
+                if self.screen.commands.is_null() {
+                    let fref = self.glk.fileref_create_by_prompt(glk::fileusage_InputRecord | glk::fileusage_TextMode, glk::filemode_Write, 0);
+                    if !fref.is_null() {
+                        self.screen.commands = self.glk.stream_open_file_uni(&fref, glk::filemode_Write, 0);
+                        let mut fref = fref;
+                        self.glk.fileref_destroy(&mut fref);
+                    }
+                }
+            },
+            -4 => {
+                if !self.screen.commands.is_null() {
+                    let mut commands = ::std::mem::replace(&mut self.screen.commands, G::StrId::null());
+                    self.glk.stream_close(&mut commands);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    pub fn input_stream(&mut self, stream: u16) {
+        match stream {
+            0 => self.close_input(),
+            1 if self.screen.input.is_null() => {
+                let fref = self.glk.fileref_create_by_prompt(glk::fileusage_InputRecord | glk::fileusage_TextMode, glk::filemode_Read, 0);
+                if !fref.is_null() {
+                    self.screen.input = self.glk.stream_open_file_uni(&fref, glk::filemode_Read, 0);
+                    let mut fref = fref;
+                    self.glk.fileref_destroy(&mut fref);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    pub fn close_input(&mut self) {
+        if !self.screen.input.is_null() {
+            let mut input = ::std::mem::replace(&mut self.screen.input, G::StrId::null());
+            self.glk.stream_close(&mut input);
+        }
+    }
+
+    // Versions 1 to 3 show the location and the score and moves, or the
+    // time, in the status line.
+    pub fn show_status(&mut self) {
+        if self.screen.status.is_null() {
+            return;
+        }
+        let status = self.screen.status.clone();
+        let width = self.glk.window_get_size(&status).0 as usize;
+        let globals = read_u16(&self.state.mem, GLOBALS) as usize;
+        let location = read_u16(&self.state.mem, globals);
+        let first = read_u16(&self.state.mem, globals + 2) as i16;
+        let second = read_u16(&self.state.mem, globals + 4);
+        let right = if self.state.version == 3 && self.state.mem[FLAGS1] & 2 != 0 {
+            let hour = first as u16;
+            format!("Time: {}:{:02} {} ", match hour % 12 { 0 => 12, hour => hour }, second, if hour >= 12 { "pm" } else { "am" })
+        } else {
+            format!("Score: {}  Moves: {} ", first, second)
+        };
+        let mut line: Vec<u32> = vec![b' ' as u32];
+        if location != 0 {
+            let (zscii,_) = text::decode(&self.state, object::name(&self.state, location));
+            line.extend(zscii.into_iter().filter_map(|ch| text::to_unicode(&self.state, ch)));
+        }
+        let right: Vec<u32> = right.chars().map(|ch| ch as u32).collect();
+        let room = width.saturating_sub(right.len());
+        line.truncate(room);
+        line.resize(room, b' ' as u32);
+        line.extend(right);
+        line.truncate(width);
+
+        self.glk.window_clear(&status);
+        self.glk.set_window(&status);
+        self.glk.put_buffer_uni(&line);
+        self.select_window();
+    }
+
+    pub fn sound_effect(&mut self, number: u16, effect: u16, volume: u16, routine: u16) {
+        // Sounds 1 and 2 are beeps.
+        if number < 3 || self.glk.gestalt(glk::gestalt_Sound, 0) == 0 {
+            return;
+        }
+        match effect {
+            1 => self.glk.sound_load_hint(number as u32, 1),
+            2 => {
+                if self.screen.sound.is_null() {
+                    self.screen.sound = self.glk.schannel_create(0);
+                    if self.screen.sound.is_null() {
+                        return;
+                    }
+                }
+                let sound = self.screen.sound.clone();
+                let level = match volume & 0xff {
+                    level @ 1 ..= 8 => 0x10000*level as u32/8,
+                    _ => 0x10000,
+                };
+                self.glk.schannel_set_volume(&sound, level);
+                let repeats = match volume >> 8 {
+                    0 => 1,
+                    255 => 0xffffffff,
+                    repeats => repeats as u32,
+                };
+                self.screen.sound_routine = if self.state.version >= 5 { routine } else { 0 };
+                let notify = if self.screen.sound_routine != 0 { number as u32 } else { 0 };
+                self.glk.schannel_play_ext(&sound, number as u32, repeats, notify);
+            },
+            3 => {
+                if !self.screen.sound.is_null() {
+                    let sound = self.screen.sound.clone();
+                    self.glk.schannel_stop(&sound);
+                }
+                self.screen.sound_routine = 0;
+            },
+            4 => self.glk.sound_load_hint(number as u32, 0),
+            _ => (),
+        }
+    }
+
+    pub fn print_table(&mut self, table: usize, width: u16, height: u16, skip: u16) {
+        let (x,y) = self.screen.cursor;
+        let mut addr = table;
+        for line in 0 .. height {
+            if line > 0 {
+                if self.screen.window == 1 {
+                    self.set_cursor(y as i16 + line as i16 + 1, x + 1);
+                } else {
+                    self.print_zscii(13);
+                }
+            }
+            for _ in 0 .. width {
+                let ch = self.state.mem[addr] as u16;
+                self.print_zscii(ch);
+                addr += 1;
+            }
+            addr += skip as usize;
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::io;
+use std::io::{Error,ErrorKind,Read};
+
+pub struct State {
+    pub rom: Box<[u8]>,
+
+    pub mem: Vec<u8>,
+    pub version: u8,
+    pub pc: usize,
+    pub stack: Vec<u16>,
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Clone)]
+pub struct Frame {
+    pub return_pc: usize,
+    // The variable for the result, or None to discard it.
+    pub store: Option<u8>,
+    pub args: usize,
+    pub locals: Vec<u16>,
+    // Where the frame's part of the evaluation stack starts.
+    pub stack_base: usize,
+    // Interrupt routines, such as timed input routines, return to the
+    // interpreter instead of to return_pc.
+    pub interrupt: bool,
+}
+
+pub struct UndoState {
+    mem: Vec<u8>,
+    pc: usize,
+    stack: Vec<u16>,
+    frames: Vec<Frame>,
+}
+
+pub const FLAGS1: usize = 0x01;
+pub const RELEASE: usize = 0x02;
+pub const INITIAL_PC: usize = 0x06;
+pub const DICTIONARY: usize = 0x08;
+pub const OBJECTS: usize = 0x0a;
+pub const GLOBALS: usize = 0x0c;
+pub const STATIC_MEMORY: usize = 0x0e;
+pub const FLAGS2: usize = 0x10;
+pub const SERIAL: usize = 0x12;
+pub const ABBREVIATIONS: usize = 0x18;
+pub const FILE_LENGTH: usize = 0x1a;
+pub const CHECKSUM: usize = 0x1c;
+pub const INTERPRETER_NUMBER: usize = 0x1e;
+pub const INTERPRETER_VERSION: usize = 0x1f;
+pub const SCREEN_HEIGHT: usize = 0x20;
+pub const SCREEN_WIDTH: usize = 0x21;
+pub const SCREEN_WIDTH_UNITS: usize = 0x22;
+pub const SCREEN_HEIGHT_UNITS: usize = 0x24;
+pub const FONT_WIDTH: usize = 0x26;
+pub const ROUTINES_OFFSET: usize = 0x28;
+pub const STRINGS_OFFSET: usize = 0x2a;
+pub const DEFAULT_BACKGROUND: usize = 0x2c;
+pub const DEFAULT_FOREGROUND: usize = 0x2d;
+pub const TERMINATING_CHARS: usize = 0x2e;
+pub const STANDARD_REVISION: usize = 0x32;
+pub const ALPHABET_TABLE: usize = 0x34;
+pub const HEADER_EXTENSION: usize = 0x36;
+
+impl State {
+    pub fn new<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut rom = Vec::new();
+        r.read_to_end(&mut rom)?;
+        if rom.len() < 64 {
+            return Err(invalid_data("invalid header"));
+        }
+        let version = rom[0];
+        if version < 1 || version > 8 {
+            return Err(invalid_data("unsupported z-machine version"));
+        }
+        let static_memory = read_u16(&rom, STATIC_MEMORY) as usize;
+        if static_memory < 64 || static_memory > rom.len() {
+            return Err(invalid_data("invalid static memory base"));
+        }
+        // Stories may be padded, or truncated after the end of the code.
+        let length = file_length(&rom);
+        if length > rom.len() {
+            rom.resize(length, 0);
+        }
+        let mut state = State{
+            rom: rom.into_boxed_slice(),
+            mem: Vec::new(),
+            version: version,
+            pc: 0,
+            stack: Vec::with_capacity(1024),
+            frames: Vec::new(),
+        };
+        state.reset_mem();
+        Ok(state)
+    }
+
+    pub fn reset_mem(&mut self) {
+        self.mem.clear();
+        self.mem.extend_from_slice(&self.rom);
+    }
+
+    pub fn dynamic_size(&self) -> usize {
+        read_u16(&self.rom, STATIC_MEMORY) as usize
+    }
+
+    pub fn routine_addr(&self, packed: u16) -> usize {
+        let packed = packed as usize;
+        match self.version {
+            1 ..= 3 => 2*packed,
+            4 | 5 => 4*packed,
+            6 | 7 => 4*packed + 8*read_u16(&self.mem, ROUTINES_OFFSET) as usize,
+            _ => 8*packed,
+        }
+    }
+
+    pub fn string_addr(&self, packed: u16) -> usize {
+        let packed = packed as usize;
+        match self.version {
+            1 ..= 3 => 2*packed,
+            4 | 5 => 4*packed,
+            6 | 7 => 4*packed + 8*read_u16(&self.mem, STRINGS_OFFSET) as usize,
+            _ => 8*packed,
+        }
+    }
+
+    // The header extension table word, or 0 if the table is too short.
+    pub fn header_extension(&self, word: usize) -> u16 {
+        let table = read_u16(&self.mem, HEADER_EXTENSION) as usize;
+        if table == 0 || read_u16(&self.mem, table) < word as u16 {
+            0
+        } else {
+            read_u16(&self.mem, table + 2*word)
+        }
+    }
+
+    pub fn set_header_extension(&mut self, word: usize, val: u16) {
+        let table = read_u16(&self.mem, HEADER_EXTENSION) as usize;
+        if table != 0 && read_u16(&self.mem, table) >= word as u16 {
+            write_u16(&mut self.mem, table + 2*word, val);
+        }
+    }
+}
+
+impl UndoState {
+    pub fn new(state: &State) -> Self {
+        let dynamic_size = state.dynamic_size();
+        UndoState{
+            mem: state.mem[.. dynamic_size].to_vec(),
+            pc: state.pc,
+            stack: state.stack.clone(),
+            frames: state.frames.clone(),
+        }
+    }
+
+    pub fn restore(self, state: &mut State) {
+        state.mem[.. self.mem.len()].copy_from_slice(&self.mem);
+        state.pc = self.pc;
+        state.stack = self.stack;
+        state.frames = self.frames;
+    }
+}
+
+// The length of the story in the header, which early stories may leave 0.
+pub fn file_length(rom: &[u8]) -> usize {
+    let length = read_u16(rom, FILE_LENGTH) as usize;
+    match rom[0] {
+        1 ..= 3 => 2*length,
+        4 | 5 => 4*length,
+        _ => 8*length,
+    }
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+pub fn read_u16(bytes: &[u8], index: usize) -> u16 {
+    (bytes[index] as u16) << 8 | bytes[index+1] as u16
+}
+
+#[inline]
+pub fn write_u16(bytes: &mut [u8], index: usize, val: u16) {
+    bytes[index] = (val >> 8) as u8;
+    bytes[index+1] = val as u8;
+}
@@ -0,0 +1,170 @@
+use super::state::{read_u16,State,ABBREVIATIONS,ALPHABET_TABLE};
+
+const A0: &'static [u8; 26] = b"abcdefghijklmnopqrstuvwxyz";
+const A1: &'static [u8; 26] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+// The first character of A2 is the escape to 10-bit ZSCII.
+const A2_V1: &'static [u8; 26] = b" 0123456789.,!?_#'\"/\\<-:()";
+const A2: &'static [u8; 26] = b" \r0123456789.,!?_#'\"/\\-:()";
+
+// ZSCII 155 to 223 when the story has no Unicode translation table.
+const DEFAULT_UNICODE: &'static [u16; 69] = &[
+    0xe4, 0xf6, 0xfc, 0xc4, 0xd6, 0xdc, 0xdf, 0xbb, 0xab, 0xeb, 0xef, 0xff, 0xcb, 0xcf, 0xe1, 0xe9,
+    0xed, 0xf3, 0xfa, 0xfd, 0xc1, 0xc9, 0xcd, 0xd3, 0xda, 0xdd, 0xe0, 0xe8, 0xec, 0xf2, 0xf9, 0xc0,
+    0xc8, 0xcc, 0xd2, 0xd9, 0xe2, 0xea, 0xee, 0xf4, 0xfb, 0xc2, 0xca, 0xce, 0xd4, 0xdb, 0xe5, 0xc5,
+    0xf8, 0xd8, 0xe3, 0xf1, 0xf5, 0xc3, 0xd1, 0xd5, 0xe6, 0xc6, 0xe7, 0xc7, 0xfe, 0xf0, 0xde, 0xd0,
+    0xa3, 0x153, 0x152, 0xa1, 0xbf,
+];
+
+// The ZSCII of the Z-character in an alphabet.
+fn alphabet(state: &State, a: usize, zchar: u8) -> u16 {
+    let index = zchar as usize - 6;
+    let table = if state.version >= 5 { read_u16(&state.mem, ALPHABET_TABLE) as usize } else { 0 };
+    if table != 0 && !(a == 2 && index < 2) {
+        return state.mem[table + 26*a + index] as u16;
+    }
+    match a {
+        0 => A0[index] as u16,
+        1 => A1[index] as u16,
+        _ => if state.version == 1 { A2_V1[index] as u16 } else { A2[index] as u16 },
+    }
+}
+
+// Decodes the string at addr into ZSCII, returning the address after it.
+pub fn decode(state: &State, addr: usize) -> (Vec<u16>,usize) {
+    decode_string(state, addr, true)
+}
+
+// Abbreviations can't themselves use abbreviations.
+fn decode_string(state: &State, addr: usize, abbreviations: bool) -> (Vec<u16>,usize) {
+    let mut zchars = Vec::new();
+    let mut addr = addr;
+    loop {
+        let word = read_u16(&state.mem, addr);
+        addr += 2;
+        zchars.push((word >> 10) as u8 & 31);
+        zchars.push((word >> 5) as u8 & 31);
+        zchars.push(word as u8 & 31);
+        if word & 0x8000 != 0 || addr + 1 >= state.mem.len() {
+            break;
+        }
+    }
+    let mut out = Vec::new();
+    let mut lock = 0;
+    let mut shift = None;
+    let mut i = 0;
+    while i < zchars.len() {
+        let zchar = zchars[i];
+        i += 1;
+        let a = shift.take().unwrap_or(lock);
+        match zchar {
+            0 => out.push(32),
+            1 if state.version == 1 => out.push(13),
+            1 ..= 3 if state.version >= 3 || zchar == 1 => {
+                if i < zchars.len() && abbreviations {
+                    let index = 32*(zchar as usize - 1) + zchars[i] as usize;
+                    let addr = 2*read_u16(&state.mem, read_u16(&state.mem, ABBREVIATIONS) as usize + 2*index) as usize;
+                    out.extend(decode_string(state, addr, false).0);
+                }
+                i += 1;
+            },
+            // Shifts up and down in versions 1 and 2, and to A1 and A2 after.
+            2 | 3 => shift = Some((lock + zchar as usize - 1) % 3),
+            4 | 5 if state.version <= 2 => lock = (lock + zchar as usize - 3) % 3,
+            4 | 5 => shift = Some(zchar as usize - 3),
+            6 if a == 2 => {
+                if i + 1 < zchars.len() {
+                    out.push((zchars[i] as u16) << 5 | zchars[i+1] as u16);
+                }
+                i += 2;
+            },
+            _ => out.push(alphabet(state, a, zchar)),
+        }
+    }
+    (out,addr)
+}
+
+// Encodes ZSCII as a dictionary word: 4 bytes in versions 1 to 3, else 6.
+pub fn encode(state: &State, text: &[u16]) -> Vec<u8> {
+    let len = if state.version <= 3 { 6 } else { 9 };
+    let mut zchars = Vec::with_capacity(len + 3);
+    for &ch in text {
+        if zchars.len() >= len {
+            break;
+        }
+        let ch = if ch >= b'A' as u16 && ch <= b'Z' as u16 { ch + 32 } else { ch };
+        if let Some(zchar) = (6 .. 32).find(|&zchar| alphabet(state, 0, zchar) == ch) {
+            zchars.push(zchar);
+            continue;
+        }
+        // In versions 1 and 2, shift 3 is down from A0 to A2.
+        let shift = if state.version <= 2 { 3 } else { 5 };
+        match (7 .. 32).find(|&zchar| alphabet(state, 2, zchar) == ch && !(state.version > 1 && zchar == 7)) {
+            Some(zchar) => {
+                zchars.push(shift);
+                zchars.push(zchar);
+            },
+            None => {
+                zchars.push(shift);
+                zchars.push(6);
+                zchars.push((ch >> 5) as u8 & 31);
+                zchars.push(ch as u8 & 31);
+            },
+        }
+    }
+    zchars.resize(len, 5);
+    let mut bytes = Vec::with_capacity(len/3*2);
+    for i in 0 .. len/3 {
+        let mut word = (zchars[3*i] as u16) << 10 | (zchars[3*i+1] as u16) << 5 | zchars[3*i+2] as u16;
+        if i == len/3 - 1 {
+            word |= 0x8000;
+        }
+        bytes.push((word >> 8) as u8);
+        bytes.push(word as u8);
+    }
+    bytes
+}
+
+// The Unicode translation table from the header extension, if any.
+fn unicode_table(state: &State) -> Option<usize> {
+    if state.version < 5 {
+        return None;
+    }
+    match state.header_extension(3) as usize {
+        0 => None,
+        table => Some(table),
+    }
+}
+
+// The Unicode character of ZSCII output, or None for none.
+pub fn to_unicode(state: &State, zscii: u16) -> Option<u32> {
+    match zscii {
+        0 => None,
+        9 | 11 | 32 ..= 126 => Some(if zscii == 11 { 32 } else { zscii as u32 }),
+        13 => Some(10),
+        155 ..= 251 => {
+            let index = zscii as usize - 155;
+            match unicode_table(state) {
+                Some(table) if index < state.mem[table] as usize => Some(read_u16(&state.mem, table + 1 + 2*index) as u32),
+                Some(_) => Some(b'?' as u32),
+                None if index < DEFAULT_UNICODE.len() => Some(DEFAULT_UNICODE[index] as u32),
+                None => Some(b'?' as u32),
+            }
+        },
+        _ => Some(b'?' as u32),
+    }
+}
+
+// The ZSCII of a Unicode character, if it has one.
+pub fn from_unicode(state: &State, ch: u32) -> Option<u16> {
+    match ch {
+        10 | 13 => return Some(13),
+        32 ..= 126 => return Some(ch as u16),
+        _ => (),
+    }
+    match unicode_table(state) {
+        Some(table) => (0 .. state.mem[table] as usize)
+            .find(|&index| read_u16(&state.mem, table + 1 + 2*index) as u32 == ch)
+            .map(|index| 155 + index as u16),
+        None => DEFAULT_UNICODE.iter().position(|&uni| uni as u32 == ch).map(|index| 155 + index as u16),
+    }
+}
@@ -0,0 +1,141 @@
+use glktest;
+use zmachine;
+
+// Where the minimal story puts things.
+#[allow(dead_code)]
+pub const TEXT: u16 = 0x600;
+#[allow(dead_code)]
+pub const PARSE: u16 = 0x680;
+pub const DICTIONARY: usize = 0x700;
+pub const CODE: usize = 0x800;
+
+const GLOBALS: usize = 0x100;
+const OBJECTS: usize = 0x300;
+const PROPERTIES: usize = 0x400;
+
+// Encodes text as a Z-string, for versions 3 and later.
+pub fn zstring(text: &str) -> Vec<u8> {
+    const A2: &'static str = "\n0123456789.,!?_#'\"/\\-:()";
+    let mut zchars = Vec::new();
+    for ch in text.chars() {
+        match ch {
+            ' ' => zchars.push(0),
+            'a' ..= 'z' => zchars.push(ch as u8 - b'a' + 6),
+            'A' ..= 'Z' => {
+                zchars.push(4);
+                zchars.push(ch as u8 - b'A' + 6);
+            },
+            _ => {
+                zchars.push(5);
+                zchars.push(A2.find(ch).expect("character not in A2") as u8 + 7);
+            },
+        }
+    }
+    let len = (zchars.len() + 2)/3*3;
+    pack(zchars, len)
+}
+
+// Packs Z-characters into words, padded with 5s to the length.
+fn pack(mut zchars: Vec<u8>, len: usize) -> Vec<u8> {
+    zchars.resize(len, 5);
+    let mut bytes = Vec::new();
+    for i in 0 .. len/3 {
+        let mut word = (zchars[3*i] as u16) << 10 | (zchars[3*i+1] as u16) << 5 | zchars[3*i+2] as u16;
+        if i == len/3 - 1 {
+            word |= 0x8000;
+        }
+        bytes.push((word >> 8) as u8);
+        bytes.push(word as u8);
+    }
+    bytes
+}
+
+// Wraps code into a minimal story of the version, with two objects, a
+// box (1) holding a ball (2), and a dictionary of ball, box, look and
+// take.  The box has property 5 = 0x1234 and property 3 = 7, the ball
+// property 4 = 42, and property 6 defaults to 99.  The text and parse
+// buffers hold 20 characters and 4 words.
+pub fn image(version: u8, code: &[u8]) -> Vec<u8> {
+    let mut mem = vec![0; CODE + code.len()];
+    mem[0] = version;
+    put_u16(&mut mem, 0x04, CODE as u16);
+    put_u16(&mut mem, 0x06, CODE as u16);
+    put_u16(&mut mem, 0x08, DICTIONARY as u16);
+    put_u16(&mut mem, 0x0a, OBJECTS as u16);
+    put_u16(&mut mem, 0x0c, GLOBALS as u16);
+    put_u16(&mut mem, 0x0e, DICTIONARY as u16);
+    mem[0x12 .. 0x18].copy_from_slice(b"161114");
+    put_u16(&mut mem, 0x18, 0x40);
+
+    // Global 0, the location in versions 1 to 3, is the box.
+    put_u16(&mut mem, GLOBALS, 1);
+
+    let (defaults,entry_len) = if version <= 3 { (31,9) } else { (63,14) };
+    put_u16(&mut mem, OBJECTS + 2*5, 99);
+    let objects = OBJECTS + 2*defaults;
+    let mut addr = PROPERTIES;
+    for &(obj,parent,child,name,properties) in &[(1,0,2,"box",&[(5,&[0x12,0x34][..]),(3,&[7][..])][..]),
+                                                  (2,1,0,"ball",&[(4,&[0,42][..])][..])] {
+        let entry = objects + entry_len*(obj - 1);
+        if version <= 3 {
+            mem[entry + 4] = parent;
+            mem[entry + 6] = child;
+            put_u16(&mut mem, entry + 7, addr as u16);
+        } else {
+            put_u16(&mut mem, entry + 6, parent as u16);
+            put_u16(&mut mem, entry + 10, child as u16);
+            put_u16(&mut mem, entry + 12, addr as u16);
+        }
+        let name = zstring(name);
+        mem[addr] = (name.len()/2) as u8;
+        mem[addr + 1 .. addr + 1 + name.len()].copy_from_slice(&name);
+        addr += 1 + name.len();
+        for &(number,data) in properties {
+            mem[addr] = if version <= 3 { 32*(data.len() as u8 - 1) + number } else { number | if data.len() == 2 { 0x40 } else { 0 } };
+            mem[addr + 1 .. addr + 1 + data.len()].copy_from_slice(data);
+            addr += 1 + data.len();
+        }
+        addr += 1;
+    }
+
+    mem[TEXT as usize] = 20;
+    mem[PARSE as usize] = 4;
+
+    let word_len = if version <= 3 { 4 } else { 6 };
+    let mut dict = vec![2,b',',b'.',word_len as u8 + 1,0,4];
+    for word in &["ball","box","look","take"] {
+        // Entries have one byte of data.
+        let mut entry = pack(word.bytes().map(|ch| ch - b'a' + 6).collect(), word_len/2*3);
+        entry.push(0);
+        dict.extend(entry);
+    }
+    mem[DICTIONARY .. DICTIONARY + dict.len()].copy_from_slice(&dict);
+
+    mem[CODE ..].copy_from_slice(code);
+    let scale = match version { 1 ..= 3 => 2, 4 | 5 => 4, _ => 8 };
+    while mem.len() % scale != 0 {
+        mem.push(0);
+    }
+    let len = mem.len();
+    put_u16(&mut mem, 0x1a, (len/scale) as u16);
+    let sum = mem[0x40 ..].iter().fold(0u16, |sum,&b| sum.wrapping_add(b as u16));
+    put_u16(&mut mem, 0x1c, sum);
+    mem
+}
+
+#[allow(dead_code)]
+pub fn run<'a>(glk: glktest::GlkTest<'a>, story: &[u8], options: zmachine::Options) -> glktest::GlkTest<'a> {
+    let (glk,result) = zmachine::run_with_options(glk, &mut &story[..], options);
+    assert!(result.is_ok());
+    glk
+}
+
+#[allow(dead_code)]
+pub fn output(version: u8, code: &[u8]) -> String {
+    run(glktest::GlkTest::new(vec![]), &image(version, code), zmachine::Options::new()).output()
+}
+
+fn put_u16(mem: &mut [u8], addr: usize, val: u16) {
+    mem[addr] = (val >> 8) as u8;
+    mem[addr + 1] = val as u8;
+}
@@ -0,0 +1,79 @@
+extern crate glk;
+extern crate glktest;
+extern crate zmachine;
+
+use glk::{Glk,IdType};
+use glktest::TestInput::{Text,Timer};
+use glktest::TestOutput::Match;
+
+mod common;
+
+#[test]
+fn aread() {
+    let code = [
+        0xe4, 0x0f, 0x06, 0x00, 0x06, 0x80, 0x00, // aread text parse -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0xd0, 0x1f, 0x06, 0x80, 0x01, 0x00,     // loadb parse 1 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0xcf, 0x1f, 0x06, 0x80, 0x01, 0x00,     // loadw parse 1 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0xcf, 0x1f, 0x06, 0x80, 0x03, 0x00,     // loadw parse 3 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0xcf, 0x1f, 0x06, 0x80, 0x05, 0x00,     // loadw parse 5 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xba,                                   // quit
+        ];
+    let glk = glktest::GlkTest::new(vec![(Match(""), "Take box.")]);
+    let glk = common::run(glk, &common::image(5, &code), zmachine::Options::new());
+    assert_eq!("13 3 1819 1805 0", glk.output());
+}
+
+#[test]
+fn read_char() {
+    let code = [
+        0xf6, 0x7f, 0x01, 0x00,                 // read_char 1 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xba,                                   // quit
+        ];
+    let glk = glktest::GlkTest::new(vec![(Match(""), "a")]);
+    let glk = common::run(glk, &common::image(5, &code), zmachine::Options::new());
+    assert_eq!("97", glk.output());
+}
+
+#[test]
+fn timed_aread() {
+    let mut code = vec![
+        0xe4, 0x04, 0x06, 0x00, 0x06, 0x80, 0x01, 0x02, 0x10, 0x00, // aread text parse 1 tick -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xba,                                   // quit
+        ];
+    code.resize(0x40, 0);
+    code.extend_from_slice(&[
+        0x00,                                   // tick: no locals
+        0xb2,                                   // print
+        ]);
+    code.extend(common::zstring("tick "));
+    code.push(0xb0);                            // rtrue
+    let glk = glktest::GlkTest::scripted(vec![(Match(""), Timer)]);
+    let glk = common::run(glk, &common::image(5, &code), zmachine::Options::new());
+    assert_eq!("tick 0", glk.output());
+}
+
+#[test]
+fn status_line() {
+    let code = [
+        0xe4, 0x0f, 0x06, 0x00, 0x06, 0x80,     // sread text parse
+        0xba,                                   // quit
+        ];
+    let glk = glktest::GlkTest::scripted(vec![(Match(""), Text("look"))]);
+    let mut glk = common::run(glk, &common::image(3, &code), zmachine::Options::new());
+    let mut win = glk.window_iterate(&glktest::WinId::null()).0;
+    while glk.window_get_type(&win) != glk::wintype_TextGrid {
+        win = glk.window_iterate(&win).0;
+    }
+    assert_eq!(Some(vec![format!(" box{}Score: 0  Moves: 0 ", " ".repeat(57))]), glk.grid(&win));
+}
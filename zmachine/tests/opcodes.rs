@@ -0,0 +1,95 @@
+extern crate glktest;
+extern crate zmachine;
+
+mod common;
+
+fn hello(version: u8) {
+    let mut code = vec![0xb2];                  // print
+    code.extend(common::zstring("Hello, world."));
+    code.extend_from_slice(&[
+        0xbb,                                   // new_line
+        0xe6, 0x3f, 0xff, 0xfe,                 // print_num -2
+        0xbb,                                   // new_line
+        0x14, 0x03, 0x04, 0x10,                 // add 3 4 -> g0
+        0xe6, 0xbf, 0x10,                       // print_num g0
+        0xba,                                   // quit
+        ]);
+    assert_eq!("Hello, world.\n-2\n7", common::output(version, &code));
+}
+
+#[test]
+fn hello_v3() {
+    hello(3);
+}
+
+#[test]
+fn hello_v5() {
+    hello(5);
+}
+
+fn objects(version: u8) {
+    let code = [
+        0x93, 0x02, 0x00,                       // get_parent 2 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x11, 0x01, 0x05, 0x00,                 // get_prop 1 5 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x11, 0x01, 0x06, 0x00,                 // get_prop 1 6 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x11, 0x02, 0x04, 0x00,                 // get_prop 2 4 -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x9a, 0x01,                             // print_obj 1
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x99, 0x02,                             // remove_obj 2
+        0x92, 0x01, 0x00, 0xc2,                 // get_child 1 -> sp ?next
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x0b, 0x01, 0x03,                       // set_attr 1 3
+        0x0a, 0x01, 0x03, 0xc5,                 // test_attr 1 3 ?skip
+        0xe5, 0x7f, 0x6e,                       // print_char 'n'
+        0xe5, 0x7f, 0x79,                       // print_char 'y'
+        0xba,                                   // quit
+        ];
+    assert_eq!("1 4660 99 42 box 0 y", common::output(version, &code));
+}
+
+#[test]
+fn objects_v3() {
+    objects(3);
+}
+
+#[test]
+fn objects_v5() {
+    objects(5);
+}
+
+fn undo(options: zmachine::Options, expected: &str) {
+    let code = [
+        0xbe, 0x09, 0xff, 0x11,                 // save_undo -> g1
+        0xe6, 0xbf, 0x11,                       // print_num g1
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0x95, 0x12,                             // inc g2
+        0xe6, 0xbf, 0x12,                       // print_num g2
+        0xbb,                                   // new_line
+        0x41, 0x11, 0x01, 0x46,                 // je g1 1 ?~skip
+        0xbe, 0x0a, 0xff, 0x11,                 // restore_undo -> g1
+        0xba,                                   // skip: quit
+        ];
+    let glk = common::run(glktest::GlkTest::new(vec![]), &common::image(5, &code), options);
+    assert_eq!(expected, glk.output());
+}
+
+#[test]
+fn save_undo() {
+    undo(zmachine::Options::new(), "1 1\n2 1\n");
+}
+
+#[test]
+fn no_undo() {
+    let mut options = zmachine::Options::new();
+    options.set_undo_depth(0);
+    undo(options, "-1 1\n");
+}
@@ -0,0 +1,42 @@
+extern crate glktest;
+extern crate zmachine;
+
+mod common;
+
+#[test]
+fn save_restore() {
+    let mut code = vec![
+        0xe0, 0x3f, 0x02, 0x10, 0x00,           // call_vs routine -> sp
+        0xe6, 0xbf, 0x00,                       // print_num sp
+        0xba,                                   // quit
+        ];
+    code.resize(0x40, 0);
+    code.extend_from_slice(&[
+        0x01,                                   // routine: 1 local
+        0x0d, 0x01, 0x05,                       // store l0 5
+        0xbe, 0x00, 0xff, 0x11,                 // save -> g1
+        0xe6, 0xbf, 0x11,                       // print_num g1
+        0xe5, 0x7f, 0x20,                       // print_char ' '
+        0xe6, 0xbf, 0x01,                       // print_num l0
+        0xbb,                                   // new_line
+        0x41, 0x11, 0x01, 0x46,                 // je g1 1 ?~skip
+        0xbe, 0x01, 0xff, 0x11,                 // restore -> g1
+        0xe8, 0x7f, 0x07,                       // skip: push 7
+        0xb8,                                   // ret_popped
+        ]);
+    let mut glk = glktest::GlkTest::new(vec![]);
+    glk.set_prompt_answers(vec!["game", "game"]);
+    let glk = common::run(glk, &common::image(5, &code), zmachine::Options::new());
+    assert!(glk.file("game").is_some());
+    assert_eq!("1 5\n2 5\n7", glk.output());
+}
+
+#[test]
+fn cancelled_restore() {
+    let code = [
+        0xbe, 0x01, 0xff, 0x10,                 // restore -> g0
+        0xe6, 0xbf, 0x10,                       // print_num g0
+        0xba,                                   // quit
+        ];
+    assert_eq!("0", common::output(5, &code));
+}